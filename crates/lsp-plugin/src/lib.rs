@@ -171,6 +171,11 @@ impl LspPlugin {
     }
 
     pub async fn stop_server(&self, server_name: &str) -> Result<()> {
+        let mut current = self.current_server.write().await;
+        if current.as_deref() == Some(server_name) {
+            *current = None;
+        }
+        drop(current);
         let mut clients = self.clients.write().await;
         if let Some(client) = clients.remove(server_name) {
             // Shutdown with timeout to prevent hanging
@@ -345,6 +350,11 @@ impl LspPlugin {
         }
     }
 
+    /// Name of the running server that requests go to.
+    pub async fn current_server(&self) -> Option<String> {
+        self.current_server.read().await.clone()
+    }
+
     pub async fn get_diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
         self.diagnostics
             .read()
@@ -2,14 +2,38 @@
 //! バッファやウィンドウ、マクロ、UI 状態などエディタの中枢がここに集約されます。
 
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
 use std::path::{Path, PathBuf};
-
-use crate::command_processor::{BufferCommand, CommandAction, CommandProcessor, WindowCommand};
-use crate::config::Config;
+use std::time::Instant;
+
+use crate::autosave::{Autosave, AutosaveReason};
+use crate::cli::{self, Args, StartPosition};
+use crate::command_processor::{
+    self, BufferCommand, CommandAction, CommandProcessor, MapCommand, WindowCommand, COMMANDS,
+};
+use crate::completion::{self, CompletionKind, Wildmenu};
+use crate::config::{Config, KeybindingStyle};
 use crate::editor::Editor;
-use crate::file_manager::FileManager;
+use crate::emacs::{IncrementalSearch, KillRing};
+use crate::explorer::{Explorer, ExplorerPrompt, FileOperation, InputKind};
+use crate::file_format::{self, Encoding, FileFormat, LineEnding};
+use crate::file_manager::{FileManager, LoadedText, PendingLoad};
+use crate::file_watch::{self, DiskChange, DiskState, FileWatcher};
+use crate::gutter::{GitChanges, Sign, Signs};
 use crate::highlight::Highlighter;
+use crate::keymap::{self, Action, KeyChord, KeyStep, Keymap, KeymapMode};
+use crate::mouse::{ClickAction, MouseState, ScreenLayout, WHEEL_SCROLL_LINES};
+use crate::options::{lookup_option, EditorOptions, LocalOptions, OPTIONS};
+use crate::pager::Pager;
+use crate::picker::{self, Picker, PickerItem, PickerKind, PickerTarget};
+use crate::recent_files::RecentFiles;
+use crate::status_manager::MessageType;
+use crate::statusline::{self, StatusInfo};
+use crate::swap::{SwapFile, SwapStore, SWAP_INTERVAL};
 use crate::text_width::{EmojiWidth, TextWidthCalculator};
 use crate::ui_state::UIState;
 
@@ -17,7 +41,7 @@ use crate::ui_state::UIState;
 #[cfg(feature = "lsp")]
 use lsp_plugin::LspPlugin;
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub enum Mode {
     Normal,
     Insert,
@@ -29,6 +53,13 @@ pub enum Mode {
     SavePrompt,
 }
 
+fn reload_prompt_message(path: &Path) -> String {
+    format!(
+        "{} がディスク上で変更されました: [l]再読み込み [k]このまま [d]差分",
+        path.display()
+    )
+}
+
 pub struct App {
     pub config: Config,
     pub ui_state: UIState,
@@ -37,7 +68,45 @@ pub struct App {
     pub buffer_manager: BufferManager,
     pub window_manager: WindowManager,
     highlighter_cache: Option<Highlighter>, // Cache highlighter
-    last_key: Option<char>,                 // For handling multi-key commands like dd
+    last_key: Option<char>,                 // Prefix of q{reg}, @{reg}, m{a-z}, '{a-z}, `{a-z}
+    // Key bindings and the partially typed key sequence
+    pub keymap: Keymap,
+    // Ex commands queued by key bindings, run after the key is handled
+    pending_commands: Vec<String>,
+    // Last action run from the keymap, for consecutive kills and M-y
+    last_action: Option<Action>,
+    // Emacs kill ring, the length of the text inserted by the last yank
+    // and the incremental search in progress
+    kill_ring: KillRing,
+    yank_len: usize,
+    isearch: Option<IncrementalSearch>,
+    // Step of the nano Replace prompt in progress
+    replace_prompt: Option<ReplacePrompt>,
+    // Fuzzy finder popup, shown over the editor while open
+    pub picker: Option<Picker>,
+    pub recent_files: RecentFiles,
+    // File explorer sidebar and the prompt of the file operation in progress
+    pub explorer: Option<Explorer>,
+    explorer_prompt: Option<ExplorerPrompt>,
+    // Completion candidates shown above the command line
+    pub wildmenu: Option<Wildmenu>,
+    // Multi-line results and the message history, shown over the editor
+    pub pager: Option<Pager>,
+    // Swap file found on open, waiting for recover/read-only/delete/abort
+    swap_prompt: Option<SwapFile>,
+    // Content hashes of the swap files this instance has written, and when.
+    // The writes run on a blocking thread and report the files that failed
+    swap_hashes: std::collections::HashMap<PathBuf, u64>,
+    last_swap_write: Instant,
+    swap_task: Option<tokio::task::JoinHandle<Vec<PathBuf>>>,
+    autosave: Autosave,
+    // Watches open files, and the file changed on disk waiting for
+    // reload/keep while its buffer is unmodified
+    file_watcher: FileWatcher,
+    reload_prompt: Option<(PathBuf, DiskState)>,
+    // File changed on disk while another prompt was open, asked about
+    // once that prompt closes
+    pending_reload: Option<PathBuf>,
     // Macro recording
     macro_recording: bool,
     macro_register: Option<char>,
@@ -45,9 +114,17 @@ pub struct App {
     macro_registers: std::collections::HashMap<char, Vec<KeyEvent>>,
     // Cross-platform text width calculator for accurate cursor positioning
     pub text_calculator: TextWidthCalculator,
+    // Pane and click target positions recorded by the last draw
+    pub screen_layout: ScreenLayout,
+    mouse_state: MouseState,
+    // Whether the terminal currently reports mouse events, so that
+    // `:set mouse` can be applied by the main loop
+    mouse_captured: bool,
     // LSP integration for enhanced syntax highlighting and code intelligence
     #[cfg(feature = "lsp")]
     lsp_plugin: Option<LspPlugin>,
+    // Running language server, refreshed with the diagnostics
+    lsp_server: Option<String>,
 }
 
 // バッファ管理
@@ -56,6 +133,16 @@ pub struct Buffer {
     pub content: Editor,
     pub file_path: Option<PathBuf>,
     pub readonly: bool,
+    // Changes against the committed file and LSP diagnostics, for the
+    // sign column
+    pub git: GitChanges,
+    pub diagnostics: Vec<(usize, Sign)>,
+    // The file on disk when last opened or saved, and the later version the
+    // user was last told about
+    pub disk: Option<DiskState>,
+    external: Option<DiskState>,
+    // The rest of a large file, still being read
+    loading: Option<PendingLoad>,
 }
 
 impl Buffer {
@@ -65,6 +152,11 @@ impl Buffer {
             content: Editor::default(),
             file_path: None,
             readonly: false,
+            git: GitChanges::default(),
+            diagnostics: Vec::new(),
+            disk: None,
+            external: None,
+            loading: None,
         }
     }
 }
@@ -117,6 +209,17 @@ impl BufferManager {
         self.current_buffer
     }
 
+    /// Make the buffer with `id` current. Returns `false` if it does not exist.
+    pub fn set_current_by_id(&mut self, id: usize) -> bool {
+        match self.find_index_by_id(id) {
+            Some(index) => {
+                self.current_buffer = index;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn next_buffer(&mut self) -> Option<&mut Buffer> {
         if self.buffers.len() <= 1 {
             return None;
@@ -143,6 +246,7 @@ impl BufferManager {
             buffer.content = Editor::default();
             buffer.file_path = None;
             buffer.readonly = false;
+            buffer.disk = None;
             None
         } else {
             let removed = self.buffers.remove(self.current_buffer);
@@ -293,6 +397,24 @@ impl WindowManager {
         self.split(buffer_id, WindowSplitKind::Vertical);
     }
 
+    /// One pane for each buffer, stacked or side by side, as `-o` and `-O`
+    /// open them. The current pane shows the first buffer and keeps focus.
+    pub fn tile(&mut self, buffer_ids: &[usize], kind: WindowSplitKind) {
+        let Some((&first, rest)) = buffer_ids.split_first() else {
+            return;
+        };
+        self.panes.retain(|pane| pane.id == self.current_window_id);
+        self.set_buffer_for_current(first);
+        for &buffer_id in rest {
+            self.panes.push(WindowPane {
+                id: self.next_window_id,
+                buffer_id,
+            });
+            self.next_window_id += 1;
+        }
+        self.split = kind;
+    }
+
     pub fn set_buffer_for_current(&mut self, buffer_id: usize) {
         if let Some(pane) = self
             .panes
@@ -306,6 +428,13 @@ impl WindowManager {
     pub fn split_kind(&self) -> WindowSplitKind {
         self.split
     }
+
+    pub fn buffer_for_window(&self, window_id: usize) -> Option<usize> {
+        self.panes
+            .iter()
+            .find(|pane| pane.id == window_id)
+            .map(|pane| pane.buffer_id)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -315,20 +444,42 @@ enum UiMessageKind {
     Warning,
 }
 
+/// The nano Replace prompt asks for the text to find, then for its
+/// replacement, so that both may contain spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ReplacePrompt {
+    Search,
+    With(String),
+}
+
 impl App {
     pub async fn new() -> Result<Self> {
         let config = Config::load().await?;
         let mut buffer_manager = BufferManager::new();
 
-        // Apply tab configuration to the initial buffer
+        // Apply the configured options to the initial buffer
+        let options = EditorOptions::resolve(&config, &LocalOptions::default());
         buffer_manager
             .get_current_mut()
             .content
-            .set_tab_config(config.editor.tab_size, config.editor.use_spaces);
+            .apply_options(&options);
 
         let initial_buffer_id = buffer_manager.get_current().id;
         let command_processor = CommandProcessor::new()?;
 
+        let mut ui_state = UIState::new();
+        let mut keymap = Keymap::for_style(config.keybindings);
+        let keymap_errors = keymap.apply_config(&config.keymap);
+        if !keymap_errors.is_empty() {
+            for error in &keymap_errors {
+                log::warn!("Invalid key mapping in config: {}", error);
+            }
+            ui_state.set_warning_message(format!(
+                "無効なキーマッピングを無視しました: {}",
+                keymap_errors.join(", ")
+            ));
+        }
+
         // Initialize LSP plugin if feature is enabled
         #[cfg(feature = "lsp")]
         let lsp_plugin = {
@@ -337,15 +488,46 @@ impl App {
             Some(plugin)
         };
 
+        let mut file_manager = FileManager::new();
+        file_manager.backup = config.editor.backup;
+        file_manager.backup_dir = config.editor.backup_dir.clone();
+        file_manager.large_file_threshold = config.editor.large_file_mb * 1024 * 1024;
+        if config.editor.swapfile {
+            file_manager.swaps = SwapStore::open();
+        }
+
+        let autosave = Autosave::new(config.autosave.clone());
+
         Ok(Self {
             config,
-            ui_state: UIState::new(),
-            file_manager: FileManager::new(),
+            ui_state,
+            file_manager,
             command_processor,
             buffer_manager,
             window_manager: WindowManager::new(initial_buffer_id),
             highlighter_cache: None,
             last_key: None,
+            keymap,
+            pending_commands: Vec::new(),
+            last_action: None,
+            kill_ring: KillRing::default(),
+            yank_len: 0,
+            isearch: None,
+            replace_prompt: None,
+            picker: None,
+            recent_files: RecentFiles::load().await,
+            explorer: None,
+            explorer_prompt: None,
+            wildmenu: None,
+            pager: None,
+            swap_prompt: None,
+            swap_hashes: std::collections::HashMap::new(),
+            last_swap_write: Instant::now(),
+            swap_task: None,
+            autosave,
+            file_watcher: FileWatcher::new(),
+            reload_prompt: None,
+            pending_reload: None,
             macro_recording: false,
             macro_register: None,
             macro_keys: Vec::new(),
@@ -354,8 +536,12 @@ impl App {
             text_calculator: TextWidthCalculator::new()
                 .east_asian_aware(true)
                 .emoji_width(EmojiWidth::Standard),
+            screen_layout: ScreenLayout::default(),
+            mouse_state: MouseState::default(),
+            mouse_captured: true,
             #[cfg(feature = "lsp")]
             lsp_plugin,
+            lsp_server: None,
         })
     }
 
@@ -414,11 +600,33 @@ impl App {
         self.ui_state.get_command_buffer()
     }
 
+    pub fn command_prompt(&self) -> &str {
+        &self.ui_state.command_prompt
+    }
+
     pub fn quit(&mut self) {
         self.ui_state.quit();
     }
 
     pub async fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
+        self.autosave.record_activity(Instant::now());
+        if let Some(pager) = self.pager.as_mut() {
+            if !pager.handle_key(key) {
+                self.pager = None;
+            }
+            return Ok(());
+        }
+        if self.picker.is_some() {
+            return self.handle_picker_key(key).await;
+        }
+        if self
+            .explorer
+            .as_ref()
+            .is_some_and(|explorer| explorer.focused)
+            && matches!(self.ui_state.get_mode(), Mode::Normal)
+        {
+            return self.handle_explorer_key(key).await;
+        }
         match self.ui_state.get_mode() {
             Mode::Normal | Mode::Insert | Mode::Visual | Mode::VisualBlock | Mode::Replace => {
                 self.handle_editor_key(key).await?
@@ -431,187 +639,443 @@ impl App {
     }
 
     async fn handle_editor_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.isearch.is_some() && self.handle_isearch_key(key) {
+            return Ok(());
+        }
+
         match self.ui_state.get_mode() {
-            Mode::Normal => self.handle_normal_mode_key(key),
-            Mode::Insert => self.handle_insert_mode_key(key),
-            Mode::Visual | Mode::VisualBlock => self.handle_visual_mode_key(key),
-            Mode::Replace => self.handle_replace_mode_key(key),
-            _ => Ok(()), // Other modes handled elsewhere
+            Mode::Normal => self.handle_normal_mode_key(key)?,
+            Mode::Insert => self.handle_insert_mode_key(key)?,
+            Mode::Visual | Mode::VisualBlock => self.handle_visual_mode_key(key)?,
+            Mode::Replace => self.handle_replace_mode_key(key)?,
+            _ => {} // Other modes handled elsewhere
         }
+        self.run_pending_commands().await;
+        Ok(())
     }
 
-    fn handle_normal_mode_key(&mut self, key: KeyEvent) -> Result<()> {
-        // Record macro if recording
-        if self.macro_recording && key.code != KeyCode::Char('q') {
-            self.macro_keys.push(key);
+    /// Run a held key sequence once its timeout has passed. Called from the
+    /// event loop between key presses.
+    pub async fn check_key_timeout(&mut self) -> Result<()> {
+        let steps = self.keymap.check_timeout(Instant::now());
+        self.run_key_steps(steps);
+        // Also runs commands queued by clicks on the shortcut bar
+        self.run_pending_commands().await;
+        Ok(())
+    }
+
+    fn feed_keymap(&mut self, mode: KeymapMode, key: KeyEvent) {
+        let mode = if self.keymap.is_modeless() {
+            KeymapMode::Normal
+        } else {
+            mode
+        };
+        let steps = self.keymap.feed(mode, key.into(), Instant::now());
+        self.run_key_steps(steps);
+    }
+
+    fn run_key_steps(&mut self, steps: Vec<KeyStep>) {
+        for step in steps {
+            match step {
+                KeyStep::Action(action) => {
+                    self.run_action(action.clone());
+                    self.last_action = Some(action);
+                }
+                KeyStep::Unmapped(key) => {
+                    self.handle_unmapped_key(key);
+                    self.last_action = None;
+                }
+            }
         }
+    }
 
-        // Clear last_key if it's not a command key and we're not pressing that key
-        if key.code != KeyCode::Char('d')
-            && key.code != KeyCode::Char('y')
-            && key.code != KeyCode::Char('q')
-            && self.last_key.is_some()
-        {
-            self.last_key = None;
+    /// Keys without a binding: register names in normal mode and plain
+    /// characters in insert mode or with a modeless keymap.
+    fn handle_unmapped_key(&mut self, key: KeyChord) {
+        if self.keymap.is_modeless() {
+            if let Some(c) = key.as_char() {
+                self.get_current_editor_mut().insert_char(c);
+            }
+            return;
+        }
+        match (self.ui_state.get_mode(), key.as_char()) {
+            (Mode::Normal, Some('q')) if self.macro_recording => self.stop_macro_recording(),
+            (Mode::Normal, Some(c @ ('q' | '@' | 'm' | '\'' | '`'))) => self.last_key = Some(c),
+            (Mode::Insert, Some(c)) => self.get_current_editor_mut().insert_char(c),
+            _ => {}
         }
+    }
 
-        match key.code {
-            // Vim-style movement
-            KeyCode::Char('h') | KeyCode::Left => self.get_current_editor_mut().move_cursor_left(),
-            KeyCode::Char('j') | KeyCode::Down => self.get_current_editor_mut().move_cursor_down(),
-            KeyCode::Char('k') | KeyCode::Up => self.get_current_editor_mut().move_cursor_up(),
-            KeyCode::Char('l') | KeyCode::Right => {
-                self.get_current_editor_mut().move_cursor_right()
-            }
-
-            // Line movement
-            KeyCode::Home => self.get_current_editor_mut().move_to_line_start(),
-            KeyCode::End => self.get_current_editor_mut().move_to_line_end(),
-            KeyCode::PageUp => self.get_current_editor_mut().page_up(),
-            KeyCode::PageDown => self.get_current_editor_mut().page_down(),
-
-            // Macro recording and playback
-            KeyCode::Char('q') => {
-                if self.last_key == Some('q') {
-                    // qq - stop recording
-                    if self.macro_recording {
-                        self.stop_macro_recording();
-                    }
-                    self.last_key = None;
-                } else if self.macro_recording {
-                    // Stop recording
-                    self.stop_macro_recording();
-                } else {
-                    // Start recording - wait for register
-                    self.last_key = Some('q');
-                }
+    fn run_action(&mut self, action: Action) {
+        let in_visual = matches!(self.ui_state.get_mode(), Mode::Visual | Mode::VisualBlock);
+        let editor = self.get_current_editor_mut();
+        match action {
+            Action::MoveLeft => editor.move_cursor_left(),
+            Action::MoveDown => editor.move_cursor_down(),
+            Action::MoveUp => editor.move_cursor_up(),
+            Action::MoveRight => editor.move_cursor_right(),
+            Action::LineStart => editor.move_to_line_start(),
+            Action::LineEnd => editor.move_to_line_end(),
+            Action::PageUp => editor.page_up(),
+            Action::PageDown => editor.page_down(),
+            Action::DisplayRowDown => editor.move_display_row_down(),
+            Action::DisplayRowUp => editor.move_display_row_up(),
+            Action::WordForward => editor.move_word_forward(),
+            Action::WordBackward => editor.move_word_backward(),
+            Action::BufferStart => editor.move_to_buffer_start(),
+            Action::BufferEnd => editor.move_to_buffer_end(),
+
+            Action::Insert => self.ui_state.enter_insert_mode(),
+            Action::Append => {
+                editor.move_cursor_right();
+                self.ui_state.enter_insert_mode();
             }
-            KeyCode::Char('@') => {
-                // Play macro - wait for register
-                self.last_key = Some('@');
+            Action::OpenBelow => {
+                editor.move_to_line_end();
+                editor.insert_newline();
+                self.ui_state.enter_insert_mode();
             }
-            KeyCode::Char(c) if self.last_key == Some('q') && !self.macro_recording => {
-                // Start recording to register
-                self.start_macro_recording(c);
-                self.last_key = None;
+            Action::OpenAbove => {
+                editor.move_to_line_start();
+                editor.insert_newline();
+                editor.move_cursor_up();
+                self.ui_state.enter_insert_mode();
             }
-            KeyCode::Char(c) if self.last_key == Some('@') => {
-                // Play macro from register
-                self.play_macro(c);
-                self.last_key = None;
+            Action::NormalMode => {
+                if in_visual {
+                    editor.clear_visual_selection();
+                }
+                self.ui_state.enter_normal_mode();
             }
-
-            // Visual mode
-            KeyCode::Char('v') if !key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.get_current_editor_mut().start_visual_selection();
+            Action::Visual => {
+                editor.start_visual_selection();
                 self.ui_state.enter_visual_mode();
             }
-            KeyCode::Char('V') => {
+            Action::VisualLine => {
                 // Visual line mode (treat as visual for now)
-                self.get_current_editor_mut().start_visual_selection();
-                self.get_current_editor_mut().move_to_line_start();
+                editor.start_visual_selection();
+                editor.move_to_line_start();
                 self.ui_state.enter_visual_mode();
             }
-            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                // Visual block mode
-                self.get_current_editor_mut().start_visual_selection();
+            Action::VisualBlock => {
+                editor.start_visual_selection();
                 self.ui_state.enter_visual_block_mode();
             }
-
-            // Replace mode
-            KeyCode::Char('R') => {
-                self.ui_state.enter_replace_mode();
+            Action::ReplaceMode => self.ui_state.enter_replace_mode(),
+            Action::CommandMode => self.ui_state.enter_command_mode(),
+            Action::Search => self.ui_state.enter_search_mode(),
+            Action::Help => self.ui_state.toggle_help(),
+            Action::ExecuteCommand => {
+                self.ui_state.enter_command_mode();
+                self.ui_state.set_command_prompt("M-x ");
             }
 
-            // Insert mode transitions
-            KeyCode::Char('i') => self.ui_state.enter_insert_mode(),
-            KeyCode::Char('a') => {
-                self.get_current_editor_mut().move_cursor_right();
-                self.ui_state.enter_insert_mode();
+            Action::InsertNewline => editor.insert_newline(),
+            Action::InsertTab => editor.insert_tab(),
+            Action::DeleteChar => editor.delete_char_forward(),
+            Action::DeleteCharBackward => editor.delete_char_backward(),
+            Action::DeleteLine => {
+                editor.delete_line();
+                self.ui_state
+                    .set_success_message("行を削除してヤンクしました".to_string());
             }
-            KeyCode::Char('o') => {
-                self.get_current_editor_mut().move_to_line_end();
-                self.get_current_editor_mut().insert_newline();
-                self.ui_state.enter_insert_mode();
+            Action::YankLine => {
+                editor.yank_line();
+                self.ui_state
+                    .set_success_message("行をヤンクしました".to_string());
+            }
+            Action::Paste => {
+                editor.paste();
+                self.ui_state
+                    .set_success_message("貼り付けました".to_string());
+            }
+            Action::DeleteSelection => {
+                editor.delete_selection();
+                self.ui_state.enter_normal_mode();
+                self.ui_state
+                    .set_success_message("選択範囲を削除してヤンクしました".to_string());
+            }
+            Action::YankSelection => {
+                editor.yank_selection();
+                editor.clear_visual_selection();
+                self.ui_state.enter_normal_mode();
+                self.ui_state
+                    .set_success_message("選択範囲をヤンクしました".to_string());
             }
-            KeyCode::Char('O') => {
-                self.get_current_editor_mut().move_to_line_start();
-                self.get_current_editor_mut().insert_newline();
-                self.get_current_editor_mut().move_cursor_up();
+            Action::ChangeSelection => {
+                editor.delete_selection();
                 self.ui_state.enter_insert_mode();
             }
 
-            // Delete operations
-            KeyCode::Char('x') => self.get_current_editor_mut().delete_char_forward(),
-            KeyCode::Char('d') => self.handle_delete_command(),
+            Action::SetMark => {
+                editor.start_visual_selection();
+                self.ui_state
+                    .set_info_message("マークを設定しました".to_string());
+            }
+            Action::KillLine => {
+                let killed = editor.kill_to_line_end();
+                if self.last_action == Some(Action::KillLine) {
+                    self.kill_ring.append(&killed);
+                } else {
+                    self.kill_ring.push(killed);
+                }
+            }
+            Action::CutLine => {
+                if editor.get_visual_selection().is_some() {
+                    self.kill_region(Action::KillRegion);
+                } else {
+                    let cut = editor.cut_line();
+                    // Consecutive cuts paste back as one block
+                    if self.last_action == Some(Action::CutLine) {
+                        self.kill_ring.append(&cut);
+                    } else {
+                        self.kill_ring.push(cut);
+                    }
+                }
+            }
+            Action::KillRegion | Action::CopyRegion => self.kill_region(action),
+            Action::Yank => match self.kill_ring.yank() {
+                Some(text) => {
+                    self.yank_len = text.chars().count();
+                    self.buffer_manager
+                        .get_current_mut()
+                        .content
+                        .insert_text(text);
+                }
+                None => self
+                    .ui_state
+                    .set_warning_message("キルリングは空です".to_string()),
+            },
+            Action::YankPop => {
+                if !matches!(self.last_action, Some(Action::Yank | Action::YankPop)) {
+                    self.ui_state
+                        .set_warning_message("直前のコマンドはヤンクではありません".to_string());
+                } else if let Some(text) = self.kill_ring.rotate() {
+                    let previous_len = std::mem::replace(&mut self.yank_len, text.chars().count());
+                    self.buffer_manager
+                        .get_current_mut()
+                        .content
+                        .replace_before_cursor(previous_len, text);
+                }
+            }
+            Action::KeyboardQuit => {
+                editor.clear_visual_selection();
+                self.ui_state.set_info_message("中断しました".to_string());
+            }
+            Action::IsearchForward | Action::IsearchBackward => {
+                let search = IncrementalSearch::new(
+                    action == Action::IsearchForward,
+                    editor.cursor_char_index(),
+                );
+                self.ui_state.set_prompt_message(search.prompt());
+                self.isearch = Some(search);
+            }
+
+            Action::Undo => self.handle_undo(),
+            Action::Redo => self.handle_redo(),
 
-            // Yank operations
-            KeyCode::Char('y') => self.handle_yank_command(),
+            Action::FoldToggle
+            | Action::FoldOpen
+            | Action::FoldClose
+            | Action::FoldOpenAll
+            | Action::FoldCloseAll => self.handle_fold_action(action),
 
-            // Paste
-            KeyCode::Char('p') => {
-                self.get_current_editor_mut().paste();
-                self.ui_state
-                    .set_success_message("貼り付けました".to_string());
+            Action::Write => self.pending_commands.push("w".to_string()),
+            Action::WriteOut => {
+                let command = match self.file_manager.get_current_path() {
+                    Some(path) => format!("w {}", path.display()),
+                    None => "w ".to_string(),
+                };
+                self.prompt_command(&command);
+                self.ui_state.set_command_prompt("書き込み: ");
+            }
+            Action::Quit => self.pending_commands.push("q".to_string()),
+            Action::WriteQuit => self.pending_commands.push("wq".to_string()),
+            Action::Exit => {
+                if self.is_modified() {
+                    self.ui_state
+                        .set_prompt_message("変更を保存しますか? (y/n/c): ".to_string());
+                    self.ui_state.set_mode(Mode::SavePrompt);
+                } else {
+                    self.quit();
+                }
             }
+            Action::Replace => {
+                self.prompt_command("");
+                self.ui_state.set_command_prompt("置換: ");
+                self.replace_prompt = Some(ReplacePrompt::Search);
+            }
+            Action::GoToLine => {
+                self.prompt_command("");
+                self.ui_state.set_command_prompt("行番号: ");
+            }
+            Action::NextBuffer => self.pending_commands.push("bn".to_string()),
+            Action::PreviousBuffer => self.pending_commands.push("bp".to_string()),
+            Action::CloseBuffer => self.pending_commands.push("bd".to_string()),
+            Action::SplitHorizontal => self.pending_commands.push("split".to_string()),
+            Action::SplitVertical => self.pending_commands.push("vsplit".to_string()),
+            Action::FindFile => self.prompt_command("e "),
+            Action::FuzzyFind => self.open_picker(PickerKind::Files, ""),
+            Action::PickBuffer => self.open_picker(PickerKind::Buffers, ""),
+            Action::PickRecent => self.open_picker(PickerKind::RecentFiles, ""),
+            Action::PickHeading => self.open_picker(PickerKind::Headings, ""),
+            Action::Explore => self.toggle_explorer(),
+            Action::CommandPalette => self.open_picker(PickerKind::Commands, ""),
+            Action::SwitchBuffer => self.prompt_command("b "),
+            Action::OtherWindow => self.focus_next_window(),
+            Action::Ex(command) => self.pending_commands.push(command),
+        }
+    }
+
+    /// Open the command line with `text` already typed.
+    fn prompt_command(&mut self, text: &str) {
+        self.ui_state.enter_command_mode();
+        self.ui_state.set_command_buffer(text.to_string());
+    }
+
+    fn kill_region(&mut self, action: Action) {
+        let editor = self.get_current_editor_mut();
+        if editor.get_visual_selection().is_none() {
+            self.ui_state
+                .set_warning_message("マークが設定されていません".to_string());
+            return;
+        }
+        let text = editor.get_selected_text();
+        if action == Action::KillRegion {
+            editor.delete_selection();
+        } else {
+            editor.clear_visual_selection();
+            self.ui_state
+                .set_info_message("リージョンをコピーしました".to_string());
+        }
+        self.kill_ring.push(text);
+    }
 
-            // Undo/Redo
-            KeyCode::Char('u') => self.handle_undo(),
-            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.handle_redo()
+    /// Keys typed during an incremental search. Returns `false` when the key
+    /// ends the search and should be handled as usual.
+    fn handle_isearch_key(&mut self, key: KeyEvent) -> bool {
+        let Some(mut search) = self.isearch.take() else {
+            return false;
+        };
+        let chord = KeyChord::from(key);
+        let action = self.keymap.lookup(KeymapMode::Normal, &[chord]).cloned();
+        let editor = &mut self.buffer_manager.get_current_mut().content;
+
+        match (action, key.code) {
+            (Some(Action::IsearchForward), _) => search.repeat(true, editor),
+            (Some(Action::IsearchBackward), _) => search.repeat(false, editor),
+            (Some(Action::KeyboardQuit), _) => {
+                editor.set_cursor_char_index(search.origin());
+                self.ui_state
+                    .set_info_message("検索を中断しました".to_string());
+                return true;
+            }
+            (_, KeyCode::Enter | KeyCode::Esc) => {
+                self.ui_state.status_message.clear();
+                return true;
             }
+            (_, KeyCode::Backspace) => search.pop_char(editor),
+            _ => match chord.as_char() {
+                Some(c) => search.push_char(c, editor),
+                None => {
+                    // Any other key ends the search and runs normally
+                    self.ui_state.status_message.clear();
+                    return false;
+                }
+            },
+        }
+        self.ui_state.set_prompt_message(search.prompt());
+        self.isearch = Some(search);
+        true
+    }
 
-            // Mode switches
-            KeyCode::Char(':') => self.ui_state.enter_command_mode(),
-            KeyCode::Char('/') => self.ui_state.enter_search_mode(),
-            KeyCode::Char('?') => self.ui_state.toggle_help(),
+    fn focus_next_window(&mut self) {
+        let panes = self.window_manager.panes();
+        let current = panes
+            .iter()
+            .position(|pane| pane.id == self.window_manager.current_window_id)
+            .unwrap_or(0);
+        let next = panes[(current + 1) % panes.len()].id;
+        self.focus_window(next);
+    }
 
-            _ => {}
+    /// Rebuild the keymap after the keybinding style changed at runtime.
+    fn reload_keymap(&mut self) {
+        let mut keymap = Keymap::for_style(self.config.keybindings);
+        for error in keymap.apply_config(&self.config.keymap) {
+            log::warn!("Invalid key mapping in config: {}", error);
         }
-        Ok(())
+        self.keymap = keymap;
+        self.isearch = None;
+        self.last_key = None;
+        self.get_current_editor_mut().clear_visual_selection();
     }
 
-    fn handle_insert_mode_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => self.ui_state.enter_normal_mode(),
-            KeyCode::Char(c) => self.get_current_editor_mut().insert_char(c),
-            KeyCode::Enter => self.get_current_editor_mut().insert_newline(),
-            KeyCode::Backspace => self.get_current_editor_mut().delete_char_backward(),
-            KeyCode::Delete => self.get_current_editor_mut().delete_char_forward(),
-            KeyCode::Tab => self.get_current_editor_mut().insert_tab(),
-
-            // Cursor movement in insert mode
-            KeyCode::Left => self.get_current_editor_mut().move_cursor_left(),
-            KeyCode::Right => self.get_current_editor_mut().move_cursor_right(),
-            KeyCode::Up => self.get_current_editor_mut().move_cursor_up(),
-            KeyCode::Down => self.get_current_editor_mut().move_cursor_down(),
+    async fn run_pending_commands(&mut self) {
+        for command in std::mem::take(&mut self.pending_commands) {
+            self.execute_command_line(&command).await;
+        }
+    }
 
-            _ => {}
+    fn handle_normal_mode_key(&mut self, key: KeyEvent) -> Result<()> {
+        // Record macro if recording
+        if self.macro_recording && key.code != KeyCode::Char('q') {
+            self.macro_keys.push(key);
+        }
+
+        // The register or mark name after q/@/m/'/` bypasses the keymap
+        if let (Some(prefix), KeyCode::Char(register)) = (self.last_key.take(), key.code) {
+            match prefix {
+                'q' if !self.macro_recording => self.start_macro_recording(register),
+                '@' => self.play_macro(register),
+                'm' | '\'' | '`' => self.handle_mark(prefix, register),
+                _ => {}
+            }
+            return Ok(());
         }
+
+        self.feed_keymap(KeymapMode::Normal, key);
+        Ok(())
+    }
+
+    fn handle_insert_mode_key(&mut self, key: KeyEvent) -> Result<()> {
+        self.feed_keymap(KeymapMode::Insert, key);
         Ok(())
     }
 
-    fn handle_delete_command(&mut self) {
-        // Vim dd command: delete line (second d press)
-        if self.last_key == Some('d') {
-            self.get_current_editor_mut().delete_line();
+    /// `m{a-z}` sets a mark; `'{a-z}` and `` `{a-z} `` jump to its line or
+    /// exact position.
+    fn handle_mark(&mut self, prefix: char, name: char) {
+        if !name.is_ascii_lowercase() {
             self.ui_state
-                .set_success_message("行を削除してヤンクしました".to_string());
-            self.last_key = None;
-        } else {
-            self.last_key = Some('d');
+                .set_warning_message(format!("E191: 無効なマーク名です: {}", name));
+            return;
+        }
+        let editor = self.get_current_editor_mut();
+        if prefix == 'm' {
+            editor.set_mark(name);
+        } else if !editor.jump_to_mark(name, prefix == '`') {
+            self.ui_state
+                .set_warning_message(format!("E20: マークが設定されていません: {}", name));
         }
     }
 
-    fn handle_yank_command(&mut self) {
-        // Vim yy command: yank line (second y press)
-        if self.last_key == Some('y') {
-            self.get_current_editor_mut().yank_line();
+    fn handle_fold_action(&mut self, action: Action) {
+        let editor = self.get_current_editor_mut();
+        let found = match action {
+            Action::FoldToggle => editor.toggle_fold(),
+            Action::FoldOpen => editor.open_fold(),
+            Action::FoldClose => editor.close_fold(),
+            Action::FoldOpenAll => {
+                editor.open_all_folds();
+                true
+            }
+            Action::FoldCloseAll => editor.close_all_folds(),
+            _ => return,
+        };
+        if !found {
             self.ui_state
-                .set_success_message("行をヤンクしました".to_string());
-            self.last_key = None;
-        } else {
-            self.last_key = Some('y');
+                .set_warning_message("折りたたみがありません".to_string());
         }
     }
 
@@ -636,44 +1100,7 @@ impl App {
     }
 
     fn handle_visual_mode_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            // Exit visual mode
-            KeyCode::Esc => {
-                self.get_current_editor_mut().clear_visual_selection();
-                self.ui_state.enter_normal_mode();
-            }
-
-            // Movement extends selection
-            KeyCode::Char('h') | KeyCode::Left => self.get_current_editor_mut().move_cursor_left(),
-            KeyCode::Char('j') | KeyCode::Down => self.get_current_editor_mut().move_cursor_down(),
-            KeyCode::Char('k') | KeyCode::Up => self.get_current_editor_mut().move_cursor_up(),
-            KeyCode::Char('l') | KeyCode::Right => {
-                self.get_current_editor_mut().move_cursor_right()
-            }
-            KeyCode::Home => self.get_current_editor_mut().move_to_line_start(),
-            KeyCode::End => self.get_current_editor_mut().move_to_line_end(),
-
-            // Operations on selection
-            KeyCode::Char('d') | KeyCode::Char('x') => {
-                self.get_current_editor_mut().delete_selection();
-                self.ui_state.enter_normal_mode();
-                self.ui_state
-                    .set_success_message("選択範囲を削除してヤンクしました".to_string());
-            }
-            KeyCode::Char('y') => {
-                self.get_current_editor_mut().yank_selection();
-                self.get_current_editor_mut().clear_visual_selection();
-                self.ui_state.enter_normal_mode();
-                self.ui_state
-                    .set_success_message("選択範囲をヤンクしました".to_string());
-            }
-            KeyCode::Char('c') => {
-                self.get_current_editor_mut().delete_selection();
-                self.ui_state.enter_insert_mode();
-            }
-
-            _ => {}
-        }
+        self.feed_keymap(KeymapMode::Visual, key);
         Ok(())
     }
 
@@ -695,57 +1122,23 @@ impl App {
     }
 
     async fn handle_command_key(&mut self, key: KeyEvent) -> Result<()> {
+        if !matches!(key.code, KeyCode::Tab | KeyCode::BackTab) {
+            self.wildmenu = None;
+        }
         match key.code {
+            KeyCode::Enter if self.replace_prompt.is_some() => self.advance_replace_prompt(),
             KeyCode::Enter => {
                 let command = self.ui_state.get_command_buffer().to_string();
 
                 // Add to history
                 self.ui_state.add_to_history(command.clone());
 
-                // Execute command - handle buffer operations in App
-                let command_result = {
-                    let current_editor = &mut self.buffer_manager.get_current_mut().content;
-                    self.command_processor
-                        .execute_command(
-                            &command,
-                            current_editor,
-                            &mut self.file_manager,
-                            &mut self.config,
-                            &mut self.ui_state.should_quit,
-                        )
-                        .await
-                };
-
-                let mut message_to_show: Option<(UiMessageKind, String)> = match command_result {
-                    Ok(message) if !message.is_empty() => {
-                        Some((classify_message(&message), message))
-                    }
-                    Ok(_) => None,
-                    Err(e) => {
-                        self.ui_state.set_error_message(e.to_string());
-                        None
-                    }
-                };
-
-                if let Some(action) = self.command_processor.take_pending_action() {
-                    if let Some(action_message) = self.apply_command_action(action) {
-                        message_to_show = Some(action_message);
-                    }
-                }
-
-                if let Some((kind, message)) = message_to_show {
-                    match kind {
-                        UiMessageKind::Info => self.ui_state.set_info_message(message),
-                        UiMessageKind::Success => self.ui_state.set_success_message(message),
-                        UiMessageKind::Warning => self.ui_state.set_warning_message(message),
-                    }
-                }
-
-                self.refresh_current_buffer_metadata();
+                self.execute_command_line(&command).await;
                 self.ui_state.enter_normal_mode();
                 self.ui_state.clear_command_buffer();
             }
             KeyCode::Esc => {
+                self.replace_prompt = None;
                 self.ui_state.clear_command_buffer();
                 self.ui_state.enter_normal_mode();
                 self.ui_state
@@ -759,19 +1152,8 @@ impl App {
                 // Navigate command history down
                 self.ui_state.history_down();
             }
-            KeyCode::Tab => {
-                // Command completion
-                let current = self.ui_state.get_command_buffer();
-                let suggestions = self.ui_state.get_command_suggestions(current);
-                match suggestions.len() {
-                    0 => {}
-                    1 => self.ui_state.set_command_buffer(suggestions[0].clone()),
-                    _ => {
-                        let msg = format!("候補: {}", suggestions.join(", "));
-                        self.ui_state.set_info_message(msg);
-                    }
-                }
-            }
+            KeyCode::Tab => self.complete_command_line(true),
+            KeyCode::BackTab => self.complete_command_line(false),
             KeyCode::Char(c) => {
                 self.ui_state.push_to_command_buffer(c);
             }
@@ -783,62 +1165,1521 @@ impl App {
         Ok(())
     }
 
-    fn handle_help_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.ui_state.hide_help();
+    /// Enter in the Replace prompt: take the text to find and ask for the
+    /// replacement, or replace every match. An empty search cancels.
+    fn advance_replace_prompt(&mut self) {
+        let text = self.ui_state.get_command_buffer().to_string();
+        self.ui_state.clear_command_buffer();
+        match self.replace_prompt.take() {
+            Some(ReplacePrompt::Search) if !text.is_empty() => {
+                self.ui_state
+                    .set_command_prompt(&format!("置換後 ({}): ", text));
+                self.replace_prompt = Some(ReplacePrompt::With(text));
             }
-            KeyCode::Esc => {
-                self.ui_state.hide_help();
+            Some(ReplacePrompt::With(from)) => {
+                self.ui_state.enter_normal_mode();
+                match self.get_current_editor_mut().replace_all(&from, &text) {
+                    0 => self
+                        .ui_state
+                        .set_error_message(format!("見つかりません: {}", from)),
+                    count => self
+                        .ui_state
+                        .set_success_message(format!("{} 箇所を置換しました", count)),
+                }
             }
-            _ => {}
+            _ => self.ui_state.enter_normal_mode(),
         }
-        Ok(())
     }
 
-    // Helper methods for main.rs
-
-    pub fn set_mode(&mut self, mode: Mode) {
-        self.ui_state.set_mode(mode);
+    /// Options of a buffer: the global config with its `:setlocal` values.
+    pub fn editor_options(&self, buffer_index: usize) -> EditorOptions {
+        let local = self.buffer_manager.buffers[buffer_index]
+            .content
+            .local_options();
+        EditorOptions::resolve(&self.config, local)
     }
 
-    pub fn update_status(&mut self) {
-        self.ui_state.update_status();
+    /// Push the resolved options into every buffer's editor.
+    fn apply_editor_options(&mut self) {
+        for buffer in &mut self.buffer_manager.buffers {
+            let options = EditorOptions::resolve(&self.config, buffer.content.local_options());
+            buffer.content.apply_options(&options);
+        }
     }
 
-    // Handle save prompt responses
-    async fn handle_save_prompt_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                let save_result = {
-                    let current_editor = &mut self.buffer_manager.get_current_mut().content;
-                    self.file_manager.save_file(current_editor).await
-                };
-
-                if let Err(e) = save_result {
-                    self.ui_state
-                        .set_error_message(format!("保存中にエラーが発生しました: {}", e));
-                    self.ui_state.enter_normal_mode();
-                } else {
-                    self.refresh_current_buffer_metadata();
-                    self.ui_state.quit();
+    /// Complete the word before the cursor on the command line. A single
+    /// candidate is inserted directly; several open the wildmenu, and
+    /// further presses cycle through it.
+    fn complete_command_line(&mut self, forward: bool) {
+        if let Some(menu) = self.wildmenu.as_mut() {
+            menu.cycle(forward);
+        } else {
+            let line = self.ui_state.get_command_buffer().to_string();
+            let Some((start, candidates)) = self.completion_candidates(&line) else {
+                return;
+            };
+            match candidates.len() {
+                0 => return,
+                1 => {
+                    self.ui_state.set_command_buffer(format!(
+                        "{}{}",
+                        &line[..start],
+                        candidates[0]
+                    ));
+                    return;
+                }
+                _ => {
+                    let mut menu = Wildmenu::new(start, candidates);
+                    if !forward {
+                        menu.cycle(false);
+                    }
+                    self.wildmenu = Some(menu);
                 }
             }
-            KeyCode::Char('n') | KeyCode::Char('N') => {
-                self.ui_state.quit();
-            }
-            KeyCode::Char('c') | KeyCode::Char('C') | KeyCode::Esc => {
-                self.ui_state.enter_normal_mode();
-                self.ui_state.status_message.clear();
-            }
-            _ => {}
         }
-        Ok(())
+        if let Some(menu) = &self.wildmenu {
+            let line = self.ui_state.get_command_buffer();
+            let text = format!("{}{}", &line[..menu.start], menu.current());
+            self.ui_state.set_command_buffer(text);
+        }
     }
 
-    // Macro recording methods
-    fn start_macro_recording(&mut self, register: char) {
-        self.macro_recording = true;
+    /// Start of the word being completed and the candidates for it.
+    fn completion_candidates(&self, line: &str) -> Option<(usize, Vec<String>)> {
+        let (start, kind) = completion::context(line)?;
+        let prefix = &line[start..];
+        let candidates = match kind {
+            CompletionKind::Command if start == 0 => self.ui_state.get_command_suggestions(prefix),
+            CompletionKind::Command => completion::filter(
+                COMMANDS
+                    .iter()
+                    .flat_map(|spec| std::iter::once(&spec.name).chain(spec.aliases))
+                    .copied(),
+                prefix,
+            ),
+            CompletionKind::File => completion::complete_path(prefix),
+            CompletionKind::Session => {
+                completion::filter(self.command_processor.session_names(), prefix)
+            }
+            CompletionKind::SetOption => {
+                completion::filter(OPTIONS.iter().map(|spec| spec.name), prefix)
+            }
+            CompletionKind::SetValue(option) => match lookup_option(&option).map(|s| s.name) {
+                Some("theme") => completion::filter(crate::highlight::theme_names(), prefix),
+                Some("keybindings") => completion::filter(
+                    [
+                        KeybindingStyle::Vim,
+                        KeybindingStyle::Emacs,
+                        KeybindingStyle::Nano,
+                    ]
+                    .map(KeybindingStyle::name),
+                    prefix,
+                ),
+                Some("fileencoding") => {
+                    completion::filter(Encoding::ALL.map(Encoding::name), prefix)
+                }
+                Some("fileformat") => {
+                    completion::filter(LineEnding::ALL.map(LineEnding::name), prefix)
+                }
+                _ => Vec::new(),
+            },
+            CompletionKind::Buffer => completion::filter(
+                self.buffer_manager.buffers().iter().filter_map(|buffer| {
+                    Some(
+                        buffer
+                            .file_path
+                            .as_ref()?
+                            .file_name()?
+                            .to_str()?
+                            .to_string(),
+                    )
+                }),
+                prefix,
+            ),
+            CompletionKind::Picker => {
+                completion::filter(completion::PICKER_NAMES.iter().copied(), prefix)
+            }
+            CompletionKind::Action => {
+                completion::filter(Action::all().map(|action| action.name()), prefix)
+            }
+        };
+        Some((start, candidates))
+    }
+
+    /// Execute an ex command and show its result. Buffer and window
+    /// operations requested by the command are applied here.
+    async fn execute_command_line(&mut self, command: &str) {
+        // Errors are already shown in the status bar
+        let _ = self.run_ex_command(command).await;
+    }
+
+    /// [`Self::execute_command_line`], also returning the command's error
+    /// for callers without a status bar, such as the headless mode.
+    pub async fn run_ex_command(&mut self, command: &str) -> Result<()> {
+        let previous_path = self.file_manager.get_current_path().cloned();
+        let command_result = {
+            let current_editor = &mut self.buffer_manager.get_current_mut().content;
+            self.command_processor
+                .execute_command(
+                    command,
+                    current_editor,
+                    &mut self.file_manager,
+                    &mut self.config,
+                    &mut self.ui_state.should_quit,
+                )
+                .await
+        };
+
+        let (mut message_to_show, mut result) = match command_result {
+            Ok(message) if !message.is_empty() => {
+                (Some((classify_message(&message), message)), Ok(()))
+            }
+            Ok(_) => (None, Ok(())),
+            Err(e) => {
+                self.ui_state.set_error_message(e.to_string());
+                (None, Err(e))
+            }
+        };
+
+        match self.command_processor.take_pending_action() {
+            // Boxed because the keys may run `:normal` again
+            Some(CommandAction::Normal(keys)) => {
+                result = Box::pin(self.run_normal_keys(keys)).await
+            }
+            Some(action) => match self.apply_command_action(action) {
+                Ok(Some(action_message)) => message_to_show = Some(action_message),
+                Ok(None) => {}
+                Err(e) => {
+                    self.ui_state.set_error_message(e.to_string());
+                    result = Err(e);
+                }
+            },
+            None => {}
+        }
+        self.check_found_swap();
+
+        // Results longer than one line go to the pager instead of the status bar
+        if let Some((_, message)) = message_to_show
+            .as_ref()
+            .filter(|(_, message)| message.contains('\n'))
+        {
+            let title = command.split_whitespace().next().unwrap_or_default();
+            self.pager = Some(Pager::from_text(format!(":{}", title), message));
+            message_to_show = None;
+        }
+
+        if let Some((kind, message)) = message_to_show {
+            match kind {
+                UiMessageKind::Info => self.ui_state.set_info_message(message),
+                UiMessageKind::Success => self.ui_state.set_success_message(message),
+                UiMessageKind::Warning => self.ui_state.set_warning_message(message),
+            }
+        }
+
+        if self.keymap.style() != self.config.keybindings {
+            self.reload_keymap();
+        }
+        // `:set` may have changed options every buffer depends on
+        self.apply_editor_options();
+        self.refresh_current_buffer_metadata();
+
+        // `:e` and `:w <path>` count as opening a file
+        if let Some(path) = self.file_manager.get_current_path().cloned() {
+            if previous_path.as_ref() != Some(&path) {
+                self.recent_files.add(&path);
+            }
+        }
+        result
+    }
+
+    /// `:normal`: press `keys` in normal mode. An insert or visual mode they
+    /// leave open is ended as with Esc.
+    async fn run_normal_keys(&mut self, keys: Vec<KeyChord>) -> Result<()> {
+        self.ui_state.enter_normal_mode();
+        for chord in keys {
+            self.handle_key_event(KeyEvent::new(chord.code, chord.modifiers))
+                .await?;
+        }
+        let steps = self.keymap.flush();
+        self.run_key_steps(steps);
+        self.run_pending_commands().await;
+        if !matches!(self.ui_state.get_mode(), Mode::Normal | Mode::Command) {
+            self.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Show the message history in the pager, newest last.
+    pub fn open_messages(&mut self) {
+        let lines: Vec<Line<'static>> = self
+            .ui_state
+            .status_manager
+            .history()
+            .flat_map(|message| {
+                let (label, color) = match message.message_type {
+                    MessageType::Info => ("情報", Color::Reset),
+                    MessageType::Success => ("成功", Color::Green),
+                    MessageType::Warning => ("警告", Color::Yellow),
+                    MessageType::Error => ("エラー", Color::Red),
+                };
+                let time = message.time.format("%H:%M:%S").to_string();
+                let style = Style::default().fg(color);
+                // Continuation lines of a multi-line message are indented
+                message
+                    .content
+                    .lines()
+                    .enumerate()
+                    .map(|(i, text)| {
+                        let head = if i == 0 {
+                            format!("{} {:<6} ", time, label)
+                        } else {
+                            " ".repeat(16)
+                        };
+                        Line::from(vec![
+                            Span::styled(head, Style::default().fg(Color::DarkGray)),
+                            Span::styled(text.to_string(), style),
+                        ])
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if lines.is_empty() {
+            self.ui_state
+                .set_info_message("メッセージはありません".to_string());
+            return;
+        }
+        let mut pager = Pager::new(":messages", lines);
+        pager.scroll(isize::MAX);
+        self.pager = Some(pager);
+    }
+
+    /// Open the fuzzy finder listing `kind`, with `query` already typed.
+    pub fn open_picker(&mut self, kind: PickerKind, query: &str) {
+        let items: Vec<PickerItem> = match kind {
+            PickerKind::Files => picker::file_items(Path::new(".")),
+            PickerKind::Buffers => self
+                .buffer_manager
+                .buffers()
+                .iter()
+                .map(|buffer| {
+                    let name = buffer
+                        .file_path
+                        .as_ref()
+                        .map_or_else(|| "[No Name]".to_string(), |p| p.display().to_string());
+                    let modified = if buffer.content.is_modified() {
+                        " [+]"
+                    } else {
+                        ""
+                    };
+                    PickerItem {
+                        label: format!("{}: {}{}", buffer.id, name, modified),
+                        target: PickerTarget::Buffer(buffer.id),
+                    }
+                })
+                .collect(),
+            PickerKind::RecentFiles => {
+                let cwd = std::env::current_dir().unwrap_or_default();
+                self.recent_files
+                    .paths()
+                    .iter()
+                    .filter(|path| path.exists())
+                    .map(|path| {
+                        let shown = path.strip_prefix(&cwd).unwrap_or(path);
+                        PickerItem {
+                            label: shown.display().to_string(),
+                            target: PickerTarget::File(path.clone()),
+                        }
+                    })
+                    .collect()
+            }
+            PickerKind::Headings => {
+                let content = self.get_current_editor().get_content();
+                let lines: Vec<&str> = content.split_inclusive('\n').collect();
+                picker::heading_items(&lines)
+            }
+            PickerKind::Commands => picker::command_items(&self.keymap),
+        };
+
+        if items.is_empty() {
+            self.ui_state
+                .set_warning_message(format!("{}の候補がありません", kind.title()));
+            return;
+        }
+        self.picker = Some(Picker::new(kind, items, query));
+    }
+
+    async fn handle_picker_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(picker) = self.picker.as_mut() else {
+            return Ok(());
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let split = match key.code {
+            KeyCode::Char('x') if ctrl => Some(WindowSplitKind::Horizontal),
+            KeyCode::Char('v') if ctrl => Some(WindowSplitKind::Vertical),
+            KeyCode::Enter => Some(WindowSplitKind::None),
+            _ => None,
+        };
+
+        match key.code {
+            _ if split.is_some() => {
+                let target = picker.selected_item().map(|item| item.target.clone());
+                self.picker = None;
+                if let (Some(target), Some(split)) = (target, split) {
+                    self.open_picker_target(target, split).await;
+                }
+            }
+            KeyCode::Esc => self.picker = None,
+            KeyCode::Char('g' | 'c') if ctrl => self.picker = None,
+            KeyCode::Up | KeyCode::BackTab => picker.move_selection(-1),
+            KeyCode::Down | KeyCode::Tab => picker.move_selection(1),
+            KeyCode::Char('p') if ctrl => picker.move_selection(-1),
+            KeyCode::Char('n') if ctrl => picker.move_selection(1),
+            KeyCode::PageUp => picker.move_selection(-10),
+            KeyCode::PageDown => picker.move_selection(10),
+            KeyCode::Char('u') if ctrl => picker.clear_query(),
+            KeyCode::Backspace => picker.pop_char(),
+            KeyCode::Char(c) if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) => {
+                picker.push_char(c)
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Open a picker selection, in a new split window unless `split` is
+    /// `WindowSplitKind::None`.
+    async fn open_picker_target(&mut self, target: PickerTarget, split: WindowSplitKind) {
+        let opens_text = !matches!(target, PickerTarget::Command(_) | PickerTarget::Action(_));
+        if split != WindowSplitKind::None && opens_text {
+            let buffer_id = self.buffer_manager.current_buffer_id();
+            match split {
+                WindowSplitKind::Vertical => self.window_manager.split_vertical(buffer_id),
+                _ => self.window_manager.split_horizontal(buffer_id),
+            }
+            self.focus_next_window();
+        }
+
+        match target {
+            PickerTarget::File(path) => match self.open_file_in_buffer(path).await {
+                Ok(message) if self.file_manager.format.mixed_endings => {
+                    self.ui_state.set_warning_message(message)
+                }
+                Ok(message) => self.ui_state.set_success_message(message),
+                Err(e) => self.ui_state.set_error_message(e.to_string()),
+            },
+            PickerTarget::Buffer(id) => {
+                if self.buffer_manager.set_current_by_id(id) {
+                    self.handle_buffer_switch("");
+                }
+            }
+            PickerTarget::Line(line) => {
+                let editor = self.get_current_editor_mut();
+                editor.set_cursor_position(line, 0);
+                editor.open_fold();
+            }
+            // Commands that need arguments open the command line instead
+            PickerTarget::Command(name) => match command_processor::lookup_command(name) {
+                Some(spec) if spec.takes_required_args() => {
+                    self.prompt_command(&format!("{} ", name))
+                }
+                _ => {
+                    self.ui_state.add_to_history(name.to_string());
+                    self.execute_command_line(name).await;
+                }
+            },
+            PickerTarget::Action(action) => {
+                self.run_action(action.clone());
+                self.last_action = Some(action);
+                self.run_pending_commands().await;
+            }
+        }
+    }
+
+    /// Open `path` in its own buffer, or switch to the buffer already
+    /// showing it. An empty unnamed buffer is reused.
+    pub async fn open_file_in_buffer(&mut self, path: PathBuf) -> Result<String> {
+        let canonical = std::fs::canonicalize(&path).ok();
+        let existing = self.buffer_manager.buffers().iter().find(|buffer| {
+            canonical.is_some()
+                && buffer
+                    .file_path
+                    .as_ref()
+                    .and_then(|p| std::fs::canonicalize(p).ok())
+                    == canonical
+        });
+        if let Some(id) = existing.map(|buffer| buffer.id) {
+            self.buffer_manager.set_current_by_id(id);
+            self.handle_buffer_switch("");
+            self.recent_files.add(&path);
+            return Ok(format!("バッファを切り替えました: {}", path.display()));
+        }
+
+        let content = self.file_manager.open_file(path.clone()).await?;
+        let current = self.buffer_manager.get_current();
+        let reusable = current.file_path.is_none()
+            && !current.content.is_modified()
+            && current.content.get_content().is_empty();
+        if !reusable {
+            self.autosave.buffer_switched();
+            let index = self.buffer_manager.create_buffer();
+            self.buffer_manager.current_buffer = index;
+            self.apply_editor_options();
+        }
+        let format = self.file_manager.format;
+        let large_file = self.file_manager.large_file;
+        self.get_current_editor_mut()
+            .load_file(content.clone(), format, large_file);
+        let buffer_id = self.buffer_manager.current_buffer_id();
+        self.window_manager.set_buffer_for_current(buffer_id);
+        self.refresh_current_buffer_metadata();
+        self.recent_files.add(&path);
+
+        #[cfg(feature = "lsp")]
+        if !large_file {
+            self.notify_lsp_document_opened(&path, &content).await;
+        }
+
+        self.check_found_swap();
+        let mut message = format!("ファイルを開きました: {}", path.display());
+        if large_file {
+            message.push_str(&format!(" ({})", LARGE_FILE_NOTE));
+        }
+        if let Some(warning) = format.mixed_endings_warning() {
+            message.push_str(&format!(" ({})", warning));
+        }
+        Ok(message)
+    }
+
+    /// Stop talking to language servers, for runs without a terminal.
+    pub fn disable_lsp(&mut self) {
+        #[cfg(feature = "lsp")]
+        {
+            self.lsp_plugin = None;
+        }
+    }
+
+    /// Put text read from standard input (`-`) into an unnamed buffer. An
+    /// empty unnamed buffer is reused.
+    pub fn open_stdin_in_buffer(&mut self, content: String, format: FileFormat) -> String {
+        let current = self.buffer_manager.get_current();
+        let reusable = current.file_path.is_none()
+            && !current.content.is_modified()
+            && current.content.get_content().is_empty();
+        if !reusable {
+            self.autosave.buffer_switched();
+            let index = self.buffer_manager.create_buffer();
+            self.buffer_manager.current_buffer = index;
+            self.apply_editor_options();
+        }
+        let threshold = self.file_manager.large_file_threshold;
+        let large_file = threshold > 0 && content.len() as u64 >= threshold;
+        self.get_current_editor_mut()
+            .load_file(content, format, large_file);
+        let buffer_id = self.buffer_manager.current_buffer_id();
+        self.window_manager.set_buffer_for_current(buffer_id);
+        self.sync_file_manager_from_buffer();
+        format!(
+            "標準入力から {} 行を読み込みました",
+            self.get_current_editor().line_count()
+        )
+    }
+
+    /// Open the files and directories named on the command line, then apply
+    /// `-R`, `-o`/`-O`, `+N`/`+/pattern` and `-S`. The cursor ends up in the
+    /// first file; a directory opens in the explorer. Returns the errors of
+    /// files that could not be read and of `+/pattern` and `-S`, which are
+    /// also shown as messages.
+    pub async fn open_command_line(&mut self, args: &Args) -> Vec<String> {
+        let mut opened = Vec::new();
+        let mut errors = Vec::new();
+        for path in &args.files {
+            if cli::is_stdin(path) {
+                match file_format::read_stdin() {
+                    Ok((content, format)) => {
+                        let message = self.open_stdin_in_buffer(content, format);
+                        self.ui_state.set_info_message(message);
+                        self.buffer_manager.get_current_mut().readonly = args.readonly;
+                        self.sync_file_manager_from_buffer();
+                        opened.push(self.buffer_manager.current_buffer_id());
+                    }
+                    Err(e) => {
+                        let error = format!("標準入力の読み込みに失敗しました: {}", e);
+                        self.ui_state.set_error_message(error.clone());
+                        errors.push(error);
+                    }
+                }
+                continue;
+            }
+            if path.is_dir() {
+                let mut explorer = Explorer::new(path.clone());
+                explorer.focused = true;
+                self.explorer = Some(explorer);
+                continue;
+            }
+            match self.open_file_in_buffer(path.clone()).await {
+                Ok(message) => {
+                    log::info!("Loaded file from command line: {}", path.display());
+                    if args.readonly {
+                        self.buffer_manager.get_current_mut().readonly = true;
+                        self.sync_file_manager_from_buffer();
+                    }
+                    if args.recover && opened.is_empty() {
+                        // The swap file is checked once the file is read
+                        errors.extend(self.finish_file_loads(true).await);
+                        self.swap_prompt = None;
+                        self.ui_state.enter_normal_mode();
+                        match self.recover_current() {
+                            Ok(message) => self.ui_state.set_success_message(message),
+                            Err(e) => self.ui_state.set_error_message(e.to_string()),
+                        }
+                    } else if self.swap_prompt.is_some() {
+                        // Keep the swap file question on screen
+                    } else if self.file_manager.format.mixed_endings {
+                        self.ui_state.set_warning_message(message);
+                    } else {
+                        self.ui_state.set_info_message(message);
+                    }
+                    opened.push(self.buffer_manager.current_buffer_id());
+                }
+                Err(e) => {
+                    log::error!("Failed to load file '{}': {}", path.display(), e);
+                    let error = format!("ファイル読み込みエラー: {}", e);
+                    self.ui_state.set_error_message(error.clone());
+                    errors.push(error);
+                }
+            }
+        }
+
+        if let Some(&first) = opened.first() {
+            if self.buffer_manager.set_current_by_id(first) {
+                self.handle_buffer_switch("");
+            }
+            if let Some(kind) = args.split.filter(|_| opened.len() > 1) {
+                self.window_manager.tile(&opened, kind);
+            }
+            // The start position may be past the lines read so far
+            if args.position.is_some() {
+                errors.extend(self.finish_file_loads(true).await);
+            }
+            match &args.position {
+                Some(StartPosition::Line(line)) => {
+                    let editor = self.get_current_editor_mut();
+                    editor.set_cursor_position(line.saturating_sub(1), 0);
+                    editor.open_fold();
+                }
+                Some(StartPosition::Last) => {
+                    let editor = self.get_current_editor_mut();
+                    editor.move_to_buffer_end();
+                    editor.open_fold();
+                }
+                Some(StartPosition::Pattern(pattern)) => {
+                    if let Err(e) = self.run_ex_command(&format!("search {}", pattern)).await {
+                        errors.push(format!("+/{}: {}", pattern, e));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        if let Some(session) = &args.session {
+            if let Err(e) = self.run_ex_command(&format!("source {}", session)).await {
+                errors.push(format!("-S {}: {}", session, e));
+            }
+        }
+        errors
+    }
+
+    /// `:Explore`: open the explorer, move focus to it, or close it when it
+    /// already has focus.
+    pub fn toggle_explorer(&mut self) {
+        match self.explorer.as_mut() {
+            Some(explorer) if explorer.focused => self.explorer = None,
+            Some(explorer) => explorer.focused = true,
+            None => {
+                let mut explorer = Explorer::new(PathBuf::from("."));
+                if let Some(path) = self.file_path() {
+                    explorer.reveal(path.strip_prefix("./").unwrap_or(path));
+                }
+                self.explorer = Some(explorer);
+            }
+        }
+    }
+
+    async fn handle_explorer_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(explorer) = self.explorer.as_mut() else {
+            return Ok(());
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let split = match key.code {
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => Some(WindowSplitKind::None),
+            KeyCode::Char('o') => Some(WindowSplitKind::Horizontal),
+            KeyCode::Char('v') => Some(WindowSplitKind::Vertical),
+            _ => None,
+        };
+
+        match key.code {
+            _ if split.is_some() => {
+                let Some(entry) = explorer.selected_entry() else {
+                    return Ok(());
+                };
+                if entry.is_dir {
+                    explorer.toggle_selected();
+                } else if let Some(split) = split {
+                    let path = explorer.full_path(&entry.path);
+                    explorer.focused = false;
+                    self.open_picker_target(PickerTarget::File(path), split)
+                        .await;
+                }
+            }
+            KeyCode::Char('n') if ctrl => explorer.move_selection(1),
+            KeyCode::Char('p') if ctrl => explorer.move_selection(-1),
+            KeyCode::Char('l') if ctrl => {
+                explorer.refresh();
+                self.ui_state
+                    .set_info_message("エクスプローラーを更新しました".to_string());
+            }
+            KeyCode::Down | KeyCode::Char('j') => explorer.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => explorer.move_selection(-1),
+            KeyCode::PageDown => explorer.move_selection(10),
+            KeyCode::PageUp => explorer.move_selection(-10),
+            KeyCode::Left | KeyCode::Char('h') => explorer.collapse_or_parent(),
+            KeyCode::Char('%') => self.start_explorer_prompt(InputKind::NewFile),
+            KeyCode::Char('d') => self.start_explorer_prompt(InputKind::NewDir),
+            KeyCode::Char('R') => self.start_explorer_prompt(InputKind::Rename),
+            KeyCode::Char('m') => self.start_explorer_prompt(InputKind::Move),
+            KeyCode::Char('D') => {
+                if let Some(prompt) = ExplorerPrompt::delete(explorer) {
+                    self.show_explorer_prompt(prompt);
+                }
+            }
+            KeyCode::Char('q') => self.explorer = None,
+            KeyCode::Tab | KeyCode::Esc => explorer.focused = false,
+            KeyCode::Char(':') => self.ui_state.enter_command_mode(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn start_explorer_prompt(&mut self, kind: InputKind) {
+        let prompt = self
+            .explorer
+            .as_ref()
+            .and_then(|explorer| ExplorerPrompt::input(kind, explorer));
+        if let Some(prompt) = prompt {
+            self.show_explorer_prompt(prompt);
+        }
+    }
+
+    fn show_explorer_prompt(&mut self, prompt: ExplorerPrompt) {
+        self.ui_state.set_prompt_message(prompt.message());
+        self.ui_state.set_mode(Mode::SavePrompt);
+        self.explorer_prompt = Some(prompt);
+    }
+
+    fn close_explorer_prompt(&mut self) {
+        self.explorer_prompt = None;
+        self.ui_state.enter_normal_mode();
+    }
+
+    /// Keys of the explorer prompt: a path to edit, then y/n to confirm.
+    async fn handle_explorer_prompt_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.explorer_prompt.as_mut() else {
+            return;
+        };
+        let cancel = key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('g') && key.modifiers.contains(KeyModifiers::CONTROL));
+        if cancel {
+            self.close_explorer_prompt();
+            self.ui_state
+                .set_info_message("キャンセルしました".to_string());
+            return;
+        }
+
+        match prompt {
+            ExplorerPrompt::Input { text, .. } => match key.code {
+                KeyCode::Enter => match prompt.operation() {
+                    Some(operation) => {
+                        self.show_explorer_prompt(ExplorerPrompt::Confirm(operation));
+                    }
+                    None => {
+                        self.close_explorer_prompt();
+                        self.ui_state
+                            .set_info_message("キャンセルしました".to_string());
+                    }
+                },
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    text.push(c);
+                }
+                _ => {}
+            },
+            ExplorerPrompt::Confirm(operation) => match key.code {
+                KeyCode::Char('y' | 'Y') => {
+                    let operation = operation.clone();
+                    self.close_explorer_prompt();
+                    self.apply_file_operation(operation);
+                }
+                KeyCode::Char('n' | 'N' | 'c' | 'C') => {
+                    self.close_explorer_prompt();
+                    self.ui_state
+                        .set_info_message("キャンセルしました".to_string());
+                }
+                _ => {}
+            },
+        }
+        if let Some(prompt) = &self.explorer_prompt {
+            self.ui_state.set_prompt_message(prompt.message());
+        }
+    }
+
+    fn apply_file_operation(&mut self, operation: FileOperation) {
+        let Some(explorer) = self.explorer.as_ref() else {
+            return;
+        };
+        let root = explorer.root().to_path_buf();
+        // Buffers showing the renamed file, or a file inside a renamed folder
+        let mut moved_buffers = Vec::new();
+        if let FileOperation::Rename { from, to } | FileOperation::Move { from, to } = &operation {
+            if let Ok(from) = std::fs::canonicalize(root.join(from)) {
+                let to = explorer.full_path(to);
+                for buffer in self.buffer_manager.buffers() {
+                    let Some(path) = buffer.file_path.as_ref() else {
+                        continue;
+                    };
+                    let Ok(path) = std::fs::canonicalize(path) else {
+                        continue;
+                    };
+                    if let Ok(rest) = path.strip_prefix(&from) {
+                        let path = if rest.as_os_str().is_empty() {
+                            to.clone()
+                        } else {
+                            to.join(rest)
+                        };
+                        moved_buffers.push((buffer.id, path));
+                    }
+                }
+            }
+        }
+
+        match operation.apply(&root) {
+            Ok(message) => {
+                for (id, path) in moved_buffers {
+                    if let Some(index) = self.buffer_manager.find_index_by_id(id) {
+                        self.buffer_manager.buffers[index].file_path = Some(path);
+                    }
+                }
+                self.sync_file_manager_from_buffer();
+                if let Some(explorer) = self.explorer.as_mut() {
+                    explorer.refresh();
+                    if let Some(path) = operation.result_path() {
+                        explorer.reveal(path);
+                    }
+                }
+                self.ui_state.set_success_message(message);
+            }
+            Err(e) => self
+                .ui_state
+                .set_error_message(format!("ファイル操作に失敗しました: {}", e)),
+        }
+    }
+
+    fn handle_help_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.ui_state.hide_help();
+            }
+            KeyCode::Esc => {
+                self.ui_state.hide_help();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Handle a mouse event using the layout recorded by the last draw.
+    pub fn handle_mouse_event(&mut self, event: MouseEvent) -> Result<()> {
+        if !self.config.editor.mouse {
+            return Ok(());
+        }
+
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.handle_mouse_click(event.column, event.row)
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                self.handle_mouse_drag(event.column, event.row)
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.mouse_state.drag_window = None,
+            MouseEventKind::ScrollUp => {
+                self.handle_mouse_scroll(event.column, event.row, -WHEEL_SCROLL_LINES)
+            }
+            MouseEventKind::ScrollDown => {
+                self.handle_mouse_scroll(event.column, event.row, WHEEL_SCROLL_LINES)
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn is_editing_mode(&self) -> bool {
+        matches!(
+            self.ui_state.get_mode(),
+            Mode::Normal | Mode::Insert | Mode::Visual | Mode::VisualBlock | Mode::Replace
+        )
+    }
+
+    fn handle_mouse_click(&mut self, column: u16, row: u16) {
+        if let Some(action) = self.screen_layout.target_at(column, row) {
+            self.apply_click_action(action);
+            return;
+        }
+
+        if !self.is_editing_mode() {
+            return;
+        }
+
+        let Some(pane) = self.screen_layout.pane_at(column, row).copied() else {
+            return;
+        };
+        let Some((screen_row, display_col)) = pane.screen_position(column, row) else {
+            return;
+        };
+        if let Some(explorer) = self.explorer.as_mut() {
+            explorer.focused = false;
+        }
+        self.focus_window(pane.window_id);
+
+        let is_double_click = self.mouse_state.register_click(column, row);
+        self.get_current_editor_mut()
+            .set_cursor_screen_position(screen_row, display_col);
+
+        if is_double_click {
+            self.mouse_state.drag_window = None;
+            if self.get_current_editor_mut().select_word_at_cursor() {
+                self.ui_state.enter_visual_mode();
+            }
+        } else {
+            if matches!(self.ui_state.get_mode(), Mode::Visual | Mode::VisualBlock) {
+                self.get_current_editor_mut().clear_visual_selection();
+                self.ui_state.enter_normal_mode();
+            }
+            self.mouse_state.drag_window = Some(pane.window_id);
+        }
+    }
+
+    fn handle_mouse_drag(&mut self, column: u16, row: u16) {
+        let Some(window_id) = self.mouse_state.drag_window else {
+            return;
+        };
+        let Some(pane) = self.screen_layout.pane_for_window(window_id).copied() else {
+            return;
+        };
+        let Some((screen_row, display_col)) = pane.screen_position(column, row) else {
+            return;
+        };
+
+        if !matches!(self.ui_state.get_mode(), Mode::Visual | Mode::VisualBlock) {
+            self.get_current_editor_mut().start_visual_selection();
+            self.ui_state.enter_visual_mode();
+        }
+
+        // Dragging past the top or bottom edge scrolls one line at a time
+        let editor = self.get_current_editor_mut();
+        if row < pane.text_area.y {
+            editor.scroll_viewport(-1);
+        } else if row >= pane.text_area.y + pane.text_area.height {
+            editor.scroll_viewport(1);
+        }
+        editor.set_cursor_screen_position(screen_row, display_col);
+    }
+
+    fn handle_mouse_scroll(&mut self, column: u16, row: u16, delta: isize) {
+        let buffer_id = match self.screen_layout.pane_at(column, row) {
+            Some(pane) => pane.buffer_id,
+            None => self.buffer_manager.current_buffer_id(),
+        };
+        if let Some(index) = self.buffer_manager.find_index_by_id(buffer_id) {
+            self.buffer_manager.buffers[index]
+                .content
+                .scroll_viewport(delta);
+        }
+    }
+
+    /// Make `window_id` the active window and its buffer the current buffer.
+    fn focus_window(&mut self, window_id: usize) {
+        if self.window_manager.current_window_id == window_id {
+            return;
+        }
+        let Some(buffer_id) = self.window_manager.buffer_for_window(window_id) else {
+            return;
+        };
+
+        if matches!(self.ui_state.get_mode(), Mode::Visual | Mode::VisualBlock) {
+            self.get_current_editor_mut().clear_visual_selection();
+            self.ui_state.enter_normal_mode();
+        }
+        self.window_manager.current_window_id = window_id;
+        if buffer_id != self.buffer_manager.current_buffer_id() {
+            self.autosave.buffer_switched();
+        }
+        self.buffer_manager.set_current_by_id(buffer_id);
+        self.sync_file_manager_from_buffer();
+    }
+
+    fn apply_click_action(&mut self, action: ClickAction) {
+        if matches!(self.ui_state.get_mode(), Mode::SavePrompt | Mode::Command) {
+            return;
+        }
+
+        match action {
+            ClickAction::EnterCommandMode => self.ui_state.enter_command_mode(),
+            ClickAction::EnterInsertMode => self.ui_state.enter_insert_mode(),
+            ClickAction::StartSearch => self.ui_state.enter_search_mode(),
+            ClickAction::ToggleHelp => self.ui_state.toggle_help(),
+            ClickAction::Shortcut(index) => {
+                let shortcut = self.keymap.shortcuts().into_iter().flatten().nth(index);
+                if let Some(shortcut) = shortcut {
+                    self.run_action(shortcut.action.clone());
+                    self.last_action = Some(shortcut.action);
+                }
+            }
+            ClickAction::ExplorerEntry(index) => {
+                if let Some(explorer) = self.explorer.as_mut() {
+                    explorer.focused = true;
+                    explorer.select(index);
+                    explorer.toggle_selected();
+                }
+            }
+            ClickAction::SwitchBuffer(buffer_id) => {
+                if buffer_id != self.buffer_manager.current_buffer_id()
+                    && self.buffer_manager.set_current_by_id(buffer_id)
+                {
+                    if let Some((_, message)) =
+                        self.handle_buffer_switch("バッファを切り替えました")
+                    {
+                        self.ui_state.set_info_message(message);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Entries for the buffer tab line: `(buffer id, label, is current)`.
+    pub fn buffer_tabs(&self) -> Vec<(usize, String, bool)> {
+        let current_id = self.buffer_manager.current_buffer_id();
+        self.buffer_manager
+            .buffers()
+            .iter()
+            .map(|buffer| {
+                let name = buffer
+                    .file_path
+                    .as_ref()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("[No Name]");
+                let modified = if buffer.content.is_modified() {
+                    "+"
+                } else {
+                    ""
+                };
+                (
+                    buffer.id,
+                    format!(" {}:{}{} ", buffer.id, name, modified),
+                    buffer.id == current_id,
+                )
+            })
+            .collect()
+    }
+
+    // Helper methods for main.rs
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.ui_state.set_mode(mode);
+    }
+
+    pub fn update_status(&mut self) {
+        self.ui_state.update_status();
+        // The explorer prompt stays visible however long it takes to answer
+        if let Some(prompt) = &self.explorer_prompt {
+            self.ui_state.status_message = prompt.message();
+        }
+        if let Some(swap) = &self.swap_prompt {
+            self.ui_state.status_message = swap.prompt_message();
+        }
+        if let Some((path, _)) = &self.reload_prompt {
+            self.ui_state.status_message = reload_prompt_message(path);
+        }
+    }
+
+    /// Look for files changed on disk when the watcher saw something, or
+    /// on every poll without one.
+    pub fn check_external_changes(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .buffer_manager
+            .buffers()
+            .iter()
+            .filter_map(|buffer| buffer.file_path.clone())
+            .collect();
+        self.file_watcher.sync(paths.iter().map(PathBuf::as_path));
+        if self.file_watcher.due() {
+            self.recheck_disk();
+        }
+        if matches!(self.ui_state.get_mode(), Mode::SavePrompt) {
+            return;
+        }
+        let Some(path) = self.pending_reload.take() else {
+            return;
+        };
+        // Skip it if the buffer was closed, edited or reloaded meanwhile
+        let state = self.buffer_index_for_path(&path).and_then(|index| {
+            let buffer = &self.buffer_manager.buffers[index];
+            if buffer.content.is_modified() {
+                None
+            } else {
+                buffer.external.clone()
+            }
+        });
+        if let Some(state) = state {
+            self.show_reload_prompt(path, state);
+        }
+    }
+
+    /// Compare every open file with the version last read or written. A
+    /// clean buffer asks whether to reload; a modified one warns that `:w`
+    /// now needs `!`.
+    fn recheck_disk(&mut self) {
+        let mut changed_clean = None;
+        for buffer in &mut self.buffer_manager.buffers {
+            let (Some(path), Some(disk)) = (&buffer.file_path, &buffer.disk) else {
+                continue;
+            };
+            match disk.check(path) {
+                DiskChange::Unchanged => {}
+                DiskChange::Touched(state) => buffer.disk = Some(state),
+                DiskChange::Deleted => {
+                    buffer.disk = None;
+                    self.ui_state.set_warning_message(format!(
+                        "W13: ファイルがディスクから削除されました: {}",
+                        path.display()
+                    ));
+                }
+                DiskChange::Modified(state) if buffer.external.as_ref() != Some(&state) => {
+                    buffer.external = Some(state.clone());
+                    if buffer.content.is_modified() {
+                        self.ui_state.set_warning_message(format!(
+                            "W12: バッファとディスクの両方で {} が変更されています (:w! で上書き、:DiffOrig で差分を表示)",
+                            path.display()
+                        ));
+                    } else if changed_clean.is_none() {
+                        changed_clean = Some((path.clone(), state));
+                    }
+                }
+                DiskChange::Modified(_) => {}
+            }
+        }
+        let Some((path, state)) = changed_clean else {
+            return;
+        };
+        if matches!(self.ui_state.get_mode(), Mode::SavePrompt) {
+            self.pending_reload = Some(path);
+            return;
+        }
+        self.show_reload_prompt(path, state);
+    }
+
+    fn show_reload_prompt(&mut self, path: PathBuf, state: DiskState) {
+        self.ui_state
+            .set_warning_message(reload_prompt_message(&path));
+        self.ui_state.set_mode(Mode::SavePrompt);
+        self.reload_prompt = Some((path, state));
+    }
+
+    fn handle_reload_prompt_key(&mut self, key: KeyEvent) {
+        let Some((path, state)) = self.reload_prompt.clone() else {
+            return;
+        };
+        let Some(index) = self.buffer_index_for_path(&path) else {
+            self.reload_prompt = None;
+            self.ui_state.enter_normal_mode();
+            return;
+        };
+        match key.code {
+            KeyCode::Char('l' | 'L') => {
+                self.reload_prompt = None;
+                self.ui_state.enter_normal_mode();
+                match file_format::read_file(&path, None) {
+                    Ok((content, format)) => {
+                        let buffer = &mut self.buffer_manager.buffers[index];
+                        // Reloading can be undone like any other change
+                        buffer.content.replace_content(&content);
+                        buffer.content.set_file_format(format);
+                        buffer.content.mark_saved();
+                        buffer.disk = DiskState::read(&path);
+                        buffer.external = None;
+                        self.sync_file_manager_from_buffer();
+                        self.ui_state
+                            .set_info_message(format!("再読み込みしました: {}", path.display()));
+                    }
+                    Err(e) => self
+                        .ui_state
+                        .set_error_message(format!("再読み込みに失敗しました: {}", e)),
+                }
+            }
+            KeyCode::Char('k' | 'K') | KeyCode::Esc => {
+                self.reload_prompt = None;
+                self.ui_state.enter_normal_mode();
+                // Keeping the buffer means the next :w replaces the disk version
+                self.buffer_manager.buffers[index].disk = Some(state);
+                self.sync_file_manager_from_buffer();
+                self.ui_state
+                    .set_info_message("バッファの内容をそのまま残しました".to_string());
+            }
+            KeyCode::Char('d' | 'D') => match self.show_disk_diff(index) {
+                Ok(Some((_, message))) => self.ui_state.set_info_message(message),
+                Ok(None) => {}
+                Err(e) => self.ui_state.set_error_message(e.to_string()),
+            },
+            _ => {}
+        }
+    }
+
+    /// Show how the buffer at `index` differs from its file on disk.
+    fn show_disk_diff(&mut self, index: usize) -> Result<Option<(UiMessageKind, String)>> {
+        let buffer = &self.buffer_manager.buffers[index];
+        let path = buffer
+            .file_path
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("E32: ファイル名がありません"))?;
+        let encoding = buffer.content.file_format().encoding;
+        let (disk, _) = file_format::read_file(&path, Some(encoding))
+            .map_err(|e| anyhow::anyhow!("ファイルを読み込めません: {}", e))?;
+        let lines = file_watch::diff_view(&disk, &buffer.content.get_content());
+        if lines.is_empty() {
+            return Ok(Some((
+                UiMessageKind::Info,
+                "ディスク上のファイルと違いはありません".to_string(),
+            )));
+        }
+        self.pager = Some(Pager::new(format!(":DiffOrig {}", path.display()), lines));
+        Ok(None)
+    }
+
+    /// Ask about the swap file found by the last open, if any.
+    pub fn check_found_swap(&mut self) {
+        if let Some(swap) = self.file_manager.found_swap.take() {
+            self.ui_state.set_warning_message(swap.prompt_message());
+            self.ui_state.set_mode(Mode::SavePrompt);
+            self.swap_prompt = Some(swap);
+        }
+    }
+
+    fn handle_swap_prompt_key(&mut self, key: KeyEvent) {
+        let Some(swap) = self.swap_prompt.clone() else {
+            return;
+        };
+        let Some(index) = self.buffer_index_for_path(&swap.file) else {
+            self.swap_prompt = None;
+            self.ui_state.enter_normal_mode();
+            return;
+        };
+        match key.code {
+            KeyCode::Char('r' | 'R') => {
+                self.swap_prompt = None;
+                self.ui_state.enter_normal_mode();
+                self.buffer_manager.buffers[index]
+                    .content
+                    .replace_content(&swap.content);
+                self.ui_state.set_success_message(
+                    "スワップファイルから復旧しました。内容を確認して :w で保存してください"
+                        .to_string(),
+                );
+            }
+            KeyCode::Char('o' | 'O') => {
+                self.swap_prompt = None;
+                self.ui_state.enter_normal_mode();
+                self.buffer_manager.buffers[index].readonly = true;
+                self.sync_file_manager_from_buffer();
+                self.ui_state
+                    .set_info_message("読み取り専用で開きました".to_string());
+            }
+            KeyCode::Char('d' | 'D') => {
+                self.swap_prompt = None;
+                self.ui_state.enter_normal_mode();
+                if let Some(swaps) = &self.file_manager.swaps {
+                    swaps.remove(&swap.file);
+                }
+                self.ui_state
+                    .set_info_message("スワップファイルを削除しました".to_string());
+            }
+            KeyCode::Char('a' | 'A' | 'q' | 'Q') | KeyCode::Esc => {
+                self.swap_prompt = None;
+                self.ui_state.enter_normal_mode();
+                let id = self.buffer_manager.buffers[index].id;
+                self.buffer_manager.set_current_by_id(id);
+                let _ =
+                    self.apply_command_action(CommandAction::Buffer(BufferCommand::DeleteCurrent));
+                self.ui_state
+                    .set_info_message(format!("{} を開くのを中止しました", swap.file.display()));
+            }
+            _ => {}
+        }
+    }
+
+    fn buffer_index_for_path(&self, path: &Path) -> Option<usize> {
+        let canonical = std::fs::canonicalize(path).ok();
+        self.buffer_manager.buffers().iter().position(|buffer| {
+            buffer.file_path.as_deref() == Some(path)
+                || buffer
+                    .file_path
+                    .as_ref()
+                    .and_then(|p| std::fs::canonicalize(p).ok())
+                    .is_some_and(|p| Some(p) == canonical)
+        })
+    }
+
+    /// `:recover`: replace the current buffer with its swap file.
+    pub fn recover_current(&mut self) -> Result<String> {
+        self.file_manager.found_swap = None;
+        let path = self
+            .file_path()
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("E32: ファイル名がありません"))?;
+        let swap = self
+            .file_manager
+            .swaps
+            .as_ref()
+            .and_then(|swaps| swaps.load(&path))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "E305: {} のスワップファイルが見つかりません",
+                    path.display()
+                )
+            })?;
+        self.get_current_editor_mut().replace_content(&swap.content);
+        Ok(format!(
+            "{} のスワップファイル ({}) から復旧しました。内容を確認して :w で保存してください",
+            path.display(),
+            swap.saved_at.format("%Y-%m-%d %H:%M:%S")
+        ))
+    }
+
+    /// Write modified buffers to their swap files every `SWAP_INTERVAL`, and
+    /// remove the swap files of buffers that were saved or closed.
+    pub async fn update_swap_files(&mut self) {
+        if self.last_swap_write.elapsed() < SWAP_INTERVAL {
+            return;
+        }
+        // The last round is still writing; try again on the next loop
+        if self
+            .swap_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+        {
+            return;
+        }
+        self.finish_swap_writes().await;
+        self.last_swap_write = Instant::now();
+        let Some(swaps) = self.file_manager.swaps.clone() else {
+            return;
+        };
+        let mut written = std::collections::HashMap::new();
+        let mut writes = Vec::new();
+        for buffer in self.buffer_manager.buffers() {
+            let Some(path) = &buffer.file_path else {
+                continue;
+            };
+            // Large files are not copied into swap files every few seconds
+            if !buffer.content.is_modified() || buffer.readonly || buffer.content.is_large_file() {
+                continue;
+            }
+            let content = buffer.content.get_content();
+            let hash = {
+                use std::hash::{Hash, Hasher};
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                content.hash(&mut hasher);
+                hasher.finish()
+            };
+            if self.swap_hashes.get(path) != Some(&hash) {
+                writes.push(SwapFile::new(path, content));
+            }
+            written.insert(path.clone(), hash);
+        }
+        let removes: Vec<PathBuf> = self
+            .swap_hashes
+            .keys()
+            .filter(|path| !written.contains_key(*path))
+            .cloned()
+            .collect();
+        self.swap_hashes = written;
+        if writes.is_empty() && removes.is_empty() {
+            return;
+        }
+        // Each write is synced to disk, which can stall on slow disks
+        self.swap_task = Some(tokio::task::spawn_blocking(move || {
+            for path in &removes {
+                swaps.remove(path);
+            }
+            writes
+                .into_iter()
+                .filter_map(|swap| match swaps.write(&swap) {
+                    Ok(()) => None,
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to write swap file for {}: {}",
+                            swap.file.display(),
+                            e
+                        );
+                        Some(swap.file)
+                    }
+                })
+                .collect()
+        }));
+    }
+
+    /// Wait for the swap files being written, and forget the ones that
+    /// failed so that the next round writes them again.
+    async fn finish_swap_writes(&mut self) {
+        let Some(task) = self.swap_task.take() else {
+            return;
+        };
+        match task.await {
+            Ok(failed) => {
+                for path in failed {
+                    self.swap_hashes.remove(&path);
+                }
+            }
+            Err(e) => log::warn!("Swap file writer failed: {}", e),
+        }
+    }
+
+    /// The mouse capture state the terminal should switch to, when the
+    /// `mouse` option no longer matches it. The caller applies it.
+    pub fn take_mouse_capture_change(&mut self) -> Option<bool> {
+        let wanted = self.config.editor.mouse;
+        if wanted == self.mouse_captured {
+            return None;
+        }
+        self.mouse_captured = wanted;
+        Some(wanted)
+    }
+
+    /// The terminal lost focus.
+    pub fn focus_lost(&mut self) {
+        self.autosave.focus_lost();
+    }
+
+    /// Save modified buffers when autosave says it is time. Waits while a
+    /// prompt is open so its question stays on the status bar.
+    pub async fn check_autosave(&mut self) {
+        if matches!(self.ui_state.get_mode(), Mode::SavePrompt) {
+            return;
+        }
+        if let Some(reason) = self.autosave.due(Instant::now()) {
+            self.autosave_buffers(reason).await;
+        }
+    }
+
+    /// Save every modified buffer that has a name and is writable.
+    async fn autosave_buffers(&mut self, reason: AutosaveReason) {
+        let mut saved = Vec::new();
+        let current = self.buffer_manager.current_index();
+        for (index, buffer) in self.buffer_manager.buffers.iter_mut().enumerate() {
+            let Some(path) = buffer.file_path.clone() else {
+                continue;
+            };
+            if !buffer.content.is_modified() || buffer.readonly || buffer.content.is_loading() {
+                continue;
+            }
+            let mut file_manager =
+                self.file_manager
+                    .for_path(path.clone(), false, buffer.disk.clone());
+            let result = file_manager.save_file(&mut buffer.content).await;
+            buffer.disk = file_manager.disk;
+            if index == current {
+                self.file_manager.disk = buffer.disk.clone();
+            }
+            match result {
+                Ok(_) => saved.push(
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                ),
+                Err(e) => {
+                    log::warn!("Autosave failed for {}: {}", path.display(), e);
+                    self.ui_state
+                        .set_error_message(format!("自動保存に失敗しました: {}", e));
+                    return;
+                }
+            }
+        }
+        if !saved.is_empty() {
+            self.ui_state.set_info_message(format!(
+                "自動保存しました（{}）: {}",
+                reason.label(),
+                saved.join(", ")
+            ));
+        }
+    }
+
+    /// Remove every swap file this instance wrote, on a normal exit.
+    pub async fn remove_swap_files(&mut self) {
+        self.finish_swap_writes().await;
+        if let Some(swaps) = &self.file_manager.swaps {
+            for path in self.swap_hashes.keys() {
+                swaps.remove(path);
+            }
+        }
+        self.swap_hashes.clear();
+    }
+
+    // Handle save prompt responses
+    async fn handle_save_prompt_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.explorer_prompt.is_some() {
+            self.handle_explorer_prompt_key(key).await;
+            return Ok(());
+        }
+        if self.swap_prompt.is_some() {
+            self.handle_swap_prompt_key(key);
+            return Ok(());
+        }
+        if self.reload_prompt.is_some() {
+            self.handle_reload_prompt_key(key);
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                let save_result = {
+                    let current_editor = &mut self.buffer_manager.get_current_mut().content;
+                    self.file_manager.save_file(current_editor).await
+                };
+
+                if let Err(e) = save_result {
+                    self.ui_state
+                        .set_error_message(format!("保存中にエラーが発生しました: {}", e));
+                    self.ui_state.enter_normal_mode();
+                } else {
+                    self.refresh_current_buffer_metadata();
+                    self.ui_state.quit();
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.ui_state.quit();
+            }
+            KeyCode::Char('c') | KeyCode::Char('C') | KeyCode::Esc => {
+                self.ui_state.enter_normal_mode();
+                self.ui_state.status_message.clear();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Macro recording methods
+    fn start_macro_recording(&mut self, register: char) {
+        self.macro_recording = true;
         self.macro_register = Some(register);
         self.macro_keys.clear();
         self.ui_state
@@ -875,6 +2716,9 @@ impl App {
                     };
                 }
             }
+            // A sequence left unfinished by the macro runs as typed
+            let steps = self.keymap.flush();
+            self.run_key_steps(steps);
             self.ui_state
                 .set_success_message("マクロの再生が完了しました".to_string());
         } else {
@@ -885,14 +2729,179 @@ impl App {
         }
     }
 
-    fn refresh_current_buffer_metadata(&mut self) {
-        if let Some(buffer) = self
-            .buffer_manager
-            .buffers
-            .get_mut(self.buffer_manager.current_buffer)
-        {
-            buffer.file_path = self.file_manager.get_current_path().cloned();
-            buffer.readonly = self.file_manager.is_readonly();
+    fn refresh_current_buffer_metadata(&mut self) {
+        if let Some(buffer) = self
+            .buffer_manager
+            .buffers
+            .get_mut(self.buffer_manager.current_buffer)
+        {
+            let path = self.file_manager.get_current_path().cloned();
+            if path != buffer.file_path {
+                buffer.git = path.as_deref().map(GitChanges::load).unwrap_or_default();
+                buffer.diagnostics.clear();
+            }
+            buffer.file_path = path;
+            buffer.readonly = self.file_manager.is_readonly();
+            if buffer.disk != self.file_manager.disk {
+                buffer.disk = self.file_manager.disk.clone();
+                buffer.external = None;
+            }
+            if let Some(load) = self.file_manager.pending_load.take() {
+                buffer.content.set_loading(true);
+                buffer.loading = Some(load);
+            }
+        }
+    }
+
+    /// Put the large files read in the background into their buffers. With
+    /// `wait`, wait for the ones still being read, as runs without a
+    /// terminal do. Returns the errors, which are also shown as messages;
+    /// a buffer that failed keeps refusing to save.
+    pub async fn finish_file_loads(&mut self, wait: bool) -> Vec<String> {
+        let mut errors = Vec::new();
+        for index in 0..self.buffer_manager.buffers.len() {
+            let buffer = &mut self.buffer_manager.buffers[index];
+            if !buffer
+                .loading
+                .as_ref()
+                .is_some_and(|load| wait || load.is_finished())
+            {
+                continue;
+            }
+            let Some(load) = buffer.loading.take() else {
+                continue;
+            };
+            let path = buffer.file_path.clone().unwrap_or_default();
+            let loaded = match load.finish().await {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    let error = format!("ファイル読み込みエラー: {}", e);
+                    self.ui_state.set_error_message(error.clone());
+                    errors.push(error);
+                    continue;
+                }
+            };
+            let editor = &mut buffer.content;
+            match loaded.text {
+                LoadedText::Rest(rest) => editor.append_loaded(rest),
+                LoadedText::Whole(content) => {
+                    let (line, col) = editor.cursor_position();
+                    editor.load_file(content, loaded.format, true);
+                    editor.set_cursor_position(line, col);
+                }
+            }
+            editor.set_file_format(loaded.format);
+            let lines = editor.line_count();
+            buffer.disk = loaded.disk;
+            buffer.external = None;
+            if index == self.buffer_manager.current_index() {
+                self.sync_file_manager_from_buffer();
+            }
+            self.ui_state.set_info_message(format!(
+                "読み込みが終わりました: {} ({} 行)",
+                path.display(),
+                lines
+            ));
+            if loaded.found_swap.is_some() {
+                self.file_manager.found_swap = loaded.found_swap;
+                self.check_found_swap();
+            }
+        }
+        errors
+    }
+
+    /// What the status line segments show for the current buffer.
+    pub fn status_info(&mut self) -> StatusInfo {
+        let file_name = self.file_path().map(|path| path.display().to_string());
+        let syntax_file = file_name.clone().unwrap_or_else(|| "text.md".to_string());
+        let file_type = self
+            .get_highlighter()
+            .find_syntax_for_filename(&syntax_file)
+            .name
+            .to_lowercase();
+        let buffer = self.buffer_manager.get_current();
+        let editor = &buffer.content;
+        let (line, col) = editor.cursor_position();
+        let count = |wanted: Sign| {
+            buffer
+                .diagnostics
+                .iter()
+                .filter(|(_, sign)| *sign == wanted)
+                .count()
+        };
+        StatusInfo {
+            mode: Some(self.mode().clone()),
+            mode_label: statusline::mode_label(self.mode(), &self.keymap),
+            file_name,
+            modified: editor.is_modified(),
+            encoding: editor.file_format().encoding_label(),
+            line_ending: editor.line_ending(),
+            file_type,
+            line,
+            col,
+            line_count: editor.line_count(),
+            word_count: (!editor.is_large_file()).then(|| editor.word_count()),
+            selection: matches!(self.mode(), Mode::Visual | Mode::VisualBlock)
+                .then(|| editor.selection_size())
+                .flatten(),
+            lsp: self
+                .lsp_server
+                .clone()
+                .map(|server| (server, count(Sign::Error), count(Sign::Warning))),
+            git_branch: buffer.git.branch().map(str::to_string),
+            recording: self.macro_register.filter(|_| self.macro_recording),
+            pending_keys: self.keymap.pending_keys(),
+        }
+    }
+
+    /// Signs of a buffer: LSP diagnostics, git changes and marks.
+    pub fn buffer_signs(&mut self, buffer_index: usize) -> Signs {
+        let buffer = &mut self.buffer_manager.buffers[buffer_index];
+        let mut signs = Signs::default();
+        // Diffing a large file against git on every change would stall editing
+        if !buffer.content.is_large_file() {
+            for &(line, sign) in buffer.git.signs(&buffer.content) {
+                signs.add(line, sign);
+            }
+        }
+        for &(line, sign) in &buffer.diagnostics {
+            signs.add(line, sign);
+        }
+        for (&name, &(line, _)) in buffer.content.marks() {
+            signs.add(line, Sign::Mark(name));
+        }
+        signs
+    }
+
+    /// Copy the diagnostics published by the language servers into the
+    /// buffers, and note which server is running. Called from the event loop.
+    pub async fn refresh_diagnostics(&mut self) {
+        #[cfg(feature = "lsp")]
+        if let Some(ref lsp_plugin) = self.lsp_plugin {
+            self.lsp_server = lsp_plugin.current_server().await;
+            for buffer in &mut self.buffer_manager.buffers {
+                let Some(uri) = buffer
+                    .file_path
+                    .as_ref()
+                    .and_then(|path| std::fs::canonicalize(path).ok())
+                    .and_then(|path| lsp_types::Url::from_file_path(path).ok())
+                else {
+                    continue;
+                };
+                buffer.diagnostics = lsp_plugin
+                    .get_diagnostics(&uri)
+                    .await
+                    .into_iter()
+                    .map(|diagnostic| {
+                        let sign = match diagnostic.severity {
+                            Some(lsp_types::DiagnosticSeverity::ERROR) => Sign::Error,
+                            Some(lsp_types::DiagnosticSeverity::WARNING) => Sign::Warning,
+                            _ => Sign::Info,
+                        };
+                        (diagnostic.range.start.line as usize, sign)
+                    })
+                    .collect();
+            }
         }
     }
 
@@ -900,6 +2909,7 @@ impl App {
         let buffer = self.buffer_manager.get_current();
         self.file_manager.current_path = buffer.file_path.clone();
         self.file_manager.is_readonly = buffer.readonly;
+        self.file_manager.disk = buffer.disk.clone();
     }
 
     // LSP integration methods
@@ -974,14 +2984,20 @@ impl App {
     }
 
     fn handle_buffer_switch(&mut self, message: &str) -> Option<(UiMessageKind, String)> {
+        self.autosave.buffer_switched();
         let active_id = self.buffer_manager.current_buffer_id();
         self.window_manager.set_buffer_for_current(active_id);
         self.sync_file_manager_from_buffer();
         Some((UiMessageKind::Info, message.to_string()))
     }
 
-    fn apply_command_action(&mut self, action: CommandAction) -> Option<(UiMessageKind, String)> {
-        match action {
+    /// Carry out what a command asked for. Failures are returned rather
+    /// than shown, so that `run_ex_command` reports them.
+    fn apply_command_action(
+        &mut self,
+        action: CommandAction,
+    ) -> Result<Option<(UiMessageKind, String)>> {
+        Ok(match action {
             CommandAction::None => None,
             CommandAction::Buffer(buffer_command) => match buffer_command {
                 BufferCommand::Next => {
@@ -1004,6 +3020,29 @@ impl App {
                         ))
                     }
                 }
+                BufferCommand::Switch(target) => {
+                    let found = self.buffer_manager.buffers().iter().find(|buffer| {
+                        target.parse::<usize>().ok() == Some(buffer.id)
+                            || buffer
+                                .file_path
+                                .as_ref()
+                                .and_then(|p| p.file_name())
+                                .and_then(|n| n.to_str())
+                                .is_some_and(|name| name.contains(target.as_str()))
+                    });
+                    match found.map(|buffer| buffer.id) {
+                        Some(id) => {
+                            self.buffer_manager.set_current_by_id(id);
+                            self.handle_buffer_switch("バッファを切り替えました")
+                        }
+                        None => {
+                            return Err(anyhow::anyhow!(
+                                "E94: バッファが見つかりません: {}",
+                                target
+                            ))
+                        }
+                    }
+                }
                 BufferCommand::List => {
                     let list = self
                         .buffer_manager
@@ -1022,11 +3061,16 @@ impl App {
                                 .and_then(|p| p.file_name())
                                 .and_then(|n| n.to_str())
                                 .unwrap_or("[No Name]");
-                            format!("{}{}:{name}", marker, buffer.id)
+                            let modified = if buffer.content.is_modified() {
+                                " [+]"
+                            } else {
+                                ""
+                            };
+                            format!("  {}{:>3}  {name}{modified}", marker, buffer.id)
                         })
                         .collect::<Vec<_>>()
-                        .join(", ");
-                    Some((UiMessageKind::Info, format!("バッファ一覧: {list}")))
+                        .join("\n");
+                    Some((UiMessageKind::Info, format!("バッファ一覧:\n{list}")))
                 }
                 BufferCommand::DeleteCurrent => {
                     let removed_id = self.buffer_manager.delete_current();
@@ -1065,14 +3109,111 @@ impl App {
                     Some((UiMessageKind::Info, "垂直分割を行いました".to_string()))
                 }
             },
+            CommandAction::Map(map_command) => Some(self.apply_map_command(map_command)?),
+            CommandAction::Picker(kind, query) => {
+                self.open_picker(kind, &query);
+                None
+            }
+            CommandAction::Explore => {
+                self.toggle_explorer();
+                None
+            }
+            CommandAction::Help => {
+                if !self.ui_state.is_help_shown() {
+                    self.ui_state.toggle_help();
+                }
+                None
+            }
+            CommandAction::Recover => Some((UiMessageKind::Success, self.recover_current()?)),
+            CommandAction::DiffOrig => self.show_disk_diff(self.buffer_manager.current_index())?,
+            // Run by `run_ex_command`, which can wait for the keys
+            CommandAction::Normal(_) => None,
+            CommandAction::Messages { clear: true } => {
+                self.ui_state.status_manager.clear_history();
+                None
+            }
+            CommandAction::Messages { clear: false } => {
+                self.open_messages();
+                None
+            }
+        })
+    }
+
+    fn apply_map_command(&mut self, command: MapCommand) -> Result<(UiMessageKind, String)> {
+        match command {
+            MapCommand::Set {
+                modes,
+                keys,
+                action,
+            } => {
+                let message = format!(
+                    "マッピングを設定しました: {} → {}",
+                    keymap::format_keys(&keys),
+                    action.name()
+                );
+                for mode in modes {
+                    self.keymap.map(mode, keys.clone(), action.clone());
+                }
+                Ok((UiMessageKind::Success, message))
+            }
+            MapCommand::Remove { modes, keys } => {
+                let removed = modes
+                    .into_iter()
+                    .filter(|mode| self.keymap.unmap(*mode, &keys))
+                    .count();
+                if removed == 0 {
+                    return Err(anyhow::anyhow!(
+                        "E31: マッピングがありません: {}",
+                        keymap::format_keys(&keys)
+                    ));
+                }
+                Ok((
+                    UiMessageKind::Success,
+                    format!("マッピングを削除しました: {}", keymap::format_keys(&keys)),
+                ))
+            }
+            MapCommand::List { modes, keys } => {
+                let entries: Vec<String> = modes
+                    .into_iter()
+                    .flat_map(|mode| {
+                        let bindings = match &keys {
+                            Some(keys) => self
+                                .keymap
+                                .lookup(mode, keys)
+                                .map(|action| (keymap::format_keys(keys), action.name()))
+                                .into_iter()
+                                .collect(),
+                            None => self.keymap.bindings(mode, true),
+                        };
+                        bindings.into_iter().map(move |(keys, action)| {
+                            format!("{} {} {}", mode.short_name(), keys, action)
+                        })
+                    })
+                    .collect();
+                Ok(if entries.is_empty() {
+                    (
+                        UiMessageKind::Warning,
+                        "マッピングが見つかりません".to_string(),
+                    )
+                } else {
+                    (
+                        UiMessageKind::Info,
+                        format!("マッピング: {}", entries.join(" | ")),
+                    )
+                })
+            }
         }
     }
 }
 
+/// Added to the open message of files in large-file mode.
+pub const LARGE_FILE_NOTE: &str =
+    "大きなファイルのため、ハイライト・折りたたみ・Git の差分表示を無効にしました";
+
 fn classify_message(message: &str) -> UiMessageKind {
     if message.contains("書き込みました") || message.contains("保存しました") {
         UiMessageKind::Success
-    } else if message.contains("変更が保存されていません") {
+    } else if message.contains("改行コードが混在") {
         UiMessageKind::Warning
     } else {
         UiMessageKind::Info
@@ -1092,6 +3233,19 @@ mod tests {
         KeyEvent::new(code, KeyModifiers::NONE)
     }
 
+    /// Feed `keys` one by one; `\n` is Enter and `\x08` Backspace.
+    async fn type_keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x08' => KeyCode::Backspace,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key_event(create_key_event(code)).await.unwrap();
+        }
+    }
+
     fn env_lock() -> &'static Mutex<()> {
         static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
         LOCK.get_or_init(|| Mutex::new(()))
@@ -1260,7 +3414,7 @@ mod tests {
             .await;
         assert!(result.is_ok());
         assert!(matches!(app.mode(), &Mode::Command));
-        assert_eq!(app.status_message(), ":");
+        assert_eq!(app.command_prompt(), ":");
     }
 
     #[tokio::test]
@@ -1438,7 +3592,7 @@ Line 3"
 Line 2
 Line 3"
         );
-        assert_eq!(app.last_key, Some('d'));
+        assert_eq!(app.keymap.pending_keys(), "d");
 
         // Second 'd' should delete the line
         let result = app
@@ -1450,7 +3604,7 @@ Line 3"
             "Line 2
 Line 3"
         );
-        assert_eq!(app.last_key, None);
+        assert_eq!(app.keymap.pending_keys(), "");
 
         // Test paste after dd (paste puts the line at cursor position)
         let result = app
@@ -1461,4 +3615,781 @@ Line 3"
         let content = app.get_current_editor().get_content();
         assert!(content.contains("Line 1")); // Original line 1 should still be in content
     }
+
+    #[tokio::test]
+    async fn test_user_key_mappings() {
+        let mut app = App::new().await.unwrap();
+        app.get_current_editor_mut()
+            .set_content("Line 1\nLine 2".to_string());
+
+        app.execute_command_line("nmap <C-d> delete_line").await;
+        app.execute_command_line("imap jk normal_mode").await;
+        assert!(app
+            .ui_state
+            .get_status_message()
+            .contains("マッピングを設定しました"));
+
+        let ctrl_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        app.handle_editor_key(ctrl_d).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "Line 2");
+
+        // `jk` leaves insert mode without typing either key
+        app.handle_editor_key(create_key_event(KeyCode::Char('i')))
+            .await
+            .unwrap();
+        for c in ['x', 'j', 'k'] {
+            app.handle_editor_key(create_key_event(KeyCode::Char(c)))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(app.mode(), Mode::Normal));
+        assert_eq!(app.get_current_editor().get_content(), "xLine 2");
+
+        app.execute_command_line("map").await;
+        assert!(app
+            .ui_state
+            .get_status_message()
+            .contains("n <C-d> delete_line"));
+
+        app.execute_command_line("nunmap <C-d>").await;
+        app.handle_editor_key(ctrl_d).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "xLine 2");
+    }
+
+    #[tokio::test]
+    async fn test_emacs_keybindings() {
+        let mut app = App::new().await.unwrap();
+        app.config.keybindings = KeybindingStyle::Emacs;
+        app.reload_keymap();
+
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+
+        // Modeless: characters are typed without entering insert mode
+        for c in "one two".chars() {
+            app.handle_editor_key(create_key_event(KeyCode::Char(c)))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(app.mode(), Mode::Normal));
+        assert_eq!(app.get_current_editor().get_content(), "one two");
+
+        app.handle_editor_key(alt('b')).await.unwrap();
+        assert_eq!(app.get_current_editor().cursor_position(), (0, 4));
+
+        // Consecutive C-k kills are yanked back as one entry
+        app.handle_editor_key(ctrl('a')).await.unwrap();
+        app.handle_editor_key(ctrl('k')).await.unwrap();
+        app.handle_editor_key(ctrl('k')).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "");
+        app.handle_editor_key(ctrl('y')).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "one two");
+
+        // Mark and region: C-space at the start, C-e, M-w then C-y and M-y
+        app.handle_editor_key(ctrl('a')).await.unwrap();
+        app.handle_editor_key(ctrl(' ')).await.unwrap();
+        app.handle_editor_key(alt('f')).await.unwrap();
+        app.handle_editor_key(alt('w')).await.unwrap();
+        app.handle_editor_key(ctrl('e')).await.unwrap();
+        app.handle_editor_key(ctrl('y')).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "one twoone");
+        app.handle_editor_key(alt('y')).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "one twoone two");
+
+        // Incremental search, cancelled with C-g. The prompt redrawn on
+        // each key is left out of the message history
+        let logged = app.ui_state.status_manager.history().count();
+        app.handle_editor_key(ctrl('r')).await.unwrap();
+        for c in "two".chars() {
+            app.handle_editor_key(create_key_event(KeyCode::Char(c)))
+                .await
+                .unwrap();
+        }
+        assert_eq!(app.get_current_editor().cursor_position(), (0, 11));
+        assert_eq!(app.ui_state.status_manager.history().count(), logged);
+        app.handle_editor_key(ctrl('r')).await.unwrap();
+        assert_eq!(app.get_current_editor().cursor_position(), (0, 4));
+        app.handle_editor_key(ctrl('g')).await.unwrap();
+        assert_eq!(app.get_current_editor().cursor_position(), (0, 14));
+
+        // C-x b opens the command line with the buffer prompt
+        app.handle_editor_key(ctrl('x')).await.unwrap();
+        app.handle_editor_key(create_key_event(KeyCode::Char('b')))
+            .await
+            .unwrap();
+        assert!(matches!(app.mode(), Mode::Command));
+        assert_eq!(app.command_buffer(), "b ");
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+
+        // The M-x prompt stays in front of what is typed, even while a
+        // message is shown
+        app.handle_editor_key(alt('x')).await.unwrap();
+        for c in "set".chars() {
+            app.handle_key_event(create_key_event(KeyCode::Char(c)))
+                .await
+                .unwrap();
+            app.update_status();
+        }
+        assert!(!app.status_message().is_empty());
+        assert_eq!(app.command_prompt(), "M-x ");
+        assert_eq!(app.command_buffer(), "set");
+    }
+
+    #[tokio::test]
+    async fn test_nano_keybindings() {
+        let mut app = App::new().await.unwrap();
+        app.config.keybindings = KeybindingStyle::Nano;
+        app.reload_keymap();
+        app.get_current_editor_mut()
+            .set_content("first\nsecond\nthird\n".to_string());
+
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+
+        // ^K twice cuts both lines as one block, ^U pastes it back
+        app.handle_editor_key(ctrl('k')).await.unwrap();
+        app.handle_editor_key(ctrl('k')).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "third\n");
+        app.handle_editor_key(ctrl('u')).await.unwrap();
+        assert_eq!(
+            app.get_current_editor().get_content(),
+            "first\nsecond\nthird\n"
+        );
+        assert!(matches!(app.mode(), Mode::Normal));
+
+        // ^_ asks for a line number
+        app.handle_editor_key(ctrl('_')).await.unwrap();
+        assert!(matches!(app.mode(), Mode::Command));
+        assert_eq!(app.command_prompt(), "行番号: ");
+        app.handle_key_event(create_key_event(KeyCode::Char('3')))
+            .await
+            .unwrap();
+        app.handle_key_event(create_key_event(KeyCode::Enter))
+            .await
+            .unwrap();
+        assert_eq!(app.get_current_editor().cursor_position(), (2, 0));
+
+        // ^\ asks for the text to find, then for the replacement
+        app.handle_editor_key(ctrl('\\')).await.unwrap();
+        assert_eq!(app.command_prompt(), "置換: ");
+        assert_eq!(app.command_buffer(), "");
+        type_keys(&mut app, "third\n").await;
+        assert_eq!(app.command_prompt(), "置換後 (third): ");
+        type_keys(&mut app, "3rd\n").await;
+        assert_eq!(
+            app.get_current_editor().get_content(),
+            "first\nsecond\n3rd\n"
+        );
+
+        // M-U undoes, ^X on a modified buffer asks before quitting
+        app.handle_editor_key(alt('u')).await.unwrap();
+        assert_eq!(
+            app.get_current_editor().get_content(),
+            "first\nsecond\nthird\n"
+        );
+        app.handle_editor_key(ctrl('x')).await.unwrap();
+        assert!(matches!(app.mode(), Mode::SavePrompt));
+        assert!(!app.should_quit());
+    }
+
+    #[tokio::test]
+    async fn test_nano_replace_prompt_allows_spaces() {
+        let mut app = App::new().await.unwrap();
+        app.config.keybindings = KeybindingStyle::Nano;
+        app.reload_keymap();
+        app.get_current_editor_mut()
+            .set_content("a big cat\na big dog\n".to_string());
+        let ctrl_backslash = KeyEvent::new(KeyCode::Char('\\'), KeyModifiers::CONTROL);
+
+        app.handle_editor_key(ctrl_backslash).await.unwrap();
+        type_keys(&mut app, "big cat\nsmall kitten\n").await;
+        assert!(matches!(app.mode(), Mode::Normal));
+        assert_eq!(
+            app.get_current_editor().get_content(),
+            "a small kitten\na big dog\n"
+        );
+
+        // Esc in the second prompt cancels without replacing
+        app.handle_editor_key(ctrl_backslash).await.unwrap();
+        type_keys(&mut app, "dog\n").await;
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        assert!(matches!(app.mode(), Mode::Normal));
+        assert_eq!(app.replace_prompt, None);
+        assert!(app.get_current_editor().get_content().contains("dog"));
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_finder_opens_files_and_headings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/guide.md"), "# Guide\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "# Notes\n\n## Todo\n").unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.get_current_editor_mut()
+            .set_content("scratch".to_string());
+        app.picker = Some(Picker::new(
+            PickerKind::Files,
+            picker::file_items(dir.path()),
+            "",
+        ));
+        for c in "guide".chars() {
+            app.handle_key_event(create_key_event(KeyCode::Char(c)))
+                .await
+                .unwrap();
+        }
+        app.handle_key_event(create_key_event(KeyCode::Enter))
+            .await
+            .unwrap();
+
+        // The modified scratch buffer is kept and the file gets its own buffer
+        assert!(app.picker.is_none());
+        assert_eq!(app.buffer_manager.buffers().len(), 2);
+        assert_eq!(app.get_current_editor().get_content(), "# Guide\n");
+        assert!(app.file_path().unwrap().ends_with("docs/guide.md"));
+        assert!(app.recent_files.paths()[0].ends_with("docs/guide.md"));
+
+        // Opening it again switches to the same buffer
+        app.open_file_in_buffer(dir.path().join("notes.md"))
+            .await
+            .unwrap();
+        app.open_file_in_buffer(dir.path().join("docs/guide.md"))
+            .await
+            .unwrap();
+        assert_eq!(app.buffer_manager.buffers().len(), 3);
+        assert_eq!(app.get_current_editor().get_content(), "# Guide\n");
+
+        // Heading picker jumps to the chosen heading
+        app.open_file_in_buffer(dir.path().join("notes.md"))
+            .await
+            .unwrap();
+        app.open_picker(PickerKind::Headings, "todo");
+        app.handle_key_event(create_key_event(KeyCode::Enter))
+            .await
+            .unwrap();
+        assert_eq!(app.get_current_editor().cursor_position(), (2, 0));
+
+        // Esc closes the picker without opening anything
+        app.open_picker(PickerKind::Buffers, "");
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        assert!(app.picker.is_none());
+        assert_eq!(app.buffer_manager.buffers().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_command_palette_runs_commands_and_actions() {
+        let mut app = App::new().await.unwrap();
+        app.open_picker(PickerKind::Commands, "vsplit");
+        type_keys(&mut app, "\n").await;
+        assert!(app.picker.is_none());
+        assert_eq!(app.window_manager.panes().len(), 2);
+
+        // Commands with required arguments open the command line
+        app.open_picker(PickerKind::Commands, ":e ");
+        type_keys(&mut app, "\n").await;
+        assert!(matches!(app.mode(), Mode::Command));
+        assert_eq!(app.command_buffer(), "e ");
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+
+        // Actions run directly
+        app.get_current_editor_mut().set_content("abc".to_string());
+        app.open_picker(PickerKind::Commands, "line_end");
+        type_keys(&mut app, "\n").await;
+        assert_eq!(app.get_current_editor().cursor_position(), (0, 3));
+    }
+
+    #[tokio::test]
+    async fn test_command_line_completion_and_wildmenu() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.md"), "alpha\n").unwrap();
+        std::fs::write(dir.path().join("ab.md"), "beta\n").unwrap();
+        let root = dir.path().display().to_string();
+
+        let mut app = App::new().await.unwrap();
+        type_keys(&mut app, ":set tex\t").await;
+        assert_eq!(app.command_buffer(), "set textwidth");
+        assert!(app.wildmenu.is_none());
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+
+        // Several candidates open the wildmenu; Tab and Shift-Tab cycle
+        type_keys(&mut app, &format!(":e {}/a\t", root)).await;
+        assert_eq!(app.wildmenu.as_ref().unwrap().candidates().len(), 2);
+        assert_eq!(app.command_buffer(), format!("e {}/a.md", root));
+        type_keys(&mut app, "\t").await;
+        assert_eq!(app.command_buffer(), format!("e {}/ab.md", root));
+        app.handle_key_event(create_key_event(KeyCode::BackTab))
+            .await
+            .unwrap();
+        assert_eq!(app.command_buffer(), format!("e {}/a.md", root));
+        type_keys(&mut app, "\n").await;
+        assert!(app.wildmenu.is_none());
+        assert_eq!(app.get_current_editor().get_content(), "alpha\n");
+
+        // :r inserts the file below the cursor line
+        type_keys(&mut app, &format!(":r {}/ab.md\n", root)).await;
+        assert_eq!(app.get_current_editor().get_content(), "alpha\nbeta\n");
+        assert_eq!(app.get_current_editor().cursor_position(), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_setlocal_applies_to_one_buffer() {
+        let mut app = App::new().await.unwrap();
+        app.config.editor.use_spaces = true;
+        app.buffer_manager.create_buffer();
+        type_keys(&mut app, ":setlocal ts=2 tw=8\n").await;
+        assert_eq!(app.editor_options(0).tabstop, 2);
+        assert_eq!(app.editor_options(1).tabstop, app.config.editor.tab_size);
+
+        // Typing past textwidth breaks the line at the last space
+        type_keys(&mut app, "ione two three").await;
+        assert_eq!(app.get_current_editor().get_content(), "one two\nthree");
+        app.handle_key_event(create_key_event(KeyCode::Tab))
+            .await
+            .unwrap();
+        assert!(app.get_current_editor().get_content().ends_with("three  "));
+    }
+
+    #[tokio::test]
+    async fn test_marks_jump_and_show_signs() {
+        let mut app = App::new().await.unwrap();
+        app.get_current_editor_mut()
+            .set_content("one\n  two\nthree\n".to_string());
+        type_keys(&mut app, "jllmagg").await;
+        assert_eq!(app.get_current_editor().cursor_position(), (0, 0));
+        type_keys(&mut app, "`a").await;
+        assert_eq!(app.get_current_editor().cursor_position(), (1, 2));
+        type_keys(&mut app, "gg'a").await;
+        assert_eq!(app.get_current_editor().cursor_position(), (1, 2));
+        assert_eq!(app.buffer_signs(0).get(1), Some(Sign::Mark('a')));
+
+        type_keys(&mut app, "'b").await;
+        assert!(app.ui_state.status_message.contains("E20"));
+    }
+
+    #[tokio::test]
+    async fn test_swap_file_prompts_for_recovery() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "saved\n").unwrap();
+        let store = SwapStore::new(dir.path().join("swap"));
+        let mut swap = SwapFile::new(&path, "unsaved\n".to_string());
+        swap.pid = u32::MAX;
+        swap.session = "crashed".to_string();
+        store.write(&swap).unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.file_manager.swaps = Some(store.clone());
+        app.open_file_in_buffer(path.clone()).await.unwrap();
+        assert!(matches!(app.mode(), Mode::SavePrompt));
+        type_keys(&mut app, "r").await;
+        assert!(matches!(app.mode(), Mode::Normal));
+        assert_eq!(app.get_current_editor().get_content(), "unsaved\n");
+        assert!(app.is_modified());
+
+        // Once saved, the swap file written for the buffer goes away
+        app.last_swap_write = Instant::now() - SWAP_INTERVAL;
+        app.update_swap_files().await;
+        app.finish_swap_writes().await;
+        assert!(store.load(&path).unwrap().is_ours());
+        type_keys(&mut app, ":w\n").await;
+        app.last_swap_write = Instant::now() - SWAP_INTERVAL;
+        app.update_swap_files().await;
+        app.finish_swap_writes().await;
+        assert!(store.load(&path).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_autosave_on_buffer_switch_skips_unnamed_buffers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let notes = dir.path().join("notes.md");
+        std::fs::write(&notes, "one\n").unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.file_manager.backup = crate::config::BackupStrategy::None;
+        app.autosave = Autosave::new(crate::config::AutosaveConfig {
+            on_buffer_switch: true,
+            ..Default::default()
+        });
+        app.open_file_in_buffer(notes.clone()).await.unwrap();
+        type_keys(&mut app, "ix").await;
+        app.buffer_manager.create_buffer();
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        type_keys(&mut app, ":bn\n").await;
+        type_keys(&mut app, "iy").await;
+        app.check_autosave().await;
+
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "xone\n");
+        assert!(!app.buffer_manager.buffers[0].content.is_modified());
+        assert!(app.buffer_manager.buffers[1].content.is_modified());
+        assert!(app.ui_state.status_message.contains("notes.md"));
+    }
+
+    #[tokio::test]
+    async fn test_external_changes_reload_or_block_saving() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "one\n").unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.file_manager.backup = crate::config::BackupStrategy::None;
+        app.open_file_in_buffer(path.clone()).await.unwrap();
+
+        // A clean buffer offers to reload
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        app.recheck_disk();
+        assert!(matches!(app.mode(), Mode::SavePrompt));
+        type_keys(&mut app, "l").await;
+        assert_eq!(app.get_current_editor().get_content(), "one\ntwo\n");
+        assert!(!app.is_modified());
+
+        // A change seen while another prompt is open is asked about once
+        // that prompt closes
+        app.ui_state.set_mode(Mode::SavePrompt);
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        app.recheck_disk();
+        assert!(app.reload_prompt.is_none());
+        app.check_external_changes();
+        assert!(app.reload_prompt.is_none());
+        app.ui_state.enter_normal_mode();
+        app.check_external_changes();
+        assert!(app.reload_prompt.is_some());
+        type_keys(&mut app, "k").await;
+        assert_eq!(app.get_current_editor().get_content(), "one\ntwo\n");
+
+        // With unsaved edits, :w refuses until forced
+        type_keys(&mut app, "ix").await;
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        std::fs::write(&path, "changed elsewhere\n").unwrap();
+        app.recheck_disk();
+        assert!(app.ui_state.status_message.contains("W12"));
+        type_keys(&mut app, ":w\n").await;
+        app.update_status();
+        assert!(app.ui_state.status_message.contains("E949"));
+        type_keys(&mut app, ":DiffOrig\n").await;
+        assert!(app.pager.is_some());
+        type_keys(&mut app, "q:w!\n").await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "xone\ntwo\n");
+        // The forced save records the new disk version, so :w works again
+        type_keys(&mut app, "ay").await;
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        type_keys(&mut app, ":w\n").await;
+        assert!(!app.is_modified());
+    }
+
+    #[tokio::test]
+    async fn test_large_files_load_in_the_background() {
+        use crate::file_manager::LAZY_HEAD_BYTES;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("big.log");
+        let line = "2024-01-01 INFO request handled\n";
+        let whole = line.repeat(LAZY_HEAD_BYTES / line.len() * 2);
+        std::fs::write(&path, &whole).unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.file_manager.backup = crate::config::BackupStrategy::None;
+        app.file_manager.large_file_threshold = 1024;
+        app.open_file_in_buffer(path.clone()).await.unwrap();
+        assert!(app.get_current_editor().is_loading());
+        assert!(app.get_current_editor().get_content().len() < whole.len());
+
+        // Edits made meanwhile stay, and saving waits for the whole file
+        type_keys(&mut app, "ix").await;
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        assert!(app.run_ex_command("w").await.is_err());
+        assert!(app.finish_file_loads(true).await.is_empty());
+        assert!(!app.get_current_editor().is_loading());
+        assert_eq!(
+            app.get_current_editor().get_content(),
+            format!("x{}", whole)
+        );
+
+        // Undoing the edit keeps the lines read later
+        type_keys(&mut app, "u").await;
+        assert_eq!(app.get_current_editor().get_content(), whole);
+        type_keys(&mut app, "ix").await;
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        app.run_ex_command("w").await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("x{}", whole)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_files_keep_their_encoding() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.md");
+        let sjis = |text: &str| Encoding::ShiftJis.encode(text).unwrap();
+        std::fs::write(&path, sjis("見出し\n")).unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.file_manager.backup = crate::config::BackupStrategy::None;
+        app.open_file_in_buffer(path.clone()).await.unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "見出し\n");
+        assert_eq!(app.status_info().encoding, "sjis");
+
+        type_keys(&mut app, "ix").await;
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        type_keys(&mut app, ":w\n").await;
+        assert_eq!(std::fs::read(&path).unwrap(), sjis("x見出し\n"));
+
+        // Changing the encoding needs a save, which converts the file
+        type_keys(&mut app, ":set fenc=utf-8\n").await;
+        assert!(app.is_modified());
+        type_keys(&mut app, ":w\n").await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x見出し\n");
+
+        // ++enc reads the file again as the given encoding
+        type_keys(&mut app, ":e ++enc=latin1\n").await;
+        assert_eq!(app.status_info().encoding, "latin1");
+        assert_ne!(app.get_current_editor().get_content(), "x見出し\n");
+    }
+
+    #[tokio::test]
+    async fn test_open_command_line() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = dir.path().join("a.md");
+        let second = dir.path().join("b.md");
+        std::fs::write(&first, "# A\n\nTODO: write\n").unwrap();
+        std::fs::write(&second, "# B\n").unwrap();
+        let path = |p: &std::path::Path| p.to_string_lossy().to_string();
+        let args = Args::parse([
+            "-O".to_string(),
+            "-R".to_string(),
+            "+/TODO".to_string(),
+            path(&first),
+            path(&second),
+            path(dir.path()),
+        ])
+        .unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.open_command_line(&args).await;
+        assert_eq!(app.file_path(), Some(&first));
+        assert_eq!(app.get_current_editor().cursor_position(), (2, 0));
+        assert!(app.buffer_manager.get_current().readonly);
+        assert_eq!(app.buffer_manager.buffers().len(), 2);
+        assert_eq!(app.window_manager.split_kind(), WindowSplitKind::Vertical);
+        let second_id = app.buffer_manager.buffers()[1].id;
+        assert_eq!(app.window_manager.panes()[1].buffer_id, second_id);
+        assert!(app
+            .explorer
+            .as_ref()
+            .is_some_and(|explorer| explorer.focused));
+
+        let mut app = App::new().await.unwrap();
+        app.open_command_line(&Args::parse(["+2".to_string(), path(&first)]).unwrap())
+            .await;
+        assert_eq!(app.get_current_editor().cursor_position(), (1, 0));
+
+        // `-o` gives every file a pane, and a pattern that is not found is
+        // reported to the caller
+        let third = dir.path().join("c.md");
+        std::fs::write(
+            &third, "# C
+",
+        )
+        .unwrap();
+        let args = Args::parse([
+            "-o".to_string(),
+            "+/missing".to_string(),
+            path(&first),
+            path(&second),
+            path(&third),
+        ])
+        .unwrap();
+        let mut app = App::new().await.unwrap();
+        let errors = app.open_command_line(&args).await;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("+/missing"));
+        let panes = app.window_manager.panes();
+        assert_eq!(panes.len(), 3);
+        assert_eq!(app.window_manager.current_window_id, panes[0].id);
+        assert_eq!(app.window_manager.split_kind(), WindowSplitKind::Horizontal);
+        assert_eq!(app.file_path(), Some(&first));
+    }
+
+    #[tokio::test]
+    async fn test_stdin_opens_in_an_unnamed_buffer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("a.md");
+        std::fs::write(&path, "# A\n").unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.open_file_in_buffer(path).await.unwrap();
+        let (content, format) = FileFormat::decode(b"line 1\r\nline 2\r\n", None);
+        let message = app.open_stdin_in_buffer(content, format);
+        assert!(message.contains("標準入力"));
+        assert_eq!(app.buffer_manager.buffers().len(), 2);
+        assert_eq!(app.file_path(), None);
+        assert!(!app.is_modified());
+        assert_eq!(app.get_current_editor().get_content(), "line 1\nline 2\n");
+        assert_eq!(app.get_current_editor().line_ending(), "CRLF");
+    }
+
+    #[tokio::test]
+    async fn test_line_endings_are_kept_and_changed_with_fileformat() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "one\r\ntwo\r\nthree\n").unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.file_manager.backup = crate::config::BackupStrategy::None;
+        let message = app.open_file_in_buffer(path.clone()).await.unwrap();
+        assert!(message.contains("改行コードが混在"));
+        assert_eq!(app.status_info().line_ending, "CRLF");
+        // The end of the line is before the line break, not on a carriage return
+        app.handle_key_event(create_key_event(KeyCode::End))
+            .await
+            .unwrap();
+        type_keys(&mut app, "i\x08").await;
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        assert_eq!(app.get_current_editor().get_content(), "on\ntwo\nthree\n");
+        type_keys(&mut app, ":w\n").await;
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "on\r\ntwo\r\nthree\r\n"
+        );
+
+        type_keys(&mut app, ":set ff=unix\n").await;
+        assert!(app.is_modified());
+        type_keys(&mut app, ":w\n").await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "on\ntwo\nthree\n");
+    }
+
+    #[tokio::test]
+    async fn test_messages_and_multiline_results_open_pager() {
+        let mut app = App::new().await.unwrap();
+        type_keys(&mut app, ":nosuchcommand\n").await;
+        let error = app.ui_state.status_message.clone();
+        type_keys(&mut app, ":messages\n").await;
+        let pager = app.pager.as_ref().unwrap();
+        assert_eq!(pager.title(), ":messages");
+        let last = pager.visible_lines().last().unwrap().to_string();
+        assert!(last.ends_with(&error));
+        type_keys(&mut app, "q").await;
+        assert!(app.pager.is_none());
+
+        app.buffer_manager.create_buffer();
+        type_keys(&mut app, ":ls\n").await;
+        let pager = app.pager.as_ref().unwrap();
+        assert_eq!(pager.title(), ":ls");
+        assert_eq!(pager.line_count(), 3);
+        app.handle_key_event(create_key_event(KeyCode::Esc))
+            .await
+            .unwrap();
+        assert!(app.pager.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_explorer_opens_and_renames_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/guide.md"), "# Guide\n").unwrap();
+
+        let mut app = App::new().await.unwrap();
+        app.explorer = Some(Explorer::new(dir.path().to_path_buf()));
+        // Expand docs, open guide.md and hand focus to the editor
+        type_keys(&mut app, "\nj\n").await;
+        assert_eq!(app.get_current_editor().get_content(), "# Guide\n");
+        assert!(!app.explorer.as_ref().unwrap().focused);
+
+        // Rename through the prompt, confirming with y
+        app.explorer.as_mut().unwrap().focused = true;
+        type_keys(&mut app, "R\x08\x08\x08\x08\x08\x08\x08\x08intro.md\n").await;
+        assert!(matches!(app.mode(), Mode::SavePrompt));
+        assert!(app.status_message().contains("docs/intro.md"));
+        type_keys(&mut app, "y").await;
+        assert!(matches!(app.mode(), Mode::Normal));
+        assert!(dir.path().join("docs/intro.md").exists());
+        assert!(app.file_path().unwrap().ends_with("docs/intro.md"));
+        assert_eq!(
+            app.explorer
+                .as_ref()
+                .unwrap()
+                .selected_entry()
+                .unwrap()
+                .path,
+            PathBuf::from("docs/intro.md")
+        );
+
+        // Declining a delete leaves the file alone
+        type_keys(&mut app, "Dn").await;
+        assert!(dir.path().join("docs/intro.md").exists());
+        type_keys(&mut app, "q").await;
+        assert!(app.explorer.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mouse_click_and_drag_selection() {
+        use crate::mouse::PaneRegion;
+        use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+        use ratatui::layout::Rect;
+
+        let mut app = App::new().await.unwrap();
+        app.get_current_editor_mut()
+            .set_content("日本語テキスト\nsecond line".to_string());
+        app.screen_layout.push_pane(PaneRegion {
+            window_id: app.window_manager.current_window_id,
+            buffer_id: app.buffer_manager.current_buffer_id(),
+            text_area: Rect::new(0, 1, 40, 10),
+        });
+
+        let mouse = |kind, column, row| MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+
+        // Column 5 is the right half of the third wide character
+        app.handle_mouse_event(mouse(MouseEventKind::Down(MouseButton::Left), 5, 1))
+            .unwrap();
+        assert_eq!(app.get_current_editor().cursor_position(), (0, 2));
+
+        app.handle_mouse_event(mouse(MouseEventKind::Drag(MouseButton::Left), 3, 2))
+            .unwrap();
+        assert!(matches!(app.mode(), Mode::Visual));
+        assert_eq!(app.get_current_editor().cursor_position(), (1, 3));
+
+        // Mouse events are ignored when disabled in the config
+        app.config.editor.mouse = false;
+        app.handle_mouse_event(mouse(MouseEventKind::Down(MouseButton::Left), 0, 1))
+            .unwrap();
+        assert_eq!(app.get_current_editor().cursor_position(), (1, 3));
+
+        // `:set mouse` asks the main loop to switch the terminal capture once
+        app.config.editor.mouse = true;
+        assert_eq!(app.take_mouse_capture_change(), None);
+        app.run_ex_command("set nomouse").await.unwrap();
+        assert_eq!(app.take_mouse_capture_change(), Some(false));
+        assert_eq!(app.take_mouse_capture_change(), None);
+        app.run_ex_command("set mouse").await.unwrap();
+        assert_eq!(app.take_mouse_capture_change(), Some(true));
+    }
 }
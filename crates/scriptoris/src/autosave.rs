@@ -0,0 +1,122 @@
+//! 自動保存。
+//!
+//! 設定ファイルの `autosave` に従い、一定間隔ごと・キー入力が一定時間
+//! 途絶えたとき・ターミナルがフォーカスを失ったとき・バッファを切り替えた
+//! ときに、変更されたバッファを保存します。名前のないバッファと読み取り専用の
+//! バッファは保存せず、結果はステータスバーに表示するだけで入力の邪魔は
+//! しません。
+
+use std::time::{Duration, Instant};
+
+use crate::config::AutosaveConfig;
+
+/// What triggered an automatic save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutosaveReason {
+    Interval,
+    Idle,
+    FocusLost,
+    BufferSwitch,
+}
+
+impl AutosaveReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            AutosaveReason::Interval => "定期",
+            AutosaveReason::Idle => "アイドル",
+            AutosaveReason::FocusLost => "フォーカス喪失",
+            AutosaveReason::BufferSwitch => "バッファ切り替え",
+        }
+    }
+}
+
+/// Decides when to save, from the time of the last key and the last save.
+/// The poll loop asks `due` about every 100 ms.
+#[derive(Debug, Clone)]
+pub struct Autosave {
+    config: AutosaveConfig,
+    last_activity: Instant,
+    last_save: Instant,
+    requested: Option<AutosaveReason>,
+}
+
+impl Autosave {
+    pub fn new(config: AutosaveConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            config,
+            last_activity: now,
+            last_save: now,
+            requested: None,
+        }
+    }
+
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    pub fn focus_lost(&mut self) {
+        if self.config.on_focus_lost {
+            self.requested = Some(AutosaveReason::FocusLost);
+        }
+    }
+
+    pub fn buffer_switched(&mut self) {
+        if self.config.on_buffer_switch {
+            self.requested = Some(AutosaveReason::BufferSwitch);
+        }
+    }
+
+    /// Whether to save now, and why. Idle saves wait for keys typed since
+    /// the last save, so a quiet editor is not saved over and over.
+    pub fn due(&mut self, now: Instant) -> Option<AutosaveReason> {
+        let reason = self.requested.take().or_else(|| {
+            let seconds = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+            let idle = seconds(self.config.idle_secs).is_some_and(|idle| {
+                self.last_activity > self.last_save && now - self.last_activity >= idle
+            });
+            let interval = seconds(self.config.interval_secs)
+                .is_some_and(|interval| now - self.last_save >= interval);
+            if idle {
+                Some(AutosaveReason::Idle)
+            } else if interval {
+                Some(AutosaveReason::Interval)
+            } else {
+                None
+            }
+        })?;
+        self.last_save = now;
+        Some(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autosave_triggers() {
+        let config = AutosaveConfig {
+            interval_secs: 60,
+            idle_secs: 2,
+            on_focus_lost: true,
+            on_buffer_switch: false,
+        };
+        let mut autosave = Autosave::new(config);
+        let start = autosave.last_save;
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(autosave.due(at(1)), None);
+        autosave.record_activity(at(3));
+        assert_eq!(autosave.due(at(4)), None);
+        assert_eq!(autosave.due(at(5)), Some(AutosaveReason::Idle));
+        // No keys since that save, so only the interval fires
+        assert_eq!(autosave.due(at(30)), None);
+        assert_eq!(autosave.due(at(65)), Some(AutosaveReason::Interval));
+
+        autosave.buffer_switched();
+        assert_eq!(autosave.due(at(66)), None);
+        autosave.focus_lost();
+        assert_eq!(autosave.due(at(66)), Some(AutosaveReason::FocusLost));
+    }
+}
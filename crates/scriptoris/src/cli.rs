@@ -0,0 +1,192 @@
+//! コマンドライン引数の解析。
+//!
+//! Vim に近い書式（`+N`、`+/pattern`、`-o`/`-O`、`-R`、`-S` など）で
+//! 開くファイルと起動時の動作を指定します。
+
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+use crate::app::WindowSplitKind;
+
+/// Printed by `--help`.
+pub const USAGE: &str = "\
+使い方: scriptoris [オプション] [ファイル・ディレクトリ...]
+        some-cmd | scriptoris [オプション] -
+        scriptoris -es -c <コマンド>... [ファイル...]
+
+  -                  標準入力を名前のないバッファに読み込む
+  +N                 N 行目にカーソルを置く
+  +                  最後の行にカーソルを置く
+  +/パターン         最初に一致した位置にカーソルを置く
+  -o                 ファイルを上下に分割して開く
+  -O                 ファイルを左右に分割して開く
+  -R                 読み取り専用で開く
+  -r, --recover      スワップファイルから復旧する（ファイルなしで一覧を表示）
+  -S <セッション>    セッションを読み込む
+  --config <パス>    この設定ファイルを使う
+  -c <コマンド>      ファイルを開いた後に ex コマンドを実行する（複数指定可）
+  -es, --headless    端末を使わずに --script と -c のコマンドを実行して終了する
+  --script <パス>    -es で実行するコマンドを 1 行に 1 つ書いたファイル
+  --cat, --print     ファイル（なければ標準入力）を色付きで出力して終了する
+  -h, --help         このヘルプを表示する
+  -V, --version      バージョンを表示する
+
+ディレクトリを指定するとファイルエクスプローラーで開きます。
+";
+
+/// Where the cursor goes in the first file (`+N`, `+`, `+/pattern`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPosition {
+    /// 1-based line number.
+    Line(usize),
+    Last,
+    Pattern(String),
+}
+
+/// Parsed command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    pub files: Vec<PathBuf>,
+    /// `-o` or `-O`: give every file its own pane, stacked or side by side.
+    pub split: Option<WindowSplitKind>,
+    pub position: Option<StartPosition>,
+    pub readonly: bool,
+    pub recover: bool,
+    pub config: Option<PathBuf>,
+    pub session: Option<String>,
+    /// `--cat`: print the files highlighted instead of editing them.
+    pub print: bool,
+    /// `-c`: ex commands run once the files are open.
+    pub commands: Vec<String>,
+    /// `-es`: run the commands without a terminal and exit.
+    pub headless: bool,
+    /// `--script`: file of commands for the headless mode.
+    pub script: Option<PathBuf>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Args {
+    /// Parse the arguments after the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        let mut only_files = false;
+        while let Some(arg) = args.next() {
+            if only_files || arg == "-" || !arg.starts_with(['-', '+']) {
+                parsed.files.push(PathBuf::from(arg));
+                continue;
+            }
+            if let Some(position) = arg.strip_prefix('+') {
+                parsed.position = Some(parse_position(position)?);
+                continue;
+            }
+            if let Some(path) = arg.strip_prefix("--config=") {
+                parsed.config = Some(PathBuf::from(path));
+                continue;
+            }
+            match arg.as_str() {
+                "--" => only_files = true,
+                "-o" => parsed.split = Some(WindowSplitKind::Horizontal),
+                "-O" => parsed.split = Some(WindowSplitKind::Vertical),
+                "-R" => parsed.readonly = true,
+                "-r" | "--recover" => parsed.recover = true,
+                "--cat" | "--print" => parsed.print = true,
+                "-es" | "--headless" => parsed.headless = true,
+                "-c" => parsed.commands.push(value(&mut args, &arg)?),
+                "--script" => parsed.script = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "-h" | "--help" => parsed.help = true,
+                "-V" | "--version" => parsed.version = true,
+                "-S" => parsed.session = Some(value(&mut args, &arg)?),
+                "--config" => parsed.config = Some(PathBuf::from(value(&mut args, &arg)?)),
+                _ => return Err(anyhow!("不明なオプションです: {}", arg)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Whether `-` asks for standard input to be read.
+    pub fn reads_stdin(&self) -> bool {
+        self.files.iter().any(|path| is_stdin(path))
+    }
+}
+
+/// `-`, the file name that stands for standard input.
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Printed by `--version`.
+pub fn version() -> String {
+    format!("scriptoris {}", env!("CARGO_PKG_VERSION"))
+}
+
+fn parse_position(position: &str) -> Result<StartPosition> {
+    if position.is_empty() {
+        return Ok(StartPosition::Last);
+    }
+    if let Some(pattern) = position.strip_prefix('/') {
+        if pattern.is_empty() {
+            return Err(anyhow!("検索パターンが空です: +/"));
+        }
+        return Ok(StartPosition::Pattern(pattern.to_string()));
+    }
+    position
+        .parse()
+        .map(StartPosition::Line)
+        .map_err(|_| anyhow!("行番号が正しくありません: +{}", position))
+}
+
+fn value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String> {
+    args.next()
+        .ok_or_else(|| anyhow!("{} には引数が必要です", option))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_files_and_options() {
+        let args = parse(&[
+            "-O", "+12", "-R", "a.md", "b.md", "--config", "my.json", "-S", "work",
+        ])
+        .unwrap();
+        assert_eq!(args.files, [PathBuf::from("a.md"), PathBuf::from("b.md")]);
+        assert_eq!(args.split, Some(WindowSplitKind::Vertical));
+        assert_eq!(args.position, Some(StartPosition::Line(12)));
+        assert!(args.readonly);
+        assert_eq!(args.config, Some(PathBuf::from("my.json")));
+        assert_eq!(args.session.as_deref(), Some("work"));
+
+        let args = parse(&["+/TODO list", "--", "-notes.md", "+"]).unwrap();
+        assert_eq!(
+            args.position,
+            Some(StartPosition::Pattern("TODO list".to_string()))
+        );
+        assert_eq!(args.files, [PathBuf::from("-notes.md"), PathBuf::from("+")]);
+        assert_eq!(parse(&["+"]).unwrap().position, Some(StartPosition::Last));
+
+        let args = parse(&["-es", "-c", ":%s/a b/c/g", "-c", "wq", "x.md"]).unwrap();
+        assert!(args.headless);
+        assert_eq!(args.commands, [":%s/a b/c/g", "wq"]);
+        assert_eq!(args.files, [PathBuf::from("x.md")]);
+
+        let args = parse(&["--cat", "-"]).unwrap();
+        assert!(args.print);
+        assert!(args.reads_stdin());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["-x"]).is_err());
+        assert!(parse(&["+abc"]).is_err());
+        assert!(parse(&["-S"]).is_err());
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["-c"]).is_err());
+    }
+}
@@ -7,7 +7,11 @@ use std::path::PathBuf;
 
 use crate::config::Config;
 use crate::editor::Editor;
+use crate::file_format::{Encoding, FileFormat};
 use crate::file_manager::FileManager;
+use crate::keymap::{self, Action, KeyChord, KeymapMode};
+use crate::options::{self, lookup_option, OptionSpec, OptionType, OptionValue, SetArg, OPTIONS};
+use crate::picker::PickerKind;
 use crate::session_manager::SessionManager;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -15,12 +19,31 @@ pub enum CommandAction {
     None,
     Buffer(BufferCommand),
     Window(WindowCommand),
+    Map(MapCommand),
+    /// Open the fuzzy finder with an initial query
+    Picker(PickerKind, String),
+    /// Toggle the file explorer sidebar
+    Explore,
+    /// Show the help screen
+    Help,
+    /// Show the message history in the pager, or clear it
+    Messages {
+        clear: bool,
+    },
+    /// Replace the current buffer with its swap file
+    Recover,
+    /// Show how the buffer differs from its file on disk
+    DiffOrig,
+    /// Run keys as if typed in normal mode (`:normal`)
+    Normal(Vec<KeyChord>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BufferCommand {
     Next,
     Previous,
+    /// Switch to the buffer with this id or file name
+    Switch(String),
     List,
     DeleteCurrent,
 }
@@ -31,6 +54,303 @@ pub enum WindowCommand {
     SplitVertical,
 }
 
+/// Key mapping changes requested by `:map` and friends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapCommand {
+    Set {
+        modes: Vec<KeymapMode>,
+        keys: Vec<KeyChord>,
+        action: Action,
+    },
+    Remove {
+        modes: Vec<KeymapMode>,
+        keys: Vec<KeyChord>,
+    },
+    /// List user mappings, or show the binding of `keys` when given.
+    List {
+        modes: Vec<KeymapMode>,
+        keys: Option<Vec<KeyChord>>,
+    },
+}
+
+/// Metadata of an ex command, shared by completion, `:help` and the
+/// command palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// Argument synopsis; `<...>` arguments are required.
+    pub args: &'static str,
+    pub help: &'static str,
+}
+
+impl CommandSpec {
+    pub fn takes_required_args(&self) -> bool {
+        self.args.starts_with('<')
+    }
+
+    /// `:name args` as shown in help and the palette.
+    pub fn usage(&self) -> String {
+        if self.args.is_empty() {
+            format!(":{}", self.name)
+        } else {
+            format!(":{} {}", self.name, self.args)
+        }
+    }
+}
+
+/// Every ex command. A command missing here is rejected as unknown, so the
+/// table cannot drift from what the processor accepts.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "w",
+        aliases: &[],
+        args: "[ファイル]",
+        help: "保存する。ファイル名を指定すると名前を付けて保存",
+    },
+    CommandSpec {
+        name: "q",
+        aliases: &[],
+        args: "",
+        help: "終了する（未保存の変更があると失敗）",
+    },
+    CommandSpec {
+        name: "w!",
+        aliases: &[],
+        args: "",
+        help: "ディスク上で変更されていても上書き保存する",
+    },
+    CommandSpec {
+        name: "DiffOrig",
+        aliases: &[],
+        args: "",
+        help: "ディスク上のファイルとバッファの差分を表示する",
+    },
+    CommandSpec {
+        name: "q!",
+        aliases: &[],
+        args: "",
+        help: "変更を破棄して終了する",
+    },
+    CommandSpec {
+        name: "wq",
+        aliases: &[],
+        args: "",
+        help: "保存して終了する",
+    },
+    CommandSpec {
+        name: "e",
+        aliases: &[],
+        args: "<ファイル>",
+        help: "ファイルを現在のバッファに開く",
+    },
+    CommandSpec {
+        name: "recover",
+        aliases: &["rec"],
+        args: "[ファイル]",
+        help: "スワップファイルから保存されていない変更を復旧する",
+    },
+    CommandSpec {
+        name: "read",
+        aliases: &["r"],
+        args: "<ファイル>",
+        help: "ファイルの内容をカーソル行の下に挿入する",
+    },
+    CommandSpec {
+        name: "split",
+        aliases: &["sp"],
+        args: "",
+        help: "ウィンドウを水平分割する",
+    },
+    CommandSpec {
+        name: "vsplit",
+        aliases: &["vsp"],
+        args: "",
+        help: "ウィンドウを垂直分割する",
+    },
+    CommandSpec {
+        name: "buffer",
+        aliases: &["b"],
+        args: "<番号|ファイル名>",
+        help: "バッファを切り替える",
+    },
+    CommandSpec {
+        name: "bnext",
+        aliases: &["bn"],
+        args: "",
+        help: "次のバッファに切り替える",
+    },
+    CommandSpec {
+        name: "bprev",
+        aliases: &["bp"],
+        args: "",
+        help: "前のバッファに切り替える",
+    },
+    CommandSpec {
+        name: "buffers",
+        aliases: &["ls"],
+        args: "",
+        help: "開いているバッファを一覧する",
+    },
+    CommandSpec {
+        name: "bdelete",
+        aliases: &["bd"],
+        args: "",
+        help: "現在のバッファを閉じる",
+    },
+    CommandSpec {
+        name: "mksession",
+        aliases: &[],
+        args: "<名前>",
+        help: "セッションを保存する",
+    },
+    CommandSpec {
+        name: "source",
+        aliases: &[],
+        args: "<名前>",
+        help: "セッションを読み込む",
+    },
+    CommandSpec {
+        name: "sessions",
+        aliases: &[],
+        args: "",
+        help: "保存済みのセッションを一覧する",
+    },
+    CommandSpec {
+        name: "delsession",
+        aliases: &[],
+        args: "<名前>",
+        help: "セッションを削除する",
+    },
+    CommandSpec {
+        name: "set",
+        aliases: &[],
+        args: "[オプション[=値|?|!|&] ...]",
+        help: "オプションを表示・変更する（all で一覧）",
+    },
+    CommandSpec {
+        name: "setlocal",
+        aliases: &["setl"],
+        args: "[オプション[=値|?|!|&|<] ...]",
+        help: "現在のバッファだけのオプションを表示・変更する",
+    },
+    CommandSpec {
+        name: "map",
+        aliases: &[],
+        args: "[キー] [アクション]",
+        help: "全モードにキーを割り当てる（引数なしで一覧）",
+    },
+    CommandSpec {
+        name: "nmap",
+        aliases: &[],
+        args: "[キー] [アクション]",
+        help: "ノーマルモードにキーを割り当てる",
+    },
+    CommandSpec {
+        name: "imap",
+        aliases: &[],
+        args: "[キー] [アクション]",
+        help: "挿入モードにキーを割り当てる",
+    },
+    CommandSpec {
+        name: "vmap",
+        aliases: &[],
+        args: "[キー] [アクション]",
+        help: "ビジュアルモードにキーを割り当てる",
+    },
+    CommandSpec {
+        name: "unmap",
+        aliases: &[],
+        args: "<キー>",
+        help: "全モードのキー割り当てを削除する",
+    },
+    CommandSpec {
+        name: "nunmap",
+        aliases: &[],
+        args: "<キー>",
+        help: "ノーマルモードのキー割り当てを削除する",
+    },
+    CommandSpec {
+        name: "iunmap",
+        aliases: &[],
+        args: "<キー>",
+        help: "挿入モードのキー割り当てを削除する",
+    },
+    CommandSpec {
+        name: "vunmap",
+        aliases: &[],
+        args: "<キー>",
+        help: "ビジュアルモードのキー割り当てを削除する",
+    },
+    CommandSpec {
+        name: "search",
+        aliases: &[],
+        args: "<文字列>",
+        help: "文字列を検索する（/ と同じ）",
+    },
+    CommandSpec {
+        name: "replace",
+        aliases: &[],
+        args: "<検索文字列> <置換文字列>",
+        help: "バッファ内のすべての一致を置換する",
+    },
+    CommandSpec {
+        name: "s",
+        aliases: &["substitute"],
+        args: "/<検索文字列>/<置換文字列>/[ge]",
+        help: "範囲内の文字列を置換する（:%s/前/後/g）",
+    },
+    CommandSpec {
+        name: "normal",
+        aliases: &["norm"],
+        args: "<キー>",
+        help: "ノーマルモードでキーを入力したように実行する",
+    },
+    CommandSpec {
+        name: "find",
+        aliases: &[],
+        args: "[検索文字列]",
+        help: "プロジェクトのファイルをあいまい検索する",
+    },
+    CommandSpec {
+        name: "pick",
+        aliases: &[],
+        args: "<files|buffers|recent|headings|commands> [検索文字列]",
+        help: "ファイル・バッファ・最近のファイル・見出し・コマンドから選ぶ",
+    },
+    CommandSpec {
+        name: "palette",
+        aliases: &[],
+        args: "[検索文字列]",
+        help: "コマンドとアクションの一覧（コマンドパレット）を開く",
+    },
+    CommandSpec {
+        name: "Explore",
+        aliases: &["Ex"],
+        args: "",
+        help: "ファイルエクスプローラーを開く・閉じる",
+    },
+    CommandSpec {
+        name: "help",
+        aliases: &["h"],
+        args: "[コマンド]",
+        help: "ヘルプを表示する。コマンド名を指定するとその説明を表示",
+    },
+    CommandSpec {
+        name: "messages",
+        aliases: &["mes"],
+        args: "[clear]",
+        help: "これまでのメッセージの履歴を表示する。clear で消去",
+    },
+];
+
+/// The command called `name` or one of its aliases.
+pub fn lookup_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS
+        .iter()
+        .find(|spec| spec.name == name || spec.aliases.contains(&name))
+}
+
 pub struct CommandProcessor {
     session_manager: SessionManager,
     pending_action: Option<CommandAction>,
@@ -68,6 +388,11 @@ impl CommandProcessor {
         self.pending_action.take()
     }
 
+    /// Names of the saved sessions, for completing `:source` and friends.
+    pub fn session_names(&self) -> Vec<String> {
+        self.session_manager.session_names()
+    }
+
     pub async fn execute_command(
         &mut self,
         command: &str,
@@ -113,13 +438,46 @@ impl CommandProcessor {
         // Handle search commands (starting with search)
         if let Some(query) = cmd.strip_prefix("search ") {
             if !query.is_empty() {
-                editor.search(query);
+                if !editor.search(query) {
+                    return Err(anyhow::anyhow!("E486: パターンが見つかりません: {}", query));
+                }
                 return Ok(format!("検索: {}", query));
             } else {
                 return Err(anyhow::anyhow!("検索文字列が空です"));
             }
         }
 
+        // The replacement text may contain spaces
+        if let Some(args) = cmd.strip_prefix("replace ") {
+            return match args.trim_start().split_once(char::is_whitespace) {
+                Some((from, to)) => {
+                    let count = editor.replace_all(from, to);
+                    if count == 0 {
+                        Err(anyhow::anyhow!("見つかりません: {}", from))
+                    } else {
+                        Ok(format!("{} 箇所を置換しました", count))
+                    }
+                }
+                None => Err(anyhow::anyhow!(
+                    "使い方: :replace <検索文字列> <置換文字列>"
+                )),
+            };
+        }
+
+        // `:[range]s/from/to/[flags]`; the text may contain spaces
+        if let Some(substitute) = Substitute::parse(cmd) {
+            return substitute?.run(editor);
+        }
+
+        // The keys may contain spaces
+        if let Some((_, keys)) = cmd
+            .split_once(' ')
+            .filter(|(name, _)| matches!(*name, "normal" | "norm"))
+        {
+            self.pending_action = Some(CommandAction::Normal(keymap::parse_keys(keys.trim())?));
+            return Ok(String::new());
+        }
+
         // Handle vim-style commands
         let parts: Vec<&str> = cmd.split_whitespace().collect();
         if parts.is_empty() {
@@ -162,15 +520,31 @@ impl CommandProcessor {
         if parts.is_empty() {
             return Ok(String::new());
         }
+        if lookup_command(parts[0]).is_none() && parts[0].parse::<usize>().is_err() {
+            return Err(anyhow::anyhow!("E492: 未定義のコマンドです: {}", parts[0]));
+        }
 
         match parts[0] {
-            "w" => self.handle_save_command(parts, editor, file_manager).await,
+            "w" | "w!" => self.handle_save_command(parts, editor, file_manager).await,
+            "DiffOrig" => {
+                self.pending_action = Some(CommandAction::DiffOrig);
+                Ok(String::new())
+            }
             "q" | "q!" => self.handle_quit_command(parts, editor, should_quit),
             "wq" => {
                 self.handle_save_quit_command(editor, file_manager, should_quit)
                     .await
             }
             "e" => self.handle_edit_command(parts, file_manager, editor).await,
+            "recover" | "rec" => {
+                if parts.len() > 1 {
+                    self.handle_edit_command(parts, file_manager, editor)
+                        .await?;
+                }
+                self.pending_action = Some(CommandAction::Recover);
+                Ok(String::new())
+            }
+            "read" | "r" => self.handle_read_command(parts, editor).await,
             "split" | "sp" | "vsplit" | "vsp" | "bnext" | "bn" | "bprev" | "bp" | "buffers"
             | "ls" | "bdelete" | "bd" => self.handle_window_buffer_commands(parts[0]),
             "mksession" => {
@@ -183,7 +557,79 @@ impl CommandProcessor {
             }
             "sessions" => self.handle_session_list_command().await,
             "delsession" => self.handle_session_delete_command(parts).await,
-            "set" => self.handle_set_command(parts, config).await,
+            "b" | "buffer" => match parts.get(1) {
+                Some(target) => {
+                    self.pending_action = Some(CommandAction::Buffer(BufferCommand::Switch(
+                        target.to_string(),
+                    )));
+                    Ok(String::new())
+                }
+                None => Err(anyhow::anyhow!("使い方: :b <番号|ファイル名>")),
+            },
+            "set" => self.handle_set_command(parts, false, editor, config).await,
+            "setlocal" | "setl" => self.handle_set_command(parts, true, editor, config).await,
+            "map" | "nmap" | "imap" | "vmap" | "unmap" | "nunmap" | "iunmap" | "vunmap" => {
+                self.handle_map_command(parts)
+            }
+            "find" => {
+                self.pending_action = Some(CommandAction::Picker(
+                    PickerKind::Files,
+                    parts[1..].join(" "),
+                ));
+                Ok(String::new())
+            }
+            "pick" => match parts.get(1).and_then(|name| PickerKind::from_name(name)) {
+                Some(kind) => {
+                    self.pending_action = Some(CommandAction::Picker(kind, parts[2..].join(" ")));
+                    Ok(String::new())
+                }
+                None => Err(anyhow::anyhow!(
+                    "使い方: :pick <files|buffers|recent|headings|commands> [検索文字列]"
+                )),
+            },
+            "palette" => {
+                self.pending_action = Some(CommandAction::Picker(
+                    PickerKind::Commands,
+                    parts[1..].join(" "),
+                ));
+                Ok(String::new())
+            }
+            "Explore" | "Ex" => {
+                self.pending_action = Some(CommandAction::Explore);
+                Ok(String::new())
+            }
+            "help" | "h" => match parts.get(1) {
+                Some(name) => {
+                    let name = name.trim_start_matches(':');
+                    let spec = lookup_command(name)
+                        .ok_or_else(|| anyhow::anyhow!("E149: ヘルプがありません: {}", name))?;
+                    Ok(format!("{} — {}", spec.usage(), spec.help))
+                }
+                None => {
+                    self.pending_action = Some(CommandAction::Help);
+                    Ok(String::new())
+                }
+            },
+            "messages" | "mes" => match parts.get(1) {
+                None | Some(&"clear") => {
+                    self.pending_action = Some(CommandAction::Messages {
+                        clear: parts.len() > 1,
+                    });
+                    Ok(String::new())
+                }
+                Some(arg) => Err(anyhow::anyhow!("E474: 無効な引数です: {}", arg)),
+            },
+            // Only reached without arguments; see execute_command_safe
+            "search" => Err(anyhow::anyhow!("検索文字列が空です")),
+            "replace" => Err(anyhow::anyhow!(
+                "使い方: :replace <検索文字列> <置換文字列>"
+            )),
+            "normal" | "norm" => Err(anyhow::anyhow!("使い方: :normal <キー>")),
+            line if line.parse::<usize>().is_ok() => {
+                let line: usize = line.parse()?;
+                editor.set_cursor_position(line.saturating_sub(1), 0);
+                Ok(String::new())
+            }
             _ => Err(anyhow::anyhow!("E492: 未定義のコマンドです: {}", parts[0])),
         }
     }
@@ -210,7 +656,12 @@ impl CommandProcessor {
                 }
             }
         } else if file_manager.has_file() {
-            match file_manager.save_file(editor).await {
+            let result = if parts[0] == "w!" {
+                file_manager.overwrite_file(editor).await
+            } else {
+                file_manager.save_file(editor).await
+            };
+            match result {
                 Ok(result) => Ok(result),
                 Err(e) => {
                     log::error!("Save failed: {}", e);
@@ -238,7 +689,9 @@ impl CommandProcessor {
 
         // :q - quit
         if editor.is_modified() {
-            Ok("変更が保存されていません (:q! で強制終了)".to_string())
+            Err(anyhow::anyhow!(
+                "E37: 変更が保存されていません (:q! で強制終了)"
+            ))
         } else {
             *should_quit = true;
             Ok("終了します".to_string())
@@ -272,17 +725,44 @@ impl CommandProcessor {
         file_manager: &mut FileManager,
         editor: &mut Editor,
     ) -> Result<String> {
-        if parts.len() <= 1 {
-            return Err(anyhow::anyhow!("E471: 引数が必要です (:e <filename>)"));
-        }
-
-        let path = PathBuf::from(parts[1]);
+        // `:e ++enc=sjis [file]`; without a file the current one is read again
+        let (encoding, args) = match parts.get(1).and_then(|arg| arg.strip_prefix("++enc=")) {
+            Some(name) => {
+                let encoding = Encoding::from_name(name)
+                    .ok_or_else(|| anyhow::anyhow!("E474: 未知の文字コードです: {}", name))?;
+                (Some(encoding), &parts[2..])
+            }
+            None => (None, &parts[1..]),
+        };
+        let path = match (args.first(), encoding) {
+            (Some(file), _) => PathBuf::from(file),
+            (None, Some(_)) => {
+                let path = file_manager
+                    .get_current_path()
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("E32: ファイル名がありません"))?;
+                if editor.is_modified() {
+                    return Err(anyhow::anyhow!(
+                        "E37: 最後の変更が保存されていません (:w で保存)"
+                    ));
+                }
+                path
+            }
+            (None, None) => return Err(anyhow::anyhow!("E471: 引数が必要です (:e <filename>)")),
+        };
         log::info!("Edit command with path: {:?}", path);
 
-        match file_manager.open_file(path).await {
+        match file_manager.open_file_as(path, encoding).await {
             Ok(content) => {
-                editor.set_content(content);
-                Ok("ファイルを開きました".to_string())
+                editor.load_file(content, file_manager.format, file_manager.large_file);
+                let mut message = "ファイルを開きました".to_string();
+                if file_manager.large_file {
+                    message.push_str(&format!(" ({})", crate::app::LARGE_FILE_NOTE));
+                }
+                if let Some(warning) = file_manager.format.mixed_endings_warning() {
+                    message.push_str(&format!(" ({})", warning));
+                }
+                Ok(message)
             }
             Err(e) => {
                 log::error!("File open failed: {}", e);
@@ -291,6 +771,24 @@ impl CommandProcessor {
         }
     }
 
+    async fn handle_read_command(&self, parts: &[&str], editor: &mut Editor) -> Result<String> {
+        if parts.len() <= 1 {
+            return Err(anyhow::anyhow!("E471: 引数が必要です (:r <filename>)"));
+        }
+
+        let path = PathBuf::from(parts[1]);
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| anyhow::anyhow!("E484: ファイルを読み込めません: {}: {}", parts[1], e))?;
+        let (content, _) = FileFormat::decode(&bytes, None);
+        editor.insert_lines_below(&content);
+        Ok(format!(
+            "{} 行を読み込みました: {}",
+            content.lines().count(),
+            path.display()
+        ))
+    }
+
     fn handle_window_buffer_commands(&mut self, command: &str) -> Result<String> {
         self.pending_action = Some(match command {
             "split" | "sp" => CommandAction::Window(WindowCommand::SplitHorizontal),
@@ -351,7 +849,7 @@ impl CommandProcessor {
                 if let Some(file_path) = &session_data.current_file {
                     match file_manager.open_file(file_path.clone()).await {
                         Ok(content) => {
-                            editor.set_content(content);
+                            editor.load_file(content, file_manager.format, file_manager.large_file);
                         }
                         Err(e) => {
                             log::error!("Failed to open session file: {}", e);
@@ -366,7 +864,8 @@ impl CommandProcessor {
                     editor.set_content(String::new());
                 }
 
-                // Restore other session data
+                // Restore other session data; folds first so the cursor lands on a visible line
+                editor.restore_closed_folds(session_data.folds);
                 editor.set_cursor_position(session_data.cursor_line, session_data.cursor_col);
                 editor.set_viewport_offset(session_data.viewport_offset);
 
@@ -431,36 +930,295 @@ impl CommandProcessor {
         }
     }
 
-    async fn handle_set_command(&self, parts: &[&str], config: &mut Config) -> Result<String> {
-        if parts.len() != 3 || parts[1] != "theme" {
-            return Err(anyhow::anyhow!("使い方: :set theme <テーマ名>"));
+    fn handle_map_command(&mut self, parts: &[&str]) -> Result<String> {
+        let name = parts[0];
+        let modes = match name.trim_end_matches("map").trim_end_matches("un") {
+            "n" => vec![KeymapMode::Normal],
+            "i" => vec![KeymapMode::Insert],
+            "v" => vec![KeymapMode::Visual],
+            _ => vec![KeymapMode::Normal, KeymapMode::Visual],
+        };
+
+        let command = if name.ends_with("unmap") {
+            if parts.len() != 2 {
+                return Err(anyhow::anyhow!("使い方: :{} <キー>", name));
+            }
+            MapCommand::Remove {
+                modes,
+                keys: keymap::parse_keys(parts[1])?,
+            }
+        } else if parts.len() <= 2 {
+            MapCommand::List {
+                modes,
+                keys: parts
+                    .get(1)
+                    .map(|keys| keymap::parse_keys(keys))
+                    .transpose()?,
+            }
+        } else {
+            MapCommand::Set {
+                modes,
+                keys: keymap::parse_keys(parts[1])?,
+                action: Action::parse(&parts[2..].join(" "))?,
+            }
+        };
+        self.pending_action = Some(CommandAction::Map(command));
+        Ok(String::new())
+    }
+
+    async fn handle_set_command(
+        &self,
+        parts: &[&str],
+        local: bool,
+        editor: &mut Editor,
+        config: &mut Config,
+    ) -> Result<String> {
+        let words = join_escaped_spaces(&parts[1..]);
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let args: Vec<String> = match words.as_slice() {
+            [] => return Ok(list_options(local, false, editor, config)),
+            ["all"] => return Ok(list_options(local, true, editor, config)),
+            // `:set theme <name>`, accepted before options took `=`
+            [name, value]
+                if lookup_option(name).is_some_and(|spec| spec.kind != OptionType::Bool) =>
+            {
+                vec![format!("{}={}", name, value)]
+            }
+            args => args.iter().map(|arg| arg.to_string()).collect(),
+        };
+
+        let mut shown = Vec::new();
+        let mut changed = Vec::new();
+        let mut changed_global = false;
+        for arg in &args {
+            let (spec, value) = match options::parse_set_arg(arg)? {
+                SetArg::Show(spec) => {
+                    shown.push(spec.display(&option_value(editor, config, spec)));
+                    continue;
+                }
+                SetArg::UseGlobal(spec) => {
+                    editor.local_options_mut().clear(spec);
+                    changed.push(spec.display(&options::get_global(config, spec)));
+                    continue;
+                }
+                SetArg::Assign(spec, value) => (spec, value),
+                SetArg::Toggle(spec) => {
+                    let value = option_value(editor, config, spec);
+                    (spec, OptionValue::Bool(!value.as_bool()))
+                }
+                SetArg::Default(spec) => (spec, options::default_value(spec)),
+            };
+            let mut format = editor.file_format();
+            if options::set_file_option(&mut format, spec, &value) {
+                // A different encoding or line ending needs a save to reach the file
+                if format != editor.file_format() {
+                    editor.set_file_format(format);
+                    editor.mark_modified();
+                }
+                changed.push(spec.display(&value));
+                continue;
+            }
+            if local {
+                if !spec.local {
+                    return Err(anyhow::anyhow!(
+                        "E520: {} はバッファごとに設定できません",
+                        spec.name
+                    ));
+                }
+                editor.local_options_mut().set(spec, value.clone());
+            } else {
+                // Like Vim, `:set` also drops the current buffer's local value
+                options::set_global(config, spec, value.clone());
+                editor.local_options_mut().clear(spec);
+                changed_global = true;
+            }
+            log::info!("Option set: {}", spec.display(&value));
+            changed.push(spec.display(&value));
         }
 
-        let theme = parts[2];
-        log::info!("Setting theme to: {}", theme);
+        let mut message = shown.join("  ");
+        if !changed.is_empty() {
+            if !message.is_empty() {
+                message.push_str("  ");
+            }
+            message.push_str(&format!("設定しました: {}", changed.join(" ")));
+        }
+        if changed_global {
+            if let Err(e) = config.save().await {
+                log::error!("Failed to save config after option change: {}", e);
+                message.push_str(" (設定の保存に失敗)");
+            }
+        }
+        Ok(message)
+    }
+}
+
+/// A parsed `:[range]s/from/to/[flags]`. Matching is literal.
+#[derive(Debug, PartialEq, Eq)]
+struct Substitute {
+    /// Lines as written: `None` for the cursor line, `%` for all lines.
+    range: Option<(String, String)>,
+    from: String,
+    to: String,
+    /// `g`: every match in a line, not just the first
+    global: bool,
+    /// `e`: no error when nothing matches
+    quiet: bool,
+}
 
-        // Validate theme name
-        if theme.is_empty() || theme.len() > 100 {
-            return Err(anyhow::anyhow!("無効なテーマ名です"));
+impl Substitute {
+    /// `None` when `cmd` is not a substitute command at all.
+    fn parse(cmd: &str) -> Option<Result<Self>> {
+        let range_len = cmd
+            .find(|c: char| !(c.is_ascii_digit() || "%.$,".contains(c)))
+            .unwrap_or(cmd.len());
+        let (range, rest) = cmd.split_at(range_len);
+        let rest = rest
+            .strip_prefix("substitute")
+            .or_else(|| rest.strip_prefix('s'))?;
+        let delimiter = match rest.chars().next() {
+            None => return Some(Err(Self::usage())),
+            Some(c) if c.is_alphanumeric() || c.is_whitespace() || c == '\\' => return None,
+            Some(c) => c,
+        };
+        Some(Self::parse_body(
+            range,
+            &rest[delimiter.len_utf8()..],
+            delimiter,
+        ))
+    }
+
+    fn parse_body(range: &str, body: &str, delimiter: char) -> Result<Self> {
+        // Split on delimiters not preceded by a backslash
+        let mut fields = vec![String::new()];
+        let mut chars = body.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\\' && chars.peek() == Some(&delimiter) {
+                fields.last_mut().unwrap().push(delimiter);
+                chars.next();
+            } else if c == delimiter && fields.len() < 3 {
+                fields.push(String::new());
+            } else {
+                fields.last_mut().unwrap().push(c);
+            }
+        }
+        let mut fields = fields.into_iter();
+        let from = fields.next().unwrap_or_default();
+        let to = fields.next().unwrap_or_default();
+        let flags = fields.next().unwrap_or_default();
+        if from.is_empty() {
+            return Err(Self::usage());
+        }
+        if let Some(flag) = flags.trim().chars().find(|c| !"ge".contains(*c)) {
+            return Err(anyhow::anyhow!("E488: 余分な文字があります: {}", flag));
         }
 
-        config.theme.syntax_theme = theme.to_string();
+        let range = match range {
+            "" => None,
+            "%" => Some(("1".to_string(), "$".to_string())),
+            range => {
+                let (first, last) = range.split_once(',').unwrap_or((range, range));
+                Some((first.to_string(), last.to_string()))
+            }
+        };
+        Ok(Self {
+            range,
+            from,
+            to,
+            global: flags.contains('g'),
+            quiet: flags.contains('e'),
+        })
+    }
 
-        // Persist configuration with error handling
-        match config.save().await {
-            Ok(_) => {
-                log::info!("Configuration saved with new theme: {}", theme);
-                Ok(format!("テーマを '{}' に設定しました", theme))
+    fn usage() -> anyhow::Error {
+        anyhow::anyhow!("使い方: :[範囲]s/検索文字列/置換文字列/[ge]")
+    }
+
+    fn run(&self, editor: &mut Editor) -> Result<String> {
+        let current = editor.cursor_position().0;
+        let last_line = editor.line_count().saturating_sub(1);
+        let line = |spec: &str| -> Result<usize> {
+            match spec {
+                "." => Ok(current),
+                "$" => Ok(last_line),
+                number => match number.parse::<usize>() {
+                    Ok(n) if n >= 1 && n <= last_line + 1 => Ok(n - 1),
+                    _ => Err(anyhow::anyhow!("E16: 範囲が正しくありません: {}", number)),
+                },
             }
-            Err(e) => {
-                log::error!("Failed to save config after theme change: {}", e);
-                Ok(format!(
-                    "テーマを '{}' に設定しました (設定の保存に失敗)",
-                    theme
-                ))
+        };
+        let (first, last) = match &self.range {
+            None => (current, current),
+            Some((first, last)) => (line(first)?, line(last)?),
+        };
+        let count = editor.substitute(
+            first.min(last),
+            first.max(last),
+            &self.from,
+            &self.to,
+            self.global,
+        );
+        match count {
+            0 if self.quiet => Ok(String::new()),
+            0 => Err(anyhow::anyhow!(
+                "E486: パターンが見つかりません: {}",
+                self.from
+            )),
+            count => Ok(format!("{} 箇所を置換しました", count)),
+        }
+    }
+}
+
+/// Rejoin words split at a backslash-escaped space, so that
+/// `:set listchars=tab:>\ ,eol:$` keeps the space in the value.
+fn join_escaped_spaces(words: &[&str]) -> Vec<String> {
+    let mut joined: Vec<String> = Vec::new();
+    for word in words {
+        match joined.last_mut() {
+            Some(last) if last.ends_with('\\') => {
+                last.pop();
+                last.push(' ');
+                last.push_str(word);
             }
+            _ => joined.push(word.to_string()),
         }
     }
+    joined
+}
+
+/// The value of `spec` for the buffer being edited.
+fn option_value(editor: &Editor, config: &Config, spec: &OptionSpec) -> OptionValue {
+    options::get_file_option(&editor.file_format(), spec)
+        .unwrap_or_else(|| options::effective_value(config, editor.local_options(), spec))
+}
+
+/// The options listed by a bare `:set` (those changed from their defaults),
+/// `:set all`, or `:setlocal` (the buffer's local values).
+fn list_options(local: bool, all: bool, editor: &Editor, config: &Config) -> String {
+    let listed: Vec<String> = if local {
+        editor
+            .local_options()
+            .iter()
+            .map(|(spec, value)| spec.display(value))
+            .collect()
+    } else {
+        OPTIONS
+            .iter()
+            .filter_map(|spec| {
+                let value = option_value(editor, config, spec);
+                (all || value != options::default_value(spec)).then(|| spec.display(&value))
+            })
+            .collect()
+    };
+    if listed.is_empty() {
+        if local {
+            "このバッファにローカルなオプションはありません".to_string()
+        } else {
+            "既定値から変更されたオプションはありません".to_string()
+        }
+    } else {
+        listed.join("  ")
+    }
 }
 
 #[cfg(test)]
@@ -515,12 +1273,9 @@ mod tests {
                 &mut should_quit,
             )
             .await;
-        if let Err(e) = &result {
-            eprintln!("Second q command error: {}", e);
-        }
-        let message = result.expect("result should contain warning");
+        let error = result.expect_err("quitting a modified buffer should fail");
         assert!(!should_quit); // Should not quit due to modifications
-        assert!(message.contains("変更が保存されていません"));
+        assert!(error.to_string().starts_with("E37"));
 
         // Test force quit
         let mut config = Config::default();
@@ -568,6 +1323,104 @@ mod tests {
         assert_eq!(col, 6); // "World" starts at column 6 in "Hello World"
     }
 
+    #[tokio::test]
+    async fn test_replace_and_line_number_commands() {
+        let mut editor = Editor::new();
+        let mut file_manager = FileManager::new();
+        let mut config = Config::default();
+        let mut should_quit = false;
+        editor.set_content("one two\ntwo three\n".to_string());
+
+        let mut cp = CommandProcessor::new().expect("command processor should initialize");
+        let message = cp
+            .execute_command(
+                "replace two 2 and",
+                &mut editor,
+                &mut file_manager,
+                &mut config,
+                &mut should_quit,
+            )
+            .await
+            .expect("replace should succeed");
+        assert!(message.contains("2 箇所"));
+        assert_eq!(editor.get_content(), "one 2 and\n2 and three\n");
+
+        cp.execute_command(
+            "2",
+            &mut editor,
+            &mut file_manager,
+            &mut config,
+            &mut should_quit,
+        )
+        .await
+        .expect("line number should move the cursor");
+        assert_eq!(editor.cursor_position(), (1, 0));
+
+        let result = cp
+            .execute_command(
+                "replace missing x",
+                &mut editor,
+                &mut file_manager,
+                &mut config,
+                &mut should_quit,
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_substitute_command() {
+        assert!(Substitute::parse("set nu").is_none());
+        assert!(Substitute::parse("12").is_none());
+        assert!(Substitute::parse("s").unwrap().is_err());
+        assert!(Substitute::parse("s/a/b/x").unwrap().is_err());
+        let parsed = Substitute::parse("%s#a/b#c d#g").unwrap().unwrap();
+        assert_eq!((parsed.from.as_str(), parsed.to.as_str()), ("a/b", "c d"));
+        assert!(parsed.global);
+        let parsed = Substitute::parse(r"s/a\/b/c").unwrap().unwrap();
+        assert_eq!((parsed.from.as_str(), parsed.to.as_str()), ("a/b", "c"));
+
+        let mut editor = Editor::new();
+        let mut file_manager = FileManager::new();
+        let mut config = Config::default();
+        let mut should_quit = false;
+        let mut cp = CommandProcessor::new().unwrap();
+        editor.set_content("x x\nx x\nx x\n".to_string());
+        for (cmd, expected) in [
+            ("s/x/y/", "y x\nx x\nx x\n"),
+            ("2,$s/x/z/g", "y x\nz z\nz z\n"),
+            ("%s/x/w/", "y w\nz z\nz z\n"),
+        ] {
+            let message = cp
+                .execute_command(
+                    cmd,
+                    &mut editor,
+                    &mut file_manager,
+                    &mut config,
+                    &mut should_quit,
+                )
+                .await
+                .unwrap();
+            assert!(message.contains("置換しました"), "{}", cmd);
+            assert_eq!(editor.get_content(), expected);
+        }
+        assert_eq!(editor.cursor_position(), (0, 0));
+        for cmd in ["s/missing/x/", "5s/y/x/"] {
+            assert!(cp
+                .execute_command(
+                    cmd,
+                    &mut editor,
+                    &mut file_manager,
+                    &mut config,
+                    &mut should_quit
+                )
+                .await
+                .is_err());
+        }
+        assert!(editor.undo());
+        assert_eq!(editor.get_content(), "y x\nz z\nz z\n");
+    }
+
     #[tokio::test]
     async fn test_file_operations() {
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -634,4 +1487,176 @@ mod tests {
             other => panic!("unexpected action: {:?}", other),
         }
     }
+
+    async fn run_for_action(
+        processor: &mut CommandProcessor,
+        command: &str,
+    ) -> Result<Option<CommandAction>> {
+        let mut editor = Editor::new();
+        let mut file_manager = FileManager::new();
+        let mut config = Config::default();
+        let mut should_quit = false;
+        processor
+            .execute_command(
+                command,
+                &mut editor,
+                &mut file_manager,
+                &mut config,
+                &mut should_quit,
+            )
+            .await?;
+        Ok(processor.take_pending_action())
+    }
+
+    #[tokio::test]
+    async fn test_map_commands_emit_pending_action() {
+        let mut processor = CommandProcessor::new().expect("command processor should initialize");
+
+        assert_eq!(
+            run_for_action(&mut processor, "nmap <C-s> :w")
+                .await
+                .unwrap(),
+            Some(CommandAction::Map(MapCommand::Set {
+                modes: vec![KeymapMode::Normal],
+                keys: keymap::parse_keys("<C-s>").unwrap(),
+                action: Action::Ex("w".to_string()),
+            }))
+        );
+        assert_eq!(
+            run_for_action(&mut processor, "iunmap jk").await.unwrap(),
+            Some(CommandAction::Map(MapCommand::Remove {
+                modes: vec![KeymapMode::Insert],
+                keys: keymap::parse_keys("jk").unwrap(),
+            }))
+        );
+        assert_eq!(
+            run_for_action(&mut processor, "map").await.unwrap(),
+            Some(CommandAction::Map(MapCommand::List {
+                modes: vec![KeymapMode::Normal, KeymapMode::Visual],
+                keys: None,
+            }))
+        );
+        assert!(run_for_action(&mut processor, "imap jk no_such_action")
+            .await
+            .is_err());
+        assert!(run_for_action(&mut processor, "unmap").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_registry_matches_dispatch() {
+        let mut processor = CommandProcessor::new().unwrap();
+        for spec in COMMANDS {
+            for name in std::iter::once(&spec.name).chain(spec.aliases) {
+                if let Err(e) = run_for_action(&mut processor, name).await {
+                    assert!(!e.to_string().starts_with("E492"), "{}: {}", name, e);
+                }
+            }
+        }
+        assert!(run_for_action(&mut processor, "frobnicate").await.is_err());
+        assert!(lookup_command("vsp").is_some_and(|spec| spec.name == "vsplit"));
+
+        let mut editor = Editor::new();
+        let mut file_manager = FileManager::new();
+        let mut config = Config::default();
+        let mut should_quit = false;
+        let help = processor
+            .execute_command(
+                "help :e",
+                &mut editor,
+                &mut file_manager,
+                &mut config,
+                &mut should_quit,
+            )
+            .await
+            .unwrap();
+        assert!(help.starts_with(":e <ファイル>"));
+        assert_eq!(
+            run_for_action(&mut processor, "help").await.unwrap(),
+            Some(CommandAction::Help)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_picker_commands_emit_pending_action() {
+        let mut processor = CommandProcessor::new().expect("command processor should initialize");
+
+        assert_eq!(
+            run_for_action(&mut processor, "find app rs").await.unwrap(),
+            Some(CommandAction::Picker(
+                PickerKind::Files,
+                "app rs".to_string()
+            ))
+        );
+        assert_eq!(
+            run_for_action(&mut processor, "pick headings")
+                .await
+                .unwrap(),
+            Some(CommandAction::Picker(PickerKind::Headings, String::new()))
+        );
+        assert!(run_for_action(&mut processor, "pick nothing")
+            .await
+            .is_err());
+        assert_eq!(
+            run_for_action(&mut processor, "Explore").await.unwrap(),
+            Some(CommandAction::Explore)
+        );
+        assert_eq!(
+            run_for_action(&mut processor, "mes clear").await.unwrap(),
+            Some(CommandAction::Messages { clear: true })
+        );
+        assert!(run_for_action(&mut processor, "messages all")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_setlocal_overrides_and_queries() {
+        async fn run(
+            processor: &mut CommandProcessor,
+            editor: &mut Editor,
+            command: &str,
+        ) -> Result<String> {
+            let mut config = Config::default();
+            processor
+                .execute_command(
+                    command,
+                    editor,
+                    &mut FileManager::new(),
+                    &mut config,
+                    &mut false,
+                )
+                .await
+        }
+        let mut processor = CommandProcessor::new().unwrap();
+        let mut editor = Editor::new();
+
+        let message = run(&mut processor, &mut editor, "setlocal ts=2 nowrap list!")
+            .await
+            .unwrap();
+        assert_eq!(message, "設定しました: tabstop=2 nowrap list");
+        assert_eq!(
+            run(&mut processor, &mut editor, "set ts? tw")
+                .await
+                .unwrap(),
+            "tabstop=2  textwidth=0"
+        );
+        assert_eq!(
+            run(&mut processor, &mut editor, "setlocal").await.unwrap(),
+            "list  tabstop=2  nowrap"
+        );
+
+        run(&mut processor, &mut editor, "setl ts< list&")
+            .await
+            .unwrap();
+        assert_eq!(
+            run(&mut processor, &mut editor, "setlocal").await.unwrap(),
+            "nolist  nowrap"
+        );
+
+        let error = run(&mut processor, &mut editor, "setlocal mouse").await;
+        assert!(error.unwrap_err().to_string().starts_with("E520"));
+        let error = run(&mut processor, &mut editor, "set bogus").await;
+        assert!(error.unwrap_err().to_string().starts_with("E518"));
+        assert!(run(&mut processor, &mut editor, "set ts=99").await.is_err());
+    }
 }
@@ -0,0 +1,208 @@
+//! コマンドラインの補完。
+//!
+//! 入力中の行を解析して、補完する語の開始位置と種類（コマンド名・ファイル・
+//! セッション名・`:set` のオプションと値・バッファ名など）を決めます。
+//! 候補が複数あるときはワイルドメニューに並べ、Tab / Shift-Tab で巡回します。
+
+use std::fs;
+use std::path::Path;
+
+use crate::command_processor::lookup_command;
+
+/// Arguments accepted by `:pick`.
+pub const PICKER_NAMES: &[&str] = &["buffers", "commands", "files", "headings", "recent"];
+
+/// What the word being typed names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionKind {
+    Command,
+    File,
+    Session,
+    SetOption,
+    SetValue(String),
+    Buffer,
+    Picker,
+    Action,
+}
+
+/// Byte offset where the word under completion starts, and what it names.
+/// `None` when the command takes no argument at that position.
+pub fn context(line: &str) -> Option<(usize, CompletionKind)> {
+    let start = line
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let mut words = line[..start].split_whitespace();
+    let Some(command) = words.next() else {
+        return Some((start, CompletionKind::Command));
+    };
+    let previous: Vec<&str> = words.collect();
+    let name = lookup_command(command)?.name;
+
+    // `:set opt=val` completes the value after `=`
+    if matches!(name, "set" | "setlocal") {
+        if let Some(eq) = line[start..].find('=') {
+            let option = line[start..start + eq].to_string();
+            return Some((start + eq + 1, CompletionKind::SetValue(option)));
+        }
+    }
+
+    let kind = match (name, previous.as_slice()) {
+        ("e" | "w" | "read" | "recover", []) => CompletionKind::File,
+        ("e", [enc]) if enc.starts_with("++enc=") => CompletionKind::File,
+        ("source" | "delsession" | "mksession", []) => CompletionKind::Session,
+        ("set" | "setlocal", _) => CompletionKind::SetOption,
+        ("buffer", []) => CompletionKind::Buffer,
+        ("pick", []) => CompletionKind::Picker,
+        ("help", []) => CompletionKind::Command,
+        ("map" | "nmap" | "imap" | "vmap", [_]) => CompletionKind::Action,
+        _ => return None,
+    };
+    Some((start, kind))
+}
+
+/// The entries of `items` starting with `prefix`, sorted and deduplicated.
+pub fn filter<I, S>(items: I, prefix: &str) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut found: Vec<String> = items
+        .into_iter()
+        .map(Into::into)
+        .filter(|item| item.starts_with(prefix))
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Files and directories whose path starts with `prefix`, relative to the
+/// current directory. Directories end with `/`, and dot files are only
+/// listed when the typed name starts with `.`.
+pub fn complete_path(prefix: &str) -> Vec<String> {
+    let (dir, name) = match prefix.rfind('/') {
+        Some(i) => (&prefix[..=i], &prefix[i + 1..]),
+        None => ("", prefix),
+    };
+    let read_from = if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    };
+    let Ok(entries) = fs::read_dir(read_from) else {
+        return Vec::new();
+    };
+    let mut found: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_str()?.to_string();
+            if !file_name.starts_with(name)
+                || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, file_name, suffix))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Candidates shown above the command line while cycling with Tab.
+#[derive(Debug, Clone)]
+pub struct Wildmenu {
+    /// Byte offset in the command line where candidates are inserted.
+    pub start: usize,
+    candidates: Vec<String>,
+    selected: usize,
+}
+
+impl Wildmenu {
+    pub fn new(start: usize, candidates: Vec<String>) -> Self {
+        Self {
+            start,
+            candidates,
+            selected: 0,
+        }
+    }
+
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn current(&self) -> &str {
+        &self.candidates[self.selected]
+    }
+
+    /// Move to the next candidate, or the previous one when `forward` is false.
+    pub fn cycle(&mut self, forward: bool) {
+        let len = self.candidates.len();
+        self.selected = if forward {
+            (self.selected + 1) % len
+        } else {
+            (self.selected + len - 1) % len
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_completion_context() {
+        assert_eq!(context(""), Some((0, CompletionKind::Command)));
+        assert_eq!(context("vs"), Some((0, CompletionKind::Command)));
+        assert_eq!(context("e docs/gu"), Some((2, CompletionKind::File)));
+        assert_eq!(context("r "), Some((2, CompletionKind::File)));
+        assert_eq!(context("source wo"), Some((7, CompletionKind::Session)));
+        assert_eq!(context("set "), Some((4, CompletionKind::SetOption)));
+        assert_eq!(
+            context("set theme=base"),
+            Some((10, CompletionKind::SetValue("theme".to_string())))
+        );
+        assert_eq!(context("setl nu t"), Some((8, CompletionKind::SetOption)));
+        assert_eq!(context("b 日本"), Some((2, CompletionKind::Buffer)));
+        assert_eq!(
+            context("nmap <C-k> und"),
+            Some((11, CompletionKind::Action))
+        );
+        assert_eq!(context("split x"), None);
+        assert_eq!(context("nosuch x"), None);
+    }
+
+    #[test]
+    fn test_complete_path_and_cycle() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().display().to_string();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("draft.md"), "").unwrap();
+        fs::write(dir.path().join(".hidden"), "").unwrap();
+
+        assert_eq!(
+            complete_path(&format!("{}/d", root)),
+            vec![format!("{}/docs/", root), format!("{}/draft.md", root)]
+        );
+        assert_eq!(complete_path(&format!("{}/", root)).len(), 2);
+        assert_eq!(
+            complete_path(&format!("{}/.h", root)),
+            vec![format!("{}/.hidden", root)]
+        );
+
+        let mut menu = Wildmenu::new(2, complete_path(&format!("{}/d", root)));
+        assert_eq!(menu.current(), format!("{}/docs/", root));
+        menu.cycle(true);
+        menu.cycle(true);
+        assert_eq!(menu.selected(), 0);
+        menu.cycle(false);
+        assert_eq!(menu.current(), format!("{}/draft.md", root));
+    }
+}
@@ -1,9 +1,16 @@
 use anyhow::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use tokio::fs::try_exists;
 
+use crate::statusline::Segment;
+
+/// Config file given with `--config`, used instead of the default location.
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub theme: Theme,
@@ -11,6 +18,15 @@ pub struct Config {
     pub editor: EditorConfig,
     pub keybindings: KeybindingStyle,
     pub ui_mode: UIMode,
+    /// Per-mode key binding overrides
+    #[serde(default)]
+    pub keymap: KeymapConfig,
+    /// Segments of the status line
+    #[serde(default)]
+    pub statusline: StatusLineConfig,
+    /// When modified buffers are saved automatically
+    #[serde(default)]
+    pub autosave: AutosaveConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +47,9 @@ pub struct Theme {
     pub accent_color: Option<String>,
     #[serde(default)]
     pub status_background: Option<String>,
+    /// Color of the glyphs drawn for invisible characters with `list`.
+    #[serde(default)]
+    pub whitespace_color: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,15 +65,247 @@ pub struct EditorConfig {
     pub line_numbers: bool,
     pub highlight_current_line: bool,
     pub wrap_lines: bool,
+    /// Marker drawn at the start of wrapped continuation rows.
+    #[serde(default = "default_showbreak")]
+    pub showbreak: String,
+    /// Capture mouse events (click, drag, wheel). Disable for terminals where
+    /// mouse capture interferes with native text selection.
+    #[serde(default = "default_true")]
+    pub mouse: bool,
+    /// Show line numbers relative to the cursor line.
+    #[serde(default)]
+    pub relativenumber: bool,
+    /// Highlight the cursor's screen column.
+    #[serde(default)]
+    pub cursorcolumn: bool,
+    /// Render tabs, trailing spaces and other invisible characters.
+    #[serde(default)]
+    pub list: bool,
+    /// Glyphs used by `list`, e.g. `tab:→ ,trail:·,eol:¬`.
+    #[serde(default = "default_listchars")]
+    pub listchars: String,
+    /// Break lines at a space once typing passes this column (0 disables).
+    #[serde(default)]
+    pub textwidth: usize,
+    /// Lines kept visible above and below the cursor.
+    #[serde(default)]
+    pub scrolloff: usize,
+    #[serde(default)]
+    pub ignorecase: bool,
+    /// With `ignorecase`, search case-sensitively when the query has capitals.
+    #[serde(default)]
+    pub smartcase: bool,
+    /// Where the previous version of a file is kept when it is saved.
+    #[serde(default)]
+    pub backup: BackupStrategy,
+    /// Directory for `"backup": "directory"`; defaults to `backup` in the
+    /// data directory.
+    #[serde(default)]
+    pub backup_dir: Option<PathBuf>,
+    /// Keep unsaved changes in swap files for crash recovery.
+    #[serde(default = "default_true")]
+    pub swapfile: bool,
+    /// Files of at least this many megabytes open in large-file mode
+    /// (0 disables it).
+    #[serde(default = "default_large_file_mb")]
+    pub large_file_mb: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_large_file_mb() -> u64 {
+    10
+}
+
+fn default_showbreak() -> String {
+    String::from(crate::view::DEFAULT_SHOWBREAK)
 }
 
+fn default_listchars() -> String {
+    String::from(crate::listchars::DEFAULT_LISTCHARS)
+}
+
+/// User key bindings, e.g. `"normal": {"<C-s>": "write"}`. Values are action
+/// names or ex commands written as `:cmd`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeymapConfig {
+    /// Milliseconds to wait for the next key of a multi-key sequence.
+    #[serde(default = "default_key_timeout")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub normal: BTreeMap<String, String>,
+    #[serde(default)]
+    pub insert: BTreeMap<String, String>,
+    #[serde(default)]
+    pub visual: BTreeMap<String, String>,
+}
+
+fn default_key_timeout() -> u64 {
+    crate::keymap::DEFAULT_TIMEOUT_MS
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_key_timeout(),
+            normal: BTreeMap::new(),
+            insert: BTreeMap::new(),
+            visual: BTreeMap::new(),
+        }
+    }
+}
+
+/// Status line layout: segments drawn from the left edge, in the middle and
+/// against the right edge, e.g. `"left": [{"segment": "mode"}]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusLineConfig {
+    #[serde(default = "default_status_left")]
+    pub left: Vec<SegmentConfig>,
+    #[serde(default)]
+    pub center: Vec<SegmentConfig>,
+    #[serde(default = "default_status_right")]
+    pub right: Vec<SegmentConfig>,
+    /// Background of the whole line (#RRGGBB); each UI's own when unset.
+    #[serde(default)]
+    pub background: Option<String>,
+}
+
+/// One status line segment with optional colors (#RRGGBB).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentConfig {
+    pub segment: Segment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+}
+
+impl From<Segment> for SegmentConfig {
+    fn from(segment: Segment) -> Self {
+        Self {
+            segment,
+            fg: None,
+            bg: None,
+        }
+    }
+}
+
+fn default_status_left() -> Vec<SegmentConfig> {
+    [
+        Segment::Mode,
+        Segment::FileName,
+        Segment::Modified,
+        Segment::GitBranch,
+        Segment::Recording,
+        Segment::PendingKeys,
+    ]
+    .into_iter()
+    .map(SegmentConfig::from)
+    .collect()
+}
+
+fn default_status_right() -> Vec<SegmentConfig> {
+    [
+        Segment::Selection,
+        Segment::Lsp,
+        Segment::WordCount,
+        Segment::FileType,
+        Segment::Encoding,
+        Segment::LineEnding,
+        Segment::Position,
+        Segment::Percentage,
+    ]
+    .into_iter()
+    .map(SegmentConfig::from)
+    .collect()
+}
+
+impl Default for StatusLineConfig {
+    fn default() -> Self {
+        Self {
+            left: default_status_left(),
+            center: Vec::new(),
+            right: default_status_right(),
+            background: None,
+        }
+    }
+}
+
+/// Automatic saving of modified buffers; everything is off by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutosaveConfig {
+    /// Save every this many seconds (0 disables).
+    #[serde(default)]
+    pub interval_secs: u64,
+    /// Save once no key has been pressed for this many seconds (0 disables).
+    #[serde(default)]
+    pub idle_secs: u64,
+    /// Save when the terminal loses focus.
+    #[serde(default)]
+    pub on_focus_lost: bool,
+    /// Save when switching to another buffer.
+    #[serde(default)]
+    pub on_buffer_switch: bool,
+}
+
+/// Parse a `#RRGGBB` color from the config.
+pub fn parse_hex_color(value: &str) -> Option<ratatui::style::Color> {
+    let hex = value.trim().trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some(ratatui::style::Color::Rgb(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+        )),
+        _ => None,
+    }
+}
+
+/// How saving keeps the previous version of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupStrategy {
+    /// Keep no backup.
+    None,
+    /// Copy the file to `name~` next to it, replaced on every save.
+    #[default]
+    NextToFile,
+    /// Keep timestamped copies of every save in one backup directory.
+    Directory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum KeybindingStyle {
+    #[serde(alias = "nano")]
     Nano,
+    #[serde(alias = "vim")]
     Vim,
+    #[serde(alias = "emacs")]
     Emacs,
 }
 
+impl KeybindingStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeybindingStyle::Nano => "nano",
+            KeybindingStyle::Vim => "vim",
+            KeybindingStyle::Emacs => "emacs",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "nano" => Some(KeybindingStyle::Nano),
+            "vim" => Some(KeybindingStyle::Vim),
+            "emacs" => Some(KeybindingStyle::Emacs),
+            _ => None,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -65,6 +316,7 @@ impl Default for Config {
                 editor_background: Some(String::from("#1E1E1E")),
                 accent_color: Some(String::from("#FFD166")),
                 status_background: Some(String::from("#005F87")),
+                whitespace_color: Some(String::from("#5C6370")),
             },
             font: FontConfig {
                 size: 14,
@@ -76,9 +328,26 @@ impl Default for Config {
                 line_numbers: true,
                 highlight_current_line: true,
                 wrap_lines: false,
+                showbreak: default_showbreak(),
+                mouse: true,
+                relativenumber: false,
+                cursorcolumn: false,
+                list: false,
+                listchars: default_listchars(),
+                textwidth: 0,
+                scrolloff: 0,
+                ignorecase: false,
+                smartcase: false,
+                backup: BackupStrategy::default(),
+                backup_dir: None,
+                swapfile: true,
+                large_file_mb: default_large_file_mb(),
             },
             keybindings: KeybindingStyle::Vim,
             ui_mode: UIMode::Enhanced,
+            keymap: KeymapConfig::default(),
+            statusline: StatusLineConfig::default(),
+            autosave: AutosaveConfig::default(),
         }
     }
 }
@@ -231,7 +500,17 @@ impl Config {
         Ok(())
     }
 
+    /// Read and save the config at `path` from now on (`--config`). Only the
+    /// first call has an effect.
+    pub fn use_path(path: PathBuf) {
+        let _ = CONFIG_PATH_OVERRIDE.set(path);
+    }
+
     fn config_path() -> Option<PathBuf> {
+        if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+            return Some(path.clone());
+        }
+
         if let Ok(path) = std::env::var("SCRIPTORIS_CONFIG_PATH") {
             return Some(PathBuf::from(path));
         }
@@ -288,6 +567,7 @@ mod tests {
         assert!(config.editor.line_numbers);
         assert!(config.editor.highlight_current_line);
         assert!(!config.editor.wrap_lines);
+        assert!(config.editor.mouse);
         assert!(matches!(config.keybindings, KeybindingStyle::Vim));
         assert_eq!(config.theme.editor_foreground.as_deref(), Some("#D8DEE9"));
         assert_eq!(config.theme.editor_background.as_deref(), Some("#1E1E1E"));
@@ -343,6 +623,22 @@ mod tests {
         restore_config_env(previous_env);
     }
 
+    #[test]
+    fn test_editor_config_defaults_missing_fields() {
+        // Config files written before newer options existed must still load
+        let json = r#"{
+            "tab_size": 2,
+            "use_spaces": true,
+            "line_numbers": true,
+            "highlight_current_line": false,
+            "wrap_lines": false
+        }"#;
+        let editor: EditorConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(editor.tab_size, 2);
+        assert!(editor.mouse);
+        assert_eq!(editor.showbreak, crate::view::DEFAULT_SHOWBREAK);
+    }
+
     #[test]
     fn test_keybinding_style_variants() {
         // Test that all keybinding styles can be serialized/deserialized
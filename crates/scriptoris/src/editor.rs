@@ -1,7 +1,14 @@
 use ropey::Rope;
+use std::borrow::Cow;
 use std::cmp;
+use std::collections::BTreeMap;
+use std::ops::Range;
 
+use crate::file_format::FileFormat;
+use crate::folding::{ClosedFold, Folds};
+use crate::options::{EditorOptions, LocalOptions};
 use crate::text_width::{EmojiWidth, TextWidthCalculator};
+use crate::view::{self, DisplayRow};
 
 #[derive(Clone)]
 pub struct Editor {
@@ -10,6 +17,12 @@ pub struct Editor {
     cursor_col: usize,
     viewport_offset: usize,
     viewport_height: usize,
+    // Soft wrap width in columns (None when wrapping is off) and the width of
+    // the marker drawn on continuation rows
+    wrap_width: Option<usize>,
+    showbreak_width: usize,
+    // Markdown fold regions and which of them are closed
+    folds: Folds,
     modified: bool,
     clipboard: String,
     // Undo/Redo support
@@ -23,12 +36,142 @@ pub struct Editor {
     // Tab configuration
     tab_size: usize,
     use_spaces: bool,
+    // Column past which typing breaks the line (0 when off) and search case
+    // handling, from the resolved options
+    text_width: usize,
+    ignorecase: bool,
+    smartcase: bool,
+    // Lines kept visible around the cursor
+    scroll_off: usize,
+    // Values set with `:setlocal`
+    local_options: LocalOptions,
+    // Encoding the text is read from and written back in
+    file_format: FileFormat,
+    // Set for files over the large-file threshold: no folds, and undo
+    // snapshots are not compared against each other
+    large_file: bool,
+    // Set while the rest of a large file is read in the background
+    loading: bool,
+    // Set when the text changed while no fold was closed; the regions are
+    // found again only when a fold command needs them
+    folds_stale: bool,
+    // Vim marks `a`-`z` as (line, column)
+    marks: BTreeMap<char, (usize, usize)>,
+    // Bumped on every change of the text, for caches keyed on the content
+    revision: u64,
+}
+
+/// Character classes used to find word boundaries. Japanese scripts are kept
+/// apart so that a run of kanji followed by hiragana splits into two words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Hiragana,
+    Katakana,
+    Kanji,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        match c {
+            c if c.is_whitespace() => CharClass::Whitespace,
+            '\u{3040}'..='\u{309F}' => CharClass::Hiragana,
+            '\u{30A0}'..='\u{30FF}' => CharClass::Katakana,
+            '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' => {
+                CharClass::Kanji
+            }
+            c if c.is_alphanumeric() || c == '_' => CharClass::Word,
+            _ => CharClass::Punctuation,
+        }
+    }
+}
+
+/// Char index of the first match of `query` at or after char `from`, or of
+/// the last one starting before it. The rope is scanned chunk by chunk, with
+/// enough of the neighbouring chunk kept to catch matches that straddle a
+/// boundary, so the document is never copied whole. With `ignore_case` the
+/// text is folded by [`fold_char`]; `query` must already be folded.
+fn find_in_rope(
+    rope: &Rope,
+    query: &str,
+    from: usize,
+    forward: bool,
+    ignore_case: bool,
+) -> Option<usize> {
+    let overlap = query.chars().count().saturating_sub(1);
+    let text = |chunk: &str| -> String {
+        if ignore_case {
+            chunk.chars().map(fold_char).collect()
+        } else {
+            chunk.to_string()
+        }
+    };
+    let from = from.min(rope.len_chars());
+    if forward {
+        // `window` holds the text from char `start` up to the chunk just read
+        let (chunks, _, chunk_start, _) = rope.chunks_at_char(from);
+        let (mut window, mut start) = (String::new(), from);
+        for (i, chunk) in chunks.enumerate() {
+            let chunk = if i == 0 {
+                &chunk[char_to_byte(chunk, from - chunk_start)..]
+            } else {
+                chunk
+            };
+            window.push_str(&text(chunk));
+            if let Some(byte) = window.find(query) {
+                return Some(start + window[..byte].chars().count());
+            }
+            let keep_byte = match overlap {
+                0 => window.len(),
+                n => window.char_indices().rev().nth(n - 1).map_or(0, |(i, _)| i),
+            };
+            start += window[..keep_byte].chars().count();
+            window.drain(..keep_byte);
+        }
+        None
+    } else {
+        // `carry` is the start of the following text, so that a match may
+        // run past the chunk it starts in
+        let end = (from + overlap).min(rope.len_chars());
+        let mut carry = text(&rope.slice(from..end).to_string());
+        let mut pos = from;
+        while pos > 0 {
+            let (chunk, _, chunk_start, _) = rope.chunk_at_char(pos - 1);
+            let mut window = text(&chunk[..char_to_byte(chunk, pos - chunk_start)]);
+            window.push_str(&carry);
+            if let Some(byte) = window.rfind(query) {
+                return Some(chunk_start + window[..byte].chars().count());
+            }
+            carry = window[..char_to_byte(&window, overlap)].to_string();
+            pos = chunk_start;
+        }
+        None
+    }
+}
+
+/// Byte offset of char `char_idx` in `s`, or its length past the end.
+fn char_to_byte(s: &str, char_idx: usize) -> usize {
+    s.char_indices().nth(char_idx).map_or(s.len(), |(i, _)| i)
+}
+
+/// Lowercase `c` when that gives a single character, for case-insensitive
+/// search that keeps character offsets.
+fn fold_char(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
 }
 
 #[derive(Clone)]
 #[allow(dead_code)]
 struct EditorState {
-    content: String,
+    // Ropes share unchanged chunks, so a snapshot costs little even for
+    // large documents
+    content: Rope,
     cursor_line: usize,
     cursor_col: usize,
     visual_start_line: Option<usize>,
@@ -38,7 +181,7 @@ struct EditorState {
 impl Editor {
     pub fn new() -> Self {
         let initial_state = EditorState {
-            content: String::new(),
+            content: Rope::new(),
             cursor_line: 0,
             cursor_col: 0,
             visual_start_line: None,
@@ -56,6 +199,9 @@ impl Editor {
             cursor_col: 0,
             viewport_offset: 0,
             viewport_height: 24, // Default, will be updated
+            wrap_width: None,
+            showbreak_width: 0,
+            folds: Folds::default(),
             modified: false,
             clipboard: String::new(),
             history: vec![initial_state],
@@ -65,6 +211,17 @@ impl Editor {
             text_calculator,
             tab_size: 4,
             use_spaces: true,
+            text_width: 0,
+            ignorecase: false,
+            smartcase: false,
+            scroll_off: 0,
+            local_options: LocalOptions::default(),
+            file_format: FileFormat::default(),
+            large_file: false,
+            loading: false,
+            folds_stale: false,
+            marks: BTreeMap::new(),
+            revision: 0,
         }
     }
 
@@ -76,10 +233,12 @@ impl Editor {
         self.modified = false;
         self.visual_start_line = None;
         self.visual_start_col = None;
+        self.folds.clear();
+        self.refresh_folds();
 
         // Reset history with new content
         let initial_state = EditorState {
-            content,
+            content: self.rope.clone(),
             cursor_line: 0,
             cursor_col: 0,
             visual_start_line: None,
@@ -87,6 +246,18 @@ impl Editor {
         };
         self.history = vec![initial_state];
         self.history_index = 0;
+        self.marks.clear();
+        self.revision += 1;
+    }
+
+    /// Replace the text with a file read from disk along with its format.
+    /// With `large_file`, folds are not computed and undo snapshots are not
+    /// compared against each other, as both scan the whole text.
+    pub fn load_file(&mut self, content: String, format: FileFormat, large_file: bool) {
+        self.large_file = large_file;
+        self.loading = false;
+        self.set_content(content);
+        self.file_format = format;
     }
 
     pub fn get_content(&self) -> String {
@@ -101,6 +272,12 @@ impl Editor {
         self.modified = false;
     }
 
+    /// Mark the buffer as needing a save without changing the text, as
+    /// changing its encoding does.
+    pub fn mark_modified(&mut self) {
+        self.modified = true;
+    }
+
     pub fn line_count(&self) -> usize {
         self.rope.len_lines()
     }
@@ -125,27 +302,162 @@ impl Editor {
         self.adjust_viewport();
     }
 
+    /// Move the cursor to a position relative to the top-left of the
+    /// viewport, mapping the screen column back to a character offset so that
+    /// clicks on either half of a wide character land on it.
+    pub fn set_cursor_screen_position(&mut self, row: usize, display_col: usize) {
+        let rows = self.display_rows();
+        let Some(target) = rows.get(row).or(rows.last()) else {
+            return;
+        };
+        self.cursor_line = target.line;
+        self.cursor_col = self.col_in_row(target, display_col);
+        self.adjust_viewport();
+    }
+
+    /// Cursor position relative to the top-left of the viewport as
+    /// (row, display column), or `None` when the cursor is off screen.
+    pub fn cursor_screen_position(&self) -> Option<(usize, usize)> {
+        let rows = self.display_rows();
+        let row = rows.iter().position(|row| {
+            row.line == self.cursor_line
+                && row.start <= self.cursor_col
+                && (self.cursor_col < row.end || !row.wraps)
+        })?;
+        Some((row, self.row_display_col(&rows[row], self.cursor_col)))
+    }
+
+    /// Scroll the viewport by `delta` lines, dragging the cursor along when it
+    /// would leave the visible area.
+    pub fn scroll_viewport(&mut self, delta: isize) {
+        // Step over visible lines so that a closed fold counts as one line
+        let max_offset = self.rope.len_lines().saturating_sub(self.viewport_height);
+        let mut new_offset = self.visible_start(self.viewport_offset);
+        for _ in 0..delta.unsigned_abs() {
+            let next = if delta < 0 {
+                self.prev_visible_line(new_offset)
+            } else {
+                self.next_visible_line(new_offset)
+                    .filter(|line| *line <= max_offset)
+            };
+            match next {
+                Some(line) => new_offset = line,
+                None => break,
+            }
+        }
+        self.viewport_offset = new_offset;
+
+        // With wrapping or folds, the last screen row is not offset + height
+        let last_visible = if self.wrap_width.is_some() || self.folds.has_closed() {
+            self.display_rows()
+                .iter()
+                .rev()
+                .find(|row| !row.wraps)
+                .map_or(new_offset, |row| row.line)
+        } else {
+            new_offset + self.viewport_height.saturating_sub(1)
+        };
+        if self.cursor_line < new_offset {
+            self.cursor_line = new_offset;
+        } else if self.cursor_line > last_visible {
+            self.cursor_line = last_visible;
+        }
+        self.adjust_cursor_col();
+    }
+
+    /// Select the word under the cursor as a visual selection.
+    /// Returns `false` when the cursor is not on a word character.
+    pub fn select_word_at_cursor(&mut self) -> bool {
+        let chars: Vec<char> = self.rope.line(self.cursor_line).chars().collect();
+        let len = self.line_len_chars(self.cursor_line);
+        if self.cursor_col >= len {
+            return false;
+        }
+
+        let class = CharClass::of(chars[self.cursor_col]);
+        if class == CharClass::Whitespace {
+            return false;
+        }
+
+        let mut start = self.cursor_col;
+        while start > 0 && CharClass::of(chars[start - 1]) == class {
+            start -= 1;
+        }
+        let mut end = self.cursor_col;
+        while end < len && CharClass::of(chars[end]) == class {
+            end += 1;
+        }
+
+        self.visual_start_line = Some(self.cursor_line);
+        self.visual_start_col = Some(start);
+        self.cursor_col = end;
+        true
+    }
+
     pub fn set_viewport_height(&mut self, height: usize) {
         self.viewport_height = height;
     }
 
+    /// Enable soft wrapping at `width` columns, or disable it with `None`.
+    pub fn set_wrap(&mut self, width: Option<usize>, showbreak_width: usize) {
+        self.wrap_width = width.filter(|w| *w > 0);
+        self.showbreak_width = showbreak_width;
+    }
+
+    /// Screen rows filling the viewport, starting at the viewport offset.
+    pub fn display_rows(&self) -> Vec<DisplayRow> {
+        let mut rows = Vec::new();
+        let mut line = self.visible_start(self.viewport_offset);
+        while rows.len() < self.viewport_height && line < self.rope.len_lines() {
+            let folded = self
+                .folds
+                .closed_region_at(line)
+                .map(|region| region.hidden_lines());
+            let segments = self.line_segments(line);
+            let count = segments.len();
+            for (i, segment) in segments.into_iter().enumerate() {
+                if rows.len() == self.viewport_height {
+                    break;
+                }
+                rows.push(DisplayRow {
+                    line,
+                    start: segment.start,
+                    end: segment.end,
+                    wraps: i + 1 < count,
+                    folded,
+                });
+            }
+            match self.next_visible_line(line) {
+                Some(next) => line = next,
+                None => break,
+            }
+        }
+        rows
+    }
+
     pub fn set_viewport_offset(&mut self, offset: usize) {
         let max_offset = self.rope.len_lines().saturating_sub(self.viewport_height);
         self.viewport_offset = offset.min(max_offset);
     }
 
     pub fn get_viewport_offset(&self) -> usize {
-        self.viewport_offset
+        self.visible_start(self.viewport_offset)
     }
 
+    /// Buffer lines from the viewport offset through the last line on screen,
+    /// including lines hidden inside closed folds.
     pub fn get_viewport_lines(&self) -> Vec<String> {
         let mut lines = Vec::new();
-        let end_line = cmp::min(
-            self.viewport_offset + self.viewport_height,
-            self.rope.len_lines(),
-        );
+        let start_line = self.get_viewport_offset();
+        let end_line = if self.folds.has_closed() {
+            self.display_rows()
+                .last()
+                .map_or(start_line, |row| row.line + row.folded.unwrap_or(0) + 1)
+        } else {
+            cmp::min(start_line + self.viewport_height, self.rope.len_lines())
+        };
 
-        for i in self.viewport_offset..end_line {
+        for i in start_line..end_line {
             if let Some(line) = self.rope.get_line(i) {
                 lines.push(line.to_string());
             }
@@ -155,31 +467,54 @@ impl Editor {
     }
 
     pub fn insert_char(&mut self, c: char) {
-        // Handle memory constraints gracefully
-        if self.rope.len_chars() > 1_000_000 {
-            // 1 million characters
-            log::warn!("Document size approaching limit, insert may be slow");
-        }
-
         let char_idx = self.line_col_to_char_idx(self.cursor_line, self.cursor_col);
 
         // Insert character
         self.rope.insert_char(char_idx, c);
 
-        // Move cursor forward using text_calculator for proper width
-        self.cursor_col += self.text_calculator.grapheme_width(&c.to_string());
+        // cursor_col is a character offset within the line
+        self.cursor_col += 1;
+        if self.text_width > 0 && !c.is_whitespace() {
+            self.break_at_text_width();
+        }
         self.modified = true;
         self.save_state();
     }
 
+    /// Replace the last blank before the cursor with a line break when the
+    /// text before the cursor is wider than `textwidth`.
+    fn break_at_text_width(&mut self) {
+        let line: Vec<char> = self.rope.line(self.cursor_line).chars().collect();
+        let before: String = line[..self.cursor_col].iter().collect();
+        if self.text_calculator.str_width(&before) <= self.text_width {
+            return;
+        }
+        let Some(blank) = line[..self.cursor_col]
+            .iter()
+            .rposition(|c| *c == ' ' || *c == '\t')
+        else {
+            return;
+        };
+        if line[..blank].iter().all(|c| c.is_whitespace()) {
+            return;
+        }
+        let line_start = self.rope.line_to_char(self.cursor_line);
+        self.rope.remove(line_start + blank..line_start + blank + 1);
+        self.rope.insert_char(line_start + blank, '\n');
+        self.cursor_line += 1;
+        self.cursor_col -= blank + 1;
+        self.adjust_viewport();
+    }
+
     pub fn insert_newline(&mut self) {
         let char_idx = self.line_col_to_char_idx(self.cursor_line, self.cursor_col);
         self.rope.insert_char(char_idx, '\n');
         self.cursor_line += 1;
         self.cursor_col = 0;
         self.modified = true;
-        self.adjust_viewport();
+        // Record the edit first so fold regions match the new text
         self.save_state();
+        self.adjust_viewport();
     }
 
     pub fn insert_tab(&mut self) {
@@ -198,6 +533,91 @@ impl Editor {
     pub fn set_tab_config(&mut self, tab_size: usize, use_spaces: bool) {
         self.tab_size = tab_size;
         self.use_spaces = use_spaces;
+        self.text_calculator = std::mem::take(&mut self.text_calculator).tab_width(tab_size);
+    }
+
+    /// Take the editing behavior of the resolved `:set` options.
+    pub fn apply_options(&mut self, options: &EditorOptions) {
+        self.set_tab_config(options.tabstop, options.expandtab);
+        self.text_width = options.textwidth;
+        self.ignorecase = options.ignorecase;
+        self.smartcase = options.smartcase;
+        self.scroll_off = options.scrolloff;
+    }
+
+    pub fn local_options(&self) -> &LocalOptions {
+        &self.local_options
+    }
+
+    pub fn local_options_mut(&mut self) -> &mut LocalOptions {
+        &mut self.local_options
+    }
+
+    pub fn file_format(&self) -> FileFormat {
+        self.file_format
+    }
+
+    /// Set the format of the file the text came from, without marking the
+    /// buffer modified.
+    pub fn set_file_format(&mut self, format: FileFormat) {
+        self.file_format = format;
+    }
+
+    pub fn is_large_file(&self) -> bool {
+        self.large_file
+    }
+
+    /// Whether the text is only the start of a large file, the rest of
+    /// which is still being read. Saving is refused until it is in.
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    pub fn set_loading(&mut self, loading: bool) {
+        self.loading = loading;
+    }
+
+    /// Add the rest of a large file after the lines shown so far. Undo
+    /// steps taken meanwhile get it too, so that undoing them keeps it.
+    pub fn append_loaded(&mut self, rest: Rope) {
+        for state in &mut self.history {
+            state.content.append(rest.clone());
+        }
+        self.rope.append(rest);
+        self.loading = false;
+        self.revision += 1;
+    }
+
+    /// Counter that changes whenever the text does.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Remember the cursor position as mark `name` (`m{a-z}`).
+    pub fn set_mark(&mut self, name: char) {
+        self.marks.insert(name, (self.cursor_line, self.cursor_col));
+    }
+
+    pub fn marks(&self) -> &BTreeMap<char, (usize, usize)> {
+        &self.marks
+    }
+
+    /// Jump to mark `name`: its exact position (`` `a ``) or the start of
+    /// its line (`'a`). Returns false when the mark is not set.
+    pub fn jump_to_mark(&mut self, name: char, exact: bool) -> bool {
+        let Some(&(line, col)) = self.marks.get(&name) else {
+            return false;
+        };
+        // `'` lands on the first non-blank character of the marked line
+        let col = if exact {
+            col
+        } else {
+            self.rope.get_line(line).map_or(0, |text| {
+                text.chars().take_while(|c| *c == ' ' || *c == '\t').count()
+            })
+        };
+        self.set_cursor_position(line, col);
+        true
     }
 
     pub fn delete_char_backward(&mut self) {
@@ -215,8 +635,8 @@ impl Editor {
             let char_idx = self.line_col_to_char_idx(self.cursor_line + 1, 0) - 1;
             self.rope.remove(char_idx..char_idx + 1);
             self.modified = true;
-            self.adjust_viewport();
             self.save_state();
+            self.adjust_viewport();
         }
     }
 
@@ -230,21 +650,30 @@ impl Editor {
     }
 
     pub fn delete_line(&mut self) {
-        if let Some(line) = self.rope.get_line(self.cursor_line) {
-            self.clipboard = line.to_string();
-            let start_idx = self.rope.line_to_char(self.cursor_line);
-            let end_idx = if self.cursor_line + 1 < self.rope.len_lines() {
-                self.rope.line_to_char(self.cursor_line + 1)
-            } else {
-                self.rope.len_chars()
-            };
-            self.rope.remove(start_idx..end_idx);
-            self.cursor_col = 0;
-            self.modified = true;
-            self.save_state();
+        if self.cursor_line < self.rope.len_lines() {
+            self.clipboard = self.cut_line();
         }
     }
 
+    /// Remove the cursor line including its line break and return it.
+    pub fn cut_line(&mut self) -> String {
+        let Some(line) = self.rope.get_line(self.cursor_line) else {
+            return String::new();
+        };
+        let line = line.to_string();
+        let start_idx = self.rope.line_to_char(self.cursor_line);
+        let end_idx = if self.cursor_line + 1 < self.rope.len_lines() {
+            self.rope.line_to_char(self.cursor_line + 1)
+        } else {
+            self.rope.len_chars()
+        };
+        self.rope.remove(start_idx..end_idx);
+        self.cursor_col = 0;
+        self.modified = true;
+        self.save_state();
+        line
+    }
+
     pub fn yank_line(&mut self) {
         if let Some(line) = self.rope.get_line(self.cursor_line) {
             self.clipboard = line.to_string();
@@ -262,16 +691,16 @@ impl Editor {
     }
 
     pub fn move_cursor_up(&mut self) {
-        if self.cursor_line > 0 {
-            self.cursor_line -= 1;
+        if let Some(line) = self.prev_visible_line(self.cursor_line) {
+            self.cursor_line = line;
             self.adjust_cursor_col();
             self.adjust_viewport();
         }
     }
 
     pub fn move_cursor_down(&mut self) {
-        if self.cursor_line + 1 < self.rope.len_lines() {
-            self.cursor_line += 1;
+        if let Some(line) = self.next_visible_line(self.cursor_line) {
+            self.cursor_line = line;
             self.adjust_cursor_col();
             self.adjust_viewport();
         }
@@ -302,14 +731,225 @@ impl Editor {
         }
     }
 
+    /// Move down one screen row (`gj`). Same as `j` when wrapping is off.
+    pub fn move_display_row_down(&mut self) {
+        if self.wrap_width.is_none() {
+            self.move_cursor_down();
+            return;
+        }
+
+        let segments = self.line_segments(self.cursor_line);
+        let index = Self::segment_index(&segments, self.cursor_col);
+        let display_col = self.row_display_col(
+            &Self::row_for_segment(self.cursor_line, &segments, index),
+            self.cursor_col,
+        );
+        let (line, index) = if index + 1 < segments.len() {
+            (self.cursor_line, index + 1)
+        } else if let Some(line) = self.next_visible_line(self.cursor_line) {
+            (line, 0)
+        } else {
+            return;
+        };
+        self.move_to_segment(line, index, display_col);
+    }
+
+    /// Move up one screen row (`gk`). Same as `k` when wrapping is off.
+    pub fn move_display_row_up(&mut self) {
+        if self.wrap_width.is_none() {
+            self.move_cursor_up();
+            return;
+        }
+
+        let segments = self.line_segments(self.cursor_line);
+        let index = Self::segment_index(&segments, self.cursor_col);
+        let display_col = self.row_display_col(
+            &Self::row_for_segment(self.cursor_line, &segments, index),
+            self.cursor_col,
+        );
+        let (line, index) = if index > 0 {
+            (self.cursor_line, index - 1)
+        } else if let Some(line) = self.prev_visible_line(self.cursor_line) {
+            (line, self.line_segments(line).len() - 1)
+        } else {
+            return;
+        };
+        self.move_to_segment(line, index, display_col);
+    }
+
+    fn move_to_segment(&mut self, line: usize, index: usize, display_col: usize) {
+        let row = Self::row_for_segment(line, &self.line_segments(line), index);
+        self.cursor_line = line;
+        self.cursor_col = self.col_in_row(&row, display_col);
+        self.adjust_viewport();
+    }
+
     pub fn move_to_line_start(&mut self) {
         self.cursor_col = 0;
     }
 
     pub fn move_to_line_end(&mut self) {
-        if let Some(line) = self.rope.get_line(self.cursor_line) {
-            self.cursor_col = line.len_chars().saturating_sub(1);
+        self.cursor_col = self.line_len_chars(self.cursor_line);
+    }
+
+    pub fn move_to_buffer_start(&mut self) {
+        self.cursor_line = 0;
+        self.cursor_col = 0;
+        self.adjust_viewport();
+    }
+
+    pub fn move_to_buffer_end(&mut self) {
+        self.cursor_line = self.rope.len_lines().saturating_sub(1);
+        self.cursor_col = self.line_len_chars(self.cursor_line);
+        self.adjust_viewport();
+    }
+
+    /// Move to the end of the next word, crossing lines (Emacs `M-f`).
+    pub fn move_word_forward(&mut self) {
+        let len = self.rope.len_chars();
+        let mut idx = self.cursor_char_index();
+        let class_at = |idx: usize| CharClass::of(self.rope.char(idx));
+        while idx < len
+            && matches!(
+                class_at(idx),
+                CharClass::Whitespace | CharClass::Punctuation
+            )
+        {
+            idx += 1;
+        }
+        if idx < len {
+            let class = class_at(idx);
+            while idx < len && class_at(idx) == class {
+                idx += 1;
+            }
+        }
+        self.set_cursor_char_index(idx);
+    }
+
+    /// Move to the start of the previous word, crossing lines (Emacs `M-b`).
+    pub fn move_word_backward(&mut self) {
+        let mut idx = self.cursor_char_index();
+        let class_at = |idx: usize| CharClass::of(self.rope.char(idx));
+        while idx > 0
+            && matches!(
+                class_at(idx - 1),
+                CharClass::Whitespace | CharClass::Punctuation
+            )
+        {
+            idx -= 1;
+        }
+        if idx > 0 {
+            let class = class_at(idx - 1);
+            while idx > 0 && class_at(idx - 1) == class {
+                idx -= 1;
+            }
+        }
+        self.set_cursor_char_index(idx);
+    }
+
+    /// Position of the cursor as a character index into the whole buffer.
+    pub fn cursor_char_index(&self) -> usize {
+        self.line_col_to_char_idx(self.cursor_line, self.cursor_col)
+    }
+
+    pub fn set_cursor_char_index(&mut self, char_idx: usize) {
+        let (line, col) = self.char_idx_to_line_col(char_idx.min(self.rope.len_chars()));
+        self.cursor_line = line;
+        self.cursor_col = col;
+        self.adjust_viewport();
+    }
+
+    /// Find `query` and return the character index of the match. Searching
+    /// forward takes the first match starting at or after `from`, backward
+    /// the last one starting before it.
+    pub fn find(&self, query: &str, from: usize, forward: bool) -> Option<usize> {
+        if query.is_empty() {
+            return None;
+        }
+        let ignore_case =
+            self.ignorecase && !(self.smartcase && query.chars().any(char::is_uppercase));
+        if ignore_case {
+            // Fold case char by char so char indices still match the rope
+            let query: String = query.chars().map(fold_char).collect();
+            return find_in_rope(&self.rope, &query, from, forward, true);
+        }
+        find_in_rope(&self.rope, query, from, forward, false)
+    }
+
+    /// Remove the text from the cursor to the end of the line, or the line
+    /// break when the cursor is already there (Emacs `C-k`). Returns the
+    /// removed text.
+    pub fn kill_to_line_end(&mut self) -> String {
+        let start = self.cursor_char_index();
+        let line_end = self.line_col_to_char_idx(self.cursor_line, usize::MAX);
+        let end = if line_end > start {
+            line_end
+        } else {
+            // Kill the line break, including a CR before the LF
+            let next_line = self.cursor_line + 1;
+            if next_line < self.rope.len_lines() {
+                self.rope.line_to_char(next_line)
+            } else {
+                self.rope.len_chars()
+            }
+        };
+        if end == start {
+            return String::new();
+        }
+
+        let killed = self.rope.slice(start..end).to_string();
+        self.rope.remove(start..end);
+        self.modified = true;
+        self.save_state();
+        killed
+    }
+
+    /// Insert `text` at the cursor and move the cursor after it.
+    pub fn insert_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
         }
+        let char_idx = self.cursor_char_index();
+        self.rope.insert(char_idx, text);
+        self.set_cursor_char_index(char_idx + text.chars().count());
+        self.modified = true;
+        self.save_state();
+    }
+
+    /// Insert `text` as whole lines below the cursor line, as Vim `:r` does,
+    /// and move the cursor to the first inserted line.
+    pub fn insert_lines_below(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let mut text = text.to_string();
+        let next_line = self.cursor_line + 1;
+        let char_idx = if next_line < self.rope.len_lines() {
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            self.rope.line_to_char(next_line)
+        } else {
+            // The cursor is on the last line, which has no newline to follow
+            text = format!("\n{}", text.strip_suffix('\n').unwrap_or(&text));
+            self.rope.len_chars()
+        };
+        self.rope.insert(char_idx, &text);
+        self.set_cursor_position(next_line, 0);
+        self.modified = true;
+        self.save_state();
+    }
+
+    /// Replace the `len` characters before the cursor with `text`, as
+    /// Emacs `M-y` does with the text inserted by the previous yank.
+    pub fn replace_before_cursor(&mut self, len: usize, text: &str) {
+        let end = self.cursor_char_index();
+        let start = end.saturating_sub(len);
+        self.rope.remove(start..end);
+        self.rope.insert(start, text);
+        self.set_cursor_char_index(start + text.chars().count());
+        self.modified = true;
+        self.save_state();
     }
 
     pub fn page_up(&mut self) {
@@ -332,78 +972,407 @@ impl Editor {
 
     fn line_col_to_char_idx(&self, line: usize, col: usize) -> usize {
         let line_start = self.rope.line_to_char(line);
-        if let Some(line_text) = self.rope.get_line(line) {
-            // text_calculatorを使用して表示カラム位置から文字インデックスに変換
-            let char_offset = self
-                .text_calculator
-                .col_to_char_index(&line_text.to_string(), col);
-            line_start + char_offset
-        } else {
-            line_start + col
-        }
+        line_start + col.min(self.line_len_chars(line))
     }
 
     fn char_idx_to_line_col(&self, char_idx: usize) -> (usize, usize) {
         let line = self.rope.char_to_line(char_idx);
         let line_start = self.rope.line_to_char(line);
-        let char_offset = char_idx - line_start;
+        (line, char_idx - line_start)
+    }
+
+    /// Number of characters on `line`, excluding the line break.
+    fn line_len_chars(&self, line: usize) -> usize {
+        match self.rope.get_line(line) {
+            Some(text) => {
+                let mut len = text.len_chars();
+                if len > 0 && text.char(len - 1) == '\n' {
+                    len -= 1;
+                }
+                if len > 0 && text.char(len - 1) == '\r' {
+                    len -= 1;
+                }
+                len
+            }
+            None => 0,
+        }
+    }
 
-        if let Some(line_text) = self.rope.get_line(line) {
-            // text_calculatorを使用して文字インデックスから表示カラム位置に変換
-            let col = self
+    /// Text of `line` without the line break.
+    fn line_text(&self, line: usize) -> String {
+        match self.rope.get_line(line) {
+            Some(text) => text.chars().take(self.line_len_chars(line)).collect(),
+            None => String::new(),
+        }
+    }
+
+    /// Character ranges of the screen rows `line` occupies.
+    fn line_segments(&self, line: usize) -> Vec<Range<usize>> {
+        // A closed fold is shown as its first line, cut off at the pane edge
+        let wrap_width = self
+            .wrap_width
+            .filter(|_| self.folds.closed_region_at(line).is_none());
+        match wrap_width {
+            Some(width) => view::wrap_line(
+                &self.text_calculator,
+                &self.line_text(line),
+                width,
+                self.showbreak_width,
+            ),
+            None => std::iter::once(0..self.line_len_chars(line)).collect(),
+        }
+    }
+
+    /// Index of the segment holding `col`; a column at a wrap point belongs
+    /// to the following row.
+    fn segment_index(segments: &[Range<usize>], col: usize) -> usize {
+        segments
+            .iter()
+            .position(|segment| col < segment.end)
+            .unwrap_or(segments.len() - 1)
+    }
+
+    fn row_for_segment(line: usize, segments: &[Range<usize>], index: usize) -> DisplayRow {
+        DisplayRow {
+            line,
+            start: segments[index].start,
+            end: segments[index].end,
+            wraps: index + 1 < segments.len(),
+            folded: None,
+        }
+    }
+
+    /// Screen column of `col` within `row`, including the showbreak marker.
+    fn row_display_col(&self, row: &DisplayRow, col: usize) -> usize {
+        // Tabs stop relative to the start of the line, not of the row
+        let text = self.line_text(row.line);
+        let indent = if row.is_continuation() {
+            self.showbreak_width
+        } else {
+            0
+        };
+        let row_start = self.text_calculator.char_offset_to_col(&text, row.start);
+        indent
+            + self
                 .text_calculator
-                .char_index_to_col(&line_text.to_string(), char_offset);
-            (line, col)
+                .char_offset_to_col(&text, col.max(row.start))
+            - row_start
+    }
+
+    /// Character offset for a screen column on `row`. Positions past the end
+    /// of a wrapped row stay on that row.
+    fn col_in_row(&self, row: &DisplayRow, display_col: usize) -> usize {
+        let indent = if row.is_continuation() {
+            self.showbreak_width
+        } else {
+            0
+        };
+        let text: String = self.line_text(row.line).chars().take(row.end).collect();
+        let row_start = self.text_calculator.char_offset_to_col(&text, row.start);
+        let col = self
+            .text_calculator
+            .col_to_char_offset(&text, row_start + display_col.saturating_sub(indent))
+            .max(row.start);
+        if row.wraps {
+            col.min(row.end.saturating_sub(1).max(row.start))
         } else {
-            (line, char_offset)
+            col
         }
     }
 
     fn adjust_cursor_col(&mut self) {
-        if let Some(line) = self.rope.get_line(self.cursor_line) {
-            // text_calculatorを使用して行の表示幅を取得
-            let line_display_width = self.text_calculator.str_width(&line.to_string());
-            self.cursor_col = cmp::min(self.cursor_col, line_display_width);
-        }
+        self.cursor_col = cmp::min(self.cursor_col, self.line_len_chars(self.cursor_line));
     }
 
     fn adjust_viewport(&mut self) {
-        if self.cursor_line < self.viewport_offset {
-            self.viewport_offset = self.cursor_line;
-        } else if self.cursor_line >= self.viewport_offset + self.viewport_height {
-            self.viewport_offset = self.cursor_line.saturating_sub(self.viewport_height - 1);
+        // Landing inside a closed fold (search, jumps, undo) opens it
+        if self.visible_start(self.cursor_line) != self.cursor_line {
+            self.folds.open_at(self.cursor_line);
+        }
+
+        // Keep `scrolloff` visible lines of context above and below the cursor
+        let scroll_off = self
+            .scroll_off
+            .min(self.viewport_height.saturating_sub(1) / 2);
+        let (mut above, mut below, mut below_count) = (self.cursor_line, self.cursor_line, 0);
+        for _ in 0..scroll_off {
+            if let Some(prev) = self.prev_visible_line(above) {
+                above = prev;
+            }
+            if let Some(next) = self.next_visible_line(below) {
+                below = next;
+                below_count += 1;
+            }
+        }
+
+        if above < self.viewport_offset {
+            self.viewport_offset = above;
+        } else if below >= self.viewport_offset + self.viewport_height {
+            self.viewport_offset = below.saturating_sub(self.viewport_height - 1);
         }
+
+        if self.wrap_width.is_some() || self.folds.has_closed() {
+            // Wrapped lines above the cursor can push it below the last row
+            self.viewport_offset = self.visible_start(self.viewport_offset);
+            let mut row_counts = Vec::new();
+            let mut line = self.viewport_offset;
+            while line < self.cursor_line {
+                row_counts.push(self.line_segments(line).len());
+                match self.next_visible_line(line) {
+                    Some(next) => line = next,
+                    None => break,
+                }
+            }
+            let segments = self.line_segments(self.cursor_line);
+            let mut rows = row_counts.iter().sum::<usize>()
+                + Self::segment_index(&segments, self.cursor_col)
+                + 1;
+            let mut counts = row_counts.into_iter();
+            while rows + below_count > self.viewport_height
+                && self.viewport_offset < self.cursor_line
+            {
+                rows -= counts.next().unwrap_or(0);
+                self.viewport_offset = self
+                    .next_visible_line(self.viewport_offset)
+                    .unwrap_or(self.cursor_line);
+            }
+        }
+    }
+
+    /// First visible line of the closed fold holding `line`, or `line` itself.
+    fn visible_start(&self, line: usize) -> usize {
+        self.folds
+            .closed_region_at(line)
+            .map_or(line, |region| region.start)
     }
 
-    pub fn search(&mut self, query: &str) {
-        let content = self.rope.to_string();
+    /// Next line shown on screen after `line`, skipping closed folds.
+    fn next_visible_line(&self, line: usize) -> Option<usize> {
+        let end = self
+            .folds
+            .closed_region_at(line)
+            .map_or(line, |region| region.end);
+        (end + 1 < self.rope.len_lines()).then_some(end + 1)
+    }
+
+    /// Previous line shown on screen before `line`, skipping closed folds.
+    fn prev_visible_line(&self, line: usize) -> Option<usize> {
+        self.visible_start(line)
+            .checked_sub(1)
+            .map(|prev| self.visible_start(prev))
+    }
+
+    fn refresh_folds(&mut self) {
+        self.folds_stale = false;
+        if self.large_file {
+            return;
+        }
+        let lines: Vec<Cow<str>> = self.rope.lines().map(Cow::from).collect();
+        self.folds.update(&lines);
+    }
+
+    /// Note that the text changed. Closed folds have to follow the edit at
+    /// once to hide the right lines; otherwise the scan waits until a fold
+    /// command needs the regions, keeping it off the typing path.
+    fn text_changed_for_folds(&mut self) {
+        if self.folds.has_closed() {
+            self.refresh_folds();
+        } else {
+            self.folds_stale = true;
+        }
+    }
+
+    fn ensure_folds(&mut self) {
+        if self.folds_stale {
+            self.refresh_folds();
+        }
+    }
+
+    /// Toggle the fold under the cursor (`za`).
+    pub fn toggle_fold(&mut self) -> bool {
+        self.ensure_folds();
+        if self.folds.closed_region_at(self.cursor_line).is_some() {
+            self.open_fold()
+        } else {
+            self.close_fold()
+        }
+    }
+
+    /// Open the closed folds under the cursor (`zo`).
+    pub fn open_fold(&mut self) -> bool {
+        self.folds.open_at(self.cursor_line)
+    }
+
+    /// Close the innermost open fold under the cursor (`zc`).
+    pub fn close_fold(&mut self) -> bool {
+        self.ensure_folds();
+        let mut folds = std::mem::take(&mut self.folds);
+        let closed = folds.close_at(self.cursor_line, |line| self.line_text(line));
+        self.folds = folds;
+        if closed.is_some() {
+            self.move_cursor_out_of_folds();
+        }
+        closed.is_some()
+    }
+
+    /// Open every fold (`zR`).
+    pub fn open_all_folds(&mut self) {
+        self.folds.open_all();
+    }
+
+    /// Close every fold (`zM`). Returns `false` when there is nothing to fold.
+    pub fn close_all_folds(&mut self) -> bool {
+        self.ensure_folds();
+        if self.folds.regions().is_empty() {
+            return false;
+        }
+        let mut folds = std::mem::take(&mut self.folds);
+        folds.close_all(|line| self.line_text(line));
+        self.folds = folds;
+        self.move_cursor_out_of_folds();
+        true
+    }
+
+    /// Closed folds, for saving in a session.
+    pub fn closed_folds(&self) -> Vec<ClosedFold> {
+        self.folds.closed().to_vec()
+    }
+
+    /// Restore closed folds saved in a session.
+    pub fn restore_closed_folds(&mut self, closed: Vec<ClosedFold>) {
+        self.folds.set_closed(closed);
+        self.refresh_folds();
+        self.move_cursor_out_of_folds();
+    }
+
+    fn move_cursor_out_of_folds(&mut self) {
+        self.cursor_line = self.visible_start(self.cursor_line);
+        self.adjust_cursor_col();
+        self.adjust_viewport();
+    }
+
+    /// Move to the next match of `query`, returning whether there was one.
+    pub fn search(&mut self, query: &str) -> bool {
         let current_pos = self.line_col_to_char_idx(self.cursor_line, self.cursor_col);
 
-        if let Some(pos) = content[current_pos..].find(query) {
-            let found_pos = current_pos + pos;
+        if let Some(found_pos) = self.find(query, current_pos, true) {
             let (line, col) = self.char_idx_to_line_col(found_pos);
             self.cursor_line = line;
             self.cursor_col = col;
             self.adjust_viewport();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Replace every occurrence of `from` with `to` and return how many
+    /// were replaced. The cursor stays on its line.
+    pub fn replace_all(&mut self, from: &str, to: &str) -> usize {
+        if from.is_empty() {
+            return 0;
+        }
+        // Matches are found chunk by chunk and edited in place from the end,
+        // so the document is never copied whole
+        let len = from.chars().count();
+        let mut matches = Vec::new();
+        let mut pos = 0;
+        while let Some(found) = find_in_rope(&self.rope, from, pos, true, false) {
+            matches.push(found);
+            pos = found + len;
+        }
+        let count = matches.len();
+        if count > 0 {
+            for &start in matches.iter().rev() {
+                self.rope.remove(start..start + len);
+                self.rope.insert(start, to);
+            }
+            let (line, col) = (self.cursor_line, self.cursor_col);
+            self.set_cursor_position(line, col);
+            self.modified = true;
+            self.save_state();
+        }
+        count
+    }
+
+    /// `:s`: replace `from` with `to` in lines `first..=last`, only the first
+    /// match of each line unless `global`. Returns how many were replaced;
+    /// the cursor moves to the last changed line.
+    pub fn substitute(
+        &mut self,
+        first: usize,
+        last: usize,
+        from: &str,
+        to: &str,
+        global: bool,
+    ) -> usize {
+        if from.is_empty() {
+            return 0;
+        }
+        let last = last.min(self.line_count().saturating_sub(1));
+        let mut count = 0;
+        let mut changed_line = None;
+        for line in first..=last {
+            let text = self.line_text(line);
+            let replaced = if global {
+                count += text.matches(from).count();
+                text.replace(from, to)
+            } else if text.contains(from) {
+                count += 1;
+                text.replacen(from, to, 1)
+            } else {
+                continue;
+            };
+            if replaced == text {
+                continue;
+            }
+            let start = self.rope.line_to_char(line);
+            self.rope.remove(start..start + text.chars().count());
+            self.rope.insert(start, &replaced);
+            changed_line = Some(line);
+        }
+        if let Some(line) = changed_line {
+            self.set_cursor_position(line, 0);
+            self.modified = true;
+            self.save_state();
         }
+        count
+    }
+
+    /// Replace the whole text as one undoable change, keeping the cursor
+    /// where it was as far as the new text allows.
+    pub fn replace_content(&mut self, content: &str) {
+        if self.rope == content {
+            return;
+        }
+        self.rope = Rope::from_str(content);
+        let (line, col) = (self.cursor_line, self.cursor_col);
+        self.set_cursor_position(line, col);
+        self.modified = true;
+        self.save_state();
     }
 
     pub fn save_state(&mut self) {
         let current_state = EditorState {
-            content: self.rope.to_string(),
+            content: self.rope.clone(),
             cursor_line: self.cursor_line,
             cursor_col: self.cursor_col,
             visual_start_line: self.visual_start_line,
             visual_start_col: self.visual_start_col,
         };
 
-        // Don't save if the content hasn't changed from current history state
+        // Don't save if the content hasn't changed from current history
+        // state. Comparing scans the whole text, so large files skip it and
+        // may get an occasional empty undo step instead
         if let Some(last_state) = self.history.get(self.history_index) {
-            if last_state.content == current_state.content {
+            if !self.large_file && last_state.content == current_state.content {
                 return;
             }
         }
 
+        self.text_changed_for_folds();
+        self.revision += 1;
+
         // Remove any states after current index (if we're not at the end)
         self.history.truncate(self.history_index + 1);
 
@@ -422,9 +1391,11 @@ impl Editor {
         if self.history_index > 0 {
             self.history_index -= 1;
             let state = &self.history[self.history_index];
-            self.rope = Rope::from_str(&state.content);
+            self.rope = state.content.clone();
             self.cursor_line = state.cursor_line;
             self.cursor_col = state.cursor_col;
+            self.text_changed_for_folds();
+            self.revision += 1;
             self.adjust_viewport();
             self.modified = true;
             true
@@ -437,9 +1408,11 @@ impl Editor {
         if self.history_index + 1 < self.history.len() {
             self.history_index += 1;
             let state = &self.history[self.history_index];
-            self.rope = Rope::from_str(&state.content);
+            self.rope = state.content.clone();
             self.cursor_line = state.cursor_line;
             self.cursor_col = state.cursor_col;
+            self.text_changed_for_folds();
+            self.revision += 1;
             self.adjust_viewport();
             self.modified = true;
             true
@@ -475,6 +1448,42 @@ impl Editor {
         }
     }
 
+    /// Lines and characters covered by the visual selection, counting the
+    /// character under the cursor.
+    pub fn selection_size(&self) -> Option<(usize, usize)> {
+        let (start_line, start_col, end_line, end_col) = self.get_visual_selection()?;
+        let start = self.line_col_to_char_idx(start_line, start_col);
+        let end = (self.line_col_to_char_idx(end_line, end_col) + 1).min(self.rope.len_chars());
+        Some((end_line - start_line + 1, end.saturating_sub(start)))
+    }
+
+    /// Number of words: runs of letters and digits, with every kana and
+    /// kanji counted on its own as Japanese has no spaces between words.
+    pub fn word_count(&self) -> usize {
+        let mut count = 0;
+        let mut in_word = false;
+        for c in self.rope.chars() {
+            match CharClass::of(c) {
+                CharClass::Word => {
+                    count += usize::from(!in_word);
+                    in_word = true;
+                }
+                CharClass::Hiragana | CharClass::Katakana | CharClass::Kanji => {
+                    count += 1;
+                    in_word = false;
+                }
+                CharClass::Whitespace | CharClass::Punctuation => in_word = false,
+            }
+        }
+        count
+    }
+
+    /// `LF`, `CRLF` or `CR`: the line ending the file is saved with. The
+    /// text itself always uses `\n`.
+    pub fn line_ending(&self) -> &'static str {
+        self.file_format.line_ending.label()
+    }
+
     pub fn get_selected_text(&self) -> String {
         if let Some((start_line, start_col, end_line, end_col)) = self.get_visual_selection() {
             let start_idx = self.line_col_to_char_idx(start_line, start_col);
@@ -528,6 +1537,7 @@ impl Default for Editor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::file_format::LineEnding;
 
     #[test]
     fn test_editor_creation() {
@@ -908,6 +1918,191 @@ mod tests {
         assert_eq!(editor.line_count(), 1);
     }
 
+    #[test]
+    fn test_display_position_with_wide_characters() {
+        let mut editor = Editor::new();
+        editor.set_content("日本語abc\nxyz".to_string());
+
+        // Column 3 is the right half of "本"
+        editor.set_cursor_screen_position(0, 3);
+        assert_eq!(editor.cursor_position(), (0, 1));
+
+        editor.set_cursor_screen_position(0, 6);
+        assert_eq!(editor.cursor_position(), (0, 3));
+
+        editor.insert_char('!');
+        assert_eq!(editor.get_content(), "日本語!abc\nxyz");
+    }
+
+    #[test]
+    fn test_select_word_at_cursor() {
+        let mut editor = Editor::new();
+        editor.set_content("hello world 漢字かな".to_string());
+
+        editor.set_cursor_position(0, 7);
+        assert!(editor.select_word_at_cursor());
+        assert_eq!(editor.get_selected_text(), "world");
+
+        editor.clear_visual_selection();
+        editor.set_cursor_position(0, 12);
+        assert!(editor.select_word_at_cursor());
+        assert_eq!(editor.get_selected_text(), "漢字");
+
+        editor.clear_visual_selection();
+        editor.set_cursor_position(0, 5);
+        assert!(!editor.select_word_at_cursor());
+    }
+
+    #[test]
+    fn test_scroll_viewport_keeps_cursor_visible() {
+        let mut editor = Editor::new();
+        let content: Vec<String> = (0..50).map(|i| format!("line {}", i)).collect();
+        editor.set_content(content.join("\n"));
+        editor.set_viewport_height(10);
+
+        editor.scroll_viewport(3);
+        assert_eq!(editor.get_viewport_offset(), 3);
+        assert_eq!(editor.cursor_position().0, 3);
+
+        editor.scroll_viewport(100);
+        assert_eq!(editor.get_viewport_offset(), 40);
+
+        editor.scroll_viewport(-100);
+        assert_eq!(editor.get_viewport_offset(), 0);
+        assert_eq!(editor.cursor_position().0, 9);
+    }
+
+    #[test]
+    fn test_word_count_and_selection_size() {
+        let mut editor = Editor::new();
+        editor.set_content("Hello, world!\n日本語の text\r\n".to_string());
+        assert_eq!(editor.word_count(), 7);
+        assert_eq!(editor.line_ending(), "LF");
+        assert_eq!(editor.selection_size(), None);
+
+        editor.set_cursor_position(0, 7);
+        editor.start_visual_selection();
+        editor.set_cursor_position(1, 1);
+        assert_eq!(editor.selection_size(), Some((2, 9)));
+
+        editor.set_file_format(FileFormat {
+            line_ending: LineEnding::Dos,
+            ..FileFormat::default()
+        });
+        assert_eq!(editor.line_ending(), "CRLF");
+    }
+
+    #[test]
+    fn test_tabs_take_tabstop_columns() {
+        let mut editor = Editor::new();
+        editor.set_content("\tx\u{3000}y".to_string());
+        editor.set_tab_config(2, false);
+        editor.set_cursor_position(0, 1);
+        assert_eq!(editor.cursor_screen_position(), Some((0, 2)));
+        editor.set_cursor_position(0, 3);
+        assert_eq!(editor.cursor_screen_position(), Some((0, 5)));
+
+        // A tab after text only reaches the next tabstop
+        editor.set_content("abc\tx".to_string());
+        editor.set_tab_config(4, false);
+        editor.set_cursor_position(0, 4);
+        assert_eq!(editor.cursor_screen_position(), Some((0, 4)));
+        editor.set_cursor_screen_position(0, 4);
+        assert_eq!(editor.cursor_position(), (0, 4));
+
+        // On a wrapped row the tab still stops at the line's tabstop: it
+        // starts at column 5 and takes three columns
+        editor.set_content("abcde\txy".to_string());
+        editor.set_viewport_height(10);
+        editor.set_wrap(Some(5), 0);
+        editor.set_cursor_position(0, 7);
+        assert_eq!(editor.cursor_screen_position(), Some((1, 4)));
+        editor.set_cursor_screen_position(1, 3);
+        assert_eq!(editor.cursor_position(), (0, 6));
+    }
+
+    #[test]
+    fn test_soft_wrap_rows_and_screen_movement() {
+        let mut editor = Editor::new();
+        editor.set_content("abcdefghij\nxy".to_string());
+        editor.set_viewport_height(10);
+        editor.set_wrap(Some(4), 2);
+
+        let rows = editor.display_rows();
+        assert_eq!(rows.len(), 5);
+        assert_eq!((rows[1].start, rows[1].end), (4, 6));
+        assert!(rows[1].is_continuation() && rows[1].wraps);
+        assert!(!rows[3].wraps);
+
+        // "f" sits after the showbreak marker on the second row
+        editor.set_cursor_position(0, 5);
+        assert_eq!(editor.cursor_screen_position(), Some((1, 3)));
+
+        editor.move_display_row_down();
+        assert_eq!(editor.cursor_position(), (0, 7));
+        editor.move_display_row_down();
+        editor.move_display_row_down();
+        assert_eq!(editor.cursor_position(), (1, 2));
+        editor.move_display_row_up();
+        assert_eq!(editor.cursor_position(), (0, 8));
+
+        // Clicking on a continuation row maps through the marker
+        editor.set_cursor_screen_position(2, 2);
+        assert_eq!(editor.cursor_position(), (0, 6));
+
+        // Wrapped rows above the cursor scroll the viewport
+        editor.set_viewport_height(3);
+        editor.set_cursor_position(1, 0);
+        assert_eq!(editor.get_viewport_offset(), 1);
+    }
+
+    #[test]
+    fn test_folding_movement_and_edits() {
+        let mut editor = Editor::new();
+        editor.set_content("# A\none\ntwo\n# B\nthree".to_string());
+
+        // Close the first section from inside it
+        editor.set_cursor_position(2, 0);
+        assert!(editor.close_fold());
+        assert_eq!(editor.cursor_position(), (0, 0));
+
+        let rows = editor.display_rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].folded, Some(2));
+        assert_eq!(rows[1].line, 3);
+
+        editor.move_cursor_down();
+        assert_eq!(editor.cursor_position().0, 3);
+        editor.move_cursor_up();
+        assert_eq!(editor.cursor_position().0, 0);
+
+        // Lines inserted above shift the fold; it stays closed
+        editor.insert_newline();
+        assert_eq!(editor.display_rows()[1].folded, Some(2));
+
+        // Jumping into the fold opens it
+        editor.set_cursor_position(2, 0);
+        assert_eq!(editor.display_rows().len(), 6);
+
+        editor.close_all_folds();
+        assert_eq!(editor.display_rows().len(), 3);
+        editor.open_all_folds();
+        assert!(editor.toggle_fold());
+        assert_eq!(editor.display_rows()[0].folded, None);
+        assert_eq!(editor.display_rows()[1].folded, Some(2));
+
+        // With every fold open, edits leave the regions to the next fold
+        // command, which still sees a section typed just before it
+        editor.open_all_folds();
+        editor.set_content("one\ntwo".to_string());
+        editor.insert_char('#');
+        editor.insert_char(' ');
+        assert!(editor.folds_stale);
+        assert!(editor.close_fold());
+        assert!(!editor.folds_stale);
+        assert_eq!(editor.display_rows()[0].folded, Some(1));
+    }
+
     #[test]
     fn test_fullwidth_characters() {
         let mut editor = Editor::new();
@@ -917,4 +2112,153 @@ mod tests {
         assert_eq!(editor.line_count(), 1);
         assert_eq!(editor.get_content(), "全角文字");
     }
+
+    #[test]
+    fn test_word_movement_and_kill_line() {
+        let mut editor = Editor::new();
+        editor.set_content("foo, bar\nbaz".to_string());
+
+        editor.move_word_forward();
+        assert_eq!(editor.cursor_position(), (0, 3));
+        editor.move_word_forward();
+        assert_eq!(editor.cursor_position(), (0, 8));
+        editor.move_word_forward();
+        assert_eq!(editor.cursor_position(), (1, 3));
+        editor.move_word_backward();
+        assert_eq!(editor.cursor_position(), (1, 0));
+        editor.move_word_backward();
+        assert_eq!(editor.cursor_position(), (0, 5));
+
+        // Kill to the end of the line, then the line break itself
+        assert_eq!(editor.kill_to_line_end(), "bar");
+        assert_eq!(editor.kill_to_line_end(), "\n");
+        assert_eq!(editor.get_content(), "foo, baz");
+
+        editor.insert_text("qux ");
+        assert_eq!(editor.get_content(), "foo, qux baz");
+        editor.replace_before_cursor(4, "x");
+        assert_eq!(editor.get_content(), "foo, xbaz");
+        assert_eq!(editor.cursor_position(), (0, 6));
+    }
+
+    #[test]
+    fn test_cut_line_replace_all_and_search() {
+        let mut editor = Editor::new();
+        editor.set_content("日本語の文\nfoo bar foo\n".to_string());
+
+        editor.search("foo");
+        assert_eq!(editor.cursor_position(), (1, 0));
+        assert_eq!(editor.cut_line(), "foo bar foo\n");
+        assert_eq!(editor.get_content(), "日本語の文\n");
+
+        editor.set_content("日本語の文\nfoo bar foo\n".to_string());
+        editor.set_cursor_position(1, 4);
+        assert_eq!(editor.replace_all("foo", "文"), 2);
+        assert_eq!(editor.get_content(), "日本語の文\n文 bar 文\n");
+        assert_eq!(editor.cursor_position(), (1, 4));
+        assert_eq!(editor.replace_all("missing", "x"), 0);
+        assert!(editor.undo());
+        assert_eq!(editor.get_content(), "日本語の文\nfoo bar foo\n");
+    }
+
+    #[test]
+    fn test_search_case_options() {
+        let mut editor = Editor::new();
+        editor.set_content("Rust and rust".to_string());
+        assert_eq!(editor.find("rust", 0, true), Some(9));
+
+        let mut options =
+            EditorOptions::resolve(&crate::config::Config::default(), &LocalOptions::default());
+        options.ignorecase = true;
+        editor.apply_options(&options);
+        assert_eq!(editor.find("rust", 0, true), Some(0));
+        assert_eq!(editor.find("Rust", 1, true), Some(9));
+
+        options.smartcase = true;
+        editor.apply_options(&options);
+        assert_eq!(editor.find("Rust", 1, true), None);
+        assert_eq!(editor.find("rust", 1, true), Some(9));
+    }
+
+    /// Typing in a generated 100 MB log must cost about the same per
+    /// keystroke as in a small file, and searching it must stay quick.
+    #[test]
+    #[ignore = "builds a 100 MB buffer; run with --ignored"]
+    fn test_large_file_editing_latency_stays_flat() {
+        use std::time::{Duration, Instant};
+
+        fn time_edits(editor: &mut Editor) -> Duration {
+            let start = Instant::now();
+            for _ in 0..200 {
+                editor.insert_char('x');
+            }
+            start.elapsed()
+        }
+
+        let line = "2024-01-01T00:00:00Z INFO request handled in 12ms status=200\n";
+        let mut small = Editor::new();
+        small.load_file(line.repeat(100), FileFormat::default(), false);
+        small.set_cursor_position(50, 0);
+        let small_time = time_edits(&mut small);
+
+        let lines = 100 * 1024 * 1024 / line.len();
+        let mut large = Editor::new();
+        large.load_file(line.repeat(lines) + "needle\n", FileFormat::default(), true);
+        large.set_cursor_position(lines / 2, 0);
+        let large_time = time_edits(&mut large);
+        assert!(
+            large_time < small_time * 10 + Duration::from_millis(200),
+            "small: {:?}, large: {:?}",
+            small_time,
+            large_time
+        );
+
+        let start = Instant::now();
+        large.move_to_buffer_start();
+        large.search("needle");
+        assert_eq!(large.cursor_position(), (lines, 0));
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "{:?}",
+            start.elapsed()
+        );
+
+        let start = Instant::now();
+        assert_eq!(large.replace_all("needle", "found"), 1);
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "{:?}",
+            start.elapsed()
+        );
+        assert!(large.undo());
+
+        assert!(large.undo());
+        assert_eq!(large.rope.line(lines / 2).len_chars(), line.len() + 199);
+    }
+
+    #[test]
+    fn test_find_across_rope_chunks() {
+        for offset in (0..4000).step_by(97) {
+            let text = format!("{}Needle{}", "日".repeat(offset), "a".repeat(3000));
+            let mut editor = Editor::new();
+            editor.set_content(text.clone());
+            let len = text.chars().count();
+            assert_eq!(editor.find("Needle", 0, true), Some(offset));
+            assert_eq!(editor.find("Needle", len, false), Some(offset));
+            assert_eq!(editor.find("Needle", offset + 1, true), None);
+            editor.ignorecase = true;
+            assert_eq!(editor.find("needle", 0, true), Some(offset));
+            assert_eq!(editor.find("needle", offset + 5, false), Some(offset));
+        }
+
+        // Matches next to each other and on chunk boundaries are all replaced
+        let mut editor = Editor::new();
+        let text = "日a".repeat(3000);
+        editor.set_content(text.clone());
+        assert_eq!(editor.replace_all("a日", "-"), 2999);
+        assert_eq!(editor.get_content(), format!("日{}a", "-".repeat(2999)));
+        editor.set_content("aaaa".to_string());
+        assert_eq!(editor.replace_all("aa", "a"), 2);
+        assert_eq!(editor.get_content(), "aa");
+    }
 }
@@ -0,0 +1,170 @@
+//! Emacs キーバインドで使う編集状態。
+//!
+//! `C-k` / `C-w` で切り取ったテキストを蓄えるキルリングと、
+//! `C-s` / `C-r` によるインクリメンタルサーチの状態を扱います。
+
+use std::collections::VecDeque;
+
+use crate::editor::Editor;
+
+/// Maximum number of entries kept in the kill ring.
+pub const KILL_RING_MAX: usize = 60;
+
+/// Killed text, most recent first.
+#[derive(Debug, Clone, Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+    yank_index: usize,
+}
+
+impl KillRing {
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.push_front(text);
+        self.entries.truncate(KILL_RING_MAX);
+        self.yank_index = 0;
+    }
+
+    /// Add to the most recent entry, so consecutive kills yank back as one.
+    pub fn append(&mut self, text: &str) {
+        match self.entries.front_mut() {
+            Some(last) => last.push_str(text),
+            None => self.push(text.to_string()),
+        }
+        self.yank_index = 0;
+    }
+
+    /// The most recent entry (`C-y`).
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = 0;
+        self.entries.front().map(String::as_str)
+    }
+
+    /// The entry before the one yanked last, wrapping around (`M-y`).
+    pub fn rotate(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        self.entries.get(self.yank_index).map(String::as_str)
+    }
+}
+
+/// State of an incremental search started with `C-s` or `C-r`.
+#[derive(Debug, Clone)]
+pub struct IncrementalSearch {
+    pub query: String,
+    pub forward: bool,
+    /// Cursor position (character index) when the search started.
+    origin: usize,
+    failing: bool,
+}
+
+impl IncrementalSearch {
+    pub fn new(forward: bool, origin: usize) -> Self {
+        Self {
+            query: String::new(),
+            forward,
+            origin,
+            failing: false,
+        }
+    }
+
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+
+    /// Text shown in the message line while searching.
+    pub fn prompt(&self) -> String {
+        let failing = if self.failing { "失敗: " } else { "" };
+        let direction = if self.forward { "" } else { " (後方)" };
+        format!(
+            "{}インクリメンタル検索{}: {}",
+            failing, direction, self.query
+        )
+    }
+
+    pub fn push_char(&mut self, c: char, editor: &mut Editor) {
+        self.query.push(c);
+        self.search_from_origin(editor);
+    }
+
+    pub fn pop_char(&mut self, editor: &mut Editor) {
+        self.query.pop();
+        self.search_from_origin(editor);
+    }
+
+    /// Jump to the next match in `forward` direction from the current one.
+    pub fn repeat(&mut self, forward: bool, editor: &mut Editor) {
+        self.forward = forward;
+        let cursor = editor.cursor_char_index();
+        let from = if forward { cursor + 1 } else { cursor };
+        self.jump(editor, from);
+    }
+
+    fn search_from_origin(&mut self, editor: &mut Editor) {
+        if self.query.is_empty() {
+            self.failing = false;
+            editor.set_cursor_char_index(self.origin);
+            return;
+        }
+        // A backward search may stay on a match at the origin itself
+        let from = if self.forward {
+            self.origin
+        } else {
+            self.origin + 1
+        };
+        self.jump(editor, from);
+    }
+
+    fn jump(&mut self, editor: &mut Editor, from: usize) {
+        match editor.find(&self.query, from, self.forward) {
+            Some(found) => {
+                self.failing = false;
+                editor.set_cursor_char_index(found);
+            }
+            None => self.failing = true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_ring_yank_and_rotate() {
+        let mut ring = KillRing::default();
+        assert_eq!(ring.yank(), None);
+
+        ring.push("first".to_string());
+        ring.push("second".to_string());
+        ring.append(" line");
+        assert_eq!(ring.yank(), Some("second line"));
+        assert_eq!(ring.rotate(), Some("first"));
+        assert_eq!(ring.rotate(), Some("second line"));
+    }
+
+    #[test]
+    fn test_incremental_search() {
+        let mut editor = Editor::new();
+        editor.set_content("alpha beta\nalpha gamma\n".to_string());
+
+        let mut search = IncrementalSearch::new(true, editor.cursor_char_index());
+        search.push_char('a', &mut editor);
+        assert_eq!(editor.cursor_position(), (0, 0));
+        search.push_char('l', &mut editor);
+        search.repeat(true, &mut editor);
+        assert_eq!(editor.cursor_position(), (1, 0));
+
+        search.repeat(false, &mut editor);
+        assert_eq!(editor.cursor_position(), (0, 0));
+
+        search.push_char('x', &mut editor);
+        assert!(search.prompt().starts_with("失敗"));
+        search.pop_char(&mut editor);
+        assert!(!search.prompt().starts_with("失敗"));
+    }
+}
@@ -9,6 +9,15 @@ use ratatui::{
 };
 
 use crate::app::{App, Mode};
+use crate::command_processor::COMMANDS;
+use crate::config::parse_hex_color;
+use crate::gutter::Gutter;
+use crate::highlight;
+use crate::listchars::Whitespace;
+use crate::mouse::{ClickAction, PaneRegion};
+use crate::overlay;
+use crate::statusline;
+use crate::view;
 
 pub struct EnhancedUI;
 
@@ -30,13 +39,16 @@ impl EnhancedUI {
             return;
         }
 
+        // Positions are re-recorded on every draw for mouse hit testing
+        app.screen_layout.clear();
+
         let result = panic::catch_unwind(AssertUnwindSafe(|| {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
                     Constraint::Length(1), // Title bar
                     Constraint::Min(0),    // Editor area
-                    Constraint::Length(3), // Enhanced status bar
+                    Constraint::Length(4), // Enhanced status bar
                 ])
                 .split(f.size());
 
@@ -53,11 +65,18 @@ impl EnhancedUI {
             if app.show_help() {
                 Self::draw_enhanced_help(f, chunks[1]);
             } else {
-                Self::draw_enhanced_editor(f, app, chunks[1]);
+                let (sidebar, editor_area) = overlay::explorer_layout(app, chunks[1]);
+                Self::draw_enhanced_editor(f, app, editor_area);
+                if let Some(sidebar) = sidebar {
+                    overlay::draw_explorer(f, app, sidebar);
+                }
             }
 
             // Draw enhanced status bar
             Self::draw_enhanced_status_bar(f, app, chunks[2]);
+            overlay::draw_wildmenu(f, app, chunks[2]);
+            overlay::draw_picker(f, app);
+            overlay::draw_pager(f, app);
 
             Ok::<(), &'static str>(())
         }));
@@ -81,7 +100,7 @@ impl EnhancedUI {
         Ok(())
     }
 
-    fn draw_enhanced_title_bar(f: &mut Frame, app: &App, area: Rect) {
+    fn draw_enhanced_title_bar(f: &mut Frame, app: &mut App, area: Rect) {
         let result = panic::catch_unwind(AssertUnwindSafe(|| {
             let file_info = match app.file_path() {
                 Some(path) => {
@@ -109,11 +128,52 @@ impl EnhancedUI {
                 Style::default().bg(Color::Blue).fg(Color::White)
             };
 
+            // Buffer tabs on the right when more than one buffer is open
+            let tabs = app.buffer_tabs();
+            let tab_widths: Vec<usize> = tabs
+                .iter()
+                .map(|(_, label, _)| app.text_calculator.str_width(label))
+                .collect();
+            let tabs_width = if tabs.len() > 1 {
+                (tab_widths.iter().sum::<usize>() as u16).min(area.width / 2)
+            } else {
+                0
+            };
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(tabs_width)])
+                .split(area);
+
             let title_bar = Paragraph::new(title)
                 .style(style)
                 .alignment(Alignment::Left);
 
-            f.render_widget(title_bar, area);
+            f.render_widget(title_bar, chunks[0]);
+
+            if tabs_width > 0 {
+                let spans: Vec<Span> = tabs
+                    .iter()
+                    .map(|(_, label, is_current)| {
+                        let tab_style = if *is_current {
+                            Style::default()
+                                .bg(Color::White)
+                                .fg(Color::Blue)
+                                .add_modifier(Modifier::BOLD)
+                        } else {
+                            style
+                        };
+                        Span::styled(label.clone(), tab_style)
+                    })
+                    .collect();
+                f.render_widget(Paragraph::new(Line::from(spans)).style(style), chunks[1]);
+
+                let items: Vec<(usize, Option<ClickAction>)> = tabs
+                    .iter()
+                    .zip(&tab_widths)
+                    .map(|((id, _, _), width)| (*width, Some(ClickAction::SwitchBuffer(*id))))
+                    .collect();
+                app.screen_layout.push_row_targets(chunks[1], &items);
+            }
         }));
 
         if result.is_err() {
@@ -123,108 +183,116 @@ impl EnhancedUI {
     }
 
     fn draw_enhanced_editor(f: &mut Frame, app: &mut App, area: Rect) {
-        let editor_area = if app.config.editor.line_numbers {
-            Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Length(5), // Line numbers
-                    Constraint::Min(0),    // Editor content
-                ])
-                .split(area)
-        } else {
-            Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Min(0)])
-                .split(area)
-        };
-
-        app.get_current_editor_mut()
-            .set_viewport_height(area.height as usize);
-
-        // Draw line numbers if enabled
-        if app.config.editor.line_numbers {
-            let start_line = app
-                .get_current_editor()
-                .cursor_position()
-                .0
-                .saturating_sub(area.height as usize / 2);
-            let line_numbers: Vec<Line> = (0..area.height as usize)
-                .map(|i| {
-                    let line_num = start_line + i + 1;
-                    let style = if line_num == app.get_current_editor().cursor_position().0 + 1 {
-                        Style::default()
-                            .fg(Color::Yellow)
-                            .add_modifier(Modifier::BOLD)
-                    } else {
-                        Style::default().fg(Color::DarkGray)
-                    };
-                    Line::from(Span::styled(format!("{:4} ", line_num), style))
-                })
-                .collect();
-
-            let line_number_widget = Paragraph::new(line_numbers)
-                .style(Style::default().bg(Color::Black))
-                .alignment(Alignment::Right);
+        let current = app.buffer_manager.current_index();
+        let options = app.editor_options(current);
+        let signs = app.buffer_signs(current);
+        let gutter = Gutter::new(&options, &signs, app.get_current_editor().line_count());
+        let editor_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(gutter.width()), // Signs and line numbers
+                Constraint::Min(0),                 // Editor content
+            ])
+            .split(area);
+
+        let text_area = editor_area[1];
+        let wrap_width = options.wrap.then_some(text_area.width as usize);
+        let showbreak_width = app.text_calculator.str_width(&app.config.editor.showbreak);
+        {
+            let editor = app.get_current_editor_mut();
+            editor.set_viewport_height(area.height as usize);
+            editor.set_wrap(wrap_width, showbreak_width);
+        }
 
-            f.render_widget(line_number_widget, editor_area[0]);
+        let viewport_offset = app.get_current_editor().get_viewport_offset();
+        let (cursor_line, _cursor_col) = app.get_current_editor().cursor_position();
+        let rows = app.get_current_editor().display_rows();
+
+        // Draw the sign column and line numbers; continuation rows of wrapped
+        // lines get none
+        if gutter.width() > 0 {
+            let gutter_lines = gutter.render(
+                &rows,
+                cursor_line,
+                &options,
+                &signs,
+                Style::default().fg(Color::DarkGray),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+            let gutter_widget =
+                Paragraph::new(gutter_lines).style(Style::default().bg(Color::Black));
+            f.render_widget(gutter_widget, editor_area[0]);
         }
 
         // Draw editor content with syntax highlighting
         let lines = app.get_current_editor().get_viewport_lines();
-        let (cursor_line, _cursor_col) = app.get_current_editor().cursor_position();
 
         // Get file path before borrowing highlighter
         let file_path = app.file_path().map(|p| p.to_string_lossy().to_string());
-        let highlighter = app.get_highlighter();
-        let syntax = match file_path.as_ref() {
-            Some(p) => highlighter.find_syntax_for_filename(p),
-            None => highlighter.find_syntax_for_filename("text.md"),
+        let showbreak = app.config.editor.showbreak.clone();
+        let whitespace = Whitespace::new(&app.config, &options);
+        let highlighted = if app.get_current_editor().is_large_file() {
+            highlight::plain_lines(&lines)
+        } else {
+            let highlighter = app.get_highlighter();
+            let syntax = match file_path.as_ref() {
+                Some(p) => highlighter.find_syntax_for_filename(p),
+                None => highlighter.find_syntax_for_filename("text.md"),
+            };
+            highlighter.highlight_lines_to_ratatui(&lines, syntax)
         };
+        let mut content_lines = view::render_rows(
+            &rows,
+            &highlighted,
+            viewport_offset,
+            &showbreak,
+            Style::default().fg(Color::DarkGray),
+            &whitespace,
+        );
 
-        let mut content_lines = highlighter.highlight_lines_to_ratatui(&lines, syntax);
-
-        // Current line background highlight overlay
-        if app.config.editor.highlight_current_line && cursor_line < content_lines.len() {
-            let bg = Style::default().bg(Color::Rgb(40, 40, 40));
-            let spans = content_lines[cursor_line]
-                .spans
-                .iter()
-                .map(|s| Span::styled(s.content.clone().into_owned(), s.style.patch(bg)))
-                .collect::<Vec<_>>();
-            content_lines[cursor_line] = Line::from(spans);
+        // Current line and column background highlight, on every row of the line
+        let bg = Style::default().bg(Color::Rgb(40, 40, 40));
+        if options.cursorline {
+            for (row, line) in rows.iter().zip(content_lines.iter_mut()) {
+                if row.line == cursor_line {
+                    *line = view::patch_line(line, bg);
+                }
+            }
+        }
+        if options.cursorcolumn {
+            if let Some((_, display_col)) = app.get_current_editor().cursor_screen_position() {
+                for line in content_lines.iter_mut() {
+                    *line = view::patch_column(line, display_col, bg, &app.text_calculator);
+                }
+            }
         }
 
-        let editor_widget = Paragraph::new(content_lines)
-            .style(Style::default().fg(Color::White))
-            .wrap(Wrap { trim: false });
+        let editor_widget = Paragraph::new(content_lines).style(Style::default().fg(Color::White));
 
-        let editor_area_index = if app.config.editor.line_numbers { 1 } else { 0 };
-        f.render_widget(editor_widget, editor_area[editor_area_index]);
+        f.render_widget(editor_widget, text_area);
 
         // Draw cursor
-        if let Some(editor_rect) = editor_area.get(editor_area_index) {
-            Self::draw_cursor(f, app, *editor_rect);
-        }
+        app.screen_layout.push_pane(PaneRegion {
+            window_id: app.window_manager.current_window_id,
+            buffer_id: app.buffer_manager.current_buffer_id(),
+            text_area,
+        });
+        Self::draw_cursor(f, app, text_area);
     }
 
     fn draw_cursor(f: &mut Frame, app: &App, area: Rect) {
-        let (cursor_line, cursor_col) = app.get_current_editor().cursor_position();
-
-        // Compute display column considering fullwidth characters on the line
-        // We recompute width from start to cursor_col for correctness
-        let line_text = {
-            let lines = app.get_current_editor().get_viewport_lines();
-            lines.get(cursor_line).cloned().unwrap_or_default()
+        // Screen position accounts for wide characters and wrapped rows
+        let Some((screen_row, display_col)) = app.get_current_editor().cursor_screen_position()
+        else {
+            return;
         };
-        let logical_prefix: String = line_text.chars().take(cursor_col).collect();
-
-        // Use accurate text width calculation for cross-platform compatibility
-        let display_col: usize = app.text_calculator.str_width(&logical_prefix);
 
         // Calculate cursor position on screen
-        if cursor_line < area.height as usize && display_col < area.width as usize {
+        if screen_row < area.height as usize && display_col < area.width as usize {
             let cursor_x = area.x + display_col as u16;
-            let cursor_y = area.y + cursor_line as u16;
+            let cursor_y = area.y + screen_row as u16;
 
             if cursor_x < area.x + area.width && cursor_y < area.y + area.height {
                 // Draw cursor based on mode
@@ -247,13 +315,13 @@ impl EnhancedUI {
         }
     }
 
-    fn draw_enhanced_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    fn draw_enhanced_status_bar(f: &mut Frame, app: &mut App, area: Rect) {
         let result = panic::catch_unwind(AssertUnwindSafe(|| {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
                     Constraint::Length(1), // File info and cursor position
-                    Constraint::Length(1), // Command input or shortcuts
+                    Constraint::Length(2), // Command input or shortcuts
                     Constraint::Length(1), // Status messages
                 ])
                 .split(area);
@@ -274,99 +342,64 @@ impl EnhancedUI {
         }
     }
 
-    fn draw_file_info(f: &mut Frame, app: &App, area: Rect) {
-        let (line, col) = app.get_current_editor().cursor_position();
-        let line_count = app.get_current_editor().line_count();
-        let progress = if line_count > 0 {
-            line * 100 / line_count.max(1)
-        } else {
-            0
-        };
-
-        let file_info = match app.file_path() {
-            Some(path) => format!("{} ", path.display()),
-            None => "[新規ファイル] ".to_string(),
-        };
-
-        let position_info = format!(
-            "行 {}, 桁 {} ({}/{})",
-            line + 1,
-            col + 1,
-            line + 1,
-            line_count
-        );
-        let progress_info = format!(" {}%", progress);
-
-        let info_spans = vec![
-            Span::styled(file_info, Style::default().fg(Color::Cyan)),
-            Span::styled(position_info, Style::default().fg(Color::White)),
-            Span::styled(progress_info, Style::default().fg(Color::Green)),
-        ];
-
-        let info_line = Paragraph::new(Line::from(info_spans))
-            .style(Style::default().bg(Color::DarkGray))
+    fn draw_file_info(f: &mut Frame, app: &mut App, area: Rect) {
+        let info = app.status_info();
+        let config = &app.config.statusline;
+        let line = statusline::render(config, &info, area.width as usize, &app.text_calculator);
+        let background = config
+            .background
+            .as_deref()
+            .and_then(parse_hex_color)
+            .unwrap_or(Color::DarkGray);
+
+        let info_line = Paragraph::new(line)
+            .style(Style::default().bg(background))
             .alignment(Alignment::Left);
 
         f.render_widget(info_line, area);
     }
 
-    fn draw_command_area(f: &mut Frame, app: &App, area: Rect) {
+    fn draw_command_area(f: &mut Frame, app: &mut App, area: Rect) {
         match app.mode() {
             Mode::Command => {
                 let input =
-                    Paragraph::new(format!("{}{}", app.status_message(), app.command_buffer()))
+                    Paragraph::new(format!("{}{}", app.command_prompt(), app.command_buffer()))
                         .style(Style::default().fg(Color::Yellow).bg(Color::Black));
                 f.render_widget(input, area);
             }
             _ => {
-                let shortcuts = vec![
-                    Span::styled(
-                        ":",
-                        Style::default()
-                            .fg(Color::Yellow)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(" コマンド  "),
-                    Span::styled(
-                        "i",
-                        Style::default()
-                            .fg(Color::Green)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(" 挿入  "),
-                    Span::styled(
-                        "/",
-                        Style::default()
-                            .fg(Color::Cyan)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(" 検索  "),
-                    Span::styled(
-                        "u",
-                        Style::default()
-                            .fg(Color::Magenta)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(" 元に戻す  "),
-                    Span::styled(
-                        "^R",
-                        Style::default()
-                            .fg(Color::Magenta)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(" やり直し  "),
-                    Span::styled(
-                        "?",
-                        Style::default()
-                            .fg(Color::White)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(" ヘルプ"),
-                ];
-
-                let shortcut_bar = Paragraph::new(Line::from(shortcuts))
-                    .style(Style::default().bg(Color::DarkGray));
-                f.render_widget(shortcut_bar, area);
+                // Two rows of shortcuts from the active keymap, in even
+                // columns like nano's help lines
+                let rows = app.keymap.shortcuts();
+                let columns = rows.iter().map(Vec::len).max().unwrap_or(1).max(1);
+                let column_width = area.width as usize / columns;
+                let mut index = 0;
+                for (row, shortcuts) in rows.iter().enumerate().take(area.height as usize) {
+                    let row_area = Rect::new(area.x, area.y + row as u16, area.width, 1);
+                    let mut spans = Vec::new();
+                    let mut targets = Vec::new();
+                    for shortcut in shortcuts {
+                        let used = app.text_calculator.str_width(&shortcut.key)
+                            + 1
+                            + app.text_calculator.str_width(shortcut.label);
+                        let padding = " ".repeat(column_width.saturating_sub(used));
+                        spans.push(Span::styled(
+                            shortcut.key.clone(),
+                            Style::default()
+                                .fg(Color::Black)
+                                .bg(Color::Gray)
+                                .add_modifier(Modifier::BOLD),
+                        ));
+                        spans.push(Span::raw(format!(" {}{}", shortcut.label, padding)));
+                        targets.push((used.max(column_width), Some(ClickAction::Shortcut(index))));
+                        index += 1;
+                    }
+                    app.screen_layout.push_row_targets(row_area, &targets);
+
+                    let shortcut_bar = Paragraph::new(Line::from(spans))
+                        .style(Style::default().bg(Color::DarkGray));
+                    f.render_widget(shortcut_bar, row_area);
+                }
             }
         }
     }
@@ -406,27 +439,8 @@ impl EnhancedUI {
             f.render_widget(status_widget, area);
         } else {
             // Show mode indicator when no status message
-            let mode_text = match app.mode() {
-                Mode::Normal => "-- NORMAL --",
-                Mode::Insert => "-- INSERT --",
-                Mode::Command => "-- COMMAND --",
-                Mode::Visual => "-- VISUAL --",
-                Mode::VisualBlock => "-- VISUAL BLOCK --",
-                Mode::Replace => "-- REPLACE --",
-                Mode::Help => "-- HELP --",
-                Mode::SavePrompt => "-- SAVE PROMPT --",
-            };
-
-            let mode_style = match app.mode() {
-                Mode::Normal => Style::default().fg(Color::Blue),
-                Mode::Insert => Style::default().fg(Color::Green),
-                Mode::Command => Style::default().fg(Color::Yellow),
-                Mode::Visual => Style::default().fg(Color::Magenta),
-                Mode::VisualBlock => Style::default().fg(Color::Cyan),
-                Mode::Replace => Style::default().fg(Color::Red),
-                Mode::Help => Style::default().fg(Color::Cyan),
-                Mode::SavePrompt => Style::default().fg(Color::Red),
-            };
+            let mode_text = format!("-- {} --", statusline::mode_label(app.mode(), &app.keymap));
+            let mode_style = Style::default().fg(statusline::mode_color(app.mode()));
 
             let mode_widget = Paragraph::new(mode_text)
                 .style(mode_style.add_modifier(Modifier::BOLD))
@@ -437,7 +451,7 @@ impl EnhancedUI {
     }
 
     fn draw_enhanced_help(f: &mut Frame, area: Rect) {
-        let help_text = vec![
+        let mut help_text = vec![
             Line::from(""),
             Line::from(vec![Span::styled(
                 " SCRIPTORIS - Enhanced Vim-style Markdown Editor",
@@ -498,6 +512,24 @@ impl EnhancedUI {
                 ),
                 Span::raw("   Move cursor (Vim-style)"),
             ]),
+            Line::from(vec![
+                Span::styled(
+                    "   gj gk",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw("     Move by screen line (wrapped lines)"),
+            ]),
+            Line::from(vec![
+                Span::styled(
+                    "   za zo zc",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw("  Toggle / open / close fold (zR zM: all)"),
+            ]),
             Line::from(vec![
                 Span::styled(
                     "   Arrow Keys",
@@ -559,40 +591,28 @@ impl EnhancedUI {
                 Span::raw("      New line below"),
             ]),
             Line::from(""),
-            Line::from(vec![Span::styled(
-                " Commands:",
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::UNDERLINED),
-            )]),
             Line::from(vec![
                 Span::styled(
-                    "   :w",
+                    " Commands:",
                     Style::default()
-                        .fg(Color::Blue)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("   Save file                "),
-                Span::styled(
-                    ":q",
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::UNDERLINED),
                 ),
-                Span::raw("     Quit"),
+                Span::raw("  (F2: command palette)"),
             ]),
+        ];
+        help_text.extend(COMMANDS.iter().map(|spec| {
             Line::from(vec![
                 Span::styled(
-                    "   :wq",
+                    format!("   {}", spec.usage()),
                     Style::default()
                         .fg(Color::Blue)
                         .add_modifier(Modifier::BOLD),
                 ),
-                Span::raw("  Save and quit            "),
-                Span::styled(
-                    ":q!",
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("    Force quit"),
-            ]),
+                Span::raw(format!("  {}", spec.help)),
+            ])
+        }));
+        help_text.extend([
             Line::from(""),
             Line::from(vec![
                 Span::styled(" Press ", Style::default().fg(Color::Gray)),
@@ -609,7 +629,7 @@ impl EnhancedUI {
                 ),
                 Span::styled(" to close this help", Style::default().fg(Color::Gray)),
             ]),
-        ];
+        ]);
 
         let help_widget = Paragraph::new(help_text)
             .block(
@@ -0,0 +1,557 @@
+//! netrw 風のファイルエクスプローラー（サイドバー）。
+//!
+//! プロジェクトのファイルとフォルダをツリー表示し、フォルダの展開・折りたたみと
+//! ファイルの作成・名前変更・移動・削除を扱います。`.gitignore` で除外された
+//! 項目は表示せず、Markdown ファイルには最初の見出しをヒントとして添えます。
+
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::folding;
+use crate::gitignore::GitIgnore;
+
+/// Width of the sidebar in columns.
+pub const EXPLORER_WIDTH: u16 = 32;
+/// Bytes read from a Markdown file to find its first heading.
+const HINT_BYTES: u64 = 16 * 1024;
+
+/// One row of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplorerEntry {
+    /// Path relative to the explorer root.
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub expanded: bool,
+    /// First heading of a Markdown file.
+    pub hint: Option<String>,
+}
+
+impl ExplorerEntry {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// State of the explorer sidebar.
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    root: PathBuf,
+    expanded: HashSet<PathBuf>,
+    entries: Vec<ExplorerEntry>,
+    selected: usize,
+    /// Whether keys go to the explorer instead of the editor.
+    pub focused: bool,
+}
+
+impl Explorer {
+    pub fn new(root: PathBuf) -> Self {
+        let mut explorer = Self {
+            root,
+            expanded: HashSet::new(),
+            entries: Vec::new(),
+            selected: 0,
+            focused: true,
+        };
+        explorer.refresh();
+        explorer
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn entries(&self) -> &[ExplorerEntry] {
+        &self.entries
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_entry(&self) -> Option<&ExplorerEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Path of `relative` as used to open it; paths below the working
+    /// directory stay relative.
+    pub fn full_path(&self, relative: &Path) -> PathBuf {
+        if self.root == Path::new(".") {
+            relative.to_path_buf()
+        } else {
+            self.root.join(relative)
+        }
+    }
+
+    /// Re-read the tree from disk, keeping the selected path when it still
+    /// exists.
+    pub fn refresh(&mut self) {
+        let selected = self.selected_entry().map(|entry| entry.path.clone());
+        let mut ignore = GitIgnore::new();
+        let mut entries = Vec::new();
+        self.collect(&mut ignore, Path::new(""), 0, &mut entries);
+        self.entries = entries;
+        match selected {
+            Some(path) => self.select_path(&path),
+            None => self.selected = 0,
+        }
+    }
+
+    fn collect(
+        &self,
+        ignore: &mut GitIgnore,
+        dir: &Path,
+        depth: usize,
+        entries: &mut Vec<ExplorerEntry>,
+    ) {
+        ignore.load_dir(&self.root, dir);
+        let Ok(read) = fs::read_dir(self.root.join(dir)) else {
+            return;
+        };
+        let mut children: Vec<(bool, PathBuf)> = read
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let is_dir = entry.file_type().ok()?.is_dir();
+                Some((is_dir, dir.join(entry.file_name())))
+            })
+            .filter(|(is_dir, path)| {
+                path.file_name() != Some(".git".as_ref()) && !ignore.is_ignored(path, *is_dir)
+            })
+            .collect();
+        // Folders first, then files, each by name
+        children.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        for (is_dir, path) in children {
+            let expanded = is_dir && self.expanded.contains(&path);
+            let hint = if is_dir {
+                None
+            } else {
+                markdown_hint(&self.root.join(&path))
+            };
+            entries.push(ExplorerEntry {
+                path: path.clone(),
+                depth,
+                is_dir,
+                expanded,
+                hint,
+            });
+            if expanded {
+                self.collect(ignore, &path, depth + 1, entries);
+            }
+        }
+    }
+
+    /// Move the selection by `delta`, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Select `path`, expanding the folders above it.
+    pub fn reveal(&mut self, path: &Path) {
+        let mut expanded = false;
+        for ancestor in path.ancestors().skip(1) {
+            if !ancestor.as_os_str().is_empty() {
+                expanded |= self.expanded.insert(ancestor.to_path_buf());
+            }
+        }
+        if expanded {
+            self.refresh();
+        }
+        self.select_path(path);
+    }
+
+    fn select_path(&mut self, path: &Path) {
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected = index;
+        } else {
+            self.select(self.selected);
+        }
+    }
+
+    /// Expand or collapse the selected folder. Returns false for files.
+    pub fn toggle_selected(&mut self) -> bool {
+        let Some(entry) = self.selected_entry() else {
+            return false;
+        };
+        if !entry.is_dir {
+            return false;
+        }
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.refresh();
+        true
+    }
+
+    /// Collapse the selected folder, or select the folder holding the entry.
+    pub fn collapse_or_parent(&mut self) {
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        if entry.expanded {
+            self.toggle_selected();
+        } else if let Some(parent) = entry.path.parent().map(Path::to_path_buf) {
+            self.select_path(&parent);
+        }
+    }
+
+    /// Folder that new files are created in: the selected folder, or the
+    /// folder holding the selected file.
+    pub fn target_dir(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            None => PathBuf::new(),
+        }
+    }
+}
+
+/// First heading of a Markdown file.
+fn markdown_hint(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if !matches!(extension.as_str(), "md" | "markdown") {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(HINT_BYTES).read_to_end(&mut bytes))
+        .ok()?;
+    let text = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = text.lines().collect();
+    folding::headings(&lines)
+        .into_iter()
+        .next()
+        .map(|heading| heading.text)
+}
+
+/// Text with a trailing `/`, used to prefill folder prompts.
+fn dir_prefix(dir: &Path) -> String {
+    if dir.as_os_str().is_empty() {
+        String::new()
+    } else {
+        format!("{}/", dir.display())
+    }
+}
+
+/// A change to the file system, applied after confirmation. Paths are
+/// relative to the explorer root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOperation {
+    CreateFile(PathBuf),
+    CreateDir(PathBuf),
+    Rename { from: PathBuf, to: PathBuf },
+    Move { from: PathBuf, to: PathBuf },
+    Delete { path: PathBuf, is_dir: bool },
+}
+
+impl FileOperation {
+    pub fn confirmation(&self) -> String {
+        match self {
+            FileOperation::CreateFile(path) => {
+                format!("{} を作成しますか? (y/n): ", path.display())
+            }
+            FileOperation::CreateDir(path) => {
+                format!("フォルダ {} を作成しますか? (y/n): ", path.display())
+            }
+            FileOperation::Rename { from, to } => format!(
+                "{} の名前を {} に変更しますか? (y/n): ",
+                from.display(),
+                to.display()
+            ),
+            FileOperation::Move { from, to } => format!(
+                "{} を {} に移動しますか? (y/n): ",
+                from.display(),
+                to.display()
+            ),
+            FileOperation::Delete { path, is_dir: true } => {
+                format!(
+                    "フォルダ {} とその中身を削除しますか? (y/n): ",
+                    path.display()
+                )
+            }
+            FileOperation::Delete { path, .. } => {
+                format!("{} を削除しますか? (y/n): ", path.display())
+            }
+        }
+    }
+
+    /// Path to select once the operation is done.
+    pub fn result_path(&self) -> Option<&Path> {
+        match self {
+            FileOperation::CreateFile(path) | FileOperation::CreateDir(path) => Some(path),
+            FileOperation::Rename { to, .. } | FileOperation::Move { to, .. } => Some(to),
+            FileOperation::Delete { .. } => None,
+        }
+    }
+
+    /// Apply the operation below `root` and describe the result.
+    pub fn apply(&self, root: &Path) -> Result<String> {
+        let existing = |path: &Path| -> Result<()> {
+            if root.join(path).exists() {
+                Err(anyhow!("既に存在します: {}", path.display()))
+            } else {
+                Ok(())
+            }
+        };
+        match self {
+            FileOperation::CreateFile(path) => {
+                existing(path)?;
+                let full = root.join(path);
+                if let Some(parent) = full.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create_new(&full)?;
+                Ok(format!("ファイルを作成しました: {}", path.display()))
+            }
+            FileOperation::CreateDir(path) => {
+                existing(path)?;
+                fs::create_dir_all(root.join(path))?;
+                Ok(format!("フォルダを作成しました: {}", path.display()))
+            }
+            FileOperation::Rename { from, to } | FileOperation::Move { from, to } => {
+                existing(to)?;
+                let target = root.join(to);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(root.join(from), target)?;
+                let verb = if matches!(self, FileOperation::Move { .. }) {
+                    "移動しました"
+                } else {
+                    "名前を変更しました"
+                };
+                Ok(format!("{}: {} → {}", verb, from.display(), to.display()))
+            }
+            FileOperation::Delete { path, is_dir } => {
+                if *is_dir {
+                    fs::remove_dir_all(root.join(path))?;
+                } else {
+                    fs::remove_file(root.join(path))?;
+                }
+                Ok(format!("削除しました: {}", path.display()))
+            }
+        }
+    }
+}
+
+/// What an explorer input prompt asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    NewFile,
+    NewDir,
+    Rename,
+    Move,
+}
+
+/// Prompt shown in the message line while the app is in
+/// `Mode::SavePrompt`: a path to type, then a yes/no confirmation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExplorerPrompt {
+    Input {
+        kind: InputKind,
+        /// Entry the prompt was opened on.
+        source: PathBuf,
+        text: String,
+    },
+    Confirm(FileOperation),
+}
+
+impl ExplorerPrompt {
+    /// Prompt for `kind` on the selected entry, prefilled with a useful path.
+    pub fn input(kind: InputKind, explorer: &Explorer) -> Option<Self> {
+        let source = match kind {
+            InputKind::NewFile | InputKind::NewDir => explorer.target_dir(),
+            InputKind::Rename | InputKind::Move => explorer.selected_entry()?.path.clone(),
+        };
+        let text = match kind {
+            InputKind::NewFile | InputKind::NewDir => dir_prefix(&source),
+            InputKind::Rename => source.display().to_string(),
+            InputKind::Move => dir_prefix(source.parent().unwrap_or(Path::new(""))),
+        };
+        Some(ExplorerPrompt::Input { kind, source, text })
+    }
+
+    /// Confirmation before deleting the selected entry.
+    pub fn delete(explorer: &Explorer) -> Option<Self> {
+        let entry = explorer.selected_entry()?;
+        Some(ExplorerPrompt::Confirm(FileOperation::Delete {
+            path: entry.path.clone(),
+            is_dir: entry.is_dir,
+        }))
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ExplorerPrompt::Input { kind, text, .. } => {
+                let label = match kind {
+                    InputKind::NewFile => "新しいファイル",
+                    InputKind::NewDir => "新しいフォルダ",
+                    InputKind::Rename => "新しい名前",
+                    InputKind::Move => "移動先フォルダ",
+                };
+                format!("{}: {}", label, text)
+            }
+            ExplorerPrompt::Confirm(operation) => operation.confirmation(),
+        }
+    }
+
+    /// The operation described by a filled-in input prompt.
+    pub fn operation(&self) -> Option<FileOperation> {
+        let ExplorerPrompt::Input { kind, source, text } = self else {
+            return None;
+        };
+        let text = text.trim();
+        match kind {
+            _ if text.is_empty() && *kind != InputKind::Move => None,
+            // Only the prefilled folder: no file name was typed
+            InputKind::NewFile if text.ends_with('/') => None,
+            InputKind::NewFile => Some(FileOperation::CreateFile(PathBuf::from(text))),
+            InputKind::NewDir => Some(FileOperation::CreateDir(PathBuf::from(
+                text.trim_end_matches('/'),
+            ))),
+            InputKind::Rename => Some(FileOperation::Rename {
+                from: source.clone(),
+                to: PathBuf::from(text),
+            }),
+            InputKind::Move => Some(FileOperation::Move {
+                from: source.clone(),
+                to: PathBuf::from(text).join(source.file_name()?),
+            }),
+        }
+        .filter(|operation| match operation {
+            FileOperation::Rename { from, to } | FileOperation::Move { from, to } => from != to,
+            _ => true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("docs/drafts")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(
+            root.join("README.md"),
+            "```\n# not a heading\n```\n# はじめに\n",
+        )
+        .unwrap();
+        fs::write(root.join("docs/guide.md"), "## Guide\n").unwrap();
+        fs::write(root.join("docs/drafts/old.txt"), "x").unwrap();
+        dir
+    }
+
+    fn names(explorer: &Explorer) -> Vec<String> {
+        explorer
+            .entries()
+            .iter()
+            .map(|entry| entry.path.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_explorer_tree_and_hints() {
+        let dir = project();
+        let mut explorer = Explorer::new(dir.path().to_path_buf());
+        assert_eq!(names(&explorer), ["docs", ".gitignore", "README.md"]);
+        assert_eq!(explorer.entries()[2].hint.as_deref(), Some("はじめに"));
+
+        assert!(explorer.toggle_selected());
+        assert_eq!(
+            names(&explorer),
+            [
+                "docs",
+                "docs/drafts",
+                "docs/guide.md",
+                ".gitignore",
+                "README.md"
+            ]
+        );
+        assert_eq!(explorer.entries()[2].depth, 1);
+        assert_eq!(explorer.entries()[2].hint.as_deref(), Some("Guide"));
+
+        explorer.move_selection(2);
+        assert_eq!(explorer.target_dir(), PathBuf::from("docs"));
+        explorer.collapse_or_parent();
+        assert_eq!(
+            explorer.selected_entry().unwrap().path,
+            PathBuf::from("docs")
+        );
+        explorer.collapse_or_parent();
+        assert_eq!(explorer.entries().len(), 3);
+
+        explorer.reveal(Path::new("docs/drafts/old.txt"));
+        assert_eq!(
+            explorer.selected_entry().unwrap().path,
+            PathBuf::from("docs/drafts/old.txt")
+        );
+    }
+
+    #[test]
+    fn test_explorer_prompts_and_operations() {
+        let dir = project();
+        let root = dir.path();
+        let mut explorer = Explorer::new(root.to_path_buf());
+        explorer.reveal(Path::new("docs/guide.md"));
+
+        let prompt = ExplorerPrompt::input(InputKind::NewFile, &explorer).unwrap();
+        assert_eq!(prompt.message(), "新しいファイル: docs/");
+        assert_eq!(prompt.operation(), None);
+
+        let rename = ExplorerPrompt::Input {
+            kind: InputKind::Rename,
+            source: PathBuf::from("docs/guide.md"),
+            text: "docs/manual.md".to_string(),
+        };
+        let operation = rename.operation().unwrap();
+        assert!(operation.confirmation().contains("docs/manual.md"));
+        operation.apply(root).unwrap();
+        assert!(root.join("docs/manual.md").exists());
+
+        let move_to_root = ExplorerPrompt::Input {
+            kind: InputKind::Move,
+            source: PathBuf::from("docs/manual.md"),
+            text: String::new(),
+        };
+        let operation = move_to_root.operation().unwrap();
+        assert_eq!(operation.result_path(), Some(Path::new("manual.md")));
+        operation.apply(root).unwrap();
+
+        let create = FileOperation::CreateFile(PathBuf::from("README.md"));
+        assert!(create.apply(root).is_err());
+        FileOperation::CreateFile(PathBuf::from("notes/new.md"))
+            .apply(root)
+            .unwrap();
+        assert!(root.join("notes/new.md").is_file());
+
+        explorer.refresh();
+        explorer.reveal(Path::new("docs"));
+        let delete = ExplorerPrompt::delete(&explorer).unwrap();
+        assert!(delete.message().contains("とその中身"));
+        if let ExplorerPrompt::Confirm(operation) = delete {
+            operation.apply(root).unwrap();
+        }
+        assert!(!root.join("docs").exists());
+    }
+}
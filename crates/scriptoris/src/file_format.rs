@@ -0,0 +1,492 @@
+//! ファイルの文字コードと改行コードの判定と変換。
+//!
+//! 開いたファイルのバイト列から BOM・UTF-8・UTF-16・ISO-2022-JP・
+//! Shift_JIS・EUC-JP・Latin-1 を判定してテキストに変換し、改行コード
+//! （LF・CRLF・CR）は最も多いものを覚えたうえで内部では LF に揃えます。
+//! 保存するときは同じ文字コード・改行コード・BOM に戻して書き込みます。
+//! `:set fileencoding=` / `:set fileformat=` で保存時の形式を変え、
+//! `:e ++enc=` で文字コードを指定して開き直せます。
+
+use anyhow::Result;
+use std::borrow::Cow;
+use std::io::Read;
+use std::path::Path;
+
+/// Byte order marks, written back when the file had one.
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Bytes looked at when guessing UTF-16 without a BOM.
+const UTF16_SAMPLE: usize = 4096;
+
+/// Character encoding of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    ShiftJis,
+    EucJp,
+    Iso2022Jp,
+    Latin1,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 7] = [
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::ShiftJis,
+        Encoding::EucJp,
+        Encoding::Iso2022Jp,
+        Encoding::Latin1,
+    ];
+
+    /// The name shown in the status line and by `:set fenc?`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::ShiftJis => "sjis",
+            Encoding::EucJp => "euc-jp",
+            Encoding::Iso2022Jp => "iso-2022-jp",
+            Encoding::Latin1 => "latin1",
+        }
+    }
+
+    /// Parse a name as accepted by `:set fenc=` and `++enc=`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            // Like Vim, plain `utf-16` is big endian
+            "utf-16" | "utf16" | "utf-16be" | "utf16be" => Some(Encoding::Utf16Be),
+            "sjis" | "shift-jis" | "cp932" | "windows-31j" => Some(Encoding::ShiftJis),
+            "euc-jp" | "eucjp" => Some(Encoding::EucJp),
+            "iso-2022-jp" | "jis" => Some(Encoding::Iso2022Jp),
+            "latin1" | "latin-1" | "iso-8859-1" | "cp1252" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    fn codec(self) -> &'static encoding_rs::Encoding {
+        match self {
+            Encoding::Utf8 => encoding_rs::UTF_8,
+            Encoding::Utf16Le => encoding_rs::UTF_16LE,
+            Encoding::Utf16Be => encoding_rs::UTF_16BE,
+            Encoding::ShiftJis => encoding_rs::SHIFT_JIS,
+            Encoding::EucJp => encoding_rs::EUC_JP,
+            Encoding::Iso2022Jp => encoding_rs::ISO_2022_JP,
+            // What files labelled Latin-1 usually are in practice
+            Encoding::Latin1 => encoding_rs::WINDOWS_1252,
+        }
+    }
+
+    fn bom(self) -> Option<&'static [u8]> {
+        match self {
+            Encoding::Utf8 => Some(UTF8_BOM),
+            Encoding::Utf16Le => Some(UTF16LE_BOM),
+            Encoding::Utf16Be => Some(UTF16BE_BOM),
+            _ => None,
+        }
+    }
+
+    /// Decode `bytes`, or `None` if they are not valid in this encoding.
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        self.codec()
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(|text| text.into_owned())
+    }
+
+    /// Decode `bytes`, replacing invalid sequences with U+FFFD.
+    pub fn decode_lossy(self, bytes: &[u8]) -> String {
+        self.codec()
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned()
+    }
+
+    /// Encode `text`, failing on the first character this encoding cannot
+    /// represent rather than writing a substitute.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => return Ok(text.as_bytes().to_vec()),
+            // encoding_rs only decodes UTF-16
+            Encoding::Utf16Le => {
+                return Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect())
+            }
+            Encoding::Utf16Be => {
+                return Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect())
+            }
+            _ => {}
+        }
+        let (bytes, _, unmappable) = self.codec().encode(text);
+        if !unmappable {
+            return Ok(bytes.into_owned());
+        }
+        let mut buf = [0; 4];
+        let (line, c) = text
+            .lines()
+            .enumerate()
+            .find_map(|(line, content)| {
+                content
+                    .chars()
+                    .find(|c| self.codec().encode(c.encode_utf8(&mut buf)).2)
+                    .map(|c| (line, c))
+            })
+            .unwrap_or((0, '?'));
+        Err(anyhow::anyhow!(
+            "E513: {} に変換できない文字があります: '{}' ({} 行目、:set fenc=utf-8 で UTF-8 として保存)",
+            self.name(),
+            c,
+            line + 1
+        ))
+    }
+}
+
+/// Line ending of a file, named as `:set fileformat` takes it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Unix,
+    Dos,
+    Mac,
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 3] = [LineEnding::Unix, LineEnding::Dos, LineEnding::Mac];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Unix => "unix",
+            LineEnding::Dos => "dos",
+            LineEnding::Mac => "mac",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ending| ending.name() == name)
+    }
+
+    /// `LF`, `CRLF` or `CR`, as the status line shows it.
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Unix => "LF",
+            LineEnding::Dos => "CRLF",
+            LineEnding::Mac => "CR",
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Unix => "\n",
+            LineEnding::Dos => "\r\n",
+            LineEnding::Mac => "\r",
+        }
+    }
+}
+
+/// How a buffer's text is stored on disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileFormat {
+    pub encoding: Encoding,
+    /// Whether the file starts with a byte order mark. Only UTF-8 and
+    /// UTF-16 have one.
+    pub bom: bool,
+    pub line_ending: LineEnding,
+    /// Whether the file mixed several line endings when it was read. The
+    /// next save writes `line_ending` everywhere.
+    pub mixed_endings: bool,
+}
+
+impl FileFormat {
+    /// Guess the format of `bytes`: a BOM decides, then UTF-16 by the
+    /// position of NUL bytes, ISO-2022-JP by its escape sequences, valid
+    /// UTF-8, Shift_JIS or EUC-JP by which reads as more Japanese, and
+    /// Latin-1 when nothing else fits.
+    pub fn detect(bytes: &[u8]) -> Self {
+        for encoding in [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+            if encoding.bom().is_some_and(|bom| bytes.starts_with(bom)) {
+                return Self {
+                    encoding,
+                    bom: true,
+                    ..Self::default()
+                };
+            }
+        }
+        let encoding = if let Some(encoding) = detect_utf16(bytes) {
+            encoding
+        } else if bytes.is_ascii() && has_iso2022jp_escape(bytes) {
+            Encoding::Iso2022Jp
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Encoding::Utf8
+        } else {
+            // `max_by_key` keeps the last of equal scores, so Shift_JIS wins a tie
+            [Encoding::EucJp, Encoding::ShiftJis]
+                .into_iter()
+                .filter_map(|encoding| Some((encoding, japanese_score(&encoding.decode(bytes)?))))
+                .max_by_key(|(_, score)| *score)
+                .map_or(Encoding::Latin1, |(encoding, _)| encoding)
+        };
+        Self {
+            encoding,
+            ..Self::default()
+        }
+    }
+
+    /// Decode file contents with their line endings turned into `\n`,
+    /// detecting the format unless `encoding` is given. A forced encoding
+    /// replaces invalid sequences instead of failing.
+    pub fn decode(bytes: &[u8], encoding: Option<Encoding>) -> (String, Self) {
+        let mut format = match encoding {
+            Some(encoding) => Self {
+                encoding,
+                bom: encoding.bom().is_some_and(|bom| bytes.starts_with(bom)),
+                ..Self::default()
+            },
+            None => Self::detect(bytes),
+        };
+        let body = match format.encoding.bom() {
+            Some(bom) if format.bom => &bytes[bom.len()..],
+            _ => bytes,
+        };
+        let text = format
+            .encoding
+            .decode(body)
+            .unwrap_or_else(|| format.encoding.decode_lossy(body));
+        let (text, line_ending, mixed_endings) = normalize_line_endings(text);
+        format.line_ending = line_ending;
+        format.mixed_endings = mixed_endings;
+        (text, format)
+    }
+
+    /// The bytes to write for `text`, with its `\n` turned into the file's
+    /// line ending and the BOM if the file had one.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let mut bytes = match self.encoding.bom() {
+            Some(bom) if self.bom => bom.to_vec(),
+            _ => Vec::new(),
+        };
+        let text = match self.line_ending {
+            LineEnding::Unix => Cow::Borrowed(text),
+            ending => Cow::Owned(text.replace('\n', ending.as_str())),
+        };
+        bytes.extend(self.encoding.encode(&text)?);
+        Ok(bytes)
+    }
+
+    /// The encoding as the status line shows it, marking a BOM.
+    pub fn encoding_label(&self) -> String {
+        if self.bom && self.encoding.bom().is_some() {
+            format!("{}[BOM]", self.encoding.name())
+        } else {
+            self.encoding.name().to_string()
+        }
+    }
+
+    /// The warning shown when the file mixed line endings.
+    pub fn mixed_endings_warning(&self) -> Option<String> {
+        self.mixed_endings.then(|| {
+            format!(
+                "W: 改行コードが混在しています。保存すると {} ({}) に統一されます",
+                self.line_ending.label(),
+                self.line_ending.name()
+            )
+        })
+    }
+}
+
+/// Turn CRLF and CR into LF. Returns the most common line ending, LF on a
+/// tie, and whether more than one kind was found.
+fn normalize_line_endings(text: String) -> (String, LineEnding, bool) {
+    let (mut lf, mut crlf, mut cr) = (0usize, 0usize, 0usize);
+    let mut bytes = text.bytes().peekable();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\r' if bytes.peek() == Some(&b'\n') => {
+                bytes.next();
+                crlf += 1;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+    }
+    let ending = if crlf > lf && crlf >= cr {
+        LineEnding::Dos
+    } else if cr > lf && cr > crlf {
+        LineEnding::Mac
+    } else {
+        LineEnding::Unix
+    };
+    let mixed = [lf, crlf, cr].iter().filter(|count| **count > 0).count() > 1;
+    if crlf + cr == 0 {
+        return (text, ending, mixed);
+    }
+    (
+        text.replace("\r\n", "\n").replace('\r', "\n"),
+        ending,
+        mixed,
+    )
+}
+
+/// Read and decode the file at `path`.
+pub fn read_file(path: &Path, encoding: Option<Encoding>) -> std::io::Result<(String, FileFormat)> {
+    let bytes = std::fs::read(path)?;
+    Ok(FileFormat::decode(&bytes, encoding))
+}
+
+/// Read and decode everything on standard input (`scriptoris -`).
+pub fn read_stdin() -> std::io::Result<(String, FileFormat)> {
+    let mut bytes = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut bytes)?;
+    Ok(FileFormat::decode(&bytes, None))
+}
+
+/// UTF-16 without a BOM: mostly ASCII text has a NUL in every other byte.
+fn detect_utf16(bytes: &[u8]) -> Option<Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SAMPLE)];
+    if sample.len() < 2 || sample.len() % 2 != 0 {
+        return None;
+    }
+    let zeros = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros(0), zeros(1));
+    let half = sample.len() / 2;
+    if odd * 2 >= half && even == 0 {
+        Some(Encoding::Utf16Le)
+    } else if even * 2 >= half && odd == 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Whether 7-bit text switches to JIS X 0208 or 0201 like ISO-2022-JP does.
+fn has_iso2022jp_escape(bytes: &[u8]) -> bool {
+    bytes.windows(3).any(|window| {
+        matches!(
+            window,
+            [0x1B, b'$', b'@' | b'B'] | [0x1B, b'(', b'J' | b'I']
+        )
+    })
+}
+
+/// How much `text` looks like Japanese: kana and kanji count for it, and
+/// half-width katakana, which EUC-JP bytes turn into when read as
+/// Shift_JIS, counts against it.
+fn japanese_score(text: &str) -> i64 {
+    text.chars()
+        .map(|c| match c {
+            '\u{3040}'..='\u{30FF}' | '\u{4E00}'..='\u{9FFF}' | '\u{3000}'..='\u{303F}' => 2,
+            '\u{FF61}'..='\u{FF9F}' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "# 見出し\n日本語のテキストです。\n";
+
+    #[test]
+    fn test_detect_and_round_trip_each_encoding() {
+        for encoding in Encoding::ALL {
+            let text = if encoding == Encoding::Latin1 {
+                "caf\u{e9} na\u{ef}ve\n"
+            } else {
+                TEXT
+            };
+            // UTF-16 without a BOM is only recognized in mostly ASCII text
+            let format = FileFormat {
+                encoding,
+                bom: matches!(encoding, Encoding::Utf16Le | Encoding::Utf16Be),
+                ..FileFormat::default()
+            };
+            let bytes = format.encode(text).unwrap();
+            assert_eq!(
+                FileFormat::decode(&bytes, None),
+                (text.to_string(), format),
+                "{}",
+                encoding.name()
+            );
+            assert_eq!(Encoding::from_name(encoding.name()), Some(encoding));
+        }
+    }
+
+    #[test]
+    fn test_bom_and_forced_encoding() {
+        let bytes = [UTF8_BOM, TEXT.as_bytes()].concat();
+        let (text, format) = FileFormat::decode(&bytes, None);
+        assert_eq!(text, TEXT);
+        assert!(format.bom);
+        assert_eq!(format.encode(&text).unwrap(), bytes);
+
+        let utf16 = FileFormat {
+            encoding: Encoding::Utf16Be,
+            bom: true,
+            ..FileFormat::default()
+        };
+        let bytes = utf16.encode(TEXT).unwrap();
+        assert!(bytes.starts_with(UTF16BE_BOM));
+        assert_eq!(FileFormat::decode(&bytes, None), (TEXT.to_string(), utf16));
+
+        let bytes = Encoding::Utf16Le.encode("plain text\n").unwrap();
+        assert_eq!(
+            FileFormat::detect(&bytes),
+            FileFormat {
+                encoding: Encoding::Utf16Le,
+                ..FileFormat::default()
+            }
+        );
+
+        // Reading UTF-8 as Shift_JIS garbles rather than fails
+        let (text, format) = FileFormat::decode(TEXT.as_bytes(), Some(Encoding::ShiftJis));
+        assert_eq!(format.encoding, Encoding::ShiftJis);
+        assert_ne!(text, TEXT);
+    }
+
+    #[test]
+    fn test_line_endings_are_normalized_and_restored() {
+        let bytes = "# 見出し\r\n本文\r\n".as_bytes();
+        let (text, format) = FileFormat::decode(bytes, None);
+        assert_eq!(text, "# 見出し\n本文\n");
+        assert_eq!(format.line_ending, LineEnding::Dos);
+        assert!(!format.mixed_endings);
+        assert_eq!(format.encode(&text).unwrap(), bytes);
+
+        let (text, format) = FileFormat::decode(b"a\rb\rc\n", None);
+        assert_eq!(text, "a\nb\nc\n");
+        assert_eq!(format.line_ending, LineEnding::Mac);
+        assert!(format.mixed_endings_warning().unwrap().contains("CR (mac)"));
+        assert_eq!(format.encode(&text).unwrap(), b"a\rb\rc\r");
+
+        // A UTF-8 BOM survives alongside the line ending
+        let bytes = [UTF8_BOM, b"x\r\n"].concat();
+        let (text, format) = FileFormat::decode(&bytes, None);
+        assert_eq!(format.encoding_label(), "utf-8[BOM]");
+        assert_eq!(format.encode(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_unmappable_characters_are_reported() {
+        let error = Encoding::ShiftJis
+            .encode("一行目\n絵文字 😀\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("E513"), "{}", error);
+        assert!(error.contains("2 行目"), "{}", error);
+        assert!(Encoding::EucJp.encode(TEXT).is_ok());
+    }
+}
@@ -1,13 +1,200 @@
 use anyhow::Result;
-use std::io::ErrorKind;
-use std::path::PathBuf;
+use ropey::Rope;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tokio::task::JoinHandle;
 
+use crate::config::BackupStrategy;
 use crate::editor::Editor;
+use crate::file_format::{Encoding, FileFormat};
+use crate::file_watch::{DiskChange, DiskState};
+use crate::swap::{SwapFile, SwapStore};
 
 pub struct FileManager {
     pub current_path: Option<PathBuf>,
     pub is_readonly: bool,
+    pub backup: BackupStrategy,
+    /// Overrides the default directory of `BackupStrategy::Directory`.
+    pub backup_dir: Option<PathBuf>,
+    /// Where swap files are kept; `None` turns them off.
+    pub swaps: Option<SwapStore>,
+    /// Swap file found by the last `open_file`, waiting to be asked about.
+    pub found_swap: Option<SwapFile>,
+    /// The file as it was on disk when last opened or saved.
+    pub disk: Option<DiskState>,
+    /// Encoding of the file read by the last `open_file`.
+    pub format: FileFormat,
+    /// Size in bytes from which files open in large-file mode; 0 turns the
+    /// mode off.
+    pub large_file_threshold: u64,
+    /// Whether the file read by the last `open_file` reached the threshold.
+    pub large_file: bool,
+    /// The rest of the large file opened last, still being read. The app
+    /// hands it to the buffer showing the file.
+    pub pending_load: Option<PendingLoad>,
+}
+
+/// Bytes of a large file read before `open_file` returns; the rest is read
+/// in the background.
+pub const LAZY_HEAD_BYTES: usize = 1024 * 1024;
+
+/// A large file whose first lines are shown while the whole of it is read
+/// and decoded on a blocking thread.
+pub struct PendingLoad {
+    task: JoinHandle<Result<LoadedFile>>,
+}
+
+/// A large file read in the background.
+pub struct LoadedFile {
+    pub text: LoadedText,
+    pub format: FileFormat,
+    pub disk: Option<DiskState>,
+    pub found_swap: Option<SwapFile>,
+}
+
+/// What to put in the buffer once a large file is read.
+pub enum LoadedText {
+    /// The text after the lines already shown.
+    Rest(Rope),
+    /// The whole text, when the file decoded differently from its start,
+    /// as when the encoding guessed from the first lines was wrong.
+    Whole(String),
+}
+
+impl PendingLoad {
+    /// Read and decode `path` as `open_file_as` would, given the `head`
+    /// already shown.
+    fn spawn(
+        path: PathBuf,
+        encoding: Option<Encoding>,
+        head: String,
+        swaps: Option<SwapStore>,
+    ) -> Self {
+        let task = tokio::task::spawn_blocking(move || {
+            let bytes = std::fs::read(&path).map_err(|e| {
+                anyhow::anyhow!("ファイル読み込みエラー: {} - {}", path.display(), e)
+            })?;
+            let (content, format) = FileFormat::decode(&bytes, encoding);
+            if content.contains('\0') {
+                return Err(anyhow::anyhow!(
+                    "ファイルがバイナリ形式の可能性があります: {}",
+                    path.display()
+                ));
+            }
+            let disk = DiskState::with_contents(&path, &bytes);
+            drop(bytes);
+            let found_swap = swaps.and_then(|swaps| swaps.check(&path, &content));
+            let text = match content.strip_prefix(head.as_str()) {
+                Some(rest) => LoadedText::Rest(Rope::from_str(rest)),
+                None => LoadedText::Whole(content),
+            };
+            Ok(LoadedFile {
+                text,
+                format,
+                disk,
+                found_swap,
+            })
+        });
+        Self { task }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Wait for the file to be read.
+    pub async fn finish(self) -> Result<LoadedFile> {
+        self.task.await?
+    }
+}
+
+/// Follow symlinks so that saving replaces the file a link points to rather
+/// than the link itself.
+fn resolve_target(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Create a hidden temporary file next to `target`.
+fn create_temp(target: &Path) -> std::io::Result<(PathBuf, File)> {
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut attempt = 0;
+    loop {
+        let path = dir.join(format!(".{}.{}.{}.tmp", name, std::process::id(), attempt));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Write `bytes` to `path` so that it never holds a half-written document:
+/// the data goes to a temporary file in the same directory, is flushed to
+/// disk and then renamed over `path`. An existing file keeps its permissions
+/// and, where allowed, its owner.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let target = resolve_target(path);
+    let (temp_path, mut file) = create_temp(&target)?;
+    let result = (|| {
+        file.write_all(bytes)?;
+        if let Ok(metadata) = std::fs::metadata(&target) {
+            file.set_permissions(metadata.permissions())?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::{fchown, MetadataExt};
+                // Only root may hand a file to another user; keep at least
+                // the group when that fails
+                if fchown(&file, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+                    let _ = fchown(&file, None, Some(metadata.gid()));
+                }
+            }
+        }
+        file.sync_all()?;
+        std::fs::rename(&temp_path, &target)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result?;
+
+    // Flush the directory entry so the rename itself survives a crash
+    #[cfg(unix)]
+    if let Some(dir) = target.parent().and_then(|dir| File::open(dir).ok()) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Refuse to write a large file before all of it is read, which would cut
+/// it short.
+fn check_loaded(editor: &Editor, path: &Path) -> Result<()> {
+    if editor.is_loading() {
+        return Err(anyhow::anyhow!(
+            "ファイルの読み込みが終わっていないため書き込めません: {}",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Default directory of `BackupStrategy::Directory`.
+fn default_backup_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("SCRIPTORIS_DATA_DIR") {
+        return Some(PathBuf::from(dir).join("backup"));
+    }
+    directories::ProjectDirs::from("com", "scriptoris", "scriptoris")
+        .map(|dirs| dirs.data_dir().join("backup"))
 }
 
 impl FileManager {
@@ -15,9 +202,83 @@ impl FileManager {
         Self {
             current_path: None,
             is_readonly: false,
+            backup: BackupStrategy::default(),
+            backup_dir: None,
+            swaps: None,
+            found_swap: None,
+            disk: None,
+            format: FileFormat::default(),
+            large_file_threshold: 0,
+            large_file: false,
+            pending_load: None,
         }
     }
 
+    /// A manager for saving another buffer's `path` with the same backup
+    /// settings.
+    pub fn for_path(&self, path: PathBuf, readonly: bool, disk: Option<DiskState>) -> Self {
+        Self {
+            current_path: Some(path),
+            is_readonly: readonly,
+            backup: self.backup,
+            backup_dir: self.backup_dir.clone(),
+            swaps: None,
+            found_swap: None,
+            disk,
+            format: FileFormat::default(),
+            large_file_threshold: self.large_file_threshold,
+            large_file: false,
+            pending_load: None,
+        }
+    }
+
+    /// Where the current contents of `target` are copied before a save, or
+    /// `None` without a backup strategy.
+    pub fn backup_path(&self, target: &Path) -> Option<PathBuf> {
+        let name = target.file_name()?.to_string_lossy().into_owned();
+        match self.backup {
+            BackupStrategy::None => None,
+            BackupStrategy::NextToFile => Some(target.with_file_name(format!("{}~", name))),
+            BackupStrategy::Directory => {
+                let dir = self.backup_dir.clone().or_else(default_backup_dir)?;
+                // The full path keeps backups of files with the same name apart
+                let flattened = target.to_string_lossy().replace(['/', '\\', ':'], "%");
+                let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+                let base = format!("{}.{}", flattened, stamp);
+                // Saves within the same millisecond get a counter
+                std::iter::once(dir.join(&base))
+                    .chain((1..).map(|n| dir.join(format!("{}-{}", base, n))))
+                    .find(|path| !path.exists())
+            }
+        }
+    }
+
+    /// Back up the file at `path` if it has content, then replace it with
+    /// `bytes` atomically. A failed backup is logged but does not stop the
+    /// save.
+    async fn write_file(&self, path: &Path, bytes: Arc<[u8]>) -> std::io::Result<()> {
+        let target = resolve_target(path);
+        let backup = match fs::metadata(&target).await {
+            Ok(metadata) if metadata.len() > 0 => self.backup_path(&target),
+            _ => None,
+        };
+        tokio::task::spawn_blocking(move || {
+            if let Some(backup) = backup {
+                let copied = backup
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .and_then(|_| std::fs::copy(&target, &backup));
+                match copied {
+                    Ok(_) => log::info!("Created backup: {}", backup.display()),
+                    Err(e) => log::warn!("Failed to create backup: {}", e),
+                }
+            }
+            write_atomic(&target, &bytes)
+        })
+        .await
+        .map_err(std::io::Error::other)?
+    }
+
     pub fn get_current_path(&self) -> Option<&PathBuf> {
         self.current_path.as_ref()
     }
@@ -31,6 +292,16 @@ impl FileManager {
     }
 
     pub async fn open_file(&mut self, path: PathBuf) -> Result<String> {
+        self.open_file_as(path, None).await
+    }
+
+    /// Open `path` reading it as `encoding`, or detecting the encoding when
+    /// `None` (`:e ++enc=`).
+    pub async fn open_file_as(
+        &mut self,
+        path: PathBuf,
+        encoding: Option<Encoding>,
+    ) -> Result<String> {
         // Validate file path
         if !path.exists() {
             return Err(anyhow::anyhow!(
@@ -46,31 +317,38 @@ impl FileManager {
             ));
         }
 
-        // Check file size (prevent loading extremely large files)
+        // Check file size for large-file mode
         match fs::metadata(&path).await {
             Ok(metadata) => {
                 self.is_readonly = metadata.permissions().readonly();
-
-                // Warn about large files (>10MB)
-                const LARGE_FILE_THRESHOLD: u64 = 10 * 1024 * 1024; // 10MB
-                if metadata.len() > LARGE_FILE_THRESHOLD {
-                    log::warn!(
-                        "Large file detected ({} bytes): {}",
-                        metadata.len(),
-                        path.display()
+                self.large_file =
+                    self.large_file_threshold > 0 && metadata.len() >= self.large_file_threshold;
+                if self.large_file {
+                    log::info!(
+                        "Opening {} in large-file mode ({} bytes)",
+                        path.display(),
+                        metadata.len()
                     );
                 }
             }
             Err(e) => {
                 log::warn!("Failed to get file metadata: {}", e);
                 self.is_readonly = false;
+                self.large_file = false;
+            }
+        }
+
+        self.pending_load = None;
+        if self.large_file {
+            if let Some(head) = self.open_head(&path, encoding).await? {
+                return Ok(head);
             }
         }
 
         // Read file with proper error handling
-        match fs::read_to_string(&path).await {
-            Ok(content) => {
-                // Check if content is valid UTF-8
+        match fs::read(&path).await {
+            Ok(bytes) => {
+                let (content, format) = FileFormat::decode(&bytes, encoding);
                 if content.contains('\0') {
                     return Err(anyhow::anyhow!(
                         "ファイルがバイナリ形式の可能性があります: {}",
@@ -78,7 +356,14 @@ impl FileManager {
                     ));
                 }
 
+                self.format = format;
                 self.current_path = Some(path.clone());
+                // Hash the bytes already read rather than reading them again
+                self.disk = DiskState::with_contents(&path, &bytes);
+                self.found_swap = self
+                    .swaps
+                    .as_ref()
+                    .and_then(|swaps| swaps.check(&path, &content));
                 log::info!("Successfully opened file: {}", path.display());
                 Ok(content)
             }
@@ -90,12 +375,6 @@ impl FileManager {
                     ErrorKind::NotFound => {
                         format!("ファイルが見つかりません: {}", path.display())
                     }
-                    ErrorKind::InvalidData => {
-                        format!(
-                            "ファイルのエンコーディングが無効です (UTF-8ではありません): {}",
-                            path.display()
-                        )
-                    }
                     _ => {
                         format!("ファイル読み込みエラー: {} - {}", path.display(), e)
                     }
@@ -105,8 +384,67 @@ impl FileManager {
         }
     }
 
-    pub async fn save_file(&self, editor: &mut Editor) -> Result<String> {
-        if let Some(ref path) = self.current_path {
+    /// Decode the lines in the first `LAZY_HEAD_BYTES` of a large file and
+    /// read the whole of it in the background, or `None` when the start is
+    /// not a whole line or the file is small enough to read at once.
+    async fn open_head(
+        &mut self,
+        path: &Path,
+        encoding: Option<Encoding>,
+    ) -> Result<Option<String>> {
+        let mut bytes = Vec::with_capacity(LAZY_HEAD_BYTES + 1);
+        // Errors are left for the whole read to report
+        let Ok(file) = fs::File::open(path).await else {
+            return Ok(None);
+        };
+        let read = file
+            .take(LAZY_HEAD_BYTES as u64 + 1)
+            .read_to_end(&mut bytes)
+            .await;
+        if read.is_err() || bytes.len() <= LAZY_HEAD_BYTES {
+            return Ok(None);
+        }
+        let Some(end) = bytes.iter().rposition(|&byte| byte == b'\n') else {
+            return Ok(None);
+        };
+        let (head, format) = FileFormat::decode(&bytes[..=end], encoding);
+        if head.contains('\0') {
+            return Err(anyhow::anyhow!(
+                "ファイルがバイナリ形式の可能性があります: {}",
+                path.display()
+            ));
+        }
+
+        self.format = format;
+        self.current_path = Some(path.to_path_buf());
+        // Known once the whole file is read
+        self.disk = None;
+        self.found_swap = None;
+        self.pending_load = Some(PendingLoad::spawn(
+            path.to_path_buf(),
+            encoding,
+            head.clone(),
+            self.swaps.clone(),
+        ));
+        log::info!("Reading the rest of {} in the background", path.display());
+        Ok(Some(head))
+    }
+
+    /// Save to the current path, refusing when the file was changed on disk
+    /// since it was opened or last saved.
+    pub async fn save_file(&mut self, editor: &mut Editor) -> Result<String> {
+        self.save(editor, false).await
+    }
+
+    /// Save to the current path even if the file changed on disk (`:w!`).
+    pub async fn overwrite_file(&mut self, editor: &mut Editor) -> Result<String> {
+        self.save(editor, true).await
+    }
+
+    async fn save(&mut self, editor: &mut Editor, force: bool) -> Result<String> {
+        if let Some(path) = self.current_path.clone() {
+            let path = &path;
+            check_loaded(editor, path)?;
             // Check if file is readonly
             if self.is_readonly {
                 return Err(anyhow::anyhow!(
@@ -115,38 +453,28 @@ impl FileManager {
                 ));
             }
 
-            let content = editor.get_content();
-
-            // Check content size (prevent writing extremely large files)
-            const LARGE_CONTENT_THRESHOLD: usize = 50 * 1024 * 1024; // 50MB
-            if content.len() > LARGE_CONTENT_THRESHOLD {
+            let changed_on_disk = self
+                .disk
+                .as_ref()
+                .is_some_and(|disk| matches!(disk.check(path), DiskChange::Modified(_)));
+            if changed_on_disk && !force {
                 return Err(anyhow::anyhow!(
-                    "コンテンツが大きすぎます ({} バイト)。大きなファイルの保存は制限されています。",
-                    content.len()
+                    "E949: 読み込んだ後にファイルが変更されています: {} (:w! で上書き、:DiffOrig で差分を表示)",
+                    path.display()
                 ));
             }
 
-            // Create backup if file exists and is not empty
-            if path.exists() {
-                if let Ok(metadata) = fs::metadata(path).await {
-                    if metadata.len() > 0 {
-                        let backup_path = path.with_extension("bak");
-                        if let Err(e) = fs::copy(path, &backup_path).await {
-                            log::warn!("Failed to create backup: {}", e);
-                        } else {
-                            log::info!("Created backup: {}", backup_path.display());
-                        }
-                    }
-                }
-            }
+            let content = editor.get_content();
+            let bytes: Arc<[u8]> = editor.file_format().encode(&content)?.into();
 
             // Attempt to save with retry logic
             const MAX_ATTEMPTS: u32 = 3;
 
             for attempt in 1..=MAX_ATTEMPTS {
-                match fs::write(path, content.as_bytes()).await {
+                match self.write_file(path, bytes.clone()).await {
                     Ok(_) => {
                         editor.mark_saved();
+                        self.disk = DiskState::with_contents(path, &bytes);
                         log::info!("Successfully saved file: {}", path.display());
                         return Ok(format!("{} 行を書き込みました", editor.line_count()));
                     }
@@ -203,6 +531,7 @@ impl FileManager {
     }
 
     pub async fn save_file_as(&mut self, path: PathBuf, editor: &mut Editor) -> Result<String> {
+        check_loaded(editor, &path)?;
         // Validate the target path
         if let Some(parent) = path.parent() {
             if !parent.exists() {
@@ -240,19 +569,13 @@ impl FileManager {
 
         let content = editor.get_content();
 
-        // Check content size
-        const LARGE_CONTENT_THRESHOLD: usize = 50 * 1024 * 1024; // 50MB
-        if content.len() > LARGE_CONTENT_THRESHOLD {
-            return Err(anyhow::anyhow!(
-                "コンテンツが大きすぎます ({} バイト)。大きなファイルの保存は制限されています。",
-                content.len()
-            ));
-        }
+        let bytes: Arc<[u8]> = editor.file_format().encode(&content)?.into();
 
         // Save the file
-        match fs::write(&path, content.as_bytes()).await {
+        match self.write_file(&path, bytes.clone()).await {
             Ok(_) => {
                 self.current_path = Some(path.clone());
+                self.disk = DiskState::with_contents(&path, &bytes);
                 self.is_readonly = false;
                 editor.mark_saved();
                 log::info!("Successfully saved file as: {}", path.display());
@@ -292,7 +615,6 @@ impl Default for FileManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
     use tempfile::NamedTempFile;
 
     #[tokio::test]
@@ -324,6 +646,41 @@ mod tests {
         assert!(!editor.is_modified());
     }
 
+    #[tokio::test]
+    async fn test_large_files_open_in_large_file_mode() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "{}", "log line\n".repeat(200)).unwrap();
+
+        let mut fm = FileManager::new();
+        fm.open_file(temp_file.path().to_path_buf()).await.unwrap();
+        assert!(!fm.large_file);
+
+        fm.large_file_threshold = 1024;
+        fm.open_file(temp_file.path().to_path_buf()).await.unwrap();
+        assert!(fm.large_file);
+        assert!(fm.pending_load.is_none());
+
+        // Past `LAZY_HEAD_BYTES` only the first lines are read at once
+        let whole = "log line\n".repeat(LAZY_HEAD_BYTES / 4);
+        write!(temp_file, "{}", whole).unwrap();
+        let whole = format!("{}{}", "log line\n".repeat(200), whole);
+        let head = fm.open_file(temp_file.path().to_path_buf()).await.unwrap();
+        assert!(head.len() <= LAZY_HEAD_BYTES && head.ends_with('\n'));
+        assert!(fm.disk.is_none());
+
+        let mut editor = Editor::new();
+        editor.load_file(head.clone(), fm.format, true);
+        editor.set_loading(true);
+        assert!(fm.save_file(&mut editor).await.is_err());
+
+        let loaded = fm.pending_load.take().unwrap().finish().await.unwrap();
+        assert!(loaded.disk.is_some());
+        match loaded.text {
+            LoadedText::Rest(rest) => assert_eq!(head + &rest.to_string(), whole),
+            LoadedText::Whole(_) => panic!("the start decoded differently"),
+        }
+    }
+
     #[tokio::test]
     async fn test_save_file_as() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -342,4 +699,84 @@ mod tests {
         assert!(fm.has_file());
         assert!(!editor.is_modified());
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_save_is_atomic_and_follows_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path().join("notes.md");
+        std::fs::write(&target, "old\n").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+        let link = dir.path().join("link.md");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let mut fm = FileManager::new();
+        let mut editor = Editor::new();
+        editor.set_content(fm.open_file(link.clone()).await.unwrap());
+        editor.insert_char('!');
+        fm.save_file(&mut editor).await.unwrap();
+
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "!old\n");
+        let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        // The backup sits next to the real file and no temporary file is left
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.md~")).unwrap(),
+            "old\n"
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_backup_strategies() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let backups = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("draft.md");
+        std::fs::write(&path, "v1").unwrap();
+
+        let mut fm = FileManager::new();
+        fm.current_path = Some(path.clone());
+        fm.backup = BackupStrategy::Directory;
+        fm.backup_dir = Some(backups.path().to_path_buf());
+        let mut editor = Editor::new();
+        editor.set_content("v2".to_string());
+        fm.save_file(&mut editor).await.unwrap();
+
+        let saved: Vec<_> = std::fs::read_dir(backups.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(std::fs::read_to_string(&saved[0]).unwrap(), "v1");
+        let name = saved[0].file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.contains("%draft.md."));
+
+        // Saves in quick succession keep every backup
+        editor.set_content("v3".to_string());
+        fm.save_file(&mut editor).await.unwrap();
+        let mut contents: Vec<_> = std::fs::read_dir(backups.path())
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        contents.sort();
+        assert_eq!(contents, ["v1", "v2"]);
+        let taken = fm.backup_path(&path).unwrap();
+        std::fs::write(&taken, "").unwrap();
+        let next = fm.backup_path(&path).unwrap();
+        assert!(next != taken && !next.exists());
+        std::fs::remove_file(&taken).unwrap();
+
+        fm.backup = BackupStrategy::None;
+        editor.set_content("v4".to_string());
+        fm.save_file(&mut editor).await.unwrap();
+        assert_eq!(std::fs::read_dir(backups.path()).unwrap().count(), 2);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v4");
+    }
 }
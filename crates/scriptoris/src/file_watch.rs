@@ -0,0 +1,265 @@
+//! ディスク上のファイルの変更検知。
+//!
+//! 開いたときと保存したときのファイルの更新時刻・サイズ・内容のハッシュを
+//! バッファごとに覚えておき、git checkout や他のツールによる書き換えを
+//! 検出します。開いているファイルのディレクトリを OS のファイル監視
+//! （Linux では inotify）で見張り、監視が使えないときは一定間隔で
+//! 確認します。ディスクの内容とバッファの差分も表示できます。
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use ratatui::{
+    style::{Color, Style},
+    text::Line,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::gutter::{diff_lines, DiffOp};
+
+/// How often files are checked when the OS watcher is not available.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Unchanged lines shown around each change in the diff view.
+const DIFF_CONTEXT: usize = 3;
+
+/// What a file looked like on disk when it was last read or written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskState {
+    modified: Option<SystemTime>,
+    size: u64,
+    hash: u64,
+}
+
+/// How a file compares with its recorded `DiskState`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiskChange {
+    Unchanged,
+    /// Written again with the same contents.
+    Touched(DiskState),
+    Modified(DiskState),
+    Deleted,
+}
+
+impl DiskState {
+    pub fn read(path: &Path) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+        Self::with_contents(path, &bytes)
+    }
+
+    /// The state of the file at `path` whose contents were just read or
+    /// written as `bytes`, without reading it again.
+    pub fn with_contents(path: &Path, bytes: &[u8]) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        Some(Self {
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+            hash: hasher.finish(),
+        })
+    }
+
+    /// Compare with the file at `path`, reading its contents only when the
+    /// time or size differ.
+    pub fn check(&self, path: &Path) -> DiskChange {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return DiskChange::Deleted;
+        };
+        if metadata.modified().ok() == self.modified && metadata.len() == self.size {
+            return DiskChange::Unchanged;
+        }
+        match Self::read(path) {
+            Some(state) if state.hash == self.hash => DiskChange::Touched(state),
+            Some(state) => DiskChange::Modified(state),
+            None => DiskChange::Deleted,
+        }
+    }
+}
+
+/// Watches the directories of open files. Directories rather than files are
+/// watched so that files replaced by a rename, as atomic saves do, are still
+/// seen.
+pub struct FileWatcher {
+    watcher: Option<RecommendedWatcher>,
+    events: Receiver<()>,
+    // The open files last synced and their directories
+    files: Vec<PathBuf>,
+    dirs: HashSet<PathBuf>,
+    // Set when a directory could not be watched and files are polled instead
+    polling: bool,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if event.is_ok_and(|event| !event.kind.is_access()) {
+                let _ = sender.send(());
+            }
+        })
+        .map_err(|e| log::warn!("File watcher unavailable, polling instead: {}", e))
+        .ok();
+        Self {
+            polling: watcher.is_none(),
+            watcher,
+            events,
+            files: Vec::new(),
+            dirs: HashSet::new(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Watch the directories of `files` and stop watching the others.
+    /// Resolving the paths touches the disk, so it is only done when the
+    /// set of files changes.
+    pub fn sync<'a>(&mut self, files: impl Iterator<Item = &'a Path>) {
+        let files: Vec<&Path> = files.collect();
+        if files
+            .iter()
+            .copied()
+            .eq(self.files.iter().map(PathBuf::as_path))
+        {
+            return;
+        }
+        self.files = files.iter().map(|file| file.to_path_buf()).collect();
+        // A file not written yet is resolved from the current directory
+        let dirs: HashSet<PathBuf> = files
+            .into_iter()
+            .filter_map(|file| {
+                std::fs::canonicalize(file)
+                    .or_else(|_| std::path::absolute(file))
+                    .ok()?
+                    .parent()
+                    .map(Path::to_path_buf)
+            })
+            .collect();
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        for dir in self.dirs.difference(&dirs) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&self.dirs) {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::warn!("Failed to watch {}, polling instead: {}", dir.display(), e);
+                self.polling = true;
+            }
+        }
+        self.dirs = dirs;
+    }
+
+    /// Whether open files should be checked now: something changed in a
+    /// watched directory, or the poll interval passed without a watcher.
+    pub fn due(&mut self) -> bool {
+        let notified = self.events.try_iter().count() > 0;
+        let poll = self.polling && self.last_poll.elapsed() >= POLL_INTERVAL;
+        if poll {
+            self.last_poll = Instant::now();
+        }
+        notified || poll
+    }
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Unified diff from the file on disk to the buffer, with `DIFF_CONTEXT`
+/// lines around each change. Empty when they are the same.
+pub fn diff_view(disk: &str, buffer: &str) -> Vec<Line<'static>> {
+    let old: Vec<String> = disk.lines().map(str::to_string).collect();
+    let new: Vec<String> = buffer.lines().map(str::to_string).collect();
+    let ops = diff_lines(&old, &new);
+
+    // Keep the changes and the lines of context around them
+    let mut shown = vec![false; ops.len()];
+    for (index, op) in ops.iter().enumerate() {
+        if *op != DiffOp::Equal {
+            let end = (index + DIFF_CONTEXT + 1).min(ops.len());
+            shown[index.saturating_sub(DIFF_CONTEXT)..end].fill(true);
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (index, op) in ops.iter().enumerate() {
+        if shown[index] && (index == 0 || !shown[index - 1]) {
+            lines.push(Line::styled(
+                format!("@@ ディスク {} 行目 / バッファ {} 行目 @@", i + 1, j + 1),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+        let (text, style) = match op {
+            DiffOp::Equal => (format!(" {}", old[i]), Style::default()),
+            DiffOp::Delete => (format!("-{}", old[i]), Style::default().fg(Color::Red)),
+            DiffOp::Insert => (format!("+{}", new[j]), Style::default().fg(Color::Green)),
+        };
+        if shown[index] {
+            lines.push(Line::styled(text, style));
+        }
+        match op {
+            DiffOp::Equal => (i, j) = (i + 1, j + 1),
+            DiffOp::Delete => i += 1,
+            DiffOp::Insert => j += 1,
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_state_detects_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "one\n").unwrap();
+        let state = DiskState::read(&path).unwrap();
+        assert_eq!(state.check(&path), DiskChange::Unchanged);
+        assert_eq!(
+            DiskState::with_contents(&path, b"one\n"),
+            Some(state.clone())
+        );
+
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        let DiskChange::Modified(changed) = state.check(&path) else {
+            panic!("change not detected");
+        };
+        assert_eq!(changed.check(&path), DiskChange::Unchanged);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(changed.check(&path), DiskChange::Deleted);
+    }
+
+    #[test]
+    fn test_diff_view_shows_changes_with_context() {
+        let disk = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let buffer = disk.replace("8\n", "eight\n");
+        let text: Vec<String> = diff_view(disk, &buffer)
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "@@ ディスク 5 行目 / バッファ 5 行目 @@",
+                " 5",
+                " 6",
+                " 7",
+                "-8",
+                "+eight",
+                " 9",
+                " 10"
+            ]
+        );
+        assert!(diff_view(disk, disk).is_empty());
+    }
+}
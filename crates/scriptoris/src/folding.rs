@@ -0,0 +1,408 @@
+//! Markdown の折りたたみ範囲の検出と開閉状態の管理。
+//!
+//! 見出し（下位の見出しを含むセクション全体）、フェンス付きコードブロック、
+//! 長いリストを折りたたみ対象として検出します。閉じた折りたたみは見出し行の
+//! テキストで識別するため、範囲内やそれより上の行を編集しても維持されます。
+//! 見出しピッカー用に、文書中の見出しの一覧も取り出せます。
+
+use serde::{Deserialize, Serialize};
+
+/// Lists with at least this many lines can be folded.
+pub const LONG_LIST_MIN_LINES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldKind {
+    Heading(usize),
+    CodeBlock,
+    List,
+}
+
+/// A foldable range of buffer lines; `start` is the line left visible when
+/// the region is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldRegion {
+    pub start: usize,
+    /// Last line of the region (inclusive).
+    pub end: usize,
+    pub kind: FoldKind,
+}
+
+impl FoldRegion {
+    pub fn contains(&self, line: usize) -> bool {
+        self.start <= line && line <= self.end
+    }
+
+    /// Number of lines hidden when the region is closed.
+    pub fn hidden_lines(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// A closed fold, identified by its first line and that line's text so it
+/// can be found again after edits shift it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClosedFold {
+    pub line: usize,
+    pub header: String,
+}
+
+fn heading_level(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+fn fence_marker(line: &str) -> Option<&'static str> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    if trimmed.starts_with("```") {
+        Some("```")
+    } else if trimmed.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+fn is_list_item(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("- ") || trimmed.starts_with("* ") || trimmed.starts_with("+ ") {
+        return true;
+    }
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && (trimmed[digits..].starts_with(". ") || trimmed[digits..].starts_with(") "))
+}
+
+fn close_list(regions: &mut Vec<FoldRegion>, start: Option<usize>, end: usize) {
+    if let Some(start) = start {
+        if end + 1 - start >= LONG_LIST_MIN_LINES {
+            regions.push(FoldRegion {
+                start,
+                end,
+                kind: FoldKind::List,
+            });
+        }
+    }
+}
+
+/// Detect the foldable regions of a Markdown document, ordered by start line.
+pub fn compute_fold_regions<S: AsRef<str>>(lines: &[S]) -> Vec<FoldRegion> {
+    let mut regions = Vec::new();
+    let mut headings: Vec<(usize, usize)> = Vec::new();
+    let mut list_start: Option<usize> = None;
+    let mut list_end = 0;
+    let mut fence: Option<(usize, &str)> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.as_ref().trim_end_matches(['\n', '\r']);
+
+        if let Some((start, marker)) = fence {
+            if line.trim_start().starts_with(marker) {
+                regions.push(FoldRegion {
+                    start,
+                    end: index,
+                    kind: FoldKind::CodeBlock,
+                });
+                fence = None;
+            }
+            continue;
+        }
+
+        // Lists continue over indented continuation lines
+        let in_list = list_start.is_some();
+        if is_list_item(line) {
+            list_start.get_or_insert(index);
+            list_end = index;
+            continue;
+        } else if in_list && line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+            list_end = index;
+            continue;
+        } else if in_list {
+            close_list(&mut regions, list_start.take(), list_end);
+        }
+
+        if let Some(marker) = fence_marker(line) {
+            fence = Some((index, marker));
+        } else if let Some(level) = heading_level(line) {
+            while let Some(&(start, open_level)) = headings.last() {
+                if open_level < level {
+                    break;
+                }
+                headings.pop();
+                if index - 1 > start {
+                    regions.push(FoldRegion {
+                        start,
+                        end: index - 1,
+                        kind: FoldKind::Heading(open_level),
+                    });
+                }
+            }
+            headings.push((index, level));
+        }
+    }
+
+    close_list(&mut regions, list_start, list_end);
+    // Sections run to the end of the document, ignoring a trailing empty line
+    let mut last = lines.len().saturating_sub(1);
+    while last > 0 && lines[last].as_ref().trim().is_empty() {
+        last -= 1;
+    }
+    for (start, level) in headings {
+        if last > start {
+            regions.push(FoldRegion {
+                start,
+                end: last,
+                kind: FoldKind::Heading(level),
+            });
+        }
+    }
+
+    // Outer regions first when several start on the same line
+    regions.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+    regions
+}
+
+/// A Markdown heading of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    pub line: usize,
+    pub level: usize,
+    /// Heading text without the `#` markers.
+    pub text: String,
+}
+
+/// ATX headings of a document, skipping lines inside fenced code blocks.
+pub fn headings<S: AsRef<str>>(lines: &[S]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.as_ref().trim_end_matches(['\n', '\r']);
+        if let Some(marker) = fence {
+            if line.trim_start().starts_with(marker) {
+                fence = None;
+            }
+        } else if let Some(marker) = fence_marker(line) {
+            fence = Some(marker);
+        } else if let Some(level) = heading_level(line) {
+            let text = line.trim_start().trim_start_matches('#').trim();
+            headings.push(Heading {
+                line: index,
+                level,
+                text: text.trim_end_matches('#').trim_end().to_string(),
+            });
+        }
+    }
+    headings
+}
+
+/// Fold regions of a buffer and which of them are closed.
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    regions: Vec<FoldRegion>,
+    closed: Vec<ClosedFold>,
+}
+
+impl Folds {
+    /// Recompute regions after the text changed and re-attach closed folds to
+    /// the region whose first line still has the same text, nearest first.
+    pub fn update<S: AsRef<str>>(&mut self, lines: &[S]) {
+        self.regions = compute_fold_regions(lines);
+        let header = |line: usize| lines[line].as_ref().trim_end_matches(['\n', '\r']);
+
+        let regions = &self.regions;
+        let mut remapped: Vec<ClosedFold> = Vec::new();
+        for fold in &self.closed {
+            let found = regions
+                .iter()
+                .filter(|region| header(region.start) == fold.header)
+                .filter(|region| remapped.iter().all(|f| f.line != region.start))
+                .min_by_key(|region| region.start.abs_diff(fold.line));
+            if let Some(region) = found {
+                remapped.push(ClosedFold {
+                    line: region.start,
+                    header: fold.header.clone(),
+                });
+            }
+        }
+        self.closed = remapped;
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+        self.closed.clear();
+    }
+
+    pub fn regions(&self) -> &[FoldRegion] {
+        &self.regions
+    }
+
+    pub fn closed(&self) -> &[ClosedFold] {
+        &self.closed
+    }
+
+    pub fn has_closed(&self) -> bool {
+        !self.closed.is_empty()
+    }
+
+    fn is_closed(&self, region: &FoldRegion) -> bool {
+        self.closed.iter().any(|fold| fold.line == region.start)
+    }
+
+    /// Outermost closed region containing `line`.
+    pub fn closed_region_at(&self, line: usize) -> Option<FoldRegion> {
+        self.regions
+            .iter()
+            .filter(|region| region.contains(line) && self.is_closed(region))
+            .max_by_key(|region| region.end - region.start)
+            .copied()
+    }
+
+    /// Open every closed region containing `line`.
+    pub fn open_at(&mut self, line: usize) -> bool {
+        let starts: Vec<usize> = self
+            .regions
+            .iter()
+            .filter(|region| region.contains(line))
+            .map(|region| region.start)
+            .collect();
+        let before = self.closed.len();
+        self.closed.retain(|fold| !starts.contains(&fold.line));
+        self.closed.len() != before
+    }
+
+    /// Close the innermost open region containing `line` and return it.
+    pub fn close_at(
+        &mut self,
+        line: usize,
+        header: impl Fn(usize) -> String,
+    ) -> Option<FoldRegion> {
+        let region = self
+            .regions
+            .iter()
+            .filter(|region| region.contains(line) && !self.is_closed(region))
+            .min_by_key(|region| region.end - region.start)
+            .copied()?;
+        self.closed.push(ClosedFold {
+            line: region.start,
+            header: header(region.start),
+        });
+        Some(region)
+    }
+
+    pub fn open_all(&mut self) {
+        self.closed.clear();
+    }
+
+    pub fn close_all(&mut self, header: impl Fn(usize) -> String) {
+        self.closed = self
+            .regions
+            .iter()
+            .map(|region| ClosedFold {
+                line: region.start,
+                header: header(region.start),
+            })
+            .collect();
+        self.closed.dedup_by_key(|fold| fold.line);
+    }
+
+    /// Restore closed folds saved in a session; unknown ones are dropped on
+    /// the next update.
+    pub fn set_closed(&mut self, closed: Vec<ClosedFold>) {
+        self.closed = closed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Title\nintro\n## Section A\ntext\n```\n# not a heading\n```\n## Section B\n- a\n- b\n- c\n- d\n- e\n";
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split_inclusive('\n').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_compute_fold_regions() {
+        let regions = compute_fold_regions(&lines(DOC));
+        assert_eq!(
+            regions,
+            vec![
+                FoldRegion {
+                    start: 0,
+                    end: 12,
+                    kind: FoldKind::Heading(1)
+                },
+                FoldRegion {
+                    start: 2,
+                    end: 6,
+                    kind: FoldKind::Heading(2)
+                },
+                FoldRegion {
+                    start: 4,
+                    end: 6,
+                    kind: FoldKind::CodeBlock
+                },
+                FoldRegion {
+                    start: 7,
+                    end: 12,
+                    kind: FoldKind::Heading(2)
+                },
+                FoldRegion {
+                    start: 8,
+                    end: 12,
+                    kind: FoldKind::List
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_headings_skip_code_blocks() {
+        let found = headings(&lines(DOC));
+        let texts: Vec<(usize, usize, &str)> = found
+            .iter()
+            .map(|h| (h.line, h.level, h.text.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![(0, 1, "Title"), (2, 2, "Section A"), (7, 2, "Section B")]
+        );
+    }
+
+    #[test]
+    fn test_closed_folds_follow_edits() {
+        let mut folds = Folds::default();
+        let mut doc = lines(DOC);
+        folds.update(&doc);
+
+        let closed = folds.close_at(3, |line| doc[line].trim_end().to_string());
+        assert_eq!(closed.map(|r| r.start), Some(2));
+        assert_eq!(folds.closed_region_at(5).map(|r| r.start), Some(2));
+
+        // Edit inside the fold and insert lines above it
+        doc[3] = "changed text\n".to_string();
+        doc.insert(1, "new line\n".to_string());
+        folds.update(&doc);
+        assert_eq!(folds.closed()[0].line, 3);
+        assert_eq!(folds.closed_region_at(4).map(|r| r.end), Some(7));
+
+        assert!(folds.open_at(4));
+        assert!(!folds.has_closed());
+
+        folds.close_all(|line| doc[line].trim_end().to_string());
+        assert_eq!(folds.closed_region_at(10).map(|r| r.start), Some(0));
+        folds.open_all();
+        assert!(folds.closed_region_at(10).is_none());
+    }
+}
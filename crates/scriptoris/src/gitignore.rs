@@ -0,0 +1,283 @@
+//! `.gitignore` のパターン照合とプロジェクト内のファイル走査。
+//!
+//! `*`・`?`・`**`・文字クラス・否定（`!`）・ディレクトリ限定（末尾の `/`）・
+//! 先頭や途中の `/` によるアンカーに対応します。各ディレクトリの
+//! `.gitignore` はそのディレクトリ以下にだけ適用されます。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories that are never listed, whatever `.gitignore` says.
+const ALWAYS_SKIPPED: &[&str] = &[".git"];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Directory of the `.gitignore` holding the rule, relative to the root.
+    base: PathBuf,
+    pattern: Vec<char>,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the whole path below `base` instead of the file name.
+    anchored: bool,
+}
+
+/// Ignore rules collected from the `.gitignore` files of a tree.
+#[derive(Debug, Clone, Default)]
+pub struct GitIgnore {
+    rules: Vec<Rule>,
+}
+
+impl GitIgnore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the rules of a `.gitignore` located in `dir` (relative to the root).
+    pub fn add_rules(&mut self, dir: &Path, content: &str) {
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let line = line.strip_prefix('/').unwrap_or(line);
+            if line.is_empty() {
+                continue;
+            }
+            self.rules.push(Rule {
+                base: dir.to_path_buf(),
+                pattern: line.chars().collect(),
+                negated,
+                dir_only,
+                anchored,
+            });
+        }
+    }
+
+    /// Read `root/dir/.gitignore` if it exists.
+    pub fn load_dir(&mut self, root: &Path, dir: &Path) {
+        if let Ok(content) = fs::read_to_string(root.join(dir).join(".gitignore")) {
+            self.add_rules(dir, &content);
+        }
+    }
+
+    /// Whether `path` (relative to the root) is ignored. The last matching
+    /// rule wins, so `!` patterns can re-include files.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let Ok(below) = path.strip_prefix(&rule.base) else {
+                continue;
+            };
+            let text: Vec<char> = if rule.anchored {
+                path_string(below).chars().collect()
+            } else {
+                below
+                    .file_name()
+                    .map(|name| name.to_string_lossy().chars().collect())
+                    .unwrap_or_default()
+            };
+            if glob_match(&rule.pattern, &text) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+fn path_string(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            match rest.split_first() {
+                // `**/` matches zero or more whole directories
+                Some(('/', after)) => (0..=text.len())
+                    .filter(|&i| i == 0 || text[i - 1] == '/')
+                    .any(|i| glob_match(after, &text[i..])),
+                _ => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+            }
+        }
+        Some('*') => {
+            let max = text.iter().position(|c| *c == '/').unwrap_or(text.len());
+            (0..=max).any(|i| glob_match(&pattern[1..], &text[i..]))
+        }
+        Some('?') => {
+            text.first().is_some_and(|c| *c != '/') && glob_match(&pattern[1..], &text[1..])
+        }
+        Some('[') => match (text.first(), class_end(pattern)) {
+            (Some(&c), Some(end)) => {
+                c != '/'
+                    && class_matches(&pattern[1..end], c)
+                    && glob_match(&pattern[end + 1..], &text[1..])
+            }
+            (Some(&c), None) => c == '[' && glob_match(&pattern[1..], &text[1..]),
+            (None, _) => false,
+        },
+        Some('\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..])
+        }
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Index of the `]` closing the class that starts at `pattern[0]`.
+fn class_end(pattern: &[char]) -> Option<usize> {
+    // A `]` right after `[` or `[!` is part of the class
+    let start = if matches!(pattern.get(1), Some('!' | '^')) {
+        3
+    } else {
+        2
+    };
+    (start..pattern.len()).find(|&i| pattern[i] == ']')
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+/// Files below `root`, relative to it and sorted, skipping ignored entries.
+/// Stops after `limit` files.
+pub fn walk_files(root: &Path, limit: usize) -> Vec<PathBuf> {
+    let mut ignore = GitIgnore::new();
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+
+    while let Some(dir) = pending.pop() {
+        ignore.load_dir(root, &dir);
+        let Ok(entries) = fs::read_dir(root.join(&dir)) else {
+            continue;
+        };
+        let mut entries: Vec<(PathBuf, bool)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let is_dir = entry.file_type().ok()?.is_dir();
+                Some((dir.join(entry.file_name()), is_dir))
+            })
+            .collect();
+        entries.sort();
+
+        let mut subdirs = Vec::new();
+        for (path, is_dir) in entries {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if ALWAYS_SKIPPED.contains(&name) || ignore.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if is_dir {
+                subdirs.push(path);
+            } else {
+                files.push(path);
+                if files.len() >= limit {
+                    files.sort();
+                    return files;
+                }
+            }
+        }
+        // Visit subdirectories in order
+        pending.extend(subdirs.into_iter().rev());
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        glob_match(
+            &pattern.chars().collect::<Vec<_>>(),
+            &text.chars().collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob("*.md", "notes.md"));
+        assert!(!glob("*.md", "docs/notes.md"));
+        assert!(glob("docs/**/*.md", "docs/a/b/notes.md"));
+        assert!(glob("docs/**/*.md", "docs/notes.md"));
+        assert!(glob("**/build", "build"));
+        assert!(glob("file?.txt", "file1.txt"));
+        assert!(glob("[a-c]x", "bx"));
+        assert!(!glob("[!a-c]x", "bx"));
+        assert!(glob("下書き*", "下書き2.md"));
+    }
+
+    #[test]
+    fn test_gitignore_rules_and_walk() {
+        let mut ignore = GitIgnore::new();
+        ignore.add_rules(
+            Path::new(""),
+            "# comment\ntarget/\n*.log\n!keep.log\n/root-only.txt\n",
+        );
+        assert!(ignore.is_ignored(Path::new("target"), true));
+        assert!(!ignore.is_ignored(Path::new("target"), false));
+        assert!(ignore.is_ignored(Path::new("a/debug.log"), false));
+        assert!(!ignore.is_ignored(Path::new("a/keep.log"), false));
+        assert!(ignore.is_ignored(Path::new("root-only.txt"), false));
+        assert!(!ignore.is_ignored(Path::new("a/root-only.txt"), false));
+
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for path in [
+            "README.md",
+            "docs/guide.md",
+            "docs/drafts/old.md",
+            "target/out.txt",
+            ".git/config",
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("docs/.gitignore"), "drafts/\n").unwrap();
+
+        let files = walk_files(root, 100);
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from(".gitignore"),
+                PathBuf::from("README.md"),
+                PathBuf::from("docs/.gitignore"),
+                PathBuf::from("docs/guide.md"),
+            ]
+        );
+        assert_eq!(walk_files(root, 1).len(), 1);
+    }
+}
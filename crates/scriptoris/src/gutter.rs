@@ -0,0 +1,442 @@
+//! 本文の左のガター（サインカラムと行番号）。
+//!
+//! 行番号は絶対・相対・ハイブリッド（現在行だけ絶対）の三通りです。
+//! サインカラムには LSP の診断、Git の HEAD からの変更（追加・変更・削除）、
+//! マークを行ごとにまとめ、一行に複数あるときは重要度の高いものを出します。
+//! Git の差分は本文が変わったときだけ計算し直します。
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::editor::Editor;
+use crate::options::EditorOptions;
+use crate::view::DisplayRow;
+
+/// Columns taken by the sign column when it is shown.
+pub const SIGN_WIDTH: u16 = 2;
+
+/// Above this many line pairs, a changed region is marked as a whole instead
+/// of being diffed line by line.
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sign {
+    Error,
+    Warning,
+    Info,
+    Mark(char),
+    Modified,
+    Added,
+    Removed,
+}
+
+impl Sign {
+    fn priority(self) -> u8 {
+        match self {
+            Sign::Error => 6,
+            Sign::Warning => 5,
+            Sign::Info => 4,
+            Sign::Mark(_) => 3,
+            Sign::Modified => 2,
+            Sign::Added => 1,
+            Sign::Removed => 0,
+        }
+    }
+
+    /// The text drawn in the column, padded to its width.
+    pub fn text(self) -> String {
+        let glyph = match self {
+            Sign::Error => 'E',
+            Sign::Warning => 'W',
+            Sign::Info => 'I',
+            Sign::Mark(name) => name,
+            Sign::Modified => '~',
+            Sign::Added => '+',
+            Sign::Removed => '_',
+        };
+        format!("{} ", glyph)
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Sign::Error => Color::Red,
+            Sign::Warning => Color::Yellow,
+            Sign::Info => Color::Blue,
+            Sign::Mark(_) => Color::Magenta,
+            Sign::Modified => Color::Cyan,
+            Sign::Added => Color::Green,
+            Sign::Removed => Color::Red,
+        }
+    }
+}
+
+/// The sign of each line, keeping the most important one.
+#[derive(Debug, Clone, Default)]
+pub struct Signs {
+    by_line: BTreeMap<usize, Sign>,
+}
+
+impl Signs {
+    pub fn add(&mut self, line: usize, sign: Sign) {
+        let slot = self.by_line.entry(line).or_insert(sign);
+        if sign.priority() > slot.priority() {
+            *slot = sign;
+        }
+    }
+
+    pub fn get(&self, line: usize) -> Option<Sign> {
+        self.by_line.get(&line).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_line.is_empty()
+    }
+}
+
+/// Lines of `path` as committed in `HEAD`, or `None` when the file is not
+/// tracked by git.
+pub fn git_head_lines(path: &Path) -> Option<Vec<String>> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let name = path.file_name()?.to_str()?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir.unwrap_or(Path::new(".")))
+        .arg("show")
+        .arg(format!("HEAD:./{}", name))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Current branch of the repository holding `path`, or `None` outside a
+/// repository or on a detached `HEAD`.
+pub fn git_branch(path: &Path) -> Option<String> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir.unwrap_or(Path::new(".")))
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .ok()?;
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(branch).filter(|branch| output.status.success() && branch != "HEAD")
+}
+
+/// One step of a line diff from a base text to the current one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Steps turning `base` into `current`, from the longest common subsequence
+/// of their lines. A changed region too large to diff line by line is
+/// replaced as a whole.
+pub fn diff_lines(base: &[String], current: &[String]) -> Vec<DiffOp> {
+    let prefix = base.iter().zip(current).take_while(|(a, b)| a == b).count();
+    let suffix = base[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &base[prefix..base.len() - suffix];
+    let new = &current[prefix..current.len() - suffix];
+
+    let mut ops = vec![DiffOp::Equal; prefix];
+    if old.len() * new.len() > MAX_DIFF_CELLS {
+        ops.extend(std::iter::repeat(DiffOp::Delete).take(old.len()));
+        ops.extend(std::iter::repeat(DiffOp::Insert).take(new.len()));
+        ops.extend(std::iter::repeat(DiffOp::Equal).take(suffix));
+        return ops;
+    }
+
+    // Longest common subsequence of the changed region, from the end
+    let width = new.len() + 1;
+    let mut lcs = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(DiffOp::Equal);
+            i += 1;
+            j += 1;
+        } else if j == new.len()
+            || (i < old.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            ops.push(DiffOp::Delete);
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat(DiffOp::Equal).take(suffix));
+    ops
+}
+
+/// Signs marking how `current` differs from `base`: added and modified
+/// lines, and `Removed` on the line above a deletion.
+pub fn diff_signs(base: &[String], current: &[String]) -> Vec<(usize, Sign)> {
+    let mut signs = Vec::new();
+    let (mut line, mut deleted, mut added, mut start) = (0, 0, 0, 0);
+    for op in diff_lines(base, current) {
+        match op {
+            DiffOp::Equal => {
+                push_hunk(&mut signs, start, deleted, added);
+                (deleted, added) = (0, 0);
+                line += 1;
+                start = line;
+            }
+            DiffOp::Delete => deleted += 1,
+            DiffOp::Insert => {
+                added += 1;
+                line += 1;
+            }
+        }
+    }
+    push_hunk(&mut signs, start, deleted, added);
+    signs
+}
+
+fn push_hunk(signs: &mut Vec<(usize, Sign)>, start: usize, deleted: usize, added: usize) {
+    if added == 0 {
+        if deleted > 0 {
+            signs.push((start.saturating_sub(1), Sign::Removed));
+        }
+        return;
+    }
+    for offset in 0..added {
+        let sign = if offset < deleted {
+            Sign::Modified
+        } else {
+            Sign::Added
+        };
+        signs.push((start + offset, sign));
+    }
+}
+
+/// How long the text has to stay unchanged before the signs are diffed
+/// again, so that typing does not diff the buffer on every key.
+const SIGN_DELAY: Duration = Duration::from_millis(300);
+
+/// Git change signs of a buffer, recomputed once its text stops changing.
+#[derive(Debug, Clone, Default)]
+pub struct GitChanges {
+    base: Option<Vec<String>>,
+    cached: Option<(u64, Vec<(usize, Sign)>)>,
+    // The latest revision seen and when it was first seen
+    seen: Option<(u64, Instant)>,
+    branch: Option<String>,
+}
+
+impl GitChanges {
+    /// Read the committed version of `path` and the current branch.
+    pub fn load(path: &Path) -> Self {
+        Self {
+            base: git_head_lines(path),
+            cached: None,
+            seen: None,
+            branch: git_branch(path),
+        }
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    /// The signs for the text of `editor`. While it keeps changing, the
+    /// signs of an earlier revision are returned.
+    pub fn signs(&mut self, editor: &Editor) -> &[(usize, Sign)] {
+        let Some(base) = &self.base else {
+            return &[];
+        };
+        let revision = editor.revision();
+        if self.cached.as_ref().map(|(rev, _)| *rev) != Some(revision) {
+            let since = match self.seen {
+                Some((rev, since)) if rev == revision => since,
+                _ => {
+                    self.seen = Some((revision, Instant::now()));
+                    Instant::now()
+                }
+            };
+            if self.cached.is_none() || since.elapsed() >= SIGN_DELAY {
+                let current: Vec<String> =
+                    editor.get_content().lines().map(str::to_string).collect();
+                self.cached = Some((revision, diff_signs(base, &current)));
+            }
+        }
+        self.cached.as_ref().map_or(&[], |(_, signs)| signs)
+    }
+}
+
+/// Widths of the two parts of the gutter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gutter {
+    pub sign_width: u16,
+    pub number_width: u16,
+}
+
+impl Gutter {
+    /// The sign column appears when there are signs, the number column when
+    /// `number` or `relativenumber` is set.
+    pub fn new(options: &EditorOptions, signs: &Signs, line_count: usize) -> Self {
+        let digits = line_count.max(1).to_string().len().max(4) as u16;
+        Self {
+            sign_width: if signs.is_empty() { 0 } else { SIGN_WIDTH },
+            number_width: if options.number || options.relativenumber {
+                digits + 1
+            } else {
+                0
+            },
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.sign_width + self.number_width
+    }
+
+    /// One gutter line per display row. Continuation rows of wrapped lines
+    /// are left blank.
+    pub fn render(
+        &self,
+        rows: &[DisplayRow],
+        cursor_line: usize,
+        options: &EditorOptions,
+        signs: &Signs,
+        number_style: Style,
+        current_style: Style,
+    ) -> Vec<Line<'static>> {
+        rows.iter()
+            .map(|row| {
+                let mut spans = Vec::new();
+                let first = !row.is_continuation();
+                if self.sign_width > 0 {
+                    spans.push(match signs.get(row.line).filter(|_| first) {
+                        Some(sign) => Span::styled(sign.text(), Style::default().fg(sign.color())),
+                        None => Span::raw(" ".repeat(self.sign_width as usize)),
+                    });
+                }
+                if self.number_width > 0 {
+                    let digits = self.number_width as usize - 1;
+                    let label = if first {
+                        number_label(row.line, cursor_line, options, digits)
+                    } else {
+                        " ".repeat(digits)
+                    };
+                    let style = if row.line == cursor_line {
+                        current_style
+                    } else {
+                        number_style
+                    };
+                    spans.push(Span::styled(format!("{} ", label), style));
+                }
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+/// The number shown for `line`, padded to `digits`: absolute, relative to
+/// the cursor line, or both (the cursor line absolute and left-aligned).
+pub fn number_label(
+    line: usize,
+    cursor_line: usize,
+    options: &EditorOptions,
+    digits: usize,
+) -> String {
+    let distance = line.abs_diff(cursor_line);
+    match (options.number, options.relativenumber) {
+        (true, true) if distance == 0 => format!("{:<digits$}", line + 1),
+        (_, true) => format!("{:>digits$}", distance),
+        _ => format!("{:>digits$}", line + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_number_labels() {
+        let mut options =
+            EditorOptions::resolve(&crate::config::Config::default(), &Default::default());
+        options.number = true;
+        assert_eq!(number_label(9, 3, &options, 4), "  10");
+        options.relativenumber = true;
+        assert_eq!(number_label(9, 3, &options, 4), "   6");
+        assert_eq!(number_label(3, 3, &options, 4), "4   ");
+        options.number = false;
+        assert_eq!(number_label(3, 3, &options, 4), "   0");
+    }
+
+    #[test]
+    fn test_diff_signs() {
+        let base = lines("a\nb\nc\nd\ne");
+        assert!(diff_signs(&base, &base).is_empty());
+        assert_eq!(
+            diff_signs(&base, &lines("a\nB\nc\nnew\nd\ne")),
+            vec![(1, Sign::Modified), (3, Sign::Added)]
+        );
+        assert_eq!(
+            diff_signs(&base, &lines("a\nd\ne")),
+            vec![(0, Sign::Removed)]
+        );
+        assert_eq!(
+            diff_signs(&base, &lines("b\nc\nd\ne")),
+            vec![(0, Sign::Removed)]
+        );
+
+        let mut signs = Signs::default();
+        signs.add(3, Sign::Added);
+        signs.add(3, Sign::Error);
+        signs.add(3, Sign::Mark('a'));
+        assert_eq!(signs.get(3), Some(Sign::Error));
+        assert_eq!(signs.get(2), None);
+    }
+
+    #[test]
+    fn test_git_signs_wait_for_typing_to_pause() {
+        let mut git = GitChanges {
+            base: Some(lines("a\nb")),
+            ..GitChanges::default()
+        };
+        let mut editor = Editor::new();
+        editor.set_content("a\nb".to_string());
+        assert!(git.signs(&editor).is_empty());
+
+        // The first change keeps the old signs until the text settles
+        editor.move_to_buffer_end();
+        editor.insert_newline();
+        editor.insert_char('c');
+        assert!(git.signs(&editor).is_empty());
+        git.seen = git.seen.map(|(rev, since)| (rev, since - SIGN_DELAY));
+        assert_eq!(git.signs(&editor), [(2, Sign::Added)]);
+    }
+}
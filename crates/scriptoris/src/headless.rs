@@ -0,0 +1,176 @@
+//! 端末を使わずにコマンドを実行するヘッドレスモード（`-es`）。
+//!
+//! `--script` のファイルの各行と `-c` のコマンドを順に [`App`] で実行します。
+//! キー入力は `:normal` で与えます。コマンドが失敗するとそこで止め、
+//! エラーを標準エラー出力に書いて 0 以外の終了コードを返します。
+
+use anyhow::{anyhow, Result};
+
+use crate::app::App;
+use crate::cli::Args;
+
+/// Exit code when a file could not be opened or a command failed.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code when the script file could not be read.
+pub const EXIT_USAGE: i32 = 2;
+
+/// A command to run and where it came from, for error messages.
+#[derive(Debug, PartialEq, Eq)]
+struct ScriptLine {
+    origin: String,
+    command: String,
+}
+
+/// Open the files of `args` in `app`, run the commands and return the exit
+/// code. Swap files and language servers are not used, so nothing asks
+/// questions or starts in the background.
+pub async fn run(app: &mut App, args: &Args) -> i32 {
+    let lines = match script_lines(args) {
+        Ok(lines) => lines,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+
+    app.file_manager.swaps = None;
+    app.disable_lsp();
+    let mut errors = app.open_command_line(args).await;
+    errors.extend(app.finish_file_loads(true).await);
+    if !errors.is_empty() {
+        for error in errors {
+            eprintln!("{}", error);
+        }
+        return EXIT_FAILURE;
+    }
+
+    for line in lines {
+        if let Err(e) = app.run_ex_command(&line.command).await {
+            eprintln!("{}: {}", line.origin, e);
+            return EXIT_FAILURE;
+        }
+        // A large file opened by `:e` is read whole before the next command
+        if let Some(error) = app.finish_file_loads(true).await.into_iter().next() {
+            eprintln!("{}: {}", line.origin, error);
+            return EXIT_FAILURE;
+        }
+        if app.should_quit() {
+            break;
+        }
+    }
+    0
+}
+
+/// The lines of the `--script` file, then the `-c` commands. Blank lines
+/// and `"` comments are skipped, and a leading `:` is optional.
+fn script_lines(args: &Args) -> Result<Vec<ScriptLine>> {
+    let mut lines = Vec::new();
+    if let Some(path) = &args.script {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            anyhow!(
+                "スクリプトを読み込めませんでした: {} - {}",
+                path.display(),
+                e
+            )
+        })?;
+        for (number, line) in text.lines().enumerate() {
+            let command = line.trim().trim_start_matches(':').trim_start();
+            if command.is_empty() || command.starts_with('"') {
+                continue;
+            }
+            lines.push(ScriptLine {
+                origin: format!("{}:{}", path.display(), number + 1),
+                command: command.to_string(),
+            });
+        }
+    }
+    for command in &args.commands {
+        lines.push(ScriptLine {
+            origin: format!("-c {}", command),
+            command: command.trim().trim_start_matches(':').to_string(),
+        });
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run_with(args: &[&str]) -> i32 {
+        let args = Args::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut app = App::new().await.unwrap();
+        app.file_manager.backup = crate::config::BackupStrategy::None;
+        run(&mut app, &args).await
+    }
+
+    #[tokio::test]
+    async fn test_commands_edit_and_save_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.md");
+        let file = path.to_str().unwrap();
+        std::fs::write(&path, "foo and foo\nfoo\n").unwrap();
+
+        let code = run_with(&["-es", "-c", ":%s/foo/bar/g", "-c", ":wq", file]).await;
+        assert_eq!(code, 0);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "bar and bar\nbar\n"
+        );
+
+        // Key sequences go through `:normal`, lines of a script run in order
+        let script = dir.path().join("edit.vim");
+        std::fs::write(
+            &script,
+            "\" Add a heading\n:normal ggO# Title<Esc>\n\n2s/bar/baz/\nw\n",
+        )
+        .unwrap();
+        let script = script.to_str().unwrap();
+        assert_eq!(run_with(&["-es", "--script", script, file]).await, 0);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Title\nbaz and bar\nbar\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_command_stops_with_an_error_code() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.md");
+        let file = path.to_str().unwrap();
+        std::fs::write(&path, "foo\n").unwrap();
+
+        let code = run_with(&["-es", "-c", "%s/missing/x/", "-c", "wq", file]).await;
+        assert_eq!(code, EXIT_FAILURE);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "foo\n");
+
+        // With the `e` flag a missing pattern is not an error
+        assert_eq!(run_with(&["-es", "-c", "%s/missing/x/e", file]).await, 0);
+        // Errors reported by the actions of commands count too
+        for command in ["recover", "b nosuch", "unmap zz"] {
+            assert_eq!(
+                run_with(&["-es", "-c", command, file]).await,
+                EXIT_FAILURE,
+                "{}",
+                command
+            );
+        }
+        // Quitting with unsaved changes fails instead of dropping them
+        let code = run_with(&["-es", "-c", "normal ix", "-c", "q", file]).await;
+        assert_eq!(code, EXIT_FAILURE);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "foo\n");
+        assert_eq!(
+            run_with(&["-es", "-c", "normal ix", "-c", "q!", file]).await,
+            0
+        );
+        let missing = dir.path().join("missing.md");
+        assert_eq!(
+            run_with(&["-es", "-c", "q", missing.to_str().unwrap()]).await,
+            EXIT_FAILURE
+        );
+        assert_eq!(
+            run_with(&["-es", "--script", "no-such-script.vim", file]).await,
+            EXIT_USAGE
+        );
+    }
+}
@@ -3,6 +3,7 @@ use ratatui::text::{Line, Span};
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Style as SynStyle, Theme, ThemeSet};
 use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
 
 pub struct Highlighter {
     syntax_set: SyntaxSet,
@@ -10,6 +11,13 @@ pub struct Highlighter {
     theme_name: String,
 }
 
+/// Names of the bundled syntect themes, sorted.
+pub fn theme_names() -> Vec<String> {
+    let mut names: Vec<String> = ThemeSet::load_defaults().themes.into_keys().collect();
+    names.sort();
+    names
+}
+
 impl Highlighter {
     pub fn new(theme_name: &str) -> Self {
         let syntax_set = SyntaxSet::load_defaults_newlines();
@@ -84,6 +92,30 @@ impl Highlighter {
             })
             .collect()
     }
+
+    /// `text` with 24-bit ANSI color escapes, for printing with `--cat`.
+    pub fn highlight_to_ansi(&self, text: &str, syntax: &SyntaxReference) -> String {
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        let mut out = String::new();
+        for line in LinesWithEndings::from(text) {
+            let regions = highlighter
+                .highlight_line(line, &self.syntax_set)
+                .unwrap_or_else(|_| vec![(SynStyle::default(), line)]);
+            out.push_str(&as_24_bit_terminal_escaped(&regions, false));
+        }
+        if !out.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+}
+
+/// Lines without any highlighting, for buffers in large-file mode.
+pub fn plain_lines(lines: &[String]) -> Vec<Line<'static>> {
+    lines
+        .iter()
+        .map(|line| Line::from(line.trim_end_matches('\n').to_string()))
+        .collect()
 }
 
 fn syn_style_to_ratatui(style: SynStyle) -> Style {
@@ -95,3 +127,23 @@ fn syn_style_to_ratatui(style: SynStyle) -> Style {
     }
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_to_ansi() {
+        let highlighter = Highlighter::new("base16-ocean.dark");
+        let syntax = highlighter.find_syntax_for_filename("notes.md");
+        let ansi = highlighter.highlight_to_ansi("# 見出し\n本文\n", syntax);
+        assert!(ansi.contains("\x1b[38;2;"));
+        assert!(ansi.ends_with("\x1b[0m"));
+
+        let plain: String = ansi.split('\x1b').fold(String::new(), |mut text, part| {
+            text.push_str(part.split_once('m').map_or(part, |(_, rest)| rest));
+            text
+        });
+        assert_eq!(plain, "# 見出し\n本文\n");
+    }
+}
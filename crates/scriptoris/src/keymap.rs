@@ -0,0 +1,1183 @@
+//! キー入力を名前付きアクションに変換するキーマップ。
+//!
+//! モードごとにキー列（`dd` や `<C-s>` など）とアクションの対応表を持ち、
+//! 設定ファイルや `:map` 系コマンドによる上書きを受け付けます。
+//! 複数キーのシーケンスは入力途中で保留し、一定時間内に続きが来なければ
+//! 確定します。
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::{KeybindingStyle, KeymapConfig};
+
+/// How long a partial key sequence waits for its next key by default.
+pub const DEFAULT_TIMEOUT_MS: u64 = 1000;
+
+/// Modes that resolve keys through the keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeymapMode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+impl KeymapMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeymapMode::Normal => "normal",
+            KeymapMode::Insert => "insert",
+            KeymapMode::Visual => "visual",
+        }
+    }
+
+    /// Single-letter prefix used by `:map` listings.
+    pub fn short_name(self) -> &'static str {
+        match self {
+            KeymapMode::Normal => "n",
+            KeymapMode::Insert => "i",
+            KeymapMode::Visual => "v",
+        }
+    }
+}
+
+/// One key press with its modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of the character itself
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            other => other,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn plain(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    /// The character typed by this key, if it carries no Ctrl or Alt.
+    pub fn as_char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c)
+                if !self
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("CR", KeyCode::Enter),
+    ("Enter", KeyCode::Enter),
+    ("Return", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("BS", KeyCode::Backspace),
+    ("Backspace", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Delete", KeyCode::Delete),
+    ("Tab", KeyCode::Tab),
+    ("Space", KeyCode::Char(' ')),
+    ("lt", KeyCode::Char('<')),
+    ("gt", KeyCode::Char('>')),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Insert", KeyCode::Insert),
+];
+
+fn parse_special(name: &str) -> Option<KeyChord> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    // Modifier prefixes such as `C-`, `M-`, `A-` and `S-`, in any order
+    while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        modifiers |= match rest.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => KeyModifiers::CONTROL,
+            b'M' | b'A' => KeyModifiers::ALT,
+            b'S' => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = &rest[2..];
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => {
+            if let Some(n) = rest
+                .strip_prefix(['F', 'f'])
+                .and_then(|n| n.parse::<u8>().ok())
+            {
+                KeyCode::F(n)
+            } else {
+                KEY_NAMES
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(rest))
+                    .map(|(_, code)| *code)?
+            }
+        }
+    };
+    // <S-Tab> is reported as BackTab by the terminal
+    if code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT) {
+        return Some(KeyChord::new(
+            KeyCode::BackTab,
+            modifiers - KeyModifiers::SHIFT,
+        ));
+    }
+    Some(KeyChord::new(code, modifiers))
+}
+
+/// Parse Vim-style key notation such as `dd`, `<C-s>` or `<C-x><C-s>`.
+pub fn parse_keys(notation: &str) -> Result<Vec<KeyChord>> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                if let Some(chord) = parse_special(&rest[1..end]) {
+                    keys.push(chord);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        keys.push(KeyChord::plain(c));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if keys.is_empty() {
+        return Err(anyhow::anyhow!("キーが指定されていません"));
+    }
+    Ok(keys)
+}
+
+fn format_chord(chord: &KeyChord) -> String {
+    let mut prefix = String::new();
+    if chord.modifiers.contains(KeyModifiers::CONTROL) {
+        prefix.push_str("C-");
+    }
+    if chord.modifiers.contains(KeyModifiers::ALT) {
+        prefix.push_str("M-");
+    }
+    if chord.modifiers.contains(KeyModifiers::SHIFT) {
+        prefix.push_str("S-");
+    }
+
+    let name = match chord.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char('>') if !prefix.is_empty() => "gt".to_string(),
+        KeyCode::Char(c) if prefix.is_empty() => return c.to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::BackTab => "S-Tab".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        code => KEY_NAMES
+            .iter()
+            .find(|(_, known)| *known == code)
+            .map_or_else(|| format!("{:?}", code), |(name, _)| name.to_string()),
+    };
+    format!("<{}{}>", prefix, name)
+}
+
+/// Format a key sequence back into key notation.
+pub fn format_keys(keys: &[KeyChord]) -> String {
+    keys.iter().map(format_chord).collect()
+}
+
+/// Something a key sequence can be bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveDown,
+    MoveUp,
+    MoveRight,
+    LineStart,
+    LineEnd,
+    PageUp,
+    PageDown,
+    DisplayRowDown,
+    DisplayRowUp,
+    WordForward,
+    WordBackward,
+    BufferStart,
+    BufferEnd,
+    Insert,
+    Append,
+    OpenBelow,
+    OpenAbove,
+    NormalMode,
+    Visual,
+    VisualLine,
+    VisualBlock,
+    ReplaceMode,
+    CommandMode,
+    Search,
+    Help,
+    /// Command prompt opened as `M-x`.
+    ExecuteCommand,
+    InsertNewline,
+    InsertTab,
+    DeleteChar,
+    DeleteCharBackward,
+    DeleteLine,
+    YankLine,
+    Paste,
+    DeleteSelection,
+    YankSelection,
+    ChangeSelection,
+    SetMark,
+    KillLine,
+    KillRegion,
+    CopyRegion,
+    Yank,
+    YankPop,
+    /// Cut the whole line, or the region when the mark is set (nano `^K`).
+    CutLine,
+    KeyboardQuit,
+    IsearchForward,
+    IsearchBackward,
+    Undo,
+    Redo,
+    FoldToggle,
+    FoldOpen,
+    FoldClose,
+    FoldOpenAll,
+    FoldCloseAll,
+    Write,
+    /// Write prompt with the current file name filled in (nano `^O`).
+    WriteOut,
+    Quit,
+    WriteQuit,
+    /// Quit, asking to save a modified buffer first.
+    Exit,
+    Replace,
+    GoToLine,
+    NextBuffer,
+    PreviousBuffer,
+    FindFile,
+    /// Fuzzy finder over the project's files.
+    FuzzyFind,
+    PickBuffer,
+    PickRecent,
+    PickHeading,
+    /// Open, focus or close the file explorer sidebar.
+    Explore,
+    CommandPalette,
+    SwitchBuffer,
+    CloseBuffer,
+    SplitHorizontal,
+    SplitVertical,
+    OtherWindow,
+    /// Run an ex command, written `:cmd` in mappings.
+    Ex(String),
+}
+
+/// Action names used in mappings, with the description shown in the
+/// command palette.
+const NAMED_ACTIONS: &[(&str, Action, &str)] = &[
+    ("move_left", Action::MoveLeft, "左へ移動"),
+    ("move_down", Action::MoveDown, "下へ移動"),
+    ("move_up", Action::MoveUp, "上へ移動"),
+    ("move_right", Action::MoveRight, "右へ移動"),
+    ("line_start", Action::LineStart, "行頭へ移動"),
+    ("line_end", Action::LineEnd, "行末へ移動"),
+    ("page_up", Action::PageUp, "1ページ上へ"),
+    ("page_down", Action::PageDown, "1ページ下へ"),
+    ("display_row_down", Action::DisplayRowDown, "表示行で下へ"),
+    ("display_row_up", Action::DisplayRowUp, "表示行で上へ"),
+    ("word_forward", Action::WordForward, "次の単語へ"),
+    ("word_backward", Action::WordBackward, "前の単語へ"),
+    ("buffer_start", Action::BufferStart, "バッファの先頭へ"),
+    ("buffer_end", Action::BufferEnd, "バッファの末尾へ"),
+    ("insert", Action::Insert, "カーソルの前から挿入"),
+    ("append", Action::Append, "カーソルの後ろから挿入"),
+    ("open_below", Action::OpenBelow, "下に行を追加して挿入"),
+    ("open_above", Action::OpenAbove, "上に行を追加して挿入"),
+    ("normal_mode", Action::NormalMode, "ノーマルモードに戻る"),
+    ("visual", Action::Visual, "ビジュアルモード"),
+    (
+        "visual_line",
+        Action::VisualLine,
+        "行単位のビジュアルモード",
+    ),
+    ("visual_block", Action::VisualBlock, "矩形ビジュアルモード"),
+    ("replace_mode", Action::ReplaceMode, "置換モード"),
+    ("command_mode", Action::CommandMode, "コマンドラインを開く"),
+    ("search", Action::Search, "検索"),
+    ("help", Action::Help, "ヘルプの表示を切り替える"),
+    (
+        "execute_command",
+        Action::ExecuteCommand,
+        "コマンドを入力する",
+    ),
+    ("insert_newline", Action::InsertNewline, "改行を挿入"),
+    ("insert_tab", Action::InsertTab, "タブを挿入"),
+    (
+        "delete_char",
+        Action::DeleteChar,
+        "カーソル位置の文字を削除",
+    ),
+    (
+        "delete_char_backward",
+        Action::DeleteCharBackward,
+        "前の文字を削除",
+    ),
+    ("delete_line", Action::DeleteLine, "行を削除"),
+    ("yank_line", Action::YankLine, "行をコピー"),
+    ("paste", Action::Paste, "貼り付け"),
+    (
+        "delete_selection",
+        Action::DeleteSelection,
+        "選択範囲を削除",
+    ),
+    ("yank_selection", Action::YankSelection, "選択範囲をコピー"),
+    (
+        "change_selection",
+        Action::ChangeSelection,
+        "選択範囲を削除して挿入",
+    ),
+    ("set_mark", Action::SetMark, "マークを設定"),
+    ("kill_line", Action::KillLine, "行末までキル"),
+    ("kill_region", Action::KillRegion, "リージョンをキル"),
+    ("copy_region", Action::CopyRegion, "リージョンをコピー"),
+    ("yank", Action::Yank, "キルしたテキストを貼り付け"),
+    (
+        "yank_pop",
+        Action::YankPop,
+        "貼り付けをより古いキルに置き換える",
+    ),
+    ("cut_line", Action::CutLine, "行を切り取る"),
+    ("keyboard_quit", Action::KeyboardQuit, "操作を中止"),
+    (
+        "isearch_forward",
+        Action::IsearchForward,
+        "前方インクリメンタル検索",
+    ),
+    (
+        "isearch_backward",
+        Action::IsearchBackward,
+        "後方インクリメンタル検索",
+    ),
+    ("undo", Action::Undo, "元に戻す"),
+    ("redo", Action::Redo, "やり直し"),
+    ("fold_toggle", Action::FoldToggle, "折りたたみを切り替える"),
+    ("fold_open", Action::FoldOpen, "折りたたみを開く"),
+    ("fold_close", Action::FoldClose, "折りたたむ"),
+    (
+        "fold_open_all",
+        Action::FoldOpenAll,
+        "すべての折りたたみを開く",
+    ),
+    ("fold_close_all", Action::FoldCloseAll, "すべて折りたたむ"),
+    ("write", Action::Write, "保存"),
+    ("write_out", Action::WriteOut, "ファイル名を指定して保存"),
+    ("quit", Action::Quit, "終了"),
+    ("write_quit", Action::WriteQuit, "保存して終了"),
+    ("exit", Action::Exit, "終了（変更があれば保存を確認）"),
+    ("replace", Action::Replace, "すべて置換"),
+    ("go_to_line", Action::GoToLine, "指定した行へ移動"),
+    ("next_buffer", Action::NextBuffer, "次のバッファへ"),
+    ("previous_buffer", Action::PreviousBuffer, "前のバッファへ"),
+    ("find_file", Action::FindFile, "ファイルを開く"),
+    ("fuzzy_find", Action::FuzzyFind, "ファイルをあいまい検索"),
+    ("pick_buffer", Action::PickBuffer, "バッファを選ぶ"),
+    ("pick_recent", Action::PickRecent, "最近のファイルを選ぶ"),
+    ("pick_heading", Action::PickHeading, "見出しへ移動"),
+    (
+        "explore",
+        Action::Explore,
+        "ファイルエクスプローラーを開く・閉じる",
+    ),
+    (
+        "command_palette",
+        Action::CommandPalette,
+        "コマンドパレットを開く",
+    ),
+    (
+        "switch_buffer",
+        Action::SwitchBuffer,
+        "バッファを切り替える",
+    ),
+    ("close_buffer", Action::CloseBuffer, "バッファを閉じる"),
+    (
+        "split_horizontal",
+        Action::SplitHorizontal,
+        "ウィンドウを水平分割",
+    ),
+    (
+        "split_vertical",
+        Action::SplitVertical,
+        "ウィンドウを垂直分割",
+    ),
+    ("other_window", Action::OtherWindow, "次のウィンドウへ"),
+];
+
+impl Action {
+    /// Parse an action name (`write`) or an ex command (`:w`, `:w<CR>`).
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim();
+        if let Some(command) = name.strip_prefix(':') {
+            let command = command.strip_suffix("<CR>").unwrap_or(command).trim();
+            if command.is_empty() {
+                return Err(anyhow::anyhow!("コマンドが指定されていません"));
+            }
+            return Ok(Action::Ex(command.to_string()));
+        }
+        NAMED_ACTIONS
+            .iter()
+            .find(|(known, _, _)| *known == name)
+            .map(|(_, action, _)| action.clone())
+            .ok_or_else(|| anyhow::anyhow!("未知のアクションです: {}", name))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Action::Ex(command) => format!(":{}", command),
+            action => NAMED_ACTIONS
+                .iter()
+                .find(|(_, known, _)| known == action)
+                .map_or_else(String::new, |(name, _, _)| name.to_string()),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Action::Ex(command) => format!("コマンド :{} を実行", command),
+            action => NAMED_ACTIONS
+                .iter()
+                .find(|(_, known, _)| known == action)
+                .map_or_else(String::new, |(_, _, description)| description.to_string()),
+        }
+    }
+
+    /// Every action that has a name, in definition order.
+    pub fn all() -> impl Iterator<Item = Action> {
+        NAMED_ACTIONS.iter().map(|(_, action, _)| action.clone())
+    }
+}
+
+/// Result of feeding keys to the keymap, in the order they should run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyStep {
+    Action(Action),
+    /// A key with no binding, left to the mode's default handling.
+    Unmapped(KeyChord),
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    action: Action,
+    user: bool,
+    /// Insertion order, so the first key listed for an action is preferred
+    /// when showing shortcuts.
+    seq: usize,
+}
+
+type Bindings = HashMap<Vec<KeyChord>, Binding>;
+
+/// Key bindings for every mode plus the partially typed key sequence.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    style: KeybindingStyle,
+    modes: HashMap<KeymapMode, Bindings>,
+    timeout: Duration,
+    pending: Vec<KeyChord>,
+    pending_mode: KeymapMode,
+    pending_since: Option<Instant>,
+    next_seq: usize,
+}
+
+const VIM_NORMAL: &[(&str, Action)] = &[
+    ("h", Action::MoveLeft),
+    ("j", Action::MoveDown),
+    ("k", Action::MoveUp),
+    ("l", Action::MoveRight),
+    ("<Left>", Action::MoveLeft),
+    ("<Down>", Action::MoveDown),
+    ("<Up>", Action::MoveUp),
+    ("<Right>", Action::MoveRight),
+    ("<Home>", Action::LineStart),
+    ("<End>", Action::LineEnd),
+    ("<PageUp>", Action::PageUp),
+    ("<PageDown>", Action::PageDown),
+    ("gj", Action::DisplayRowDown),
+    ("gk", Action::DisplayRowUp),
+    ("gg", Action::BufferStart),
+    ("G", Action::BufferEnd),
+    ("za", Action::FoldToggle),
+    ("zo", Action::FoldOpen),
+    ("zc", Action::FoldClose),
+    ("zR", Action::FoldOpenAll),
+    ("zM", Action::FoldCloseAll),
+    ("v", Action::Visual),
+    ("V", Action::VisualLine),
+    ("<C-v>", Action::VisualBlock),
+    ("R", Action::ReplaceMode),
+    ("i", Action::Insert),
+    ("a", Action::Append),
+    ("o", Action::OpenBelow),
+    ("O", Action::OpenAbove),
+    ("x", Action::DeleteChar),
+    ("dd", Action::DeleteLine),
+    ("yy", Action::YankLine),
+    ("p", Action::Paste),
+    ("u", Action::Undo),
+    ("<C-r>", Action::Redo),
+    (":", Action::CommandMode),
+    ("/", Action::Search),
+    ("?", Action::Help),
+    ("<C-p>", Action::FuzzyFind),
+    ("<F2>", Action::CommandPalette),
+    ("<C-x>", Action::Exit),
+];
+
+const VIM_INSERT: &[(&str, Action)] = &[
+    ("<Esc>", Action::NormalMode),
+    ("<CR>", Action::InsertNewline),
+    ("<BS>", Action::DeleteCharBackward),
+    ("<Del>", Action::DeleteChar),
+    ("<Tab>", Action::InsertTab),
+    ("<Left>", Action::MoveLeft),
+    ("<Right>", Action::MoveRight),
+    ("<Up>", Action::MoveUp),
+    ("<Down>", Action::MoveDown),
+    ("<C-x>", Action::Exit),
+];
+
+const VIM_VISUAL: &[(&str, Action)] = &[
+    ("<Esc>", Action::NormalMode),
+    ("h", Action::MoveLeft),
+    ("j", Action::MoveDown),
+    ("k", Action::MoveUp),
+    ("l", Action::MoveRight),
+    ("<Left>", Action::MoveLeft),
+    ("<Down>", Action::MoveDown),
+    ("<Up>", Action::MoveUp),
+    ("<Right>", Action::MoveRight),
+    ("<Home>", Action::LineStart),
+    ("<End>", Action::LineEnd),
+    ("d", Action::DeleteSelection),
+    ("x", Action::DeleteSelection),
+    ("y", Action::YankSelection),
+    ("c", Action::ChangeSelection),
+];
+
+/// Emacs bindings. Emacs is modeless, so these all live in the normal table
+/// and unbound characters are inserted.
+const EMACS: &[(&str, Action)] = &[
+    ("<C-f>", Action::MoveRight),
+    ("<C-b>", Action::MoveLeft),
+    ("<C-n>", Action::MoveDown),
+    ("<C-p>", Action::MoveUp),
+    ("<C-a>", Action::LineStart),
+    ("<C-e>", Action::LineEnd),
+    ("<Left>", Action::MoveLeft),
+    ("<Down>", Action::MoveDown),
+    ("<Up>", Action::MoveUp),
+    ("<Right>", Action::MoveRight),
+    ("<Home>", Action::LineStart),
+    ("<End>", Action::LineEnd),
+    ("<M-f>", Action::WordForward),
+    ("<M-b>", Action::WordBackward),
+    ("<C-v>", Action::PageDown),
+    ("<M-v>", Action::PageUp),
+    ("<PageDown>", Action::PageDown),
+    ("<PageUp>", Action::PageUp),
+    ("<M-lt>", Action::BufferStart),
+    ("<M-gt>", Action::BufferEnd),
+    ("<CR>", Action::InsertNewline),
+    ("<Tab>", Action::InsertTab),
+    ("<BS>", Action::DeleteCharBackward),
+    ("<C-d>", Action::DeleteChar),
+    ("<Del>", Action::DeleteChar),
+    ("<C-k>", Action::KillLine),
+    ("<C-w>", Action::KillRegion),
+    ("<M-w>", Action::CopyRegion),
+    ("<C-y>", Action::Yank),
+    ("<M-y>", Action::YankPop),
+    ("<C-Space>", Action::SetMark),
+    ("<C-@>", Action::SetMark),
+    ("<C-g>", Action::KeyboardQuit),
+    ("<C-s>", Action::IsearchForward),
+    ("<C-r>", Action::IsearchBackward),
+    ("<C-/>", Action::Undo),
+    ("<C-_>", Action::Undo),
+    // Some terminals report C-/ and C-_ as C-7
+    ("<C-7>", Action::Undo),
+    ("<C-x>u", Action::Undo),
+    ("<C-x><C-s>", Action::Write),
+    ("<C-x><C-f>", Action::FindFile),
+    ("<C-x><C-c>", Action::Quit),
+    ("<C-x>b", Action::SwitchBuffer),
+    ("<C-x><C-b>", Action::PickBuffer),
+    ("<C-x>d", Action::Explore),
+    ("<C-x>k", Action::CloseBuffer),
+    ("<C-x>2", Action::SplitHorizontal),
+    ("<C-x>3", Action::SplitVertical),
+    ("<C-x>o", Action::OtherWindow),
+    ("<M-x>", Action::ExecuteCommand),
+    ("<F1>", Action::Help),
+    ("<F2>", Action::CommandPalette),
+];
+
+/// Nano bindings, modeless like Emacs.
+const NANO: &[(&str, Action)] = &[
+    ("<C-g>", Action::Help),
+    ("<F1>", Action::Help),
+    ("<F2>", Action::CommandPalette),
+    ("<C-x>", Action::Exit),
+    ("<C-o>", Action::WriteOut),
+    ("<C-s>", Action::Write),
+    ("<C-r>", Action::FindFile),
+    ("<C-t>", Action::FuzzyFind),
+    ("<C-w>", Action::Search),
+    ("<C-\\>", Action::Replace),
+    // Terminals report C-\ as C-4 and C-_ as C-7
+    ("<C-4>", Action::Replace),
+    ("<C-k>", Action::CutLine),
+    ("<C-u>", Action::Yank),
+    ("<M-a>", Action::SetMark),
+    ("<C-6>", Action::SetMark),
+    ("<M-6>", Action::CopyRegion),
+    ("<M-u>", Action::Undo),
+    ("<M-e>", Action::Redo),
+    ("<C-_>", Action::GoToLine),
+    ("<C-7>", Action::GoToLine),
+    ("<C-f>", Action::MoveRight),
+    ("<C-b>", Action::MoveLeft),
+    ("<C-n>", Action::MoveDown),
+    ("<C-p>", Action::MoveUp),
+    ("<C-a>", Action::LineStart),
+    ("<C-e>", Action::LineEnd),
+    ("<Left>", Action::MoveLeft),
+    ("<Down>", Action::MoveDown),
+    ("<Up>", Action::MoveUp),
+    ("<Right>", Action::MoveRight),
+    ("<Home>", Action::LineStart),
+    ("<End>", Action::LineEnd),
+    ("<C-y>", Action::PageUp),
+    ("<C-v>", Action::PageDown),
+    ("<PageUp>", Action::PageUp),
+    ("<PageDown>", Action::PageDown),
+    ("<M-\\>", Action::BufferStart),
+    ("<M-/>", Action::BufferEnd),
+    ("<M-lt>", Action::PreviousBuffer),
+    ("<M-gt>", Action::NextBuffer),
+    ("<CR>", Action::InsertNewline),
+    ("<Tab>", Action::InsertTab),
+    ("<BS>", Action::DeleteCharBackward),
+    ("<C-d>", Action::DeleteChar),
+    ("<Del>", Action::DeleteChar),
+];
+
+/// Actions shown in the two-line shortcut bar of each style, with labels.
+type ShortcutRows = [&'static [(Action, &'static str)]; 2];
+
+const VIM_SHORTCUTS: ShortcutRows = [
+    &[
+        (Action::CommandMode, "コマンド"),
+        (Action::Insert, "挿入"),
+        (Action::Visual, "選択"),
+        (Action::Search, "検索"),
+        (Action::Undo, "元に戻す"),
+        (Action::Redo, "やり直し"),
+    ],
+    &[
+        (Action::DeleteLine, "行削除"),
+        (Action::YankLine, "行コピー"),
+        (Action::Paste, "貼り付け"),
+        (Action::FoldToggle, "折りたたみ"),
+        (Action::Exit, "終了"),
+        (Action::Help, "ヘルプ"),
+    ],
+];
+
+const EMACS_SHORTCUTS: ShortcutRows = [
+    &[
+        (Action::Write, "保存"),
+        (Action::FindFile, "開く"),
+        (Action::IsearchForward, "検索"),
+        (Action::KillLine, "キル"),
+        (Action::Yank, "ヤンク"),
+        (Action::Undo, "元に戻す"),
+    ],
+    &[
+        (Action::Quit, "終了"),
+        (Action::SwitchBuffer, "バッファ"),
+        (Action::SetMark, "マーク"),
+        (Action::KillRegion, "リージョン"),
+        (Action::ExecuteCommand, "コマンド"),
+        (Action::Help, "ヘルプ"),
+    ],
+];
+
+const NANO_SHORTCUTS: ShortcutRows = [
+    &[
+        (Action::Help, "ヘルプ"),
+        (Action::WriteOut, "書き込み"),
+        (Action::Search, "検索"),
+        (Action::CutLine, "切り取り"),
+        (Action::Undo, "元に戻す"),
+        (Action::SetMark, "マーク"),
+    ],
+    &[
+        (Action::Exit, "終了"),
+        (Action::FindFile, "ファイルを開く"),
+        (Action::Replace, "置換"),
+        (Action::Yank, "貼り付け"),
+        (Action::Redo, "やり直し"),
+        (Action::GoToLine, "行へ移動"),
+    ],
+];
+
+/// One entry of the shortcut bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    /// Key in caret notation, such as `^O` or `M-U`.
+    pub key: String,
+    pub label: &'static str,
+    pub action: Action,
+}
+
+/// Format keys the way nano's shortcut bar does: `^O`, `M-U`, `^X^S`.
+pub fn format_hint(keys: &[KeyChord]) -> String {
+    keys.iter()
+        .map(|chord| {
+            let ctrl = chord.modifiers.contains(KeyModifiers::CONTROL);
+            let alt = chord.modifiers.contains(KeyModifiers::ALT);
+            match chord.code {
+                KeyCode::Char(c) if ctrl || alt => {
+                    let mut hint = String::new();
+                    if alt {
+                        hint.push_str("M-");
+                    }
+                    if ctrl {
+                        hint.push('^');
+                    }
+                    match c {
+                        ' ' => hint.push_str("Space"),
+                        c => hint.push(c.to_ascii_uppercase()),
+                    }
+                    hint
+                }
+                _ => format_chord(chord)
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string(),
+            }
+        })
+        .collect()
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Keymap {
+    /// Vim-style default bindings.
+    pub fn new() -> Self {
+        Self::for_style(KeybindingStyle::Vim)
+    }
+
+    /// Default bindings of a keybinding style.
+    pub fn for_style(style: KeybindingStyle) -> Self {
+        let mut keymap = Self {
+            style,
+            modes: HashMap::new(),
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            pending: Vec::new(),
+            pending_mode: KeymapMode::Normal,
+            pending_since: None,
+            next_seq: 0,
+        };
+        let tables: &[(KeymapMode, &[(&str, Action)])] = match style {
+            KeybindingStyle::Vim => &[
+                (KeymapMode::Normal, VIM_NORMAL),
+                (KeymapMode::Insert, VIM_INSERT),
+                (KeymapMode::Visual, VIM_VISUAL),
+            ],
+            KeybindingStyle::Emacs => &[(KeymapMode::Normal, EMACS)],
+            KeybindingStyle::Nano => &[(KeymapMode::Normal, NANO)],
+        };
+        for &(mode, defaults) in tables {
+            for (keys, action) in defaults {
+                // Default tables are written in valid notation
+                if let Ok(keys) = parse_keys(keys) {
+                    keymap.insert(mode, keys, action.clone(), false);
+                }
+            }
+        }
+        keymap
+    }
+
+    pub fn style(&self) -> KeybindingStyle {
+        self.style
+    }
+
+    /// Modeless styles type unbound characters straight into the buffer and
+    /// resolve every mode through the normal table.
+    pub fn is_modeless(&self) -> bool {
+        self.style != KeybindingStyle::Vim
+    }
+
+    /// Apply user overrides from the config file. Invalid entries are
+    /// skipped and returned as error messages.
+    pub fn apply_config(&mut self, config: &KeymapConfig) -> Vec<String> {
+        self.timeout = Duration::from_millis(config.timeout_ms);
+        let mut errors = Vec::new();
+        for (mode, overrides) in [
+            (KeymapMode::Normal, &config.normal),
+            (KeymapMode::Insert, &config.insert),
+            (KeymapMode::Visual, &config.visual),
+        ] {
+            for (keys, action) in overrides {
+                match parse_keys(keys).and_then(|keys| Ok((keys, Action::parse(action)?))) {
+                    Ok((keys, action)) => self.map(mode, keys, action),
+                    Err(e) => errors.push(format!("{} {}: {}", mode.name(), keys, e)),
+                }
+            }
+        }
+        errors
+    }
+
+    fn insert(&mut self, mode: KeymapMode, keys: Vec<KeyChord>, action: Action, user: bool) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.modes
+            .entry(mode)
+            .or_default()
+            .insert(keys, Binding { action, user, seq });
+    }
+
+    /// Bind `keys` to `action` in `mode`, replacing any existing binding.
+    pub fn map(&mut self, mode: KeymapMode, keys: Vec<KeyChord>, action: Action) {
+        self.insert(mode, keys, action, true);
+    }
+
+    /// Remove the binding for `keys` in `mode`. Returns `false` if there was none.
+    pub fn unmap(&mut self, mode: KeymapMode, keys: &[KeyChord]) -> bool {
+        self.modes
+            .get_mut(&mode)
+            .is_some_and(|bindings| bindings.remove(keys).is_some())
+    }
+
+    pub fn lookup(&self, mode: KeymapMode, keys: &[KeyChord]) -> Option<&Action> {
+        self.modes
+            .get(&mode)
+            .and_then(|bindings| bindings.get(keys))
+            .map(|binding| &binding.action)
+    }
+
+    /// Bindings in `mode` as `(keys, action name)`, sorted by key notation.
+    /// With `user_only`, only mappings added by the user are listed.
+    pub fn bindings(&self, mode: KeymapMode, user_only: bool) -> Vec<(String, String)> {
+        let mut list: Vec<(String, String)> = self
+            .modes
+            .get(&mode)
+            .into_iter()
+            .flatten()
+            .filter(|(_, binding)| binding.user || !user_only)
+            .map(|(keys, binding)| (format_keys(keys), binding.action.name()))
+            .collect();
+        list.sort();
+        list
+    }
+
+    /// Preferred key sequence for `action` in `mode`: user mappings first,
+    /// then the shortest, then the first listed in the default table.
+    pub fn keys_for(&self, mode: KeymapMode, action: &Action) -> Option<Vec<KeyChord>> {
+        self.modes
+            .get(&mode)?
+            .iter()
+            .filter(|(_, binding)| &binding.action == action)
+            .min_by_key(|(keys, binding)| (!binding.user, keys.len(), binding.seq))
+            .map(|(keys, _)| keys.clone())
+    }
+
+    /// Shortcut bar rows for the current style. Actions that lost their
+    /// binding are left out, so the bar follows user remappings.
+    pub fn shortcuts(&self) -> Vec<Vec<Shortcut>> {
+        let rows = match self.style {
+            KeybindingStyle::Vim => VIM_SHORTCUTS,
+            KeybindingStyle::Emacs => EMACS_SHORTCUTS,
+            KeybindingStyle::Nano => NANO_SHORTCUTS,
+        };
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .filter_map(|(action, label)| {
+                        let keys = self.keys_for(KeymapMode::Normal, action)?;
+                        Some(Shortcut {
+                            key: format_hint(&keys),
+                            label,
+                            action: action.clone(),
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Keys typed so far of an unfinished sequence.
+    pub fn pending_keys(&self) -> String {
+        format_keys(&self.pending)
+    }
+
+    fn is_prefix(&self, mode: KeymapMode, keys: &[KeyChord]) -> bool {
+        self.modes.get(&mode).is_some_and(|bindings| {
+            bindings
+                .keys()
+                .any(|bound| bound.len() > keys.len() && bound.starts_with(keys))
+        })
+    }
+
+    /// Feed one key typed in `mode`. Keys that start a longer binding are
+    /// held until the sequence completes, diverges or times out.
+    pub fn feed(&mut self, mode: KeymapMode, key: KeyChord, now: Instant) -> Vec<KeyStep> {
+        let mut steps = Vec::new();
+        if mode != self.pending_mode {
+            self.resolve(true, &mut steps);
+            self.pending_mode = mode;
+        }
+        self.pending.push(key);
+        self.pending_since = Some(now);
+        self.resolve(false, &mut steps);
+        steps
+    }
+
+    /// Resolve a held sequence once the timeout has passed.
+    pub fn check_timeout(&mut self, now: Instant) -> Vec<KeyStep> {
+        let mut steps = Vec::new();
+        if let Some(since) = self.pending_since {
+            if now.duration_since(since) >= self.timeout {
+                self.resolve(true, &mut steps);
+            }
+        }
+        steps
+    }
+
+    /// Resolve a held sequence immediately.
+    pub fn flush(&mut self) -> Vec<KeyStep> {
+        let mut steps = Vec::new();
+        self.resolve(true, &mut steps);
+        steps
+    }
+
+    fn resolve(&mut self, mut force: bool, steps: &mut Vec<KeyStep>) {
+        let mode = self.pending_mode;
+        while !self.pending.is_empty() {
+            if !force && self.is_prefix(mode, &self.pending) {
+                return;
+            }
+            // The longest bound prefix wins; keys after it are resolved again
+            let bound = (1..=self.pending.len())
+                .rev()
+                .find_map(|len| Some((len, self.lookup(mode, &self.pending[..len])?.clone())));
+            match bound {
+                Some((len, action)) => {
+                    steps.push(KeyStep::Action(action));
+                    self.pending.drain(..len);
+                }
+                None => steps.push(KeyStep::Unmapped(self.pending.remove(0))),
+            }
+            force = false;
+        }
+        self.pending_since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_str(keymap: &mut Keymap, mode: KeymapMode, keys: &str) -> Vec<KeyStep> {
+        let now = Instant::now();
+        parse_keys(keys)
+            .unwrap()
+            .into_iter()
+            .flat_map(|key| keymap.feed(mode, key, now))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_and_format_keys() {
+        let keys = parse_keys("<C-s>").unwrap();
+        assert_eq!(
+            keys,
+            vec![KeyChord::new(KeyCode::Char('s'), KeyModifiers::CONTROL)]
+        );
+        assert_eq!(parse_keys("<C-X><c-S>").unwrap().len(), 2);
+        assert_eq!(parse_keys("<M-x>").unwrap()[0].modifiers, KeyModifiers::ALT);
+        assert_eq!(parse_keys("<S-Tab>").unwrap()[0].code, KeyCode::BackTab);
+        assert_eq!(parse_keys("<F5>").unwrap()[0].code, KeyCode::F(5));
+        // An unknown name is taken literally
+        assert_eq!(parse_keys("<foo>").unwrap().len(), 5);
+        assert!(parse_keys("").is_err());
+
+        for notation in [
+            "dd",
+            "<C-s>",
+            "<M-x>",
+            "<CR>",
+            "<lt>a",
+            "<Space>",
+            "<C-x><C-f>",
+        ] {
+            assert_eq!(format_keys(&parse_keys(notation).unwrap()), notation);
+        }
+    }
+
+    #[test]
+    fn test_action_names() {
+        assert_eq!(Action::parse("write").unwrap(), Action::Write);
+        assert_eq!(
+            Action::parse(":w<CR>").unwrap(),
+            Action::Ex("w".to_string())
+        );
+        assert!(Action::parse("no_such_action").is_err());
+        for (name, action, description) in NAMED_ACTIONS {
+            assert_eq!(&action.name(), name);
+            assert_eq!(&action.description(), description);
+        }
+    }
+
+    #[test]
+    fn test_multi_key_sequences() {
+        let mut keymap = Keymap::new();
+        assert_eq!(
+            feed_str(&mut keymap, KeymapMode::Normal, "j"),
+            vec![KeyStep::Action(Action::MoveDown)]
+        );
+
+        // `d` waits for the rest of `dd`
+        assert!(feed_str(&mut keymap, KeymapMode::Normal, "d").is_empty());
+        assert_eq!(keymap.pending_keys(), "d");
+        assert_eq!(
+            feed_str(&mut keymap, KeymapMode::Normal, "d"),
+            vec![KeyStep::Action(Action::DeleteLine)]
+        );
+
+        // A diverging key releases the held one unmapped and runs its own binding
+        assert_eq!(
+            feed_str(&mut keymap, KeymapMode::Normal, "zj"),
+            vec![
+                KeyStep::Unmapped(KeyChord::plain('z')),
+                KeyStep::Action(Action::MoveDown),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timeout_and_user_overrides() {
+        let mut config = KeymapConfig::default();
+        config
+            .normal
+            .insert("<C-s>".to_string(), "write".to_string());
+        config.normal.insert("g".to_string(), ":bn".to_string());
+        config
+            .insert
+            .insert("jk".to_string(), "normal_mode".to_string());
+        config
+            .insert
+            .insert("<C-q>".to_string(), "bogus".to_string());
+
+        let mut keymap = Keymap::new();
+        let errors = keymap.apply_config(&config);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            keymap.bindings(KeymapMode::Normal, true),
+            vec![
+                ("<C-s>".to_string(), "write".to_string()),
+                ("g".to_string(), ":bn".to_string()),
+            ]
+        );
+
+        // `g` is bound and also starts `gj`: it runs once the timeout passes
+        let start = Instant::now();
+        assert!(keymap
+            .feed(KeymapMode::Normal, KeyChord::plain('g'), start)
+            .is_empty());
+        assert!(keymap.check_timeout(start).is_empty());
+        assert_eq!(
+            keymap.check_timeout(start + Duration::from_millis(DEFAULT_TIMEOUT_MS)),
+            vec![KeyStep::Action(Action::Ex("bn".to_string()))]
+        );
+
+        // A timed-out `j` in insert mode is typed as-is
+        assert!(feed_str(&mut keymap, KeymapMode::Insert, "j").is_empty());
+        assert_eq!(
+            keymap.flush(),
+            vec![KeyStep::Unmapped(KeyChord::plain('j'))]
+        );
+        assert_eq!(
+            feed_str(&mut keymap, KeymapMode::Insert, "jk"),
+            vec![KeyStep::Action(Action::NormalMode)]
+        );
+
+        assert!(keymap.unmap(KeymapMode::Insert, &parse_keys("jk").unwrap()));
+        assert!(!keymap.unmap(KeymapMode::Insert, &parse_keys("jk").unwrap()));
+    }
+
+    #[test]
+    fn test_nano_bindings_and_shortcuts() {
+        let mut keymap = Keymap::for_style(KeybindingStyle::Nano);
+        assert!(keymap.is_modeless());
+        assert_eq!(
+            feed_str(&mut keymap, KeymapMode::Normal, "<C-o><M-u><C-\\>"),
+            vec![
+                KeyStep::Action(Action::WriteOut),
+                KeyStep::Action(Action::Undo),
+                KeyStep::Action(Action::Replace),
+            ]
+        );
+
+        let shortcuts = keymap.shortcuts();
+        assert_eq!(shortcuts.len(), 2);
+        assert_eq!(shortcuts[0][1].key, "^O");
+        assert_eq!(shortcuts[0][4].key, "M-U");
+        assert_eq!(shortcuts[1][5].key, "^_");
+
+        // The bar follows remappings and drops unbound actions
+        keymap.map(
+            KeymapMode::Normal,
+            parse_keys("<F3>").unwrap(),
+            Action::WriteOut,
+        );
+        keymap.unmap(KeymapMode::Normal, &parse_keys("<C-g>").unwrap());
+        keymap.unmap(KeymapMode::Normal, &parse_keys("<F1>").unwrap());
+        let shortcuts = keymap.shortcuts();
+        assert_eq!(shortcuts[0][0].key, "F3");
+        assert_eq!(format_hint(&parse_keys("<C-x><C-s>").unwrap()), "^X^S");
+    }
+}
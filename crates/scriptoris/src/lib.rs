@@ -4,23 +4,67 @@
 //! アプリケーション状態、入出力制御、セッション管理などを提供します。
 //! 各モジュールの概要:
 //! - `app`: アプリケーション全体の状態管理と UI との橋渡し。
+//! - `autosave`: 一定間隔・アイドル時・フォーカス喪失時・バッファ切り替え時の自動保存。
+//! - `cli`: コマンドライン引数（`+N`・`-o`・`-R`・`--config` など）の解析。
 //! - `command_processor`: `:` コマンドのパーサと実行。
+//! - `completion`: コマンドラインの文脈に応じた補完とワイルドメニュー。
 //! - `config`: 設定ファイルの読み書きと型定義。
 //! - `editor`: Rope ベースのテキスト編集エンジン。
+//! - `emacs`: Emacs キーバインド用のキルリングとインクリメンタルサーチ。
+//! - `explorer`: netrw 風のファイルエクスプローラー（サイドバー）。
+//! - `file_format`: ファイルの文字コード（Shift_JIS・EUC-JP など）の判定と変換。
+//! - `file_watch`: ディスク上のファイルの変更検知と差分表示。
+//! - `folding`: 見出し・コードブロック・リストの折りたたみ。
+//! - `gitignore`: `.gitignore` の照合とプロジェクト内のファイル走査。
+//! - `gutter`: 行番号とサインカラム（診断・Git の変更・マーク）。
+//! - `headless`: 端末を使わずに ex コマンドを実行するヘッドレスモード（`-es`）。
+//! - `keymap`: キー列から名前付きアクションへの変換とユーザー定義マッピング。
+//! - `listchars`: タブ・行末の空白・全角スペースなど不可視文字の表示。
+//! - `mouse`: マウス入力のヒットテストとクリック状態。
+//! - `options`: `:set` / `:setlocal` で扱うオプションの登録表。
+//! - `overlay`: ファジーファインダーなどのポップアップ描画。
+//! - `pager`: `:messages` や複数行の結果を表示するページャー。
+//! - `picker`: ファイル・バッファ・見出しのファジーファインダー。
+//! - `recent_files`: 最近開いたファイルの履歴。
 //! - `session_manager`: セッションの保存・復元ユーティリティ。
+//! - `statusline`: 設定で並びと色を変えられるステータスラインのセグメント。
+//! - `swap`: 保存されていない変更を書き出すスワップファイルとクラッシュからの復旧。
 //! - `status_manager` / `ui_state`: ステータスバーやモード遷移の状態管理。
+//! - `view`: ソフトラップの表示行レイアウトと禁則処理。
 
 pub mod app;
+pub mod autosave;
+pub mod cli;
 pub mod command_processor;
+pub mod completion;
 pub mod config;
 pub mod editor;
+pub mod emacs;
 pub mod enhanced_ui;
+pub mod explorer;
+pub mod file_format;
 pub mod file_manager;
+pub mod file_watch;
+pub mod folding;
+pub mod gitignore;
+pub mod gutter;
+pub mod headless;
 pub mod highlight;
+pub mod keymap;
+pub mod listchars;
+pub mod mouse;
+pub mod options;
+pub mod overlay;
+pub mod pager;
+pub mod picker;
+pub mod recent_files;
 pub mod session_manager;
 pub mod status_manager;
+pub mod statusline;
+pub mod swap;
 pub mod text_width;
 pub mod ui_state;
+pub mod view;
 
 pub use app::{App, BufferManager, Mode, Plugin, PluginManager, WindowManager};
 pub use config::Config;
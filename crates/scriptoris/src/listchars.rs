@@ -0,0 +1,209 @@
+//! 不可視文字の表示（`:set list`）。
+//!
+//! タブ・行末の空白・ノーブレークスペース・全角スペース（U+3000）・行末を
+//! `listchars` で指定した記号に置き換えて描画します。置き換えた記号は元の
+//! 文字と同じ表示幅を占めるので、カーソル位置の計算はそのまま使えます。
+
+use ratatui::{
+    style::{Color, Style},
+    text::Span,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+use crate::config::{parse_hex_color, Config};
+use crate::options::EditorOptions;
+use crate::text_width::TextWidthCalculator;
+
+/// Glyphs used when `listchars` is not configured.
+pub const DEFAULT_LISTCHARS: &str = "tab:→ ,trail:·,nbsp:␣,ideosp:□,eol:¬";
+
+const IDEOGRAPHIC_SPACE: char = '\u{3000}';
+
+/// Glyphs parsed from a `listchars` value such as `tab:→ ,trail:·,eol:¬`.
+/// Characters without a glyph are drawn as usual.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListChars {
+    /// First column of a tab, then the glyph filling the rest of it.
+    pub tab: Option<(char, char)>,
+    pub trail: Option<char>,
+    pub nbsp: Option<char>,
+    pub ideosp: Option<char>,
+    pub eol: Option<char>,
+}
+
+impl ListChars {
+    /// Parse a comma-separated `name:glyph` list. Every glyph must be a
+    /// single-column character; `tab` takes two of them.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut chars = Self::default();
+        for item in spec.split(',').filter(|item| !item.is_empty()) {
+            let (name, value) = item.split_once(':')?;
+            let glyphs: Vec<char> = value.chars().collect();
+            if glyphs.iter().any(|c| c.width() != Some(1)) {
+                return None;
+            }
+            match (name, glyphs.as_slice()) {
+                ("tab", &[first, fill]) => chars.tab = Some((first, fill)),
+                ("trail", &[glyph]) => chars.trail = Some(glyph),
+                ("nbsp", &[glyph]) => chars.nbsp = Some(glyph),
+                ("ideosp", &[glyph]) => chars.ideosp = Some(glyph),
+                ("eol", &[glyph]) => chars.eol = Some(glyph),
+                _ => return None,
+            }
+        }
+        Some(chars)
+    }
+}
+
+/// How whitespace is drawn. Tabs reach the next multiple of `tab_width`,
+/// matching the editor's width calculation; with `list` set, invisible
+/// characters are replaced by glyphs drawn in `style`.
+#[derive(Debug, Clone)]
+pub struct Whitespace {
+    pub list: Option<ListChars>,
+    pub tab_width: usize,
+    pub style: Style,
+}
+
+impl Whitespace {
+    /// Whitespace drawing for a buffer with the resolved `options`, in the
+    /// theme's `whitespace_color` (dark gray by default).
+    pub fn new(config: &Config, options: &EditorOptions) -> Self {
+        let color = config
+            .theme
+            .whitespace_color
+            .as_deref()
+            .and_then(parse_hex_color)
+            .unwrap_or(Color::DarkGray);
+        Self {
+            list: options
+                .list
+                .then(|| ListChars::parse(&config.editor.listchars).unwrap_or_default()),
+            tab_width: options.tabstop,
+            style: Style::default().fg(color),
+        }
+    }
+
+    /// The width calculation the tabs are drawn with.
+    pub fn calculator(&self) -> TextWidthCalculator {
+        TextWidthCalculator::new().tab_width(self.tab_width)
+    }
+
+    /// Redraw the whitespace in the spans of one screen row. `offset` and
+    /// `col` are the character offset and display column of the row within
+    /// its line, `trail_start` the offset where the line's trailing spaces
+    /// begin, and `line_end` whether the row ends the line.
+    pub fn render(
+        &self,
+        spans: Vec<Span<'static>>,
+        offset: usize,
+        col: usize,
+        trail_start: usize,
+        line_end: bool,
+    ) -> Vec<Span<'static>> {
+        let calculator = self.calculator();
+        let mut out = Vec::new();
+        let mut index = offset;
+        let mut col = col;
+        for span in spans {
+            let mut text = String::new();
+            for grapheme in span.content.graphemes(true) {
+                let width = calculator.grapheme_width_at(grapheme, col);
+                let mut chars = grapheme.chars();
+                let c = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => '\0',
+                };
+                match self.glyph(c, width, index >= trail_start) {
+                    Some(glyph) => {
+                        if !text.is_empty() {
+                            out.push(Span::styled(std::mem::take(&mut text), span.style));
+                        }
+                        out.push(Span::styled(glyph, span.style.patch(self.style)));
+                    }
+                    None if c == '\t' => text.push_str(&" ".repeat(width)),
+                    None => text.push_str(grapheme),
+                }
+                index += grapheme.chars().count();
+                col += width;
+            }
+            if !text.is_empty() {
+                out.push(Span::styled(text, span.style));
+            }
+        }
+        let eol = self.list.as_ref().and_then(|list| list.eol);
+        if let (true, Some(glyph)) = (line_end, eol) {
+            out.push(Span::styled(glyph.to_string(), self.style));
+        }
+        out
+    }
+
+    /// The glyph drawn for `c` with `list`, as wide as `c` itself (`width`
+    /// columns for a tab).
+    fn glyph(&self, c: char, width: usize, trailing: bool) -> Option<String> {
+        let list = self.list.as_ref()?;
+        let glyph = match c {
+            '\t' => {
+                let (first, fill) = list.tab?;
+                let fill = fill.to_string().repeat(width.saturating_sub(1));
+                return Some(format!("{}{}", first, fill));
+            }
+            ' ' if trailing => list.trail?,
+            '\u{A0}' | '\u{202F}' => list.nbsp?,
+            // Full-width, so the glyph is padded to two columns
+            IDEOGRAPHIC_SPACE => return Some(format!("{} ", list.ideosp?)),
+            _ => return None,
+        };
+        Some(glyph.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(spans: &[Span]) -> String {
+        spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_parse_listchars() {
+        let chars = ListChars::parse(DEFAULT_LISTCHARS).unwrap();
+        assert_eq!(chars.tab, Some(('→', ' ')));
+        assert_eq!(chars.eol, Some('¬'));
+        assert_eq!(ListChars::parse(""), Some(ListChars::default()));
+        assert_eq!(ListChars::parse("tab:>"), None);
+        assert_eq!(ListChars::parse("trail:全"), None);
+        assert_eq!(ListChars::parse("space:x"), None);
+    }
+
+    #[test]
+    fn test_render_keeps_display_width() {
+        let mut whitespace = Whitespace {
+            list: None,
+            tab_width: 4,
+            style: Style::default().fg(Color::DarkGray),
+        };
+        let row = vec![Span::raw("\ta\u{3000}b  ")];
+        assert_eq!(
+            text(&whitespace.render(row.clone(), 0, 0, 4, true)),
+            "    a\u{3000}b  "
+        );
+
+        whitespace.list = ListChars::parse(DEFAULT_LISTCHARS);
+        let spans = whitespace.render(row.clone(), 0, 0, 4, true);
+        assert_eq!(text(&spans), "→   a□ b··¬");
+        assert_eq!(spans[0].style, whitespace.style);
+        // Only spaces after the last non-blank are trailing; a wrapped row
+        // that does not end the line gets no eol glyph
+        assert_eq!(text(&whitespace.render(row, 0, 0, 5, false)), "→   a□ b ·");
+
+        // A tab after text only reaches the next tabstop, also on a row that
+        // starts in the middle of the line
+        let row = vec![Span::raw("ab\tc\t")];
+        assert_eq!(text(&whitespace.render(row, 0, 0, 9, false)), "ab→ c→  ");
+        let row = vec![Span::raw("\tc")];
+        assert_eq!(text(&whitespace.render(row, 3, 3, 9, false)), "→c");
+    }
+}
@@ -1,22 +1,45 @@
 mod app;
+mod autosave;
+mod cli;
 mod command_processor;
+mod completion;
 mod config;
 mod editor;
+mod emacs;
 mod enhanced_ui;
+mod explorer;
+mod file_format;
 mod file_manager;
+mod file_watch;
+mod folding;
+mod gitignore;
+mod gutter;
+mod headless;
 mod highlight;
+mod keymap;
+mod listchars;
+mod mouse;
+mod options;
+mod overlay;
+mod pager;
+mod picker;
+mod recent_files;
 mod session_manager;
 mod status_manager;
+mod statusline;
+mod swap;
 mod text_width;
 mod ui;
 mod ui_state;
+mod view;
 
 use crate::app::{App, Mode};
 use anyhow::Result;
 use crossterm::{
     cursor,
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
@@ -26,16 +49,35 @@ use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 use std::{env, io, panic, time::Duration};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logger with debug fallback for development
+    // Parse command line arguments
+    let args = match cli::Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("詳しくは scriptoris --help を参照してください");
+            std::process::exit(2);
+        }
+    };
+
+    // Initialize logger with debug fallback for development. `--cat` only
+    // reports problems so its output stays clean in scripts, and `-es`
+    // prints its own errors, which the log would repeat
     let mut logger = env_logger::Builder::from_default_env();
     if env::var_os("RUST_LOG").is_none() {
-        logger.filter_level(LevelFilter::Info);
-        logger.filter_module("scriptoris", LevelFilter::Debug);
+        if args.headless {
+            logger.filter_level(LevelFilter::Off);
+        } else if args.print {
+            logger.filter_level(LevelFilter::Warn);
+        } else {
+            logger.filter_level(LevelFilter::Info);
+            logger.filter_module("scriptoris", LevelFilter::Debug);
+        }
     }
     logger.init();
 
@@ -46,13 +88,81 @@ async fn main() -> Result<()> {
         original_hook(panic_info);
     }));
 
+    if args.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if args.version {
+        println!("{}", cli::version());
+        return Ok(());
+    }
+    if let Some(path) = &args.config {
+        config::Config::use_path(path.clone());
+    }
+
+    // `--cat` prints the files instead of starting the editor
+    if args.print {
+        if let Err(e) = print_files(&args).await {
+            // A reader such as `head` closing the pipe early is not an error
+            let broken_pipe = e
+                .downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe);
+            if !broken_pipe {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if args.reads_stdin() && io::stdin().is_terminal() {
+        eprintln!("標準入力が端末です。some-cmd | scriptoris - のようにパイプで渡してください");
+        std::process::exit(2);
+    }
+
+    // `-es` runs the commands without a terminal and exits with their status
+    if args.headless {
+        let mut app = App::new().await?;
+        let code = headless::run(&mut app, &args).await;
+        std::process::exit(code);
+    }
+
+    // `--recover` without a file lists the swap files left behind
+    if args.recover && args.files.is_empty() {
+        let swaps = swap::SwapStore::open()
+            .map(|store| store.list())
+            .unwrap_or_default();
+        if swaps.is_empty() {
+            println!("スワップファイルは見つかりません");
+        }
+        for swap in swaps {
+            let state = if swap.is_running() {
+                "編集中"
+            } else {
+                "終了済み"
+            };
+            println!(
+                "{}  (PID {} {}, {})",
+                swap.file.display(),
+                swap.pid,
+                state,
+                swap.saved_at.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        return Ok(());
+    }
+
     // Setup terminal
     if let Err(e) = enable_raw_mode() {
         eprintln!("ターミナルの初期化に失敗しました: {}", e);
         return Err(e.into());
     }
     let mut stdout = io::stdout();
-    if let Err(e) = execute!(stdout, EnterAlternateScreen, EnableMouseCapture) {
+    if let Err(e) = execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange
+    ) {
         let _ = disable_raw_mode();
         eprintln!("ターミナルの設定に失敗しました: {}", e);
         return Err(e.into());
@@ -60,8 +170,6 @@ async fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
     let mut app = match App::new().await {
         Ok(app) => {
             log::info!("Application initialized successfully");
@@ -79,42 +187,15 @@ async fn main() -> Result<()> {
         }
     };
 
-    // Load file from command line if provided
-    if args.len() > 1 {
-        let file_path = PathBuf::from(&args[1]);
-
-        // Validate file path arguments
-        match app.file_manager.open_file(file_path.clone()).await {
-            Ok(content) => {
-                let content_str = content.clone();
-                app.get_current_editor_mut().set_content(content);
-
-                // Notify LSP plugin of document opening
-                #[cfg(feature = "lsp")]
-                app.notify_lsp_document_opened(&file_path, &content_str)
-                    .await;
-
-                app.ui_state
-                    .set_info_message(format!("ファイルを読み込みました: {}", args[1]));
-                log::info!("Successfully loaded file from command line: {}", args[1]);
-            }
-            Err(e) => {
-                let error_msg = format!("ファイル読み込みエラー: {}", e);
-                app.ui_state.set_error_message(error_msg.clone());
-                log::error!("Failed to load file '{}': {}", args[1], e);
-
-                // If it's a critical error (not file not found), show it prominently
-                if !e.to_string().contains("見つかりません") {
-                    app.ui_state.set_warning_message(format!(
-                        "続行しますが、ファイル '{}' は読み込まれませんでした",
-                        args[1]
-                    ));
-                }
-            }
-        }
-    } else {
+    // Open the files and directories given on the command line
+    if args.files.is_empty() && args.session.is_none() {
         log::info!("No file specified, starting with empty buffer");
     }
+    app.open_command_line(&args).await;
+    for command in &args.commands {
+        // Failures show up in the status bar like typed commands
+        let _ = app.run_ex_command(command.trim_start_matches(':')).await;
+    }
 
     let res = run_app(&mut terminal, app).await;
 
@@ -147,6 +228,33 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// `--cat`: write the files, or standard input when none are given, to
+/// stdout with the editor's syntax highlighting.
+async fn print_files(args: &cli::Args) -> Result<()> {
+    let config = config::Config::load().await?;
+    let highlighter = highlight::Highlighter::new(&config.theme.syntax_theme);
+    let stdin = [PathBuf::from("-")];
+    let files = if args.files.is_empty() {
+        &stdin[..]
+    } else {
+        &args.files[..]
+    };
+    let mut stdout = io::stdout().lock();
+    for path in files {
+        let (text, name) = if cli::is_stdin(path) {
+            (file_format::read_stdin()?.0, "stdin.md".to_string())
+        } else {
+            let (text, _) = file_format::read_file(path, None)
+                .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+            (text, path.to_string_lossy().to_string())
+        };
+        let syntax = highlighter.find_syntax_for_filename(&name);
+        stdout.write_all(highlighter.highlight_to_ansi(&text, syntax).as_bytes())?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
 async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<()> {
     // Main application loop with safe error handling
     loop {
@@ -162,6 +270,19 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
         // Update status messages
         app.update_status();
 
+        // Mouse capture can be turned off so the terminal keeps native
+        // selection, from the config or with `:set nomouse`
+        if let Some(capture) = app.take_mouse_capture_change() {
+            let result = if capture {
+                execute!(io::stdout(), EnableMouseCapture)
+            } else {
+                execute!(io::stdout(), DisableMouseCapture)
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to change mouse capture: {}", e);
+            }
+        }
+
         // Check if app should quit
         if app.should_quit() {
             log::info!("Application shutdown requested");
@@ -178,16 +299,40 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                             .set_error_message(format!("キー処理エラー: {}", e));
                     }
                 }
+                Event::FocusLost => app.focus_lost(),
                 Event::Resize(_, _) => {
                     log::info!("Terminal resized");
                     // Handle resize implicitly through next draw
                 }
-                Event::Mouse(_) => {
-                    // Ignore mouse events for now
+                Event::Mouse(mouse) => {
+                    if let Err(e) = app.handle_mouse_event(mouse) {
+                        log::error!("Mouse event handling error: {}", e);
+                        app.ui_state
+                            .set_error_message(format!("マウス処理エラー: {}", e));
+                    }
                 }
                 _ => {}
             }
         }
+
+        app.finish_file_loads(false).await;
+        app.refresh_diagnostics().await;
+        app.update_swap_files().await;
+        app.check_autosave().await;
+        app.check_external_changes();
+
+        // Resolve a multi-key sequence whose timeout has passed
+        if let Err(e) = app.check_key_timeout().await {
+            log::error!("Key timeout handling error: {}", e);
+            app.ui_state
+                .set_error_message(format!("キー処理エラー: {}", e));
+        }
+    }
+
+    app.remove_swap_files().await;
+
+    if let Err(e) = app.recent_files.save().await {
+        log::warn!("Failed to save recent files: {}", e);
     }
 
     log::info!("Application loop ended successfully");
@@ -201,23 +346,12 @@ async fn handle_key_event_safe(key: KeyEvent, app: &mut App) -> Result<()> {
         if app.is_modified() {
             // Prompt to save before exiting
             app.ui_state
-                .set_warning_message("Save changes before exit? (y/n/c): ".to_string());
+                .set_prompt_message("Save changes before exit? (y/n/c): ".to_string());
             app.set_mode(Mode::SavePrompt);
         } else {
             app.quit();
         }
         return Ok(());
-    } else if key.code == KeyCode::Char('x') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        log::info!("Nano-style exit requested via Ctrl+X");
-        // Ctrl+X for nano-like users - redirect to vim :q
-        if app.is_modified() {
-            app.ui_state.set_info_message(
-                "Save changes? (:wq to save and quit, :q! to quit without saving)".to_string(),
-            );
-        } else {
-            app.quit();
-        }
-        return Ok(());
     }
 
     // Regular key event handling
@@ -228,7 +362,12 @@ async fn handle_key_event_safe(key: KeyEvent, app: &mut App) -> Result<()> {
 fn restore_terminal() -> Result<()> {
     disable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, LeaveAlternateScreen, DisableMouseCapture)?;
+    execute!(
+        stdout,
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableFocusChange
+    )?;
     // Attempt to show cursor, but don't fail if it errors
     let _ = execute!(stdout, cursor::Show);
     Ok(())
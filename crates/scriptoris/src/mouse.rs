@@ -0,0 +1,200 @@
+//! マウス入力のためのヒットテストとクリック状態の管理。
+//!
+//! UI は描画のたびにペインやクリック可能な項目の位置を `ScreenLayout` に
+//! 記録し、`App` はその情報を使って画面座標をバッファ位置に変換します。
+
+use ratatui::layout::Rect;
+use std::time::{Duration, Instant};
+
+/// Maximum delay between two clicks to count as a double-click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Number of lines scrolled per wheel notch.
+pub const WHEEL_SCROLL_LINES: isize = 3;
+
+/// Actions triggered by clicking an item in the status or tab line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickAction {
+    EnterCommandMode,
+    EnterInsertMode,
+    StartSearch,
+    ToggleHelp,
+    /// Entry of the keymap shortcut bar, indexed row by row.
+    Shortcut(usize),
+    /// Row of the file explorer sidebar.
+    ExplorerEntry(usize),
+    SwitchBuffer(usize),
+}
+
+/// Screen region of a rendered editor pane.
+#[derive(Debug, Clone, Copy)]
+pub struct PaneRegion {
+    pub window_id: usize,
+    pub buffer_id: usize,
+    /// Area holding the text itself, without borders or the gutter.
+    pub text_area: Rect,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClickTarget {
+    pub area: Rect,
+    pub action: ClickAction,
+}
+
+/// Positions recorded during the last draw.
+#[derive(Debug, Clone, Default)]
+pub struct ScreenLayout {
+    panes: Vec<PaneRegion>,
+    targets: Vec<ClickTarget>,
+}
+
+impl ScreenLayout {
+    pub fn clear(&mut self) {
+        self.panes.clear();
+        self.targets.clear();
+    }
+
+    pub fn push_pane(&mut self, pane: PaneRegion) {
+        self.panes.push(pane);
+    }
+
+    /// Register click targets for a row of items laid out left to right.
+    /// Each item is given as its display width and an optional action.
+    pub fn push_row_targets(&mut self, area: Rect, items: &[(usize, Option<ClickAction>)]) {
+        let mut x = area.x as usize;
+        let right = (area.x + area.width) as usize;
+        for (width, action) in items {
+            if x >= right {
+                break;
+            }
+            let width = (*width).min(right - x);
+            if let Some(action) = action {
+                self.targets.push(ClickTarget {
+                    area: Rect::new(x as u16, area.y, width as u16, 1),
+                    action: *action,
+                });
+            }
+            x += width;
+        }
+    }
+
+    pub fn pane_at(&self, column: u16, row: u16) -> Option<&PaneRegion> {
+        self.panes
+            .iter()
+            .find(|pane| contains(pane.text_area, column, row))
+    }
+
+    pub fn pane_for_window(&self, window_id: usize) -> Option<&PaneRegion> {
+        self.panes.iter().find(|pane| pane.window_id == window_id)
+    }
+
+    pub fn target_at(&self, column: u16, row: u16) -> Option<ClickAction> {
+        self.targets
+            .iter()
+            .find(|target| contains(target.area, column, row))
+            .map(|target| target.action)
+    }
+}
+
+impl PaneRegion {
+    /// Convert a screen position to a (row, display column) pair relative to
+    /// the pane, clamping positions outside the text area to its edges, or
+    /// `None` for a pane squeezed to no rows.
+    /// The editor maps the row to a buffer line, taking wrapping into account.
+    pub fn screen_position(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        let area = self.text_area;
+        if area.height == 0 {
+            return None;
+        }
+        let row = row.clamp(area.y, area.y + area.height - 1);
+        let display_col = column.saturating_sub(area.x) as usize;
+        Some(((row - area.y) as usize, display_col))
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
+
+/// Click bookkeeping used to detect double-clicks and drags.
+#[derive(Debug, Clone, Default)]
+pub struct MouseState {
+    last_click: Option<(Instant, u16, u16)>,
+    /// Window in which the current left-button drag started.
+    pub drag_window: Option<usize>,
+}
+
+impl MouseState {
+    /// Record a left click and return `true` if it completes a double-click.
+    pub fn register_click(&mut self, column: u16, row: u16) -> bool {
+        let now = Instant::now();
+        let is_double = matches!(
+            self.last_click,
+            Some((at, c, r)) if c == column && r == row && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL
+        );
+        // A third click starts a new sequence instead of chaining.
+        self.last_click = if is_double {
+            None
+        } else {
+            Some((now, column, row))
+        };
+        is_double
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pane_hit_testing() {
+        let mut layout = ScreenLayout::default();
+        layout.push_pane(PaneRegion {
+            window_id: 0,
+            buffer_id: 0,
+            text_area: Rect::new(6, 1, 40, 10),
+        });
+
+        assert!(layout.pane_at(5, 1).is_none());
+        let pane = layout.pane_at(10, 3).expect("pane should be hit");
+        assert_eq!(pane.screen_position(10, 3), Some((2, 4)));
+        // Rows below the pane are clamped to its last row
+        assert_eq!(pane.screen_position(10, 30), Some((9, 4)));
+
+        // A split in a tiny terminal may leave a pane without rows
+        let squeezed = PaneRegion {
+            window_id: 1,
+            buffer_id: 0,
+            text_area: Rect::new(6, 12, 40, 0),
+        };
+        assert_eq!(squeezed.screen_position(10, 3), None);
+        assert_eq!(squeezed.screen_position(10, 12), None);
+    }
+
+    #[test]
+    fn test_row_targets() {
+        let mut layout = ScreenLayout::default();
+        layout.push_row_targets(
+            Rect::new(0, 5, 20, 1),
+            &[
+                (1, Some(ClickAction::EnterCommandMode)),
+                (6, None),
+                (1, Some(ClickAction::EnterInsertMode)),
+            ],
+        );
+
+        assert_eq!(layout.target_at(0, 5), Some(ClickAction::EnterCommandMode));
+        assert_eq!(layout.target_at(3, 5), None);
+        assert_eq!(layout.target_at(7, 5), Some(ClickAction::EnterInsertMode));
+        assert_eq!(layout.target_at(7, 6), None);
+    }
+
+    #[test]
+    fn test_double_click_detection() {
+        let mut state = MouseState::default();
+        assert!(!state.register_click(3, 4));
+        assert!(state.register_click(3, 4));
+        assert!(!state.register_click(3, 4));
+        assert!(!state.register_click(5, 4));
+    }
+}
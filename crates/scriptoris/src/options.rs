@@ -0,0 +1,645 @@
+//! `:set` / `:setlocal` で扱うオプションの登録表。
+//!
+//! グローバル値は `Config`（`EditorConfig` と `Theme`）に保存され、
+//! バッファローカルな値は各バッファの `LocalOptions` に上書きとして持ちます。
+//! 描画や編集は両者を合わせた `EditorOptions` を参照します。
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::config::{Config, KeybindingStyle};
+use crate::file_format::{Encoding, FileFormat, LineEnding};
+use crate::listchars::ListChars;
+
+/// Type of an option's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionType {
+    Bool,
+    Number,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    Bool(bool),
+    Number(usize),
+    Text(String),
+}
+
+impl OptionValue {
+    pub fn as_bool(&self) -> bool {
+        matches!(self, OptionValue::Bool(true))
+    }
+
+    pub fn as_number(&self) -> usize {
+        match self {
+            OptionValue::Number(n) => *n,
+            _ => 0,
+        }
+    }
+
+    pub fn as_text(&self) -> &str {
+        match self {
+            OptionValue::Text(text) => text,
+            _ => "",
+        }
+    }
+}
+
+/// A registered option.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub kind: OptionType,
+    /// Whether `:setlocal` can give the option a per-buffer value.
+    pub local: bool,
+    pub help: &'static str,
+}
+
+impl OptionSpec {
+    /// `name=value`, or `name` / `noname` for booleans, as `:set` shows it.
+    pub fn display(&self, value: &OptionValue) -> String {
+        match value {
+            OptionValue::Bool(true) => self.name.to_string(),
+            OptionValue::Bool(false) => format!("no{}", self.name),
+            value => format!("{}={}", self.name, value),
+        }
+    }
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionValue::Bool(value) => write!(f, "{}", value),
+            OptionValue::Number(value) => write!(f, "{}", value),
+            OptionValue::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Every option accepted by `:set`, in display order.
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "number",
+        aliases: &["nu"],
+        kind: OptionType::Bool,
+        local: true,
+        help: "行番号を表示する",
+    },
+    OptionSpec {
+        name: "relativenumber",
+        aliases: &["rnu"],
+        kind: OptionType::Bool,
+        local: true,
+        help: "カーソル行からの相対行番号を表示する",
+    },
+    OptionSpec {
+        name: "cursorline",
+        aliases: &["cul"],
+        kind: OptionType::Bool,
+        local: true,
+        help: "カーソル行を強調表示する",
+    },
+    OptionSpec {
+        name: "cursorcolumn",
+        aliases: &["cuc"],
+        kind: OptionType::Bool,
+        local: true,
+        help: "カーソル桁を強調表示する",
+    },
+    OptionSpec {
+        name: "wrap",
+        aliases: &[],
+        kind: OptionType::Bool,
+        local: true,
+        help: "長い行を折り返して表示する",
+    },
+    OptionSpec {
+        name: "showbreak",
+        aliases: &["sbr"],
+        kind: OptionType::Text,
+        local: false,
+        help: "折り返した行の先頭に表示する文字列",
+    },
+    OptionSpec {
+        name: "tabstop",
+        aliases: &["ts"],
+        kind: OptionType::Number,
+        local: true,
+        help: "タブ幅（1〜16）",
+    },
+    OptionSpec {
+        name: "expandtab",
+        aliases: &["et"],
+        kind: OptionType::Bool,
+        local: true,
+        help: "タブの代わりに空白を挿入する",
+    },
+    OptionSpec {
+        name: "textwidth",
+        aliases: &["tw"],
+        kind: OptionType::Number,
+        local: true,
+        help: "入力中にこの桁を超えたら空白位置で改行する（0 で無効）",
+    },
+    OptionSpec {
+        name: "list",
+        aliases: &[],
+        kind: OptionType::Bool,
+        local: true,
+        help: "タブや行末の空白などの不可視文字を表示する",
+    },
+    OptionSpec {
+        name: "listchars",
+        aliases: &["lcs"],
+        kind: OptionType::Text,
+        local: false,
+        help: "list で表示する記号（tab:→ ,trail:·,nbsp:␣,ideosp:□,eol:¬）",
+    },
+    OptionSpec {
+        name: "scrolloff",
+        aliases: &["so"],
+        kind: OptionType::Number,
+        local: true,
+        help: "カーソルの上下に確保する行数",
+    },
+    OptionSpec {
+        name: "fileencoding",
+        aliases: &["fenc"],
+        kind: OptionType::Text,
+        local: true,
+        help: "保存するときの文字コード（utf-8 / sjis / euc-jp など）",
+    },
+    OptionSpec {
+        name: "fileformat",
+        aliases: &["ff"],
+        kind: OptionType::Text,
+        local: true,
+        help: "保存するときの改行コード（unix / dos / mac）",
+    },
+    OptionSpec {
+        name: "bomb",
+        aliases: &[],
+        kind: OptionType::Bool,
+        local: true,
+        help: "UTF-8 / UTF-16 のファイルの先頭に BOM を書き込む",
+    },
+    OptionSpec {
+        name: "ignorecase",
+        aliases: &["ic"],
+        kind: OptionType::Bool,
+        local: false,
+        help: "検索で大文字と小文字を区別しない",
+    },
+    OptionSpec {
+        name: "smartcase",
+        aliases: &["scs"],
+        kind: OptionType::Bool,
+        local: false,
+        help: "検索文字列に大文字があれば ignorecase を無視する",
+    },
+    OptionSpec {
+        name: "mouse",
+        aliases: &[],
+        kind: OptionType::Bool,
+        local: false,
+        help: "マウス操作を受け付ける",
+    },
+    OptionSpec {
+        name: "theme",
+        aliases: &[],
+        kind: OptionType::Text,
+        local: false,
+        help: "シンタックスハイライトのテーマ",
+    },
+    OptionSpec {
+        name: "themename",
+        aliases: &[],
+        kind: OptionType::Text,
+        local: false,
+        help: "配色テーマの名前",
+    },
+    OptionSpec {
+        name: "fgcolor",
+        aliases: &[],
+        kind: OptionType::Text,
+        local: false,
+        help: "本文の文字色（#RRGGBB、空で既定）",
+    },
+    OptionSpec {
+        name: "bgcolor",
+        aliases: &[],
+        kind: OptionType::Text,
+        local: false,
+        help: "本文の背景色（#RRGGBB、空で既定）",
+    },
+    OptionSpec {
+        name: "accentcolor",
+        aliases: &[],
+        kind: OptionType::Text,
+        local: false,
+        help: "強調色（#RRGGBB、空で既定）",
+    },
+    OptionSpec {
+        name: "statuscolor",
+        aliases: &[],
+        kind: OptionType::Text,
+        local: false,
+        help: "ステータスバーの背景色（#RRGGBB、空で既定）",
+    },
+    OptionSpec {
+        name: "listcolor",
+        aliases: &[],
+        kind: OptionType::Text,
+        local: false,
+        help: "不可視文字の記号の色（#RRGGBB、空で既定）",
+    },
+    OptionSpec {
+        name: "keybindings",
+        aliases: &["keymap"],
+        kind: OptionType::Text,
+        local: false,
+        help: "キーバインドの種類（vim / emacs / nano）",
+    },
+];
+
+/// Find an option by its name or alias.
+pub fn lookup_option(name: &str) -> Option<&'static OptionSpec> {
+    OPTIONS
+        .iter()
+        .find(|spec| spec.name == name || spec.aliases.contains(&name))
+}
+
+/// The global value of `spec`, read from `config`.
+pub fn get_global(config: &Config, spec: &OptionSpec) -> OptionValue {
+    let editor = &config.editor;
+    let theme = &config.theme;
+    let color = |value: &Option<String>| OptionValue::Text(value.clone().unwrap_or_default());
+    match spec.name {
+        "number" => OptionValue::Bool(editor.line_numbers),
+        "relativenumber" => OptionValue::Bool(editor.relativenumber),
+        "cursorline" => OptionValue::Bool(editor.highlight_current_line),
+        "cursorcolumn" => OptionValue::Bool(editor.cursorcolumn),
+        "wrap" => OptionValue::Bool(editor.wrap_lines),
+        "showbreak" => OptionValue::Text(editor.showbreak.clone()),
+        "tabstop" => OptionValue::Number(editor.tab_size),
+        "expandtab" => OptionValue::Bool(editor.use_spaces),
+        "textwidth" => OptionValue::Number(editor.textwidth),
+        "list" => OptionValue::Bool(editor.list),
+        "listchars" => OptionValue::Text(editor.listchars.clone()),
+        "scrolloff" => OptionValue::Number(editor.scrolloff),
+        "fileencoding" => OptionValue::Text(Encoding::default().name().to_string()),
+        "fileformat" => OptionValue::Text(LineEnding::default().name().to_string()),
+        "bomb" => OptionValue::Bool(false),
+        "ignorecase" => OptionValue::Bool(editor.ignorecase),
+        "smartcase" => OptionValue::Bool(editor.smartcase),
+        "mouse" => OptionValue::Bool(editor.mouse),
+        "theme" => OptionValue::Text(theme.syntax_theme.clone()),
+        "themename" => OptionValue::Text(theme.name.clone()),
+        "fgcolor" => color(&theme.editor_foreground),
+        "bgcolor" => color(&theme.editor_background),
+        "accentcolor" => color(&theme.accent_color),
+        "statuscolor" => color(&theme.status_background),
+        "listcolor" => color(&theme.whitespace_color),
+        "keybindings" => OptionValue::Text(config.keybindings.name().to_string()),
+        name => unreachable!("unregistered option: {}", name),
+    }
+}
+
+/// Store `value` as the global value of `spec`. The value must already be
+/// checked with [`parse_value`].
+pub fn set_global(config: &mut Config, spec: &OptionSpec, value: OptionValue) {
+    let editor = &mut config.editor;
+    let theme = &mut config.theme;
+    let color = |value: &OptionValue| Some(value.as_text().to_string()).filter(|c| !c.is_empty());
+    match spec.name {
+        "number" => editor.line_numbers = value.as_bool(),
+        "relativenumber" => editor.relativenumber = value.as_bool(),
+        "cursorline" => editor.highlight_current_line = value.as_bool(),
+        "cursorcolumn" => editor.cursorcolumn = value.as_bool(),
+        "wrap" => editor.wrap_lines = value.as_bool(),
+        "showbreak" => editor.showbreak = value.as_text().to_string(),
+        "tabstop" => editor.tab_size = value.as_number(),
+        "expandtab" => editor.use_spaces = value.as_bool(),
+        "textwidth" => editor.textwidth = value.as_number(),
+        "list" => editor.list = value.as_bool(),
+        "listchars" => editor.listchars = value.as_text().to_string(),
+        "scrolloff" => editor.scrolloff = value.as_number(),
+        // Kept in each buffer's `FileFormat`, see `set_file_option`
+        "fileencoding" | "fileformat" | "bomb" => {}
+        "ignorecase" => editor.ignorecase = value.as_bool(),
+        "smartcase" => editor.smartcase = value.as_bool(),
+        "mouse" => editor.mouse = value.as_bool(),
+        "theme" => theme.syntax_theme = value.as_text().to_string(),
+        "themename" => theme.name = value.as_text().to_string(),
+        "fgcolor" => theme.editor_foreground = color(&value),
+        "bgcolor" => theme.editor_background = color(&value),
+        "accentcolor" => theme.accent_color = color(&value),
+        "statuscolor" => theme.status_background = color(&value),
+        "listcolor" => theme.whitespace_color = color(&value),
+        "keybindings" => {
+            if let Some(style) = KeybindingStyle::from_name(value.as_text()) {
+                config.keybindings = style;
+            }
+        }
+        name => unreachable!("unregistered option: {}", name),
+    }
+}
+
+/// The built-in default of `spec`, restored by `:set opt&`.
+pub fn default_value(spec: &OptionSpec) -> OptionValue {
+    get_global(&Config::default(), spec)
+}
+
+/// Parse and validate the text after `opt=`.
+pub fn parse_value(spec: &OptionSpec, text: &str) -> Result<OptionValue> {
+    let invalid = || anyhow::anyhow!("E474: 無効な値です: {}={}", spec.name, text);
+    let value = match spec.kind {
+        OptionType::Bool => return Err(anyhow::anyhow!("E474: {} は値を取りません", spec.name)),
+        OptionType::Number => OptionValue::Number(text.parse().map_err(|_| invalid())?),
+        OptionType::Text => OptionValue::Text(text.to_string()),
+    };
+    let valid = match (spec.name, &value) {
+        ("tabstop", OptionValue::Number(n)) => (1..=16).contains(n),
+        ("theme", OptionValue::Text(name)) => crate::highlight::theme_names().contains(name),
+        ("themename", OptionValue::Text(name)) => !name.is_empty(),
+        ("listchars", OptionValue::Text(spec)) => ListChars::parse(spec).is_some(),
+        (
+            "fgcolor" | "bgcolor" | "accentcolor" | "statuscolor" | "listcolor",
+            OptionValue::Text(color),
+        ) => {
+            let hex = color.trim_start_matches('#');
+            color.is_empty() || (hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        }
+        ("keybindings", OptionValue::Text(name)) => KeybindingStyle::from_name(name).is_some(),
+        ("fileencoding", OptionValue::Text(name)) => Encoding::from_name(name).is_some(),
+        ("fileformat", OptionValue::Text(name)) => LineEnding::from_name(name).is_some(),
+        _ => true,
+    };
+    if valid {
+        Ok(value)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// The value of an option that describes the file rather than how it is
+/// edited, read from the buffer's format. `None` for other options.
+pub fn get_file_option(format: &FileFormat, spec: &OptionSpec) -> Option<OptionValue> {
+    match spec.name {
+        "fileencoding" => Some(OptionValue::Text(format.encoding.name().to_string())),
+        "fileformat" => Some(OptionValue::Text(format.line_ending.name().to_string())),
+        "bomb" => Some(OptionValue::Bool(format.bom)),
+        _ => None,
+    }
+}
+
+/// Store a file option in the buffer's format. Returns `false` for other
+/// options, which go to the config or the local values instead.
+pub fn set_file_option(format: &mut FileFormat, spec: &OptionSpec, value: &OptionValue) -> bool {
+    match spec.name {
+        "fileencoding" => {
+            if let Some(encoding) = Encoding::from_name(value.as_text()) {
+                format.encoding = encoding;
+            }
+            true
+        }
+        "fileformat" => {
+            if let Some(ending) = LineEnding::from_name(value.as_text()) {
+                format.line_ending = ending;
+                format.mixed_endings = false;
+            }
+            true
+        }
+        "bomb" => {
+            format.bom = value.as_bool();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Per-buffer values set with `:setlocal`, overriding the global ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalOptions {
+    values: BTreeMap<&'static str, OptionValue>,
+}
+
+impl LocalOptions {
+    pub fn get(&self, spec: &OptionSpec) -> Option<&OptionValue> {
+        self.values.get(spec.name)
+    }
+
+    pub fn set(&mut self, spec: &'static OptionSpec, value: OptionValue) {
+        self.values.insert(spec.name, value);
+    }
+
+    /// Drop the local value so the buffer follows the global one again.
+    pub fn clear(&mut self, spec: &OptionSpec) {
+        self.values.remove(spec.name);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static OptionSpec, &OptionValue)> {
+        self.values
+            .iter()
+            .filter_map(|(name, value)| Some((lookup_option(name)?, value)))
+    }
+}
+
+/// The value of `spec` for a buffer: its local value if it has one,
+/// otherwise the global one.
+pub fn effective_value(config: &Config, local: &LocalOptions, spec: &OptionSpec) -> OptionValue {
+    local
+        .get(spec)
+        .cloned()
+        .unwrap_or_else(|| get_global(config, spec))
+}
+
+/// Options as seen by one buffer, resolved from the global config and the
+/// buffer's local values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorOptions {
+    pub number: bool,
+    pub relativenumber: bool,
+    pub cursorline: bool,
+    pub cursorcolumn: bool,
+    pub wrap: bool,
+    pub tabstop: usize,
+    pub expandtab: bool,
+    pub textwidth: usize,
+    pub list: bool,
+    pub scrolloff: usize,
+    pub ignorecase: bool,
+    pub smartcase: bool,
+}
+
+impl EditorOptions {
+    pub fn resolve(config: &Config, local: &LocalOptions) -> Self {
+        let value = |name: &str| {
+            let spec = lookup_option(name).expect("registered option");
+            effective_value(config, local, spec)
+        };
+        Self {
+            number: value("number").as_bool(),
+            relativenumber: value("relativenumber").as_bool(),
+            cursorline: value("cursorline").as_bool(),
+            cursorcolumn: value("cursorcolumn").as_bool(),
+            wrap: value("wrap").as_bool(),
+            tabstop: value("tabstop").as_number(),
+            expandtab: value("expandtab").as_bool(),
+            textwidth: value("textwidth").as_number(),
+            list: value("list").as_bool(),
+            scrolloff: value("scrolloff").as_number(),
+            ignorecase: value("ignorecase").as_bool(),
+            smartcase: value("smartcase").as_bool(),
+        }
+    }
+}
+
+/// One argument of `:set` / `:setlocal`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetArg {
+    /// `opt?`, or a bare non-boolean `opt`
+    Show(&'static OptionSpec),
+    /// `opt=value`, or `opt` / `noopt` for booleans
+    Assign(&'static OptionSpec, OptionValue),
+    /// `invopt` or `opt!`
+    Toggle(&'static OptionSpec),
+    /// `opt&`: back to the built-in default
+    Default(&'static OptionSpec),
+    /// `opt<`: drop the local value
+    UseGlobal(&'static OptionSpec),
+}
+
+/// Parse one `:set` argument such as `nu`, `nonumber`, `ts=2`, `wrap?`,
+/// `list!` or `tw&`.
+pub fn parse_set_arg(arg: &str) -> Result<SetArg> {
+    let unknown = |name: &str| anyhow::anyhow!("E518: 未知のオプションです: {}", name);
+    let spec_of = |name: &str| lookup_option(name).ok_or_else(|| unknown(name));
+
+    if let Some((name, text)) = arg.split_once(['=', ':']) {
+        let spec = spec_of(name)?;
+        return Ok(SetArg::Assign(spec, parse_value(spec, text)?));
+    }
+    if let Some(name) = arg.strip_suffix('?') {
+        return Ok(SetArg::Show(spec_of(name)?));
+    }
+    if let Some(name) = arg.strip_suffix('&') {
+        return Ok(SetArg::Default(spec_of(name)?));
+    }
+    if let Some(name) = arg.strip_suffix('<') {
+        return Ok(SetArg::UseGlobal(spec_of(name)?));
+    }
+    let bool_spec = |name: &str| {
+        lookup_option(name)
+            .filter(|spec| spec.kind == OptionType::Bool)
+            .ok_or_else(|| unknown(name))
+    };
+    if let Some(name) = arg.strip_suffix('!') {
+        return Ok(SetArg::Toggle(bool_spec(name)?));
+    }
+    if let Some(spec) = lookup_option(arg) {
+        return Ok(match spec.kind {
+            OptionType::Bool => SetArg::Assign(spec, OptionValue::Bool(true)),
+            _ => SetArg::Show(spec),
+        });
+    }
+    // Errors name the argument as typed, prefix included
+    if let Some(name) = arg.strip_prefix("inv") {
+        let spec = bool_spec(name).map_err(|_| unknown(arg))?;
+        return Ok(SetArg::Toggle(spec));
+    }
+    if let Some(name) = arg.strip_prefix("no") {
+        let spec = bool_spec(name).map_err(|_| unknown(arg))?;
+        return Ok(SetArg::Assign(spec, OptionValue::Bool(false)));
+    }
+    Err(unknown(arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_set_args() {
+        let spec = |name| lookup_option(name).unwrap();
+        assert_eq!(
+            parse_set_arg("nu").unwrap(),
+            SetArg::Assign(spec("number"), OptionValue::Bool(true))
+        );
+        assert_eq!(
+            parse_set_arg("nowrap").unwrap(),
+            SetArg::Assign(spec("wrap"), OptionValue::Bool(false))
+        );
+        assert_eq!(
+            parse_set_arg("ts=2").unwrap(),
+            SetArg::Assign(spec("tabstop"), OptionValue::Number(2))
+        );
+        assert_eq!(
+            parse_set_arg("tw").unwrap(),
+            SetArg::Show(spec("textwidth"))
+        );
+        assert_eq!(parse_set_arg("list?").unwrap(), SetArg::Show(spec("list")));
+        assert_eq!(
+            parse_set_arg("invlist").unwrap(),
+            SetArg::Toggle(spec("list"))
+        );
+        assert_eq!(
+            parse_set_arg("list!").unwrap(),
+            SetArg::Toggle(spec("list"))
+        );
+        assert_eq!(
+            parse_set_arg("so&").unwrap(),
+            SetArg::Default(spec("scrolloff"))
+        );
+        assert_eq!(
+            parse_set_arg("et<").unwrap(),
+            SetArg::UseGlobal(spec("expandtab"))
+        );
+
+        let error = |arg| parse_set_arg(arg).unwrap_err().to_string();
+        assert_eq!(error("nosuchopt"), "E518: 未知のオプションです: nosuchopt");
+        assert_eq!(
+            error("invsuchopt"),
+            "E518: 未知のオプションです: invsuchopt"
+        );
+        assert!(parse_set_arg("ts=0").is_err());
+        assert!(parse_set_arg("ts=abc").is_err());
+        assert!(parse_set_arg("nots").is_err());
+        assert!(parse_set_arg("fgcolor=#12345").is_err());
+        assert!(parse_set_arg("keybindings=ed").is_err());
+        assert!(parse_set_arg("fenc=ebcdic").is_err());
+        assert!(parse_set_arg("ff=vms").is_err());
+        assert!(parse_set_arg("bogus").is_err());
+    }
+
+    #[test]
+    fn test_option_values_round_trip_and_resolve() {
+        let mut config = Config::default();
+        for spec in OPTIONS {
+            let value = get_global(&config, spec);
+            set_global(&mut config, spec, value.clone());
+            assert_eq!(get_global(&config, spec), value, "{}", spec.name);
+        }
+
+        set_global(
+            &mut config,
+            lookup_option("ts").unwrap(),
+            OptionValue::Number(8),
+        );
+        set_global(
+            &mut config,
+            lookup_option("fgcolor").unwrap(),
+            OptionValue::Text(String::new()),
+        );
+        assert_eq!(config.theme.editor_foreground, None);
+
+        let mut local = LocalOptions::default();
+        local.set(lookup_option("ts").unwrap(), OptionValue::Number(2));
+        assert_eq!(EditorOptions::resolve(&config, &local).tabstop, 2);
+        local.clear(lookup_option("ts").unwrap());
+        assert_eq!(EditorOptions::resolve(&config, &local).tabstop, 8);
+    }
+}
@@ -0,0 +1,343 @@
+//! エディタの上に重ねて表示するポップアップとサイドバーの描画。
+//!
+//! 標準 UI と拡張 UI の両方から呼ばれ、ファジーファインダーの候補一覧と
+//! 選択中の候補のプレビュー、ファイルエクスプローラーのツリー、ページャーを
+//! 描きます。
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::App;
+use crate::command_processor;
+use crate::explorer::EXPLORER_WIDTH;
+use crate::keymap::{self, Action, KeymapMode};
+use crate::mouse::ClickAction;
+use crate::picker::{PickerTarget, PREVIEW_LINES};
+
+/// Popups narrower than this show no preview.
+const MIN_PREVIEW_WIDTH: u16 = 60;
+
+/// A rectangle of `percent_x` by `percent_y` of `area`, centered in it.
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Lines of the selected candidate's preview and the file name used to pick
+/// its syntax.
+fn preview_source(app: &mut App) -> Option<(String, Vec<String>)> {
+    let target = app.picker.as_ref()?.selected_item()?.target.clone();
+    let current_name = app
+        .file_path()
+        .map_or_else(|| "text.md".to_string(), |p| p.display().to_string());
+    match target {
+        PickerTarget::File(path) => {
+            let lines = app.picker.as_mut()?.file_preview(&path).to_vec();
+            Some((path.display().to_string(), lines))
+        }
+        PickerTarget::Buffer(id) => {
+            let buffer = app.buffer_manager.buffers().iter().find(|b| b.id == id)?;
+            let name = buffer
+                .file_path
+                .as_ref()
+                .map_or_else(|| "text.md".to_string(), |p| p.display().to_string());
+            let lines = buffer
+                .content
+                .get_content()
+                .lines()
+                .take(PREVIEW_LINES)
+                .map(str::to_string)
+                .collect();
+            Some((name, lines))
+        }
+        PickerTarget::Line(line) => {
+            let lines = app
+                .get_current_editor()
+                .get_content()
+                .lines()
+                .skip(line)
+                .take(PREVIEW_LINES)
+                .map(str::to_string)
+                .collect();
+            Some((current_name, lines))
+        }
+        PickerTarget::Command(name) => {
+            let spec = command_processor::lookup_command(name)?;
+            let mut lines = vec![spec.usage(), String::new(), spec.help.to_string()];
+            if !spec.aliases.is_empty() {
+                lines.push(format!("別名: :{}", spec.aliases.join(", :")));
+            }
+            if let Some(keys) = app
+                .keymap
+                .keys_for(KeymapMode::Normal, &Action::Ex(name.to_string()))
+            {
+                lines.push(format!("キー: {}", keymap::format_keys(&keys)));
+            }
+            Some(("help.txt".to_string(), lines))
+        }
+        PickerTarget::Action(action) => {
+            let keys = app
+                .keymap
+                .keys_for(KeymapMode::Normal, &action)
+                .map_or_else(|| "(なし)".to_string(), |keys| keymap::format_keys(&keys));
+            let lines = vec![
+                action.name(),
+                String::new(),
+                action.description(),
+                format!("キー: {}", keys),
+                format!("割り当て: :map <キー> {}", action.name()),
+            ];
+            Some(("help.txt".to_string(), lines))
+        }
+    }
+}
+
+/// Draw the fuzzy finder popup if one is open.
+/// Draw the pager over the editor, recording how many rows it shows.
+pub fn draw_pager(f: &mut Frame, app: &mut App) {
+    let Some(pager) = app.pager.as_mut() else {
+        return;
+    };
+    let area = centered(f.size(), 80, 70);
+    if area.width < 10 || area.height < 3 {
+        return;
+    }
+    f.render_widget(Clear, area);
+
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        " {} {}/{} ",
+        pager.title(),
+        (pager.offset() + 1).min(pager.line_count()),
+        pager.line_count()
+    ));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    pager.set_height(inner.height as usize);
+    f.render_widget(Paragraph::new(pager.visible_lines().to_vec()), inner);
+}
+
+pub fn draw_picker(f: &mut Frame, app: &mut App) {
+    let Some(picker) = app.picker.as_ref() else {
+        return;
+    };
+    let area = centered(f.size(), 90, 80);
+    if area.width < 10 || area.height < 5 {
+        return;
+    }
+    f.render_widget(Clear, area);
+
+    let chunks = if area.width >= MIN_PREVIEW_WIDTH {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(area)
+    } else {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(100)])
+            .split(area)
+    };
+
+    // Candidate list with the query on its first row
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        " {} {}/{} ",
+        picker.kind().title(),
+        picker.matches().len(),
+        picker.total()
+    ));
+    let inner = block.inner(chunks[0]);
+    f.render_widget(block, chunks[0]);
+
+    let visible = (inner.height as usize).saturating_sub(1);
+    let offset = (picker.selected() + 1).saturating_sub(visible);
+    let mut lines = vec![Line::from(vec![
+        Span::styled("> ", Style::default().fg(Color::Cyan)),
+        Span::raw(picker.query().to_string()),
+    ])];
+    for (row, found) in picker
+        .matches()
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+    {
+        let selected = row == picker.selected();
+        let base = if selected {
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let mut spans = vec![Span::styled(if selected { "▶ " } else { "  " }, base)];
+        for (i, c) in picker.item(found.index).label.chars().enumerate() {
+            let style = if found.positions.contains(&i) {
+                base.fg(Color::Yellow)
+            } else {
+                base
+            };
+            spans.push(Span::styled(c.to_string(), style));
+        }
+        lines.push(Line::from(spans));
+    }
+    let cursor_x = inner.x + 2 + app.text_calculator.str_width(picker.query()) as u16;
+    f.render_widget(Paragraph::new(lines), inner);
+    if cursor_x < inner.x + inner.width {
+        f.set_cursor(cursor_x, inner.y);
+    }
+
+    if chunks.len() < 2 {
+        return;
+    }
+    let (name, preview) = preview_source(app).unwrap_or_default();
+    let highlighter = app.get_highlighter();
+    let syntax = highlighter.find_syntax_for_filename(&name);
+    let highlighted = highlighter.highlight_lines_to_ratatui(&preview, syntax);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} ", name));
+    f.render_widget(Paragraph::new(highlighted).block(block), chunks[1]);
+}
+
+/// Draw the command-line completion candidates on the row above the
+/// status area, scrolled so the selected one is visible.
+pub fn draw_wildmenu(f: &mut Frame, app: &App, status_area: Rect) {
+    let Some(menu) = app.wildmenu.as_ref() else {
+        return;
+    };
+    if status_area.y == 0 {
+        return;
+    }
+    let area = Rect::new(status_area.x, status_area.y - 1, status_area.width, 1);
+    let width = area.width as usize;
+
+    // Start at the first candidate that keeps the selection on screen
+    let widths: Vec<usize> = menu
+        .candidates()
+        .iter()
+        .map(|c| app.text_calculator.str_width(c) + 2)
+        .collect();
+    let mut first = menu.selected();
+    let mut used = widths[first];
+    while first > 0 && used + widths[first - 1] <= width.saturating_sub(2) {
+        first -= 1;
+        used += widths[first];
+    }
+
+    let base = Style::default().bg(Color::DarkGray).fg(Color::White);
+    let mut spans = Vec::new();
+    if first > 0 {
+        spans.push(Span::styled("< ", base));
+    }
+    for (index, candidate) in menu.candidates().iter().enumerate().skip(first) {
+        let style = if index == menu.selected() {
+            base.bg(Color::Yellow).fg(Color::Black)
+        } else {
+            base
+        };
+        spans.push(Span::styled(format!(" {} ", candidate), style));
+    }
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(Line::from(spans)).style(base), area);
+}
+
+/// Split the editor area into the explorer sidebar, when it is open, and
+/// the area left for the editor panes.
+pub fn explorer_layout(app: &App, area: Rect) -> (Option<Rect>, Rect) {
+    if app.explorer.is_none() || area.width < EXPLORER_WIDTH * 2 {
+        return (None, area);
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(EXPLORER_WIDTH), Constraint::Min(0)])
+        .split(area);
+    (Some(chunks[0]), chunks[1])
+}
+
+/// Draw the file explorer tree in `area`.
+pub fn draw_explorer(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(explorer) = app.explorer.as_ref() else {
+        return;
+    };
+    let border = if explorer.focused {
+        Color::Yellow
+    } else {
+        Color::DarkGray
+    };
+    let root = std::fs::canonicalize(explorer.root())
+        .ok()
+        .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| explorer.root().display().to_string());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border))
+        .title(format!(" {}/ ", root));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let visible = inner.height as usize;
+    let offset = (explorer.selected() + 1).saturating_sub(visible);
+    let mut lines = Vec::new();
+    let mut rows = Vec::new();
+    for (index, entry) in explorer
+        .entries()
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+    {
+        let selected = index == explorer.selected();
+        let base = match (selected, explorer.focused) {
+            (true, true) => Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+            (true, false) => Style::default().add_modifier(Modifier::UNDERLINED),
+            _ => Style::default(),
+        };
+        let marker = match (entry.is_dir, entry.expanded) {
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
+            _ => "  ",
+        };
+        let name_style = if entry.is_dir {
+            base.fg(Color::Cyan)
+        } else {
+            base
+        };
+        let suffix = if entry.is_dir { "/" } else { "" };
+        let mut spans = vec![
+            Span::styled("  ".repeat(entry.depth), base),
+            Span::styled(marker, base),
+            Span::styled(format!("{}{}", entry.name(), suffix), name_style),
+        ];
+        if let Some(hint) = &entry.hint {
+            spans.push(Span::styled(
+                format!("  {}", hint),
+                base.fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+            ));
+        }
+        lines.push(Line::from(spans));
+
+        let row = Rect::new(inner.x, inner.y + rows.len() as u16, inner.width, 1);
+        rows.push((row, ClickAction::ExplorerEntry(index)));
+    }
+    f.render_widget(Paragraph::new(lines), inner);
+    if explorer.focused && explorer.selected() >= offset && inner.height > 0 {
+        f.set_cursor(inner.x, inner.y + (explorer.selected() - offset) as u16);
+    }
+    for (row, action) in rows {
+        app.screen_layout
+            .push_row_targets(row, &[(row.width as usize, Some(action))]);
+    }
+}
@@ -0,0 +1,117 @@
+//! 複数行の結果を表示するページャー。
+//!
+//! `:messages` の履歴や `:buffers`・`:sessions`・`:set all` のように複数行に
+//! なる結果を、1 行のステータスバーで切り詰めずにスクロールできる
+//! オーバーレイで表示します。`j`/`k` で 1 行、`Space`/`b` で 1 ページ、
+//! `g`/`G` で先頭と末尾へ移動し、`q`・`Esc`・`Enter` で閉じます。
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::text::Line;
+
+/// Scrollable text shown over the editor.
+#[derive(Debug, Clone)]
+pub struct Pager {
+    title: String,
+    lines: Vec<Line<'static>>,
+    offset: usize,
+    // Rows available for text, recorded by the last draw
+    height: usize,
+}
+
+impl Pager {
+    pub fn new(title: impl Into<String>, lines: Vec<Line<'static>>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            offset: 0,
+            height: 1,
+        }
+    }
+
+    /// A pager over the lines of `text`.
+    pub fn from_text(title: impl Into<String>, text: &str) -> Self {
+        Self::new(
+            title,
+            text.lines()
+                .map(|line| Line::from(line.to_string()))
+                .collect(),
+        )
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The lines visible from the current offset.
+    pub fn visible_lines(&self) -> &[Line<'static>] {
+        let end = (self.offset + self.height).min(self.lines.len());
+        &self.lines[self.offset..end]
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn set_height(&mut self, height: usize) {
+        self.height = height.max(1);
+        self.scroll(0);
+    }
+
+    /// Move by `delta` lines, stopping once the last line is at the bottom.
+    pub fn scroll(&mut self, delta: isize) {
+        let max_offset = self.lines.len().saturating_sub(self.height);
+        self.offset = self.offset.saturating_add_signed(delta).min(max_offset);
+    }
+
+    /// Handle a key; returns `false` when the pager should close.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let page = self.height as isize;
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter, _) => return false,
+            (KeyCode::Char('j') | KeyCode::Down, _) => self.scroll(1),
+            (KeyCode::Char('k') | KeyCode::Up, _) => self.scroll(-1),
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => self.scroll(page / 2),
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => self.scroll(-page / 2),
+            (KeyCode::Char(' ' | 'f') | KeyCode::PageDown, _) => self.scroll(page),
+            (KeyCode::Char('b') | KeyCode::PageUp, _) => self.scroll(-page),
+            (KeyCode::Char('g') | KeyCode::Home, _) => self.offset = 0,
+            (KeyCode::Char('G') | KeyCode::End, _) => self.scroll(isize::MAX),
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_pager_scrolls_within_bounds() {
+        let text: Vec<String> = (1..=10).map(|n| format!("line {}", n)).collect();
+        let mut pager = Pager::from_text(":buffers", &text.join("\n"));
+        pager.set_height(4);
+        assert_eq!(pager.visible_lines().len(), 4);
+
+        assert!(pager.handle_key(key(KeyCode::Char(' '))));
+        assert_eq!(pager.offset(), 4);
+        pager.handle_key(key(KeyCode::Char('G')));
+        assert_eq!(pager.offset(), 6);
+        pager.handle_key(key(KeyCode::Char('j')));
+        assert_eq!(pager.offset(), 6);
+        assert_eq!(pager.visible_lines()[3], Line::from("line 10"));
+        pager.handle_key(key(KeyCode::Char('g')));
+        pager.handle_key(key(KeyCode::Char('k')));
+        assert_eq!(pager.offset(), 0);
+
+        assert!(!pager.handle_key(key(KeyCode::Char('q'))));
+    }
+}
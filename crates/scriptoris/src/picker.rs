@@ -0,0 +1,457 @@
+//! ファジーファインダーのピッカー。
+//!
+//! ファイル・開いているバッファ・最近のファイル・見出し・コマンドを同じ
+//! 仕組みで一覧し、入力した文字列でファジー検索して絞り込みます。スコアは
+//! 連続一致や単語・パス区切りの先頭での一致を高く評価します。
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::command_processor::COMMANDS;
+use crate::folding;
+use crate::gitignore;
+use crate::keymap::{self, Action, Keymap, KeymapMode};
+
+/// Maximum number of files listed by the file picker.
+pub const MAX_FILES: usize = 20_000;
+/// Lines shown in the file preview.
+pub const PREVIEW_LINES: usize = 200;
+/// Bytes read from a file for its preview.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// What a picker lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerKind {
+    Files,
+    Buffers,
+    RecentFiles,
+    Headings,
+    /// The command palette: ex commands and keymap actions.
+    Commands,
+}
+
+impl PickerKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            PickerKind::Files => "ファイル",
+            PickerKind::Buffers => "バッファ",
+            PickerKind::RecentFiles => "最近のファイル",
+            PickerKind::Headings => "見出し",
+            PickerKind::Commands => "コマンド",
+        }
+    }
+
+    /// Parse the argument of `:pick`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "files" | "file" => Some(PickerKind::Files),
+            "buffers" | "buffer" => Some(PickerKind::Buffers),
+            "recent" => Some(PickerKind::RecentFiles),
+            "headings" | "heading" => Some(PickerKind::Headings),
+            "commands" | "command" | "palette" => Some(PickerKind::Commands),
+            _ => None,
+        }
+    }
+}
+
+/// What choosing an item opens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerTarget {
+    File(PathBuf),
+    Buffer(usize),
+    /// A line of the current buffer.
+    Line(usize),
+    /// An ex command, by its registered name.
+    Command(&'static str),
+    Action(Action),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerItem {
+    pub label: String,
+    pub target: PickerTarget,
+}
+
+/// Score of a candidate and the character positions that matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '/' | '\\' | '_' | '-' | '.' | ' ')
+}
+
+/// Match one whitespace-free term: the shortest window holding the term as a
+/// subsequence that ends at its first complete occurrence.
+fn match_term(term: &[char], text: &[char], folded: &[char]) -> Option<FuzzyMatch> {
+    let mut next = 0;
+    let end = folded.iter().position(|&c| {
+        if c == term[next] {
+            next += 1;
+        }
+        next == term.len()
+    })?;
+
+    let mut remaining = term.len();
+    let mut start = end;
+    for i in (0..=end).rev() {
+        if folded[i] == term[remaining - 1] {
+            remaining -= 1;
+            if remaining == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut positions = Vec::with_capacity(term.len());
+    let mut next = 0;
+    for (i, &c) in folded.iter().enumerate().take(end + 1).skip(start) {
+        if next < term.len() && c == term[next] {
+            positions.push(i);
+            next += 1;
+        }
+    }
+
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &position in &positions {
+        score += 16;
+        match previous {
+            Some(prev) if prev + 1 == position => score += 12,
+            Some(prev) => score -= 3 + (position - prev - 1).min(10) as i64,
+            None => {}
+        }
+        let before = position.checked_sub(1).map(|i| text[i]);
+        match before {
+            None | Some('/') => score += 14,
+            Some(c) if is_separator(c) => score += 10,
+            // camelCase boundary
+            Some(c) if c.is_lowercase() && text[position].is_uppercase() => score += 8,
+            _ => {}
+        }
+        previous = Some(position);
+    }
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Fuzzy-match `query` against `candidate`. Whitespace separates terms that
+/// must all match; the query is case-sensitive only when it has uppercase.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let text: Vec<char> = candidate.chars().collect();
+    let folded: Vec<char> = text.iter().map(|&c| fold(c)).collect();
+
+    let mut result = FuzzyMatch {
+        score: 0,
+        positions: Vec::new(),
+    };
+    for term in query.split_whitespace() {
+        let term: Vec<char> = term.chars().map(fold).collect();
+        let found = match_term(&term, &text, &folded)?;
+        result.score += found.score;
+        result.positions.extend(found.positions);
+    }
+    result.positions.sort_unstable();
+    result.positions.dedup();
+    Some(result)
+}
+
+/// An item passing the current query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerMatch {
+    /// Index into the picker's items.
+    pub index: usize,
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+/// State of an open picker.
+#[derive(Debug, Clone)]
+pub struct Picker {
+    kind: PickerKind,
+    items: Vec<PickerItem>,
+    query: String,
+    matches: Vec<PickerMatch>,
+    selected: usize,
+    /// Preview of the last file shown, read once per selection.
+    preview: Option<(PathBuf, Vec<String>)>,
+}
+
+impl Picker {
+    pub fn new(kind: PickerKind, items: Vec<PickerItem>, query: &str) -> Self {
+        let mut picker = Self {
+            kind,
+            items,
+            query: query.to_string(),
+            matches: Vec::new(),
+            selected: 0,
+            preview: None,
+        };
+        picker.refilter();
+        picker
+    }
+
+    pub fn kind(&self) -> PickerKind {
+        self.kind
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn total(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn matches(&self) -> &[PickerMatch] {
+        &self.matches
+    }
+
+    pub fn item(&self, index: usize) -> &PickerItem {
+        &self.items[index]
+    }
+
+    /// Position of the selection within `matches`.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&PickerItem> {
+        self.matches
+            .get(self.selected)
+            .map(|found| &self.items[found.index])
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.refilter();
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.refilter();
+    }
+
+    pub fn clear_query(&mut self) {
+        self.query.clear();
+        self.refilter();
+    }
+
+    /// Move the selection by `delta`, stopping at either end.
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.matches.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// Best matches first; ties keep shorter labels, then the item order.
+    fn refilter(&mut self) {
+        self.matches = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                let found = fuzzy_match(&self.query, &item.label)?;
+                Some(PickerMatch {
+                    index,
+                    score: found.score,
+                    positions: found.positions,
+                })
+            })
+            .collect();
+        if !self.query.trim().is_empty() {
+            let items = &self.items;
+            self.matches.sort_by(|a, b| {
+                b.score
+                    .cmp(&a.score)
+                    .then(items[a.index].label.len().cmp(&items[b.index].label.len()))
+                    .then(a.index.cmp(&b.index))
+            });
+        }
+        self.selected = 0;
+    }
+
+    /// First lines of `path`, cached until another file is previewed.
+    pub fn file_preview(&mut self, path: &Path) -> &[String] {
+        let cached = self
+            .preview
+            .as_ref()
+            .is_some_and(|(previewed, _)| previewed == path);
+        if !cached {
+            self.preview = Some((path.to_path_buf(), read_preview(path)));
+        }
+        self.preview
+            .as_ref()
+            .map(|(_, lines)| lines.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+fn read_preview(path: &Path) -> Vec<String> {
+    let mut bytes = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut bytes));
+    if let Err(e) = read {
+        return vec![format!("プレビューできません: {}", e)];
+    }
+    if bytes.contains(&0) {
+        return vec!["(バイナリファイル)".to_string()];
+    }
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Files of the project below `root`, respecting `.gitignore`.
+pub fn file_items(root: &Path) -> Vec<PickerItem> {
+    gitignore::walk_files(root, MAX_FILES)
+        .into_iter()
+        .map(|relative| {
+            // Paths below the working directory stay relative
+            let target = if root == Path::new(".") {
+                relative.clone()
+            } else {
+                root.join(&relative)
+            };
+            PickerItem {
+                label: relative.to_string_lossy().into_owned(),
+                target: PickerTarget::File(target),
+            }
+        })
+        .collect()
+}
+
+/// Headings of the current buffer.
+pub fn heading_items<S: AsRef<str>>(lines: &[S]) -> Vec<PickerItem> {
+    folding::headings(lines)
+        .into_iter()
+        .map(|heading| PickerItem {
+            label: format!("{} {}", "#".repeat(heading.level), heading.text),
+            target: PickerTarget::Line(heading.line),
+        })
+        .collect()
+}
+
+/// Entries of the command palette: every ex command, then every named
+/// action, each with its description and current key binding.
+pub fn command_items(keymap: &Keymap) -> Vec<PickerItem> {
+    let bound = |action: &Action| {
+        keymap
+            .keys_for(KeymapMode::Normal, action)
+            .map(|keys| format!("  ({})", keymap::format_keys(&keys)))
+            .unwrap_or_default()
+    };
+    let commands = COMMANDS.iter().map(|spec| PickerItem {
+        label: format!(
+            "{} — {}{}",
+            spec.usage(),
+            spec.help,
+            bound(&Action::Ex(spec.name.to_string()))
+        ),
+        target: PickerTarget::Command(spec.name),
+    });
+    let actions = Action::all().map(|action| PickerItem {
+        label: format!(
+            "{} — {}{}",
+            action.name(),
+            action.description(),
+            bound(&action)
+        ),
+        target: PickerTarget::Action(action),
+    });
+    commands.chain(actions).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str) -> PickerItem {
+        PickerItem {
+            label: label.to_string(),
+            target: PickerTarget::File(PathBuf::from(label)),
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_match_scores() {
+        assert!(fuzzy_match("xyz", "src/main.rs").is_none());
+        let found = fuzzy_match("main", "src/main.rs").unwrap();
+        assert_eq!(found.positions, vec![4, 5, 6, 7]);
+
+        // Consecutive and boundary matches beat scattered ones
+        let boundary = fuzzy_match("ed", "src/editor.rs").unwrap();
+        let scattered = fuzzy_match("ed", "src/mode_handler.rs").unwrap();
+        assert!(boundary.score > scattered.score);
+
+        // Smart case and multiple terms
+        assert!(fuzzy_match("Readme", "readme.md").is_none());
+        assert!(fuzzy_match("readme", "README.md").is_some());
+        assert!(fuzzy_match("doc 設計", "docs/設計メモ.md").is_some());
+        assert!(fuzzy_match("doc 設計", "docs/memo.md").is_none());
+    }
+
+    #[test]
+    fn test_picker_filter_and_selection() {
+        let items = vec![
+            item("docs/mode_handler.md"),
+            item("src/editor.rs"),
+            item("README.md"),
+        ];
+        let mut picker = Picker::new(PickerKind::Files, items, "");
+        assert_eq!(picker.matches().len(), 3);
+        picker.move_selection(5);
+        assert_eq!(picker.selected(), 2);
+
+        picker.push_char('e');
+        picker.push_char('d');
+        assert_eq!(picker.selected(), 0);
+        assert_eq!(picker.selected_item().unwrap().label, "src/editor.rs");
+        picker.push_char('i');
+        assert_eq!(picker.matches().len(), 1);
+
+        picker.push_char('q');
+        assert!(picker.selected_item().is_none());
+        picker.clear_query();
+        assert_eq!(picker.matches().len(), picker.total());
+    }
+
+    #[test]
+    fn test_command_items() {
+        let items = command_items(&Keymap::new());
+        let undo = items
+            .iter()
+            .find(|item| item.target == PickerTarget::Action(Action::Undo))
+            .unwrap();
+        assert_eq!(undo.label, "undo — 元に戻す  (u)");
+        assert!(items
+            .iter()
+            .any(|item| item.target == PickerTarget::Command("Explore")));
+
+        let mut picker = Picker::new(PickerKind::Commands, items, "vsplit");
+        assert_eq!(
+            picker.selected_item().unwrap().target,
+            PickerTarget::Command("vsplit")
+        );
+        picker.clear_query();
+        assert!(picker.matches().len() > COMMANDS.len());
+    }
+
+    #[test]
+    fn test_heading_items() {
+        let items = heading_items(&["# Title\n", "text\n", "## Part ##\n"]);
+        assert_eq!(items[1].label, "## Part");
+        assert_eq!(items[1].target, PickerTarget::Line(2));
+    }
+}
@@ -0,0 +1,85 @@
+//! 最近開いたファイルの履歴。
+//!
+//! データディレクトリの `recent_files.json` に保存し、起動時に読み込みます。
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Number of files remembered.
+pub const RECENT_FILES_MAX: usize = 50;
+
+/// Recently opened files, most recent first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    fn data_path() -> Result<PathBuf> {
+        if let Ok(dir) = std::env::var("SCRIPTORIS_DATA_DIR") {
+            return Ok(PathBuf::from(dir).join("recent_files.json"));
+        }
+        let dirs = directories::ProjectDirs::from("com", "scriptoris", "scriptoris")
+            .ok_or_else(|| anyhow::anyhow!("プロジェクトディレクトリを特定できませんでした"))?;
+        Ok(dirs.data_dir().join("recent_files.json"))
+    }
+
+    /// Load the saved history. A missing or broken file gives an empty one.
+    pub async fn load() -> Self {
+        let Ok(path) = Self::data_path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!("Failed to parse recent files '{}': {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let path = Self::data_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    /// Move `path` to the front of the history, stored as an absolute path.
+    pub fn add(&mut self, path: &Path) {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.paths.retain(|known| *known != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(RECENT_FILES_MAX);
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_files_order_and_limit() {
+        let mut recent = RecentFiles::default();
+        recent.add(Path::new("/tmp/a.md"));
+        recent.add(Path::new("/tmp/b.md"));
+        recent.add(Path::new("/tmp/a.md"));
+        assert_eq!(
+            recent.paths(),
+            [PathBuf::from("/tmp/a.md"), PathBuf::from("/tmp/b.md")]
+        );
+
+        for i in 0..RECENT_FILES_MAX + 5 {
+            recent.add(Path::new(&format!("/tmp/{}.md", i)));
+        }
+        assert_eq!(recent.paths().len(), RECENT_FILES_MAX);
+    }
+}
@@ -11,6 +11,7 @@ use tokio::fs::try_exists;
 use crate::config::Config;
 use crate::editor::Editor;
 use crate::file_manager::FileManager;
+use crate::folding::ClosedFold;
 
 /// Session data that can be saved and restored
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +25,9 @@ pub struct SessionData {
     pub viewport_offset: usize,
     pub editor_config: EditorConfigSnapshot,
     pub readonly: bool,
+    /// Closed folds in the current file
+    #[serde(default)]
+    pub folds: Vec<ClosedFold>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,6 +118,7 @@ impl SessionManager {
                 wrap_lines: config.editor.wrap_lines,
             },
             readonly: file_manager.is_readonly(),
+            folds: editor.closed_folds(),
         };
         let json = serde_json::to_string_pretty(&session_data)?;
         fs::write(&filepath, json).await?;
@@ -167,6 +172,21 @@ impl SessionManager {
         Ok(sessions)
     }
 
+    /// Names of the saved sessions, sorted, for command-line completion
+    pub fn session_names(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.session_dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        names.sort();
+        names
+    }
+
     /// Delete session by name
     pub async fn delete_session(&self, name: &str) -> Result<String> {
         let filename = format!("{}.json", name);
@@ -258,6 +278,41 @@ mod tests {
         assert!(delete_message.contains("削除"));
     }
 
+    #[tokio::test]
+    async fn test_session_keeps_closed_folds() {
+        let _data_dir_guard = {
+            let _guard = session_test_lock().lock().unwrap();
+            let data_dir = TempDir::new().unwrap();
+            EnvGuard::new(data_dir.path())
+        }; // lock is released, but EnvGuard persists
+
+        let manager = SessionManager::new().expect("session manager should initialize");
+
+        let mut editor = Editor::new();
+        editor.set_content("# Title\n\n## Part\ntext\nmore\n".to_string());
+        editor.set_cursor_position(3, 0);
+        assert!(editor.close_fold());
+
+        let file_manager = FileManager::new();
+        let config = Config::default();
+        manager
+            .save_session("folds", &editor, &file_manager, &config)
+            .await
+            .expect("session should save");
+
+        let loaded = manager
+            .load_session("folds")
+            .await
+            .expect("session should load");
+        assert_eq!(loaded.folds.len(), 1);
+        assert_eq!(loaded.folds[0].header, "## Part");
+
+        let mut restored = Editor::new();
+        restored.set_content("# Title\n\n## Part\ntext\nmore\n".to_string());
+        restored.restore_closed_folds(loaded.folds);
+        assert_eq!(restored.display_rows().len(), 4);
+    }
+
     #[tokio::test]
     async fn test_load_missing_session_returns_error() {
         let _data_dir_guard = {
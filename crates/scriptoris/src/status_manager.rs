@@ -1,5 +1,11 @@
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Local};
+
+/// Number of messages kept for `:messages`.
+pub const MESSAGE_HISTORY_LIMIT: usize = 200;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum MessageType {
     Info,
@@ -61,10 +67,21 @@ impl StatusMessage {
     }
 }
 
+/// A status message as kept in the history.
+#[derive(Debug, Clone)]
+pub struct LoggedMessage {
+    pub message_type: MessageType,
+    pub content: String,
+    pub time: DateTime<Local>,
+}
+
 #[derive(Clone)]
 pub struct StatusManager {
     pub current_message: Option<StatusMessage>,
     pub mode_message: String,
+    // Every message shown, oldest first, so that ones that expired can be
+    // read again with `:messages`
+    history: VecDeque<LoggedMessage>,
 }
 
 impl StatusManager {
@@ -72,23 +89,50 @@ impl StatusManager {
         Self {
             current_message: None,
             mode_message: String::new(),
+            history: VecDeque::new(),
         }
     }
 
+    fn set(&mut self, message: String, message_type: MessageType) {
+        if self.history.len() == MESSAGE_HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(LoggedMessage {
+            message_type: message_type.clone(),
+            content: message.clone(),
+            time: Local::now(),
+        });
+        self.current_message = Some(StatusMessage::new(message, message_type));
+    }
+
     pub fn set_info(&mut self, message: String) {
-        self.current_message = Some(StatusMessage::new(message, MessageType::Info));
+        self.set(message, MessageType::Info);
     }
 
     pub fn set_success(&mut self, message: String) {
-        self.current_message = Some(StatusMessage::new(message, MessageType::Success));
+        self.set(message, MessageType::Success);
     }
 
     pub fn set_warning(&mut self, message: String) {
-        self.current_message = Some(StatusMessage::new(message, MessageType::Warning));
+        self.set(message, MessageType::Warning);
     }
 
     pub fn set_error(&mut self, message: String) {
-        self.current_message = Some(StatusMessage::new(message, MessageType::Error));
+        self.set(message, MessageType::Error);
+    }
+
+    /// Show a prompt without recording it, since prompts are shown again
+    /// on every key typed into them.
+    pub fn set_prompt(&mut self, message: String) {
+        self.current_message = Some(StatusMessage::new(message, MessageType::Warning));
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &LoggedMessage> {
+        self.history.iter()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
     }
 
     pub fn set_mode_message(&mut self, message: String) {
@@ -192,6 +236,33 @@ mod tests {
         assert!(manager.current_message.is_none());
     }
 
+    #[test]
+    fn test_history_is_bounded_and_typed() {
+        let mut manager = StatusManager::new();
+        manager.set_error("保存に失敗しました".to_string());
+        manager.current_message = None;
+        let logged: Vec<_> = manager.history().collect();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].message_type, MessageType::Error);
+        assert_eq!(logged[0].content, "保存に失敗しました");
+
+        for n in 0..MESSAGE_HISTORY_LIMIT {
+            manager.set_info(format!("message {}", n));
+        }
+        assert_eq!(manager.history().count(), MESSAGE_HISTORY_LIMIT);
+        assert_eq!(manager.history().next().unwrap().content, "message 0");
+
+        manager.clear_history();
+        assert_eq!(manager.history().count(), 0);
+
+        manager.set_prompt("I-search: ab".to_string());
+        assert_eq!(
+            manager.get_current_message().unwrap().content,
+            "I-search: ab"
+        );
+        assert_eq!(manager.history().count(), 0);
+    }
+
     #[test]
     fn test_update_expired_message() {
         let mut manager = StatusManager::new();
@@ -0,0 +1,271 @@
+//! 設定可能なステータスライン。
+//!
+//! ステータスラインはセグメント（モード・ファイル名・変更フラグ・文字コード・
+//! 改行コード・ファイルタイプ・カーソル位置・割合・単語数・選択範囲・LSP の状態・
+//! Git ブランチ・マクロ記録中の表示など）を左寄せ・中央・右寄せの 3 か所に並べた
+//! ものです。並び順と色は設定ファイルの `statusline` で変更できます。
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::app::Mode;
+use crate::config::{parse_hex_color, SegmentConfig, StatusLineConfig};
+use crate::keymap::Keymap;
+use crate::text_width::TextWidthCalculator;
+
+/// A piece of information the status line can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Segment {
+    Mode,
+    FileName,
+    Modified,
+    Encoding,
+    LineEnding,
+    FileType,
+    Position,
+    Percentage,
+    WordCount,
+    Selection,
+    Lsp,
+    GitBranch,
+    Recording,
+    /// Keys typed so far of an unfinished multi-key sequence.
+    PendingKeys,
+}
+
+/// What the segments show, gathered from the app before drawing.
+#[derive(Debug, Clone, Default)]
+pub struct StatusInfo {
+    pub mode: Option<Mode>,
+    pub mode_label: &'static str,
+    pub file_name: Option<String>,
+    pub modified: bool,
+    pub encoding: String,
+    pub line_ending: &'static str,
+    pub file_type: String,
+    /// Zero-based cursor line and column.
+    pub line: usize,
+    pub col: usize,
+    pub line_count: usize,
+    /// `None` in large-file mode, where counting would scan the whole text.
+    pub word_count: Option<usize>,
+    /// Selected lines and characters in visual mode.
+    pub selection: Option<(usize, usize)>,
+    /// Running language server and the error and warning counts of the buffer.
+    pub lsp: Option<(String, usize, usize)>,
+    pub git_branch: Option<String>,
+    pub recording: Option<char>,
+    pub pending_keys: String,
+}
+
+/// Name of the mode shown in the status line; modeless keymaps show their
+/// style instead of NORMAL.
+pub fn mode_label(mode: &Mode, keymap: &Keymap) -> &'static str {
+    match mode {
+        Mode::Normal if keymap.is_modeless() => match keymap.style() {
+            crate::config::KeybindingStyle::Nano => "NANO",
+            _ => "EMACS",
+        },
+        Mode::Normal => "NORMAL",
+        Mode::Insert => "INSERT",
+        Mode::Command => "COMMAND",
+        Mode::Visual => "VISUAL",
+        Mode::VisualBlock => "VISUAL BLOCK",
+        Mode::Replace => "REPLACE",
+        Mode::Help => "HELP",
+        Mode::SavePrompt => "SAVE PROMPT",
+    }
+}
+
+pub fn mode_color(mode: &Mode) -> Color {
+    match mode {
+        Mode::Normal => Color::Blue,
+        Mode::Insert => Color::Green,
+        Mode::Command => Color::Yellow,
+        Mode::Visual => Color::Magenta,
+        Mode::VisualBlock | Mode::Help => Color::Cyan,
+        Mode::Replace | Mode::SavePrompt => Color::Red,
+    }
+}
+
+/// The text of `segment`, or `None` when it has nothing to show.
+pub fn segment_text(segment: Segment, info: &StatusInfo) -> Option<String> {
+    let text = match segment {
+        Segment::Mode => info.mode_label.to_string(),
+        Segment::FileName => info
+            .file_name
+            .clone()
+            .unwrap_or_else(|| "[新規ファイル]".to_string()),
+        Segment::Modified if info.modified => "[+]".to_string(),
+        Segment::Encoding => info.encoding.clone(),
+        Segment::LineEnding => info.line_ending.to_string(),
+        Segment::FileType => info.file_type.clone(),
+        Segment::Position => format!(
+            "行 {}/{}, 桁 {}",
+            info.line + 1,
+            info.line_count,
+            info.col + 1
+        ),
+        Segment::Percentage => format!("{}%", info.line * 100 / info.line_count.max(1)),
+        Segment::WordCount => format!("{} 語", info.word_count?),
+        Segment::Selection => {
+            let (lines, chars) = info.selection?;
+            format!("選択 {}行 {}字", lines, chars)
+        }
+        Segment::Lsp => {
+            let (server, errors, warnings) = info.lsp.as_ref()?;
+            format!("LSP {} E{} W{}", server, errors, warnings)
+        }
+        Segment::GitBranch => format!("⎇ {}", info.git_branch.as_ref()?),
+        Segment::Recording => format!("記録中 @{}", info.recording?),
+        Segment::PendingKeys => info.pending_keys.clone(),
+        Segment::Modified => return None,
+    };
+    Some(text).filter(|text| !text.is_empty())
+}
+
+fn default_style(segment: Segment, info: &StatusInfo) -> Style {
+    let style = Style::default();
+    match segment {
+        Segment::Mode => style
+            .fg(info.mode.as_ref().map_or(Color::Blue, mode_color))
+            .add_modifier(Modifier::BOLD),
+        Segment::FileName => style.fg(Color::Cyan),
+        Segment::Modified | Segment::Recording => style.fg(Color::Red),
+        Segment::Position => style.fg(Color::White),
+        Segment::Percentage => style.fg(Color::Green),
+        Segment::GitBranch => style.fg(Color::Magenta),
+        Segment::Lsp | Segment::Selection => style.fg(Color::LightBlue),
+        Segment::PendingKeys => style.fg(Color::Yellow),
+        Segment::Encoding | Segment::LineEnding | Segment::FileType | Segment::WordCount => {
+            style.fg(Color::Gray)
+        }
+    }
+}
+
+fn render_group(configs: &[SegmentConfig], info: &StatusInfo) -> Vec<Span<'static>> {
+    configs
+        .iter()
+        .filter_map(|config| {
+            let text = segment_text(config.segment, info)?;
+            let mut style = default_style(config.segment, info);
+            if let Some(fg) = config.fg.as_deref().and_then(parse_hex_color) {
+                style = style.fg(fg);
+            }
+            if let Some(bg) = config.bg.as_deref().and_then(parse_hex_color) {
+                style = style.bg(bg);
+            }
+            Some(Span::styled(format!(" {} ", text), style))
+        })
+        .collect()
+}
+
+/// Lay out the configured segments on a line `width` columns wide: the left
+/// group from the left edge, the center group centered, the right group
+/// against the right edge.
+pub fn render(
+    config: &StatusLineConfig,
+    info: &StatusInfo,
+    width: usize,
+    calculator: &TextWidthCalculator,
+) -> Line<'static> {
+    let left = render_group(&config.left, info);
+    let center = render_group(&config.center, info);
+    let right = render_group(&config.right, info);
+    let group_width = |spans: &[Span]| -> usize {
+        spans
+            .iter()
+            .map(|span| calculator.str_width(&span.content))
+            .sum()
+    };
+    let (left_width, center_width, right_width) = (
+        group_width(&left),
+        group_width(&center),
+        group_width(&right),
+    );
+
+    let center_start = (width.saturating_sub(center_width) / 2).max(left_width);
+    let right_start = width
+        .saturating_sub(right_width)
+        .max(center_start + center_width);
+
+    let mut spans = left;
+    spans.push(Span::raw(" ".repeat(center_start - left_width)));
+    spans.extend(center);
+    spans.push(Span::raw(
+        " ".repeat(right_start - center_start - center_width),
+    ));
+    spans.extend(right);
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_segment_texts() {
+        let mut info = StatusInfo {
+            mode_label: "NORMAL",
+            line: 9,
+            col: 2,
+            line_count: 40,
+            ..Default::default()
+        };
+        assert_eq!(
+            segment_text(Segment::FileName, &info).unwrap(),
+            "[新規ファイル]"
+        );
+        assert_eq!(segment_text(Segment::Modified, &info), None);
+        assert_eq!(
+            segment_text(Segment::Position, &info).unwrap(),
+            "行 10/40, 桁 3"
+        );
+        assert_eq!(segment_text(Segment::Percentage, &info).unwrap(), "22%");
+        assert_eq!(segment_text(Segment::Recording, &info), None);
+        info.recording = Some('q');
+        info.selection = Some((2, 15));
+        assert_eq!(
+            segment_text(Segment::Recording, &info).unwrap(),
+            "記録中 @q"
+        );
+        assert_eq!(
+            segment_text(Segment::Selection, &info).unwrap(),
+            "選択 2行 15字"
+        );
+    }
+
+    #[test]
+    fn test_render_aligns_groups() {
+        let config: StatusLineConfig = serde_json::from_str(
+            r##"{
+                "left": [{"segment": "mode"}],
+                "center": [{"segment": "modified", "fg": "#FF0000"}],
+                "right": [{"segment": "percentage"}]
+            }"##,
+        )
+        .unwrap();
+        let info = StatusInfo {
+            mode_label: "NORMAL",
+            modified: true,
+            line_count: 1,
+            ..Default::default()
+        };
+        let line = render(&config, &info, 30, &TextWidthCalculator::new());
+        assert_eq!(text(&line), " NORMAL      [+]           0% ");
+        let modified = line.spans.iter().find(|s| s.content == " [+] ").unwrap();
+        assert_eq!(modified.style.fg, Some(Color::Rgb(255, 0, 0)));
+
+        // Groups that do not fit push the later ones right instead of overlapping
+        let line = render(&config, &info, 10, &TextWidthCalculator::new());
+        assert_eq!(text(&line), " NORMAL  [+]  0% ");
+    }
+}
@@ -0,0 +1,289 @@
+//! スワップファイルによるクラッシュからの復旧。
+//!
+//! 変更されたまま保存されていないバッファの内容を、一定間隔で復旧用の
+//! ディレクトリ（データディレクトリの `swap`）に書き出し、保存や終了のときに
+//! 削除します。ターミナルが落ちるなどして残ったスワップファイルは、次に
+//! そのファイルを開いたときに見つかり、復旧・読み取り専用で開く・削除・中止を
+//! 選べます。記録されたプロセス ID から、別の Scriptoris が同じファイルを
+//! 編集中かどうかも判別します。プロセス ID は再利用されるので、自分の
+//! スワップファイルかどうかは起動ごとのセッション ID で見分けます。
+//! 内容を他のユーザーが読めないよう、スワップファイルは所有者だけが読み書き
+//! できる権限で作ります。
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::file_manager::write_atomic;
+
+/// How often modified buffers are written to their swap files.
+pub const SWAP_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Longest swap file name, leaving room for the temporary file suffix
+/// within the usual 255-byte limit.
+const MAX_NAME_LEN: usize = 200;
+
+/// Unsaved contents of a file, as written by the instance with `pid`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapFile {
+    pub file: PathBuf,
+    pub pid: u32,
+    /// Identifies the run that wrote the file, as pids get reused.
+    #[serde(default)]
+    pub session: String,
+    pub saved_at: DateTime<Local>,
+    pub content: String,
+}
+
+/// A token unique to this run of the program.
+fn session_id() -> &'static str {
+    static SESSION: OnceLock<String> = OnceLock::new();
+    SESSION.get_or_init(|| {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        format!("{}-{}", std::process::id(), started.as_nanos())
+    })
+}
+
+impl SwapFile {
+    pub fn new(file: &Path, content: String) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            pid: std::process::id(),
+            session: session_id().to_string(),
+            saved_at: Local::now(),
+            content,
+        }
+    }
+
+    /// Whether this run of the program wrote the swap file.
+    pub fn is_ours(&self) -> bool {
+        self.session == session_id()
+    }
+
+    /// Whether the instance that wrote this swap file is still running.
+    pub fn is_running(&self) -> bool {
+        self.pid != std::process::id() && process_running(self.pid)
+    }
+
+    /// The question shown when the swap file is found.
+    pub fn prompt_message(&self) -> String {
+        let owner = if self.is_running() {
+            format!("PID {} で編集中", self.pid)
+        } else {
+            format!("PID {}、終了済み", self.pid)
+        };
+        format!(
+            "スワップファイルがあります ({}、{}): [r]復旧 [o]読み取り専用 [d]削除 [a]中止",
+            owner,
+            self.saved_at.format("%Y-%m-%d %H:%M:%S")
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_running(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn process_running(_pid: u32) -> bool {
+    false
+}
+
+/// The directory holding swap files, one per edited file.
+#[derive(Debug, Clone)]
+pub struct SwapStore {
+    dir: PathBuf,
+}
+
+impl SwapStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The store in the data directory.
+    pub fn open() -> Option<Self> {
+        if let Ok(dir) = std::env::var("SCRIPTORIS_DATA_DIR") {
+            return Some(Self::new(PathBuf::from(dir).join("swap")));
+        }
+        directories::ProjectDirs::from("com", "scriptoris", "scriptoris")
+            .map(|dirs| Self::new(dirs.data_dir().join("swap")))
+    }
+
+    /// Swap file of `file`, named after its full path so that files with
+    /// the same name in different directories do not collide. Long paths
+    /// keep their end behind a hash of the whole path.
+    pub fn path_for(&self, file: &Path) -> PathBuf {
+        let full = std::fs::canonicalize(file)
+            .or_else(|_| std::path::absolute(file))
+            .unwrap_or_else(|_| file.to_path_buf());
+        let mut name = full.to_string_lossy().replace(['/', '\\', ':'], "%");
+        if name.len() > MAX_NAME_LEN {
+            let hash = format!("{:016x}", fnv1a(name.as_bytes()));
+            let mut start = name.len() - (MAX_NAME_LEN - hash.len());
+            while !name.is_char_boundary(start) {
+                start += 1;
+            }
+            name = format!("{}{}", hash, &name[start..]);
+        }
+        self.dir.join(format!("{}.swp", name))
+    }
+
+    pub fn load(&self, file: &Path) -> Option<SwapFile> {
+        let json = std::fs::read_to_string(self.path_for(file)).ok()?;
+        serde_json::from_str(&json)
+            .map_err(|e| log::warn!("Failed to parse swap file for {}: {}", file.display(), e))
+            .ok()
+    }
+
+    /// Write `swap`, readable by the owner only; `write_atomic` keeps the
+    /// mode of the file created here.
+    pub fn write(&self, swap: &SwapFile) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path_for(&swap.file);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        if let Err(e) = options.open(&path) {
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(e.into());
+            }
+        }
+        let json = serde_json::to_string(swap)?;
+        write_atomic(&path, json.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn remove(&self, file: &Path) {
+        let path = self.path_for(file);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove swap file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// The swap file to ask about when `file` is opened with `content` on
+    /// disk. Our own swap files are ignored, and a leftover one that matches
+    /// the file is removed without asking.
+    pub fn check(&self, file: &Path, content: &str) -> Option<SwapFile> {
+        let swap = self.load(file)?;
+        if swap.is_ours() {
+            return None;
+        }
+        if !swap.is_running() && swap.content == content {
+            self.remove(file);
+            return None;
+        }
+        Some(swap)
+    }
+
+    /// Every swap file in the store, oldest first.
+    pub fn list(&self) -> Vec<SwapFile> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut swaps: Vec<SwapFile> = entries
+            .filter_map(|entry| std::fs::read_to_string(entry.ok()?.path()).ok())
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect();
+        swaps.sort_by_key(|swap| swap.saved_at);
+        swaps
+    }
+}
+
+/// 64-bit FNV-1a, a hash that stays the same across builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_round_trip_and_check() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = SwapStore::new(dir.path().join("swap"));
+        let file = dir.path().join("notes.md");
+        std::fs::write(&file, "saved").unwrap();
+
+        // Our own swap file is never reported
+        store
+            .write(&SwapFile::new(&file, "unsaved".to_string()))
+            .unwrap();
+        assert_eq!(store.load(&file).unwrap().content, "unsaved");
+        assert_eq!(store.check(&file, "saved"), None);
+
+        // A crashed instance left changes behind
+        let mut swap = SwapFile::new(&file, "unsaved".to_string());
+        swap.pid = u32::MAX;
+        swap.session = "crashed".to_string();
+        store.write(&swap).unwrap();
+        assert!(!swap.is_running());
+        assert_eq!(store.check(&file, "saved"), Some(swap.clone()));
+        assert_eq!(store.list(), vec![swap]);
+
+        // A leftover that matches the file on disk is cleaned up silently
+        assert_eq!(store.check(&file, "unsaved"), None);
+        assert!(store.load(&file).is_none());
+
+        // An earlier run that happened to get our pid left it behind
+        let mut swap = SwapFile::new(&file, "unsaved".to_string());
+        swap.session = "earlier".to_string();
+        store.write(&swap).unwrap();
+        assert!(!swap.is_ours() && !swap.is_running());
+        assert_eq!(store.check(&file, "saved"), Some(swap));
+    }
+
+    #[test]
+    fn test_swap_files_are_private_and_short() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = SwapStore::new(dir.path().join("swap"));
+        let deep = dir.path().join("あ".repeat(40)).join("b".repeat(200));
+        let file = deep.join("notes.md");
+        let path = store.path_for(&file);
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.len() <= MAX_NAME_LEN + ".swp".len());
+        assert!(name.ends_with("%notes.md.swp"));
+        assert!(path != store.path_for(&deep.join("other.md")));
+
+        store
+            .write(&SwapFile::new(&file, "secret".to_string()))
+            .unwrap();
+        assert_eq!(store.load(&file).unwrap().content, "secret");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_running_owner_is_detected() {
+        let mut swap = SwapFile::new(Path::new("a.md"), String::new());
+        assert!(!swap.is_running());
+        swap.pid = std::os::unix::process::parent_id();
+        assert!(swap.is_running());
+        assert!(swap.prompt_message().contains("編集中"));
+    }
+}
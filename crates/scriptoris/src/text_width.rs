@@ -13,6 +13,8 @@ pub struct TextWidthCalculator {
     east_asian_aware: bool,
     /// 絵文字の幅をどう扱うか
     emoji_width: EmojiWidth,
+    /// タブ1つが占める幅
+    tab_width: usize,
 }
 
 /// 絵文字の幅扱い
@@ -33,6 +35,7 @@ impl Default for TextWidthCalculator {
         Self {
             east_asian_aware: true,
             emoji_width: EmojiWidth::Standard,
+            tab_width: 4,
         }
     }
 }
@@ -55,22 +58,41 @@ impl TextWidthCalculator {
         self
     }
 
-    /// 文字列の表示幅を計算
+    /// タブ幅の設定
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = width;
+        self
+    }
+
+    /// 文字列の表示幅を計算（行頭から始まるものとしてタブを展開）
     pub fn str_width(&self, text: &str) -> usize {
         if text.is_empty() {
             return 0;
         }
 
         // Unicodeセグメントに分割して処理
-        text.graphemes(true).map(|g| self.grapheme_width(g)).sum()
+        text.graphemes(true)
+            .fold(0, |col, g| col + self.grapheme_width_at(g, col))
     }
 
-    /// グラフェムクラスタの表示幅を計算
+    /// グラフェムクラスタの表示幅を計算（行頭にあるものとして扱う）
     pub fn grapheme_width(&self, grapheme: &str) -> usize {
+        self.grapheme_width_at(grapheme, 0)
+    }
+
+    /// 表示カラム `col` から始まるグラフェムクラスタの表示幅を計算
+    ///
+    /// タブは次のタブストップまでの幅になります。
+    pub fn grapheme_width_at(&self, grapheme: &str, col: usize) -> usize {
         if grapheme.is_empty() {
             return 0;
         }
 
+        if grapheme == "\t" {
+            let tab_width = self.tab_width.max(1);
+            return tab_width - col % tab_width;
+        }
+
         // 制御文字は幅0
         if grapheme.chars().all(|c| c.is_control()) {
             return 0;
@@ -215,40 +237,33 @@ impl TextWidthCalculator {
         }
     }
 
-    /// 指定されたカラム位置に対応する文字インデックスを取得
-    pub fn col_to_char_index(&self, text: &str, display_col: usize) -> usize {
+    /// 表示カラム位置に対応する文字オフセット（`char` 単位）を取得
+    ///
+    /// 全角文字の右半分を指すカラムはその文字自身のオフセットになります。
+    pub fn col_to_char_offset(&self, text: &str, display_col: usize) -> usize {
         let mut current_width = 0;
-        let mut char_index = 0;
+        let mut char_offset = 0;
 
         for grapheme in text.graphemes(true) {
-            let grapheme_width = self.grapheme_width(grapheme);
-
+            if grapheme == "\n" || grapheme == "\r\n" {
+                break;
+            }
+            let grapheme_width = self.grapheme_width_at(grapheme, current_width);
             if current_width + grapheme_width > display_col {
                 break;
             }
 
             current_width += grapheme_width;
-            char_index += grapheme.len();
+            char_offset += grapheme.chars().count();
         }
 
-        char_index.min(text.len())
+        char_offset
     }
 
-    /// 指定された文字インデックスに対応する表示カラム位置を取得
-    pub fn char_index_to_col(&self, text: &str, char_index: usize) -> usize {
-        let mut current_width = 0;
-        let mut processed_chars = 0;
-
-        for grapheme in text.graphemes(true) {
-            if processed_chars >= char_index {
-                break;
-            }
-
-            current_width += self.grapheme_width(grapheme);
-            processed_chars += grapheme.len();
-        }
-
-        current_width
+    /// 文字オフセット（`char` 単位）に対応する表示カラム位置を取得
+    pub fn char_offset_to_col(&self, text: &str, char_offset: usize) -> usize {
+        let prefix: String = text.chars().take(char_offset).collect();
+        self.str_width(&prefix)
     }
 }
 
@@ -355,10 +370,21 @@ mod tests {
         // 絵文字
         assert_eq!(calc.grapheme_width("😀"), 2);
         assert_eq!(calc.grapheme_width("👍"), 2);
+
+        // タブは次のタブストップまでの幅
+        assert_eq!(calc.grapheme_width("\t"), 4);
+        assert_eq!(calc.grapheme_width_at("\t", 6), 2);
+        assert_eq!(calc.str_width("ab\tc"), 5);
+        assert_eq!(calc.str_width("abcd\t"), 8);
+        assert_eq!(calc.str_width("日\t"), 4);
+        assert_eq!(calc.col_to_char_offset("ab\tc", 3), 2);
+        assert_eq!(calc.col_to_char_offset("ab\tc", 4), 3);
+        assert_eq!(calc.char_offset_to_col("ab\tc", 3), 4);
+        assert_eq!(calc.tab_width(2).str_width("\ta"), 3);
     }
 
     #[test]
-    fn test_col_to_char_index() {
+    fn test_col_to_char_offset() {
         let calc = TextWidthCalculator::new();
         let text = "Hello世界";
 
@@ -374,13 +400,30 @@ mod tests {
         assert_eq!(calc.str_width(text), 9);
 
         // ASCII部分 (width 1 each)
-        assert_eq!(calc.col_to_char_index(text, 3), 3);
+        assert_eq!(calc.col_to_char_offset(text, 3), 3);
 
         // 日本語部分
-        // col 5 = after "Hello", at start of "世" (byte index 5)
-        assert_eq!(calc.col_to_char_index(text, 5), 5);
-        // col 7 = after "Hello世" (5+2), at start of "界" (byte index 8)
-        assert_eq!(calc.col_to_char_index(text, 7), 8);
+        // col 5 = after "Hello", at start of "世" (char offset 5)
+        assert_eq!(calc.col_to_char_offset(text, 5), 5);
+        // col 7 = after "Hello世" (5+2), at start of "界" (char offset 6)
+        assert_eq!(calc.col_to_char_offset(text, 7), 6);
+        assert_eq!(calc.char_offset_to_col(text, 6), 7);
+    }
+
+    #[test]
+    fn test_col_to_char_offset_with_wide_chars() {
+        let calc = TextWidthCalculator::new();
+        let text = "Hello世界\n";
+
+        assert_eq!(calc.col_to_char_offset(text, 3), 3);
+        // 全角文字の左右どちらの半分をクリックしても同じ文字になる
+        assert_eq!(calc.col_to_char_offset(text, 5), 5);
+        assert_eq!(calc.col_to_char_offset(text, 6), 5);
+        assert_eq!(calc.col_to_char_offset(text, 7), 6);
+        // 行末を越えた位置は改行の手前で止まる
+        assert_eq!(calc.col_to_char_offset(text, 40), 7);
+
+        assert_eq!(calc.char_offset_to_col(text, 6), 7);
     }
 
     #[test]
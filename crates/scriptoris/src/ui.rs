@@ -12,6 +12,14 @@ use ratatui::{
 };
 
 use crate::app::{App, Mode};
+use crate::command_processor::COMMANDS;
+use crate::gutter::Gutter;
+use crate::highlight;
+use crate::listchars::Whitespace;
+use crate::mouse::{ClickAction, PaneRegion};
+use crate::overlay;
+use crate::statusline;
+use crate::view;
 
 fn parse_color(value: &str) -> Option<Color> {
     let hex = value.trim().trim_start_matches('#');
@@ -64,16 +72,26 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         return;
     }
 
+    // Positions are re-recorded on every draw for mouse hit testing
+    app.screen_layout.clear();
+
     // Draw components
     draw_title_bar(f, app, chunks[0]);
 
     if app.show_help() {
         draw_help(f, chunks[1]);
     } else {
-        draw_editor_panes(f, app, chunks[1]);
+        let (sidebar, editor_area) = overlay::explorer_layout(app, chunks[1]);
+        draw_editor_panes(f, app, editor_area);
+        if let Some(sidebar) = sidebar {
+            overlay::draw_explorer(f, app, sidebar);
+        }
     }
 
     draw_status_bar(f, app, chunks[2]);
+    overlay::draw_wildmenu(f, app, chunks[2]);
+    overlay::draw_picker(f, app);
+    overlay::draw_pager(f, app);
 }
 
 fn draw_editor_panes(f: &mut Frame, app: &mut App, area: Rect) {
@@ -137,7 +155,7 @@ fn draw_editor_panes(f: &mut Frame, app: &mut App, area: Rect) {
 
 fn draw_single_pane(f: &mut Frame, app: &mut App, area: Rect, pane: &WindowPane, is_current: bool) {
     if let Some(buffer_index) = app.buffer_manager.find_index_by_id(pane.buffer_id) {
-        draw_buffer_by_index(f, app, buffer_index, area, is_current);
+        draw_buffer_by_index(f, app, buffer_index, pane.id, area, is_current);
     }
 }
 
@@ -145,10 +163,32 @@ fn draw_buffer_by_index(
     f: &mut Frame,
     app: &mut App,
     buffer_index: usize,
+    window_id: usize,
     area: Rect,
     is_current: bool,
 ) {
-    let (buffer_title, filename, viewport_lines, viewport_offset, cursor_line, cursor_col) = {
+    // Keep the editor's notion of the viewport in sync with the pane size
+    // (borders take two rows/columns, the gutter its own width when shown)
+    let options = app.editor_options(buffer_index);
+    let signs = app.buffer_signs(buffer_index);
+    let line_count = app.buffer_manager.buffers[buffer_index]
+        .content
+        .line_count();
+    let gutter = Gutter::new(&options, &signs, line_count);
+    let gutter_width = gutter.width();
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let text_width = area.width.saturating_sub(2 + gutter_width) as usize;
+    let wrap_width = options.wrap.then_some(text_width);
+    let showbreak_width = app.text_calculator.str_width(&app.config.editor.showbreak);
+    {
+        let editor = &mut app.buffer_manager.buffers[buffer_index].content;
+        if inner_height > 0 {
+            editor.set_viewport_height(inner_height);
+        }
+        editor.set_wrap(wrap_width, showbreak_width);
+    }
+
+    let (buffer_title, filename, viewport_lines, viewport_offset, rows, cursor_screen, cursor_line) = {
         let buffer = &app.buffer_manager.buffers[buffer_index];
         let title = buffer
             .file_path
@@ -165,14 +205,17 @@ fn draw_buffer_by_index(
             .unwrap_or_else(|| "text.md".to_string());
         let viewport_lines = buffer.content.get_viewport_lines();
         let viewport_offset = buffer.content.get_viewport_offset();
-        let (cursor_line, cursor_col) = buffer.content.cursor_position();
+        let rows = buffer.content.display_rows();
+        let cursor_screen = buffer.content.cursor_screen_position();
+        let (cursor_line, _) = buffer.content.cursor_position();
         (
             title,
             filename,
             viewport_lines,
             viewport_offset,
+            rows,
+            cursor_screen,
             cursor_line,
-            cursor_col,
         )
     };
 
@@ -215,41 +258,72 @@ fn draw_buffer_by_index(
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Length(5), // Line numbers
-            Constraint::Min(0),    // Content
+            Constraint::Length(gutter_width), // Line numbers
+            Constraint::Min(0),               // Content
         ])
         .split(inner);
 
-    // Draw line numbers
-    let line_numbers: Vec<String> = (0..viewport_lines.len())
-        .map(|i| format!("{:4}", viewport_offset + i + 1))
-        .collect();
-
-    let line_numbers_widget =
-        Paragraph::new(line_numbers.join("\n")).style(Style::default().fg(inactive_border));
-
-    f.render_widget(line_numbers_widget, chunks[0]);
-
-    let highlighter = app.get_highlighter();
-    let syntax = highlighter.find_syntax_for_filename(&filename);
-    let content_lines = highlighter.highlight_lines_to_ratatui(&viewport_lines, syntax);
+    // Draw the sign column and line numbers (continuation rows of wrapped
+    // lines get none)
+    let gutter_lines = gutter.render(
+        &rows,
+        cursor_line,
+        &options,
+        &signs,
+        Style::default().fg(inactive_border),
+        Style::default().fg(accent_color),
+    );
+    f.render_widget(Paragraph::new(gutter_lines), chunks[0]);
+
+    app.screen_layout.push_pane(PaneRegion {
+        window_id,
+        buffer_id: app.buffer_manager.buffers[buffer_index].id,
+        text_area: chunks[1],
+    });
+
+    let showbreak = app.config.editor.showbreak.clone();
+    let whitespace = Whitespace::new(&app.config, &options);
+    let highlighted = if app.buffer_manager.buffers[buffer_index]
+        .content
+        .is_large_file()
+    {
+        highlight::plain_lines(&viewport_lines)
+    } else {
+        let highlighter = app.get_highlighter();
+        let syntax = highlighter.find_syntax_for_filename(&filename);
+        highlighter.highlight_lines_to_ratatui(&viewport_lines, syntax)
+    };
+    let mut content_lines = view::render_rows(
+        &rows,
+        &highlighted,
+        viewport_offset,
+        &showbreak,
+        Style::default().fg(inactive_border),
+        &whitespace,
+    );
+
+    // Current line (underlined, as in Vim) and column highlight
+    if options.cursorline {
+        let underline = Style::default().add_modifier(Modifier::UNDERLINED);
+        for (row, line) in rows.iter().zip(content_lines.iter_mut()) {
+            if row.line == cursor_line {
+                *line = view::patch_line(line, underline);
+            }
+        }
+    }
+    if let (true, Some((_, display_col))) = (options.cursorcolumn, cursor_screen) {
+        let reversed = Style::default().add_modifier(Modifier::REVERSED);
+        for line in content_lines.iter_mut() {
+            *line = view::patch_column(line, display_col, reversed, &app.text_calculator);
+        }
+    }
     let content_widget = Paragraph::new(content_lines).style(Style::default().fg(editor_fg));
     f.render_widget(content_widget, chunks[1]);
 
     // Draw cursor if this is the current window
-    if is_current
-        && cursor_line >= viewport_offset
-        && cursor_line < viewport_offset + viewport_lines.len()
-    {
-        let screen_line = cursor_line - viewport_offset;
-        let line_text = viewport_lines.get(screen_line).cloned().unwrap_or_default();
-
-        // Use accurate text width calculation for cross-platform compatibility
-        let logical_prefix: String = line_text.chars().take(cursor_col).collect();
-        let display_col: usize = app.text_calculator.str_width(&logical_prefix);
-
+    if let (true, Some((screen_row, display_col))) = (is_current, cursor_screen) {
         let x = chunks[1].x + display_col as u16;
-        let y = chunks[1].y + screen_line as u16;
+        let y = chunks[1].y + screen_row as u16;
 
         if x < chunks[1].x + chunks[1].width && y < chunks[1].y + chunks[1].height {
             f.set_cursor(x, y);
@@ -257,7 +331,7 @@ fn draw_buffer_by_index(
     }
 }
 
-fn draw_title_bar(f: &mut Frame, app: &App, area: Rect) {
+fn draw_title_bar(f: &mut Frame, app: &mut App, area: Rect) {
     let title = match app.file_path() {
         Some(path) => format!("  Scriptoris -- {}", path.display()),
         None => String::from("  Scriptoris -- [新規ファイル]"),
@@ -284,15 +358,68 @@ fn draw_title_bar(f: &mut Frame, app: &App, area: Rect) {
         .as_deref()
         .and_then(parse_color)
         .unwrap_or(Color::White);
+    let accent = app
+        .config
+        .theme
+        .accent_color
+        .as_deref()
+        .and_then(parse_color)
+        .unwrap_or(Color::Yellow);
+
+    // With several buffers open, the right side of the title bar becomes a
+    // clickable tab line
+    let tabs = app.buffer_tabs();
+    let tab_widths: Vec<usize> = tabs
+        .iter()
+        .map(|(_, label, _)| app.text_calculator.str_width(label))
+        .collect();
+    let tabs_width = if tabs.len() > 1 {
+        (tab_widths.iter().sum::<usize>() as u16).min(area.width / 2)
+    } else {
+        0
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(tabs_width)])
+        .split(area);
 
     let title_bar = Paragraph::new(title)
         .style(Style::default().bg(status_bg).fg(status_fg))
         .alignment(Alignment::Left);
 
-    f.render_widget(title_bar, area);
+    f.render_widget(title_bar, chunks[0]);
+
+    if tabs_width > 0 {
+        let spans: Vec<Span> = tabs
+            .iter()
+            .map(|(_, label, is_current)| {
+                let style = if *is_current {
+                    Style::default()
+                        .fg(status_bg)
+                        .bg(accent)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(status_fg).bg(status_bg)
+                };
+                Span::styled(label.clone(), style)
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(Line::from(spans)).style(Style::default().bg(status_bg)),
+            chunks[1],
+        );
+
+        let items: Vec<(usize, Option<ClickAction>)> = tabs
+            .iter()
+            .zip(&tab_widths)
+            .map(|((id, _, _), width)| (*width, Some(ClickAction::SwitchBuffer(*id))))
+            .collect();
+        app.screen_layout.push_row_targets(chunks[1], &items);
+    }
 }
 
-fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
+fn draw_status_bar(f: &mut Frame, app: &mut App, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -321,53 +448,64 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     // Draw command shortcuts or command input
     match app.mode() {
         Mode::Command => {
-            let input = Paragraph::new(format!("{}{}", app.status_message(), app.command_buffer()))
+            let input = Paragraph::new(format!("{}{}", app.command_prompt(), app.command_buffer()))
                 .style(Style::default().fg(accent).bg(status_bg));
             f.render_widget(input, chunks[0]);
         }
         _ => {
-            let shortcuts = vec![
-                Span::styled(
-                    ":",
-                    Style::default().fg(accent).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(" コマンド  ", Style::default().fg(status_fg)),
-                Span::styled(
-                    "i",
-                    Style::default().fg(accent).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(" 挿入  ", Style::default().fg(status_fg)),
-                Span::styled(
-                    "/",
-                    Style::default().fg(accent).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(" 検索  ", Style::default().fg(status_fg)),
-                Span::styled(
-                    "?",
-                    Style::default().fg(accent).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(" ヘルプ  ", Style::default().fg(status_fg)),
-                Span::styled(
-                    "hjkl",
-                    Style::default().fg(accent).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(" Move", Style::default().fg(status_fg)),
+            let items = [
+                (":", " コマンド  ", Some(ClickAction::EnterCommandMode)),
+                ("i", " 挿入  ", Some(ClickAction::EnterInsertMode)),
+                ("/", " 検索  ", Some(ClickAction::StartSearch)),
+                ("?", " ヘルプ  ", Some(ClickAction::ToggleHelp)),
+                ("hjkl", " Move", None),
             ];
 
+            let mut shortcuts = Vec::new();
+            let mut targets = Vec::new();
+            for (key, label, action) in items {
+                shortcuts.push(Span::styled(
+                    key,
+                    Style::default().fg(accent).add_modifier(Modifier::BOLD),
+                ));
+                shortcuts.push(Span::styled(label, Style::default().fg(status_fg)));
+                let width =
+                    app.text_calculator.str_width(key) + app.text_calculator.str_width(label);
+                targets.push((width, action));
+            }
+            app.screen_layout.push_row_targets(chunks[0], &targets);
+
             let shortcut_bar = Paragraph::new(Line::from(shortcuts))
                 .style(Style::default().bg(status_bg).fg(status_fg));
             f.render_widget(shortcut_bar, chunks[0]);
         }
     }
 
-    // Draw status message
-    let status = Paragraph::new(app.status_message().to_string())
-        .style(Style::default().fg(accent).bg(status_bg));
+    // Draw the status message, or the status line segments when there is none
+    let status_bg = app
+        .config
+        .statusline
+        .background
+        .as_deref()
+        .and_then(parse_color)
+        .unwrap_or(status_bg);
+    let status_line = if app.status_message().is_empty() {
+        let info = app.status_info();
+        statusline::render(
+            &app.config.statusline,
+            &info,
+            chunks[1].width as usize,
+            &app.text_calculator,
+        )
+    } else {
+        Line::from(app.status_message().to_string())
+    };
+    let status = Paragraph::new(status_line).style(Style::default().fg(accent).bg(status_bg));
     f.render_widget(status, chunks[1]);
 }
 
 fn draw_help(f: &mut Frame, area: Rect) {
-    let help_text = vec![
+    let mut help_text = vec![
         Line::from(""),
         Line::from(vec![Span::styled(
             " ヘルプ — Vim風キー割り当て",
@@ -390,21 +528,23 @@ fn draw_help(f: &mut Frame, area: Rect) {
         Line::from("  O       改行(上)       - 上に新しい行"),
         Line::from("  x       1文字削除       - カーソル位置の文字"),
         Line::from(""),
-        Line::from(" ファイル操作:"),
-        Line::from("  :w      保存           - ファイルを保存"),
-        Line::from("  :q      終了           - 変更なし時のみ終了"),
-        Line::from("  :q!     強制終了       - 保存せず終了"),
-        Line::from("  :wq     保存して終了   - 保存後に終了"),
-        Line::from("  :e file 開く           - 指定ファイルを開く"),
-        Line::from(""),
         Line::from(" 検索:"),
         Line::from("  /text   検索           - テキストを検索"),
+        Line::from(""),
+        Line::from(" コマンド (F2 でコマンドパレット):"),
+    ];
+    help_text.extend(
+        COMMANDS
+            .iter()
+            .map(|spec| Line::from(format!("  {}  {}", spec.usage(), spec.help))),
+    );
+    help_text.extend([
         Line::from(""),
         Line::from(vec![Span::styled(
             " ? キーでヘルプを閉じる",
             Style::default().add_modifier(Modifier::ITALIC),
         )]),
-    ];
+    ]);
 
     let help = Paragraph::new(help_text)
         .style(Style::default().fg(Color::White))
@@ -1,4 +1,5 @@
 use crate::app::Mode;
+use crate::command_processor::COMMANDS;
 use crate::status_manager::StatusManager;
 
 #[derive(Clone)]
@@ -8,6 +9,9 @@ pub struct UIState {
     pub status_manager: StatusManager,
     pub show_help: bool,
     pub command_buffer: String,
+    /// Drawn before the command buffer: `:`, `/`, `M-x ` or a nano prompt.
+    /// Kept apart from the status message so that messages cannot hide it.
+    pub command_prompt: String,
     pub should_quit: bool,
     // Command history
     pub command_history: Vec<String>,
@@ -22,6 +26,7 @@ impl UIState {
             status_manager: StatusManager::new(),
             show_help: false,
             command_buffer: String::new(),
+            command_prompt: String::new(),
             should_quit: false,
             command_history: Vec::new(),
             history_index: None,
@@ -63,13 +68,18 @@ impl UIState {
     pub fn enter_command_mode(&mut self) {
         self.mode = Mode::Command;
         self.command_buffer.clear();
-        self.status_message = ":".to_string();
+        self.command_prompt = ":".to_string();
     }
 
     pub fn enter_search_mode(&mut self) {
         self.mode = Mode::Command;
         self.command_buffer = "search ".to_string();
-        self.status_message = "/".to_string();
+        self.command_prompt = "/".to_string();
+    }
+
+    /// Replace the `:` of the command line with another prompt.
+    pub fn set_command_prompt(&mut self, prompt: &str) {
+        self.command_prompt = prompt.to_string();
     }
 
     pub fn enter_insert_mode(&mut self) {
@@ -155,6 +165,12 @@ impl UIState {
         self.status_manager.set_error(message);
     }
 
+    /// Show the question of a prompt, left out of `:messages`.
+    pub fn set_prompt_message(&mut self, message: String) {
+        self.status_message = message.clone();
+        self.status_manager.set_prompt(message);
+    }
+
     pub fn update_status(&mut self) {
         self.status_manager.update();
 
@@ -219,15 +235,12 @@ impl UIState {
     pub fn get_command_suggestions(&self, prefix: &str) -> Vec<String> {
         let mut suggestions = Vec::new();
 
-        // Built-in commands
-        let commands = vec![
-            "w", "q", "wq", "q!", "e", "help", "set", "vsplit", "split", "tabnew", "tabnext",
-            "tabprev", "buffer", "bnext", "bprev",
-        ];
-
-        for cmd in commands {
-            if cmd.starts_with(prefix) {
-                suggestions.push(cmd.to_string());
+        // Registered commands and their aliases
+        for spec in COMMANDS {
+            for name in std::iter::once(&spec.name).chain(spec.aliases) {
+                if name.starts_with(prefix) {
+                    suggestions.push(name.to_string());
+                }
             }
         }
 
@@ -281,13 +294,13 @@ mod tests {
         // Test command mode
         state.enter_command_mode();
         assert!(matches!(state.mode, Mode::Command));
-        assert_eq!(state.status_message, ":");
+        assert_eq!(state.command_prompt, ":");
         assert_eq!(state.command_buffer, "");
 
         // Test search mode
         state.enter_search_mode();
         assert!(matches!(state.mode, Mode::Command));
-        assert_eq!(state.status_message, "/");
+        assert_eq!(state.command_prompt, "/");
         assert_eq!(state.command_buffer, "search ");
     }
 
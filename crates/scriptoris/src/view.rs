@@ -0,0 +1,405 @@
+//! ソフトラップ（画面上の表示行）のレイアウト計算。
+//!
+//! バッファの論理行を表示幅に合わせて複数の表示行に分割します。
+//! 日本語の禁則処理（句読点や閉じ括弧を行頭に置かない、開き括弧を
+//! 行末に置かない）と、英単語の途中で折り返さない処理を行います。
+
+use std::ops::Range;
+
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::listchars::Whitespace;
+use crate::text_width::TextWidthCalculator;
+
+/// Marker drawn at the start of wrapped continuation rows by default.
+pub const DEFAULT_SHOWBREAK: &str = "↪ ";
+
+/// One screen row of a (possibly wrapped) buffer line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayRow {
+    /// Buffer line shown on this row.
+    pub line: usize,
+    /// Character offsets of the slice of `line` shown on this row.
+    pub start: usize,
+    pub end: usize,
+    /// Whether the line continues on the next row.
+    pub wraps: bool,
+    /// Number of lines hidden below this row by a closed fold.
+    pub folded: Option<usize>,
+}
+
+impl DisplayRow {
+    /// Continuation rows carry the showbreak marker and no line number.
+    pub fn is_continuation(&self) -> bool {
+        self.start > 0
+    }
+}
+
+/// 行頭禁則文字（折り返した行の先頭に来てはいけない文字）
+const LINE_START_PROHIBITED: &str = "、。，．,.:;?!？！：；・…‥)]}）］｝〕〉》」』】〙〗〟’”｠»\
+    ゝゞーヽヾ々ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ";
+
+/// 行末禁則文字（行の末尾に来てはいけない文字）
+const LINE_END_PROHIBITED: &str = "([{（［｛〔〈《「『【〘〖〝‘“｟«";
+
+pub fn is_line_start_prohibited(c: char) -> bool {
+    LINE_START_PROHIBITED.contains(c)
+}
+
+pub fn is_line_end_prohibited(c: char) -> bool {
+    LINE_END_PROHIBITED.contains(c)
+}
+
+struct Grapheme {
+    /// Character offset of the grapheme within the line
+    offset: usize,
+    width: usize,
+    first: char,
+}
+
+/// Split `text` into display rows of at most `width` columns and return the
+/// character range shown on each row. Continuation rows lose
+/// `continuation_indent` columns to the showbreak marker.
+///
+/// A line always produces at least one (possibly empty) row.
+pub fn wrap_line(
+    calculator: &TextWidthCalculator,
+    text: &str,
+    width: usize,
+    continuation_indent: usize,
+) -> Vec<Range<usize>> {
+    let text = text.trim_end_matches(['\n', '\r']);
+    let mut graphemes = Vec::new();
+    let mut offset = 0;
+    // Tabs stop relative to the start of the line, as they are drawn
+    let mut col = 0;
+    for grapheme in text.graphemes(true) {
+        let width = calculator.grapheme_width_at(grapheme, col);
+        graphemes.push(Grapheme {
+            offset,
+            width,
+            first: grapheme.chars().next().unwrap_or(' '),
+        });
+        offset += grapheme.chars().count();
+        col += width;
+    }
+    let total_chars = offset;
+    let char_at = |index: usize| graphemes.get(index).map_or(total_chars, |g| g.offset);
+
+    if width == 0 || graphemes.is_empty() {
+        return std::iter::once(0..total_chars).collect();
+    }
+
+    let mut rows = Vec::new();
+    let mut row_start = 0;
+    loop {
+        let available = if rows.is_empty() {
+            width
+        } else {
+            width.saturating_sub(continuation_indent).max(1)
+        };
+
+        let mut used = 0;
+        let mut end = row_start;
+        while end < graphemes.len() && used + graphemes[end].width <= available {
+            used += graphemes[end].width;
+            end += 1;
+        }
+        // A character wider than the row still has to go somewhere
+        if end == row_start {
+            end += 1;
+        }
+
+        if end >= graphemes.len() {
+            rows.push(char_at(row_start)..total_chars);
+            break;
+        }
+
+        let brk = adjust_break(&graphemes, row_start, end);
+        rows.push(char_at(row_start)..char_at(brk));
+        row_start = brk;
+    }
+
+    rows
+}
+
+/// Move a break point back so that words are not split and kinsoku rules
+/// hold. Falls back to the hard break when no better position exists.
+fn adjust_break(graphemes: &[Grapheme], start: usize, end: usize) -> usize {
+    let is_word = |g: &Grapheme| g.first.is_ascii_alphanumeric() || g.first == '_';
+
+    let mut brk = end;
+    if is_word(&graphemes[end - 1]) && is_word(&graphemes[end]) {
+        if let Some(space) = (start + 1..end)
+            .rev()
+            .find(|&i| graphemes[i - 1].first.is_whitespace())
+        {
+            brk = space;
+        }
+    }
+
+    let violates = |brk: usize| {
+        is_line_start_prohibited(graphemes[brk].first)
+            || is_line_end_prohibited(graphemes[brk - 1].first)
+    };
+    let mut candidate = brk;
+    while candidate > start + 1 && violates(candidate) {
+        candidate -= 1;
+    }
+    if violates(candidate) {
+        brk
+    } else {
+        candidate
+    }
+}
+
+/// Take the characters in `start..end` from a highlighted line.
+pub fn slice_spans(line: &Line<'static>, start: usize, end: usize) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in &line.spans {
+        let len = span.content.chars().count();
+        let span_start = offset;
+        offset += len;
+        if offset <= start {
+            continue;
+        }
+        if span_start >= end {
+            break;
+        }
+
+        let from = start.saturating_sub(span_start);
+        let to = end.min(offset) - span_start;
+        let text: String = span.content.chars().skip(from).take(to - from).collect();
+        spans.push(Span::styled(text, span.style));
+    }
+    spans
+}
+
+/// Build the screen lines for `rows` from highlighted buffer lines, where
+/// `highlighted[0]` corresponds to buffer line `first_line`. Showbreak markers
+/// and closed-fold summaries are drawn with `marker_style`, whitespace as
+/// `whitespace` says.
+pub fn render_rows(
+    rows: &[DisplayRow],
+    highlighted: &[Line<'static>],
+    first_line: usize,
+    showbreak: &str,
+    marker_style: Style,
+    whitespace: &Whitespace,
+) -> Vec<Line<'static>> {
+    rows.iter()
+        .map(|row| {
+            let mut spans = Vec::new();
+            if row.is_continuation() && !showbreak.is_empty() {
+                spans.push(Span::styled(showbreak.to_string(), marker_style));
+            }
+            if let Some(line) = row
+                .line
+                .checked_sub(first_line)
+                .and_then(|i| highlighted.get(i))
+            {
+                let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+                let trail_start = text.trim_end_matches(' ').chars().count();
+                let line_end = !row.wraps && row.folded.is_none();
+                let col = whitespace.calculator().char_offset_to_col(&text, row.start);
+                spans.extend(whitespace.render(
+                    slice_spans(line, row.start, row.end),
+                    row.start,
+                    col,
+                    trail_start,
+                    line_end,
+                ));
+            }
+            if let Some(hidden) = row.folded {
+                spans.push(Span::styled(format!(" ··· {} 行", hidden), marker_style));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Patch `style` onto every span of a screen line (current-line highlight).
+pub fn patch_line(line: &Line<'static>, style: Style) -> Line<'static> {
+    Line::from(
+        line.spans
+            .iter()
+            .map(|span| Span::styled(span.content.clone(), span.style.patch(style)))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Patch `style` onto the grapheme covering display column `column` of a
+/// screen line (current-column highlight). Rows shorter than the column are
+/// padded with spaces so the column stays visible.
+pub fn patch_column(
+    line: &Line<'static>,
+    column: usize,
+    style: Style,
+    calculator: &TextWidthCalculator,
+) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut used = 0;
+    let mut done = false;
+    for span in &line.spans {
+        if done {
+            spans.push(span.clone());
+            continue;
+        }
+        let (mut before, mut hit, mut after) = (String::new(), String::new(), String::new());
+        for grapheme in span.content.graphemes(true) {
+            let width = calculator.grapheme_width_at(grapheme, used);
+            if hit.is_empty() && used + width <= column {
+                before.push_str(grapheme);
+            } else if hit.is_empty() {
+                hit.push_str(grapheme);
+            } else {
+                after.push_str(grapheme);
+            }
+            used += width;
+        }
+        if hit.is_empty() {
+            spans.push(span.clone());
+            continue;
+        }
+        done = true;
+        for (text, text_style) in [
+            (before, span.style),
+            (hit, span.style.patch(style)),
+            (after, span.style),
+        ] {
+            if !text.is_empty() {
+                spans.push(Span::styled(text, text_style));
+            }
+        }
+    }
+    if !done {
+        spans.push(Span::raw(" ".repeat(column - used)));
+        spans.push(Span::styled(" ", style));
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows_text(text: &str, rows: &[Range<usize>]) -> Vec<String> {
+        rows.iter()
+            .map(|r| text.chars().skip(r.start).take(r.end - r.start).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_wrap_line_by_width() {
+        let calc = TextWidthCalculator::new();
+        assert_eq!(wrap_line(&calc, "", 10, 0).len(), 1);
+        assert_eq!(
+            rows_text("short", &wrap_line(&calc, "short\n", 10, 0)),
+            vec!["short"]
+        );
+
+        // Wide characters take two columns each
+        let text = "日本語のテキスト";
+        let rows = wrap_line(&calc, text, 6, 0);
+        assert_eq!(rows_text(text, &rows), vec!["日本語", "のテキ", "スト"]);
+
+        // Continuation rows are narrowed by the showbreak width
+        let rows = wrap_line(&calc, "abcdefghij", 4, 2);
+        assert_eq!(
+            rows_text("abcdefghij", &rows),
+            vec!["abcd", "ef", "gh", "ij"]
+        );
+
+        // Tabs only reach the next tabstop of the line
+        let text = "ab\tcd\tef";
+        let rows = wrap_line(&calc, text, 8, 0);
+        assert_eq!(rows_text(text, &rows), vec!["ab\tcd\t", "ef"]);
+    }
+
+    #[test]
+    fn test_wrap_line_keeps_words_together() {
+        let calc = TextWidthCalculator::new();
+        let text = "hello brave world";
+        let rows = wrap_line(&calc, text, 8, 0);
+        assert_eq!(rows_text(text, &rows), vec!["hello ", "brave ", "world"]);
+    }
+
+    #[test]
+    fn test_wrap_line_kinsoku() {
+        let calc = TextWidthCalculator::new();
+
+        // "。" would start the second row; the preceding character moves with it
+        let text = "これはテスト。次";
+        let rows = wrap_line(&calc, text, 12, 0);
+        assert_eq!(rows_text(text, &rows), vec!["これはテス", "ト。次"]);
+
+        // "「" must not end a row
+        let text = "あいう「えお」";
+        let rows = wrap_line(&calc, text, 8, 0);
+        assert_eq!(rows_text(text, &rows), vec!["あいう", "「えお」"]);
+
+        for row in wrap_line(&calc, "ああああ、、、、、", 4, 0) {
+            assert!(!row.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_render_rows_slices_spans() {
+        let line = Line::from(vec![
+            Span::styled("abc".to_string(), Style::default()),
+            Span::styled("def".to_string(), Style::default()),
+        ]);
+        let rows = vec![
+            DisplayRow {
+                line: 3,
+                start: 0,
+                end: 4,
+                wraps: true,
+                folded: None,
+            },
+            DisplayRow {
+                line: 3,
+                start: 4,
+                end: 6,
+                wraps: false,
+                folded: Some(2),
+            },
+        ];
+        let whitespace = Whitespace {
+            list: None,
+            tab_width: 4,
+            style: Style::default(),
+        };
+        let lines = render_rows(&rows, &[line], 3, "> ", Style::default(), &whitespace);
+        let text = |l: &Line| {
+            l.spans
+                .iter()
+                .map(|s| s.content.as_ref())
+                .collect::<String>()
+        };
+        assert_eq!(text(&lines[0]), "abcd");
+        assert_eq!(text(&lines[1]), "> ef ··· 2 行");
+    }
+
+    #[test]
+    fn test_patch_column_splits_wide_characters() {
+        let calc = TextWidthCalculator::new();
+        let mark = Style::default().bg(ratatui::style::Color::Blue);
+        let line = Line::from(vec![Span::raw("ab"), Span::raw("日本")]);
+        let spans = patch_column(&line, 3, mark, &calc).spans;
+        let parts: Vec<(&str, bool)> = spans
+            .iter()
+            .map(|s| (s.content.as_ref(), s.style == mark))
+            .collect();
+        assert_eq!(parts, vec![("ab", false), ("日", true), ("本", false)]);
+
+        let spans = patch_column(&Line::from("ab"), 4, mark, &calc).spans;
+        assert_eq!(spans.last().unwrap().style, mark);
+        assert_eq!(spans.iter().map(|s| s.content.len()).sum::<usize>(), 5);
+    }
+}